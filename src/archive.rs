@@ -0,0 +1,278 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::fail::{HResult, HError};
+
+
+// MIME subtypes of the kinds of archives, GNU tar figures out the
+// compression itself
+const ZIP: &[&str] = &["zip", "x-zip-compressed", "java-archive"];
+const TAR: &[&str] = &["x-tar", "x-compressed", "x-compressed-tar",
+                       "x-bzip-compressed-tar", "x-xz-compressed-tar",
+                       "x-zstd-compressed-tar"];
+const SEVEN_Z: &[&str] = &["x-7z-compressed"];
+const RAR: &[&str] = &["vnd.rar", "x-rar", "x-rar-compressed"];
+// Only a single compressed file, unless the name says it's a tarball
+const GZIP: &[&str] = &["gzip", "x-gzip"];
+const BZIP2: &[&str] = &["x-bzip2", "x-bzip"];
+const XZ: &[&str] = &["x-xz"];
+const ZSTD: &[&str] = &["zstd"];
+
+// Names of tarballs compressed with each of them
+const TARBALLS: &[(&[&str], &[&str])] = &[
+    (GZIP, &[".tar.gz", ".tgz"]),
+    (BZIP2, &[".tar.bz2", ".tbz2"]),
+    (XZ, &[".tar.xz", ".txz"]),
+    (ZSTD, &[".tar.zst"]),
+];
+
+// How to extract archives, {archive} and {dest} are replaced with the
+// paths, {stem} with the archive name without extension
+const EXTRACTORS: &[(&[&str], &[&str])] = &[
+    (ZIP, &["unzip", "-q", "{archive}", "-d", "{dest}"]),
+    (TAR, &["tar", "-xf", "{archive}", "-C", "{dest}"]),
+    (SEVEN_Z, &["7z", "x", "-o{dest}", "{archive}"]),
+    (RAR, &["unrar", "x", "{archive}", "{dest}/"]),
+    (GZIP, &["sh", "-c", "gzip -dc \"$1\" > \"$2\"", "sh", "{archive}", "{dest}/{stem}"]),
+    (BZIP2, &["sh", "-c", "bzip2 -dc \"$1\" > \"$2\"", "sh", "{archive}", "{dest}/{stem}"]),
+    (XZ, &["sh", "-c", "xz -dc \"$1\" > \"$2\"", "sh", "{archive}", "{dest}/{stem}"]),
+    (ZSTD, &["sh", "-c", "zstd -dc \"$1\" > \"$2\"", "sh", "{archive}", "{dest}/{stem}"]),
+];
+
+// How to list what's in them for the preview
+const LISTERS: &[(&[&str], &[&str])] = &[
+    (ZIP, &["unzip", "-l", "{archive}"]),
+    (TAR, &["tar", "-tvf", "{archive}"]),
+    (SEVEN_Z, &["7z", "l", "{archive}"]),
+    (RAR, &["unrar", "l", "{archive}"]),
+    (GZIP, &["gzip", "-l", "{archive}"]),
+    (XZ, &["xz", "-l", "{archive}"]),
+    (ZSTD, &["zstd", "-l", "{archive}"]),
+];
+
+// The extension of a new archive decides how it's packed, {files} are
+// the files going into it
+const COMPRESSORS: &[(&str, &[&str])] = &[
+    (".tar.gz", &["tar", "-czf", "{archive}", "{files}"]),
+    (".tgz", &["tar", "-czf", "{archive}", "{files}"]),
+    (".tar.bz2", &["tar", "-cjf", "{archive}", "{files}"]),
+    (".tar.xz", &["tar", "-cJf", "{archive}", "{files}"]),
+    (".tar.zst", &["tar", "--zstd", "-cf", "{archive}", "{files}"]),
+    (".tar", &["tar", "-cf", "{archive}", "{files}"]),
+    (".zip", &["zip", "-qr", "{archive}", "{files}"]),
+    (".7z", &["7z", "a", "{archive}", "{files}"]),
+];
+
+// A file named "--foo" would be taken as an option otherwise
+fn no_option(path: &Path) -> PathBuf {
+    match path.is_relative() && !path.as_os_str().is_empty() {
+        true => Path::new(".").join(path),
+        false => path.to_path_buf()
+    }
+}
+
+fn fill_in(template: &[&str],
+           archive: &Path,
+           dest: &Path,
+           files: &[PathBuf]) -> Vec<OsString> {
+    let stem = archive.file_name()
+                      .map(|name| archive_stem(&name.to_string_lossy()).to_string())
+                      .unwrap_or_default();
+    let archive = no_option(archive);
+    let dest = no_option(dest);
+
+    template.iter()
+            .flat_map(|part| match *part {
+                "{files}" => files.iter()
+                                  .map(|file| no_option(file).into_os_string())
+                                  .collect(),
+                part => {
+                    let part = part.replace("{archive}", &archive.to_string_lossy())
+                                   .replace("{dest}", &dest.to_string_lossy())
+                                   .replace("{stem}", &stem);
+                    vec![OsString::from(part)]
+                }
+            })
+            .collect()
+}
+
+// gzip, bzip2, xz and zstd are used for both plain files and tarballs,
+// only the name tells them apart
+fn archive_kind<'a>(mime: &'a mime::Mime, archive: &Path) -> &'a str {
+    let subtype = mime.subtype().as_str();
+    let name = archive.to_string_lossy();
+
+    let tarball = TARBALLS.iter()
+        .filter(|(subtypes, _)| subtypes.contains(&subtype))
+        .any(|(_, exts)| exts.iter().any(|ext| name.ends_with(ext)));
+
+    match tarball {
+        true => "x-compressed-tar",
+        false => subtype
+    }
+}
+
+fn find_template(table: &[(&[&str], &'static [&'static str])],
+                 subtype: &str) -> Option<&'static [&'static str]> {
+    table.iter()
+         .find(|(subtypes, _)| subtypes.contains(&subtype))
+         .map(|(_, template)| *template)
+}
+
+pub fn extract_cmd(mime: &mime::Mime,
+                   archive: &Path,
+                   dest: &Path) -> HResult<Vec<OsString>> {
+    find_template(EXTRACTORS, archive_kind(mime, archive))
+        .map(|template| fill_in(template, archive, dest, &[]))
+        .ok_or_else(|| HError::Error(format!("Don't know how to extract {}",
+                                             mime)))
+}
+
+// Runs args and removes dest again if they fail, so no half-extracted
+// directory is left behind
+pub fn remove_on_failure(args: Vec<OsString>, dest: &Path) -> Vec<OsString> {
+    let script = "dest=\"$1\"; shift; \"$@\" || { rm -rf -- \"$dest\"; exit 1; }";
+
+    vec![OsString::from("sh"),
+         OsString::from("-c"),
+         OsString::from(script),
+         OsString::from("sh"),
+         no_option(dest).into_os_string()]
+        .into_iter()
+        .chain(args)
+        .collect()
+}
+
+pub fn list_cmd(mime: &mime::Mime, archive: &Path) -> HResult<Vec<OsString>> {
+    find_template(LISTERS, archive_kind(mime, archive))
+        .map(|template| fill_in(template, archive, Path::new(""), &[]))
+        .ok_or_else(|| HError::Error(format!("Don't know how to list {}",
+                                             mime)))
+}
+
+pub fn compress_cmd(archive: &Path, files: &[PathBuf]) -> HResult<Vec<OsString>> {
+    let name = archive.to_string_lossy();
+
+    COMPRESSORS.iter()
+               .find(|(ext, _)| name.ends_with(ext))
+               .map(|(_, template)| fill_in(template, archive, Path::new(""), files))
+               .ok_or_else(|| {
+                   let exts = COMPRESSORS.iter()
+                                         .map(|(ext, _)| *ext)
+                                         .collect::<Vec<_>>()
+                                         .join(", ");
+                   HError::Error(format!("Archive name has to end with one of {}",
+                                         exts))
+               })
+}
+
+// "foo.tar.gz" is "foo", not "foo.tar"
+pub fn archive_stem(name: &str) -> &str {
+    COMPRESSORS.iter()
+               .find(|(ext, _)| name.ends_with(ext) && name.len() > ext.len())
+               .map(|(ext, _)| &name[..name.len() - ext.len()])
+               .or_else(|| name.rfind('.')
+                               .filter(|&dot| dot > 0)
+                               .map(|dot| &name[..dot]))
+               .unwrap_or(name)
+}
+
+// Appends -1, -2, ... until nothing's in the way
+pub fn unused_path(path: PathBuf) -> PathBuf {
+    if path.symlink_metadata().is_err() {
+        return path;
+    }
+
+    let name = path.file_name()
+                   .map(|name| name.to_string_lossy().to_string())
+                   .unwrap_or_default();
+
+    (1..).map(|i| path.with_file_name(format!("{}-{}", name, i)))
+         .find(|path| path.symlink_metadata().is_err())
+         .unwrap()
+}
+
+#[test]
+fn test_archive_stem() {
+    assert_eq!(archive_stem("foo.tar.gz"), "foo");
+    assert_eq!(archive_stem("foo.bar.zip"), "foo.bar");
+    assert_eq!(archive_stem("foo.rar"), "foo");
+    assert_eq!(archive_stem("foo"), "foo");
+    assert_eq!(archive_stem(".tar"), ".tar");
+}
+
+#[test]
+fn test_extract_cmd() {
+    let args = |mime: &str, archive: &str| {
+        extract_cmd(&mime.parse().unwrap(), Path::new(archive), Path::new("dest"))
+            .unwrap()
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(args("application/zip", "--foo.zip"),
+               vec!["unzip", "-q", "./--foo.zip", "-d", "./dest"]);
+    assert_eq!(args("application/gzip", "foo.tar.gz"),
+               vec!["tar", "-xf", "./foo.tar.gz", "-C", "./dest"]);
+    assert_eq!(args("application/x-7z-compressed", "/tmp/foo.7z"),
+               vec!["7z", "x", "-o./dest", "/tmp/foo.7z"]);
+    assert_eq!(&args("application/gzip", "foo.txt.gz")[..2], ["sh", "-c"]);
+    assert_eq!(&args("application/gzip", "foo.txt.gz")[4..],
+               ["./foo.txt.gz", "./dest/foo.txt"]);
+    assert!(extract_cmd(&"text/plain".parse().unwrap(),
+                        Path::new("foo.txt"),
+                        Path::new("dest")).is_err());
+}
+
+#[test]
+fn test_extract_compressed_file() {
+    let args = |mime: &str, archive: &str| {
+        extract_cmd(&mime.parse().unwrap(), Path::new(archive), Path::new("dest"))
+            .unwrap()
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // Tarballs only by name, otherwise it's a single file
+    assert_eq!(args("application/x-bzip2", "foo.tar.bz2")[..2], ["tar", "-xf"]);
+    assert_eq!(args("application/x-bzip2", "foo.tbz2")[..2], ["tar", "-xf"]);
+    assert_eq!(args("application/x-xz", "foo.txz")[..2], ["tar", "-xf"]);
+    assert_eq!(args("application/zstd", "foo.tar.zst")[..2], ["tar", "-xf"]);
+
+    let bzip2 = args("application/x-bzip2", "dump.sql.bz2");
+    assert_eq!(bzip2[2], "bzip2 -dc \"$1\" > \"$2\"");
+    assert_eq!(bzip2[4..], ["./dump.sql.bz2", "./dest/dump.sql"]);
+    assert_eq!(args("application/x-xz", "notes.txt.xz")[2], "xz -dc \"$1\" > \"$2\"");
+    assert_eq!(args("application/zstd", "foo.zst")[2], "zstd -dc \"$1\" > \"$2\"");
+
+    // Nothing to list in a plain bzip2 file, so its preview stays as it is
+    assert!(list_cmd(&"application/x-bzip2".parse().unwrap(),
+                     Path::new("dump.sql.bz2")).is_err());
+    assert_eq!(list_cmd(&"application/x-xz".parse().unwrap(), Path::new("foo.txz"))
+                   .unwrap()[..2],
+               ["tar", "-tvf"]);
+}
+
+#[test]
+fn test_compress_cmd() {
+    let files = vec![PathBuf::from("--checkpoint-action=exec=sh"),
+                     PathBuf::from("/tmp/bar")];
+    let args = compress_cmd(Path::new("foo.tar.gz"), &files).unwrap();
+
+    assert_eq!(args, vec!["tar", "-czf", "./foo.tar.gz",
+                          "./--checkpoint-action=exec=sh", "/tmp/bar"]);
+    assert!(compress_cmd(Path::new("foo.gz"), &files).is_err());
+}
+
+#[test]
+fn test_unused_path() {
+    let tmp = crate::test_dir::TestDir::new("unused");
+
+    assert_eq!(unused_path(tmp.join("foo")), tmp.join("foo"));
+    std::fs::create_dir(tmp.join("foo")).unwrap();
+    assert_eq!(unused_path(tmp.join("foo")), tmp.join("foo-1"));
+    std::fs::write(tmp.join("foo-1"), "").unwrap();
+    assert_eq!(unused_path(tmp.join("foo")), tmp.join("foo-2"));
+}
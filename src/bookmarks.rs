@@ -7,9 +7,64 @@ use crate::widget::{Widget, WidgetCore};
 use crate::coordinates::Coordinates;
 use crate::term;
 
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Bookmark {
+    pub path: String,
+    pub label: Option<String>,
+    // Name of the file that was selected, so jumping back restores it
+    pub selection: Option<String>,
+}
+
+impl Bookmark {
+    pub fn new(path: &str) -> Bookmark {
+        Bookmark {
+            path: path.to_string(),
+            label: None,
+            selection: None
+        }
+    }
+
+    // Lines are "key:path", optionally followed by a label and the
+    // selected file, separated by tabs. Plain "key:path" lines, like
+    // the ones from ranger, still work
+    pub fn parse(line: &str) -> Option<(char, Bookmark)> {
+        let mut parts = line.splitn(2, ":");
+        let key = parts.next()?.chars().next()?;
+        let mut fields = parts.next()?.split('\t');
+        let path = fields.next()?.to_string();
+        let mut field = || fields.next()
+                                 .filter(|field| !field.is_empty())
+                                 .map(String::from);
+        let label = field();
+        let selection = field();
+
+        Some((key, Bookmark { path, label, selection }))
+    }
+
+    pub fn to_line(&self, key: char) -> String {
+        match (&self.label, &self.selection) {
+            (None, None) => format!("{}:{}\n", key, self.path),
+            (label, selection) => format!("{}:{}\t{}\t{}\n",
+                                          key,
+                                          self.path,
+                                          label.as_deref().unwrap_or(""),
+                                          selection.as_deref().unwrap_or(""))
+        }
+    }
+
+    fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        self.label
+            .as_ref()
+            .map(|label| label.to_lowercase().contains(&filter))
+            .unwrap_or(false)
+            || self.path.to_lowercase().contains(&filter)
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct Bookmarks {
-    mapping: HashMap<char, String>,
+    mapping: HashMap<char, Bookmark>,
 }
 
 impl Bookmarks {
@@ -18,14 +73,14 @@ impl Bookmarks {
         bm.load().or_else(|_| HError::log("Couldn't load bookmarks!")).ok();
         bm
     }
-    pub fn add(&mut self, key: char, path: &str) -> HResult<()> {
-        self.mapping.insert(key, path.to_string());
+    pub fn add(&mut self, key: char, bookmark: Bookmark) -> HResult<()> {
+        self.mapping.insert(key, bookmark);
         self.save()?;
         Ok(())
     }
-    pub fn get(&self, key: char) -> HResult<&String> {
-        let path = self.mapping.get(&key)?;
-        Ok(path)
+    pub fn get(&self, key: char) -> HResult<&Bookmark> {
+        let bookmark = self.mapping.get(&key)?;
+        Ok(bookmark)
     }
     pub fn load(&mut self) -> HResult<()> {
         let bm_file = crate::paths::bookmark_path()?;
@@ -36,16 +91,8 @@ impl Bookmarks {
 
         let bm_content = std::fs::read_to_string(bm_file)?;
         let mapping = bm_content.lines()
-            .fold(HashMap::new(), |mut bm, line| {
-            let parts = line.splitn(2, ":").collect::<Vec<&str>>();
-            if parts.len() == 2 {
-                if let Some(key) = parts[0].chars().next() {
-                    let path = parts[1].to_string();
-                    bm.insert(key, path);
-                }
-            }
-            bm
-        });
+            .filter_map(Bookmark::parse)
+            .collect();
 
         self.mapping = mapping;
         Ok(())
@@ -62,8 +109,8 @@ impl Bookmarks {
     }
     pub fn save(&self) -> HResult<()> {
         let bm_file = crate::paths::bookmark_path()?;
-        let bookmarks = self.mapping.iter().map(|(key, path)| {
-            format!("{}:{}\n", key, path)
+        let bookmarks = self.mapping.iter().map(|(key, bookmark)| {
+            bookmark.to_line(*key)
         }).collect::<String>();
 
         std::fs::write(bm_file, bookmarks)?;
@@ -76,7 +123,8 @@ impl Bookmarks {
 pub struct BMPopup {
     core: WidgetCore,
     bookmarks: Bookmarks,
-    bookmark_path: Option<String>,
+    bookmark: Option<Bookmark>,
+    filter: Option<String>,
     add_mode: bool,
 }
 
@@ -85,15 +133,16 @@ impl BMPopup {
         let mut bmpopup = BMPopup {
             core: core.clone(),
             bookmarks: Bookmarks::new(),
-            bookmark_path: None,
+            bookmark: None,
+            filter: None,
             add_mode: false
         };
         bmpopup.set_coordinates(&core.coordinates).log();
         bmpopup
     }
 
-    pub fn pick(&mut self, cwd: String) -> HResult<String> {
-        self.bookmark_path = Some(cwd);
+    pub fn pick(&mut self, cwd: String) -> HResult<Bookmark> {
+        self.bookmark = Some(Bookmark::new(&cwd));
         self.refresh()?;
         match self.popup() {
             Ok(_) => {},
@@ -103,14 +152,19 @@ impl BMPopup {
             err @ Err(_) => err?,
         }
         self.get_core()?.clear()?;
+        self.filter = None;
 
-        let bookmark = self.bookmark_path.take();
+        let bookmark = self.bookmark.take();
         Ok(bookmark?)
     }
 
-    pub fn add(&mut self, path: &str) -> HResult<()> {
+    pub fn add(&mut self, path: &str, selection: Option<String>) -> HResult<()> {
         self.add_mode = true;
-        self.bookmark_path = Some(path.to_string());
+        self.filter = None;
+        self.bookmark = Some(Bookmark {
+            selection,
+            ..Bookmark::new(path)
+        });
         self.refresh()?;
         self.get_core()?.clear()?;
         self.popup()?;
@@ -122,17 +176,86 @@ impl BMPopup {
         HError::terminal_resized()?
     }
 
-    pub fn render_line(&self, n: u16, key: &char, path: &str) -> String {
-        let xsize = term::xsize();
-        let padding = xsize - 4;
+    // Sorted by key and without the ones not matching the filter
+    fn shown(&self) -> Vec<(&char, &Bookmark)> {
+        let mut shown = self.bookmarks
+            .mapping
+            .iter()
+            .filter(|(_, bookmark)| {
+                self.filter
+                    .as_ref()
+                    .map(|filter| bookmark.matches(filter))
+                    .unwrap_or(true)
+            })
+            .collect::<Vec<_>>();
+
+        shown.sort_by_key(|(key, _)| **key);
+        shown
+    }
+
+    pub fn render_line(&self,
+                       n: u16,
+                       key: &char,
+                       bookmark: &Bookmark,
+                       label_xsize: usize) -> String {
+        let xsize = term::xsize() as usize;
+
+        let line = match label_xsize {
+            0 => format!("{}  {}", key, bookmark.path),
+            _ => {
+                let label = bookmark.label.as_deref().unwrap_or("");
+                let label = term::sized_string(label, label_xsize as u16 + 1);
+                let padding = label_xsize.saturating_sub(term::str_width(label));
+
+                format!("{}  {}{:padding$}  {}",
+                        key,
+                        label,
+                        "",
+                        bookmark.path,
+                        padding = padding)
+            }
+        };
+
+        let line = term::sized_string(&line, xsize as u16);
+        let padding = xsize.saturating_sub(term::str_width(line) + 1);
 
         format!(
-            "{}{}{}: {:padding$}",
+            "{}{}{}{:padding$}",
             crate::term::goto_xy(1, n),
             crate::term::reset(),
-            key,
-            path,
-            padding = padding as usize)
+            line,
+            "",
+            padding = padding)
+    }
+
+    // Labels take up at most a third of the line
+    fn label_xsize(&self) -> usize {
+        let max_xsize = term::xsize() as usize / 3;
+
+        self.shown()
+            .iter()
+            .filter_map(|(_, bookmark)| bookmark.label.as_ref())
+            .map(|label| term::str_width(label))
+            .max()
+            .unwrap_or(0)
+            .min(max_xsize)
+    }
+
+    fn query_label(&mut self) -> Option<String> {
+        self.core
+            .minibuffer("label")
+            .ok()
+            .filter(|label| !label.is_empty())
+            .map(|label| label.replace('\t', " "))
+    }
+
+    // The minibuffer takes over input, so the popup has to be restarted
+    fn query_filter(&mut self) -> HResult<()> {
+        self.filter = self.core
+            .minibuffer("filter")
+            .ok()
+            .filter(|filter| !filter.is_empty());
+        HError::widget_resized()
     }
 }
 
@@ -154,7 +277,7 @@ impl Widget for BMPopup {
 
     fn set_coordinates(&mut self, _: &Coordinates) -> HResult<()> {
         let (xsize, ysize) = crate::term::size()?;
-        let len = self.bookmarks.mapping.len();
+        let len = self.shown().len();
         let ysize = ysize.saturating_sub( len + 1 );
 
         self.core.coordinates.set_size_u(xsize.saturating_sub(1), len);
@@ -165,17 +288,18 @@ impl Widget for BMPopup {
 
     fn get_drawlist(&self) -> HResult<String> {
         let ypos = self.get_coordinates()?.ypos();
+        let label_xsize = self.label_xsize();
 
         let mut drawlist = String::new();
 
         if !self.add_mode {
-            let cwd = self.bookmark_path.as_ref()?;
-            drawlist += &self.render_line(ypos, &'`', cwd);
+            let cwd = self.bookmark.as_ref()?;
+            drawlist += &self.render_line(ypos, &'`', cwd, label_xsize);
         }
 
-        let bm_list = self.bookmarks.mapping.iter().enumerate().map(|(i, (key, path))| {
+        let bm_list = self.shown().iter().enumerate().map(|(i, (key, bookmark))| {
             let line = i as u16 + ypos + 1;
-            self.render_line(line, key, path)
+            self.render_line(line, key, bookmark, label_xsize)
         }).collect::<String>();
 
         drawlist += &bm_list;
@@ -185,20 +309,22 @@ impl Widget for BMPopup {
     fn on_key(&mut self, key: Key) -> HResult<()> {
         match key {
             Key::Ctrl('c') | Key::Esc => {
-                self.bookmark_path = None;
+                self.bookmark = None;
                 return HError::popup_finnished()
             },
+            Key::Ctrl('f') => return self.query_filter(),
             Key::Char('`') => return HError::popup_finnished(),
             Key::Char(key) => {
                 if self.add_mode {
-                    let path = self.bookmark_path.take()?;
-                    self.bookmarks.add(key, &path)?;
+                    let mut bookmark = self.bookmark.take()?;
+                    bookmark.label = self.query_label();
+                    self.bookmarks.add(key, bookmark)?;
                     self.add_mode = false;
                     self.bookmarks.save().log();
                     return HError::popup_finnished();
                 }
-                if let Ok(path) = self.bookmarks.get(key) {
-                    self.bookmark_path.replace(path.clone());
+                if let Ok(bookmark) = self.bookmarks.get(key) {
+                    self.bookmark.replace(bookmark.clone());
                     return HError::popup_finnished();
                 }
             }
@@ -212,3 +338,25 @@ impl Widget for BMPopup {
         Ok(())
     }
 }
+
+
+
+#[test]
+fn test_parse_bookmark() {
+    let (key, bookmark) = Bookmark::parse("a:/home/user/some:dir").unwrap();
+    assert_eq!(key, 'a');
+    assert_eq!(bookmark, Bookmark::new("/home/user/some:dir"));
+    assert_eq!(bookmark.to_line(key), "a:/home/user/some:dir\n");
+
+    let line = "b:/tmp\tTemporary files\tfoo.txt\n";
+    let (key, bookmark) = Bookmark::parse(line.trim_end()).unwrap();
+    assert_eq!(bookmark.label.as_deref(), Some("Temporary files"));
+    assert_eq!(bookmark.selection.as_deref(), Some("foo.txt"));
+    assert_eq!(bookmark.to_line(key), line);
+
+    let (_, bookmark) = Bookmark::parse("c:/tmp\t\tfoo.txt").unwrap();
+    assert_eq!(bookmark.label, None);
+    assert_eq!(bookmark.selection.as_deref(), Some("foo.txt"));
+
+    assert_eq!(Bookmark::parse("no bookmark"), None);
+}
@@ -0,0 +1,80 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::fail::{HResult, HError};
+use crate::term::ScreenExt;
+use crate::widget::WidgetCore;
+
+
+fn clipboard_cmds() -> Vec<(&'static str, Vec<&'static str>)> {
+    let mut cmds = vec![];
+
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        cmds.push(("wl-copy", vec![]));
+    }
+
+    if std::env::var_os("DISPLAY").is_some() {
+        cmds.push(("xclip", vec!["-selection", "clipboard"]));
+        cmds.push(("xsel", vec!["--clipboard", "--input"]));
+    }
+
+    if cfg!(target_os = "macos") {
+        cmds.push(("pbcopy", vec![]));
+    }
+
+    cmds
+}
+
+fn copy_with(cmd: &str, args: &[&str], text: &str) -> HResult<()> {
+    let mut child = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    child.stdin
+        .take()?
+        .write_all(text.as_bytes())?;
+
+    match child.wait()?.success() {
+        true => Ok(()),
+        false => Err(HError::Error(format!("{} failed to copy to clipboard", cmd)))
+    }
+}
+
+// Works over ssh and without any clipboard tools, if the terminal supports it
+pub fn copy_osc52(core: &WidgetCore, text: &str) -> HResult<()> {
+    let mut screen = core.screen.clone();
+    let encoded = base64::encode(text);
+
+    // tmux/screen need the sequence wrapped so it's passed to the terminal
+    if std::env::var_os("TMUX").is_some() {
+        screen.write_str(&format!("\x1bPtmux;\x1b\x1b]52;c;{}\x07\x1b\\", encoded))?;
+    } else {
+        screen.write_str(&format!("\x1b]52;c;{}\x07", encoded))?;
+    }
+
+    screen.flush()?;
+    Ok(())
+}
+
+// For when hunter might be running over ssh, the local clipboard gets it too
+pub fn copy_with_osc52(core: &WidgetCore, text: &str) -> HResult<()> {
+    clipboard_cmds()
+        .into_iter()
+        .any(|(cmd, args)| copy_with(cmd, &args, text).is_ok());
+
+    copy_osc52(core, text)
+}
+
+pub fn copy(core: &WidgetCore, text: &str) -> HResult<()> {
+    let copied = clipboard_cmds()
+        .into_iter()
+        .any(|(cmd, args)| copy_with(cmd, &args, text).is_ok());
+
+    match copied {
+        true => Ok(()),
+        false => copy_osc52(core, text)
+    }
+}
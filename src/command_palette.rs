@@ -0,0 +1,276 @@
+use strum::IntoEnumIterator;
+
+use crate::coordinates::Coordinates;
+use crate::keybind::{BindingEntry, BindingSection, Bindings, KeyBinds};
+use crate::keybind::{FileBrowserAction, FileListAction, TabAction};
+use crate::term;
+
+
+// The actions that can be run from the file browser, everything else only
+// makes sense in its own view
+#[derive(Copy, Clone, Debug)]
+pub enum PaletteAction {
+    FileBrowser(FileBrowserAction),
+    FileList(FileListAction),
+    Tab(TabAction)
+}
+
+#[derive(Clone, Debug)]
+pub struct PaletteEntry {
+    pub action: PaletteAction,
+    pub name: String,
+    pub description: &'static str,
+    pub keys: String
+}
+
+fn section_entries<T>(bound: &[BindingEntry],
+                      wrap: impl Fn(T) -> PaletteAction,
+                      describe: impl Fn(T) -> &'static str) -> Vec<PaletteEntry>
+where
+    T: BindingSection + IntoEnumIterator,
+    <T as IntoEnumIterator>::Iterator: Iterator<Item=T>,
+    Bindings<T>: Default
+{
+    T::iter()
+        .filter_map(|action| {
+            let name = action.to_string();
+            let bindings = bound.iter()
+                                .filter(|entry| entry.section == T::section())
+                                .filter(|entry| entry.action.split("(").next() == Some(&name))
+                                .collect::<Vec<_>>();
+
+            // Actions getting their parameter from the key can't run without it
+            if bindings.iter().any(|entry| !entry.editable()) {
+                return None;
+            }
+
+            let keys = bindings.iter()
+                               .flat_map(|entry| entry.keys.iter())
+                               .map(|key| key.config_string())
+                               .collect::<Vec<_>>()
+                               .join(", ");
+
+            Some(PaletteEntry {
+                action: wrap(action.as_default()),
+                name: name,
+                description: describe(action),
+                keys: keys
+            })
+        })
+        .collect()
+}
+
+pub fn entries(keybinds: &KeyBinds) -> Vec<PaletteEntry> {
+    let bound = keybinds.entries();
+
+    let mut entries = section_entries(&bound,
+                                      PaletteAction::FileBrowser,
+                                      browser_description);
+    entries.extend(section_entries(&bound,
+                                   PaletteAction::FileList,
+                                   list_description));
+    entries.extend(section_entries(&bound,
+                                   PaletteAction::Tab,
+                                   tab_description));
+
+    entries.retain(|entry| match entry.action {
+        PaletteAction::FileBrowser(FileBrowserAction::CommandPalette) => false,
+        _ => true
+    });
+
+    entries
+}
+
+// Positions of the entries matching "query", best first. Names are matched
+// fuzzily, descriptions only when they contain the query.
+pub fn ranked(entries: &[PaletteEntry], query: &str) -> Vec<usize> {
+    let query = query.trim().to_lowercase();
+
+    let mut scored = entries.iter()
+        .enumerate()
+        .filter_map(|(i, entry)| {
+            let score = crate::listview::fuzzy_score(&entry.name, &query)
+                .or_else(|| match entry.description.to_lowercase().contains(&query) {
+                    true => Some(-1000),
+                    false => None
+                })?;
+            Some((i, score))
+        })
+        .collect::<Vec<_>>();
+
+    // Stable, so equally good matches stay in the order of the keys file
+    scored.sort_by_key(|&(_, score)| -score);
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+// Always fills the lower half of the area, so fewer matches don't leave
+// old lines behind
+pub fn render(entries: &[PaletteEntry],
+              ranked: &[usize],
+              coordinates: &Coordinates) -> String {
+    let (xpos, ypos) = coordinates.u16position();
+    let xsize = coordinates.xsize_u();
+    let ysize = coordinates.ysize_u();
+    let rows = (ysize / 2).max(1);
+    let top = ypos as usize + ysize - rows;
+
+    let mut lines = ranked.iter()
+        .take(rows)
+        .map(|&entry| {
+            let entry = &entries[entry];
+            format!("{:20} {:14} {}", entry.name, entry.keys, entry.description)
+        })
+        .collect::<Vec<_>>();
+
+    if lines.is_empty() {
+        lines.push(String::from("No matching actions"));
+    }
+
+    lines.resize(rows, String::new());
+
+    lines.iter()
+         .enumerate()
+         .map(|(i, line)| {
+             // Enter runs the best match
+             let color = match (i, ranked.len()) {
+                 (0, len) if len > 0 => term::invert(),
+                 _ => term::header_color()
+             };
+
+             format!("{}{}{}{}",
+                     term::goto_xy(xpos, (top + i) as u16),
+                     color,
+                     term::sized_string_u(line, xsize),
+                     term::reset())
+         })
+         .collect()
+}
+
+fn browser_description(action: FileBrowserAction) -> &'static str {
+    use FileBrowserAction::*;
+
+    match action {
+        LeftColumnDown => "Move down in the parent directory",
+        LeftColumnUp => "Move up in the parent directory",
+        GotoHome => "Go to the home directory",
+        TurboCd => "Jump through directories by typing their names",
+        SelectExternal => "Select files with an external program",
+        EnterDirExternal => "Enter a directory chosen with an external program",
+        Quit => "Quit hunter",
+        QuitWithDir => "Quit and leave the shell in the current directory",
+        ForceQuit => "Quit without asking, even with processes running",
+        RunInBackground => "Open the selected file in the background",
+        GotoPrevCwd => "Go back to the previous directory",
+        ShowBookmarks => "Show the bookmarks",
+        AddBookmark => "Bookmark the current directory",
+        GotoFrecent => "Jump to a frequently used directory",
+        OpenInNewTab => "Open the selected directory in a new tab",
+        ShowMounts => "Show mounted filesystems",
+        ShowProcesses => "Show the running and finished processes",
+        ShowLog => "Show the log",
+        ShowQuickActions => "Show the quick actions for the selected files",
+        RunLastQuickAction => "Run the last quick action again",
+        RunSubshell => "Start a shell in the current directory",
+        ToggleColumns => "Toggle the parent directory column",
+        ZoomPreview => "Show the preview over the whole screen",
+        ExecCmd => "Run a shell command",
+        EditDirConfig => "Edit the .hunter file of the current directory",
+        OpenWith => "Open the selected files with an application",
+        XdgOpen => "Open the selected file with xdg-open",
+        FileInfo => "Show details of the selected file",
+        CopyFiles => "Copy the selected files",
+        MoveFiles => "Move the selected files",
+        DeleteFiles => "Delete the selected files",
+        TrashFiles => "Move the selected files to the trash",
+        SymlinkFiles => "Link the files selected in another tab here",
+        CreateSymlink => "Create a symlink to the selected file",
+        BulkRename => "Rename the selected files in an editor",
+        AddToScratch => "Add the selected files to the scratch directory",
+        GotoScratch => "Go to the scratch directory",
+        Undo => "Undo the last file operation",
+        Redo => "Redo the last undone file operation",
+        RepeatLastAction => "Repeat the last action",
+        RecordMacro => "Start or stop recording a macro",
+        PlayMacro(_) => "Play the recorded macro",
+        EditKeyBindings => "List and change the key bindings",
+        EditFile => "Edit the selected file",
+        Diff => "Compare the two selected files or directories",
+        ExtractArchive => "Extract the selected archive",
+        CompressFiles => "Pack the selected files into an archive",
+        LinkToBookmark => "Link the selected files into a bookmarked directory",
+        CopyToBookmark => "Copy the selected files into a bookmarked directory",
+        CommandPalette => "Search and run actions",
+        GotoConfigDir => "Go to the configuration directory",
+        FindDuplicates => "Find files with the same content in the list",
+        GrepOpen => "Search file contents with rg and edit a match",
+        FollowLink => "Go to the real location of the selected symlink",
+        PinPreview => "Keep previewing the current file while moving on",
+        GrowPreview => "Make the preview bigger",
+        ShrinkPreview => "Make the preview smaller",
+        ResetLayout => "Restore the configured column ratios",
+        TogglePreviewHidden => "Show or hide hidden files in directory previews"
+    }
+}
+
+fn list_description(action: FileListAction) -> &'static str {
+    use FileListAction::*;
+
+    match action {
+        Search => "Search for a file",
+        SearchNext(_) => "Go to the next search match",
+        SearchPrev(_) => "Go to the previous search match",
+        Filter => "Only show files matching a filter",
+        Select => "Select the file under the cursor",
+        InvertSelection => "Invert the selection",
+        ClearSelection => "Unselect all files",
+        SelectGlob => "Select the files matching a pattern like *.jpg",
+        FilterSelection => "Only show the selected files",
+        ToggleTag => "Tag the file under the cursor",
+        ToggleHidden => "Show or hide hidden files",
+        ReverseSort => "Reverse the sort order",
+        CycleSort => "Sort by the next criterion",
+        ToNextMtime => "Go to the next newer file",
+        ToPrevMtime => "Go to the next older file",
+        ToggleDirsFirst => "Toggle showing directories first",
+        YankPaths => "Copy the paths of the selected files",
+        ToggleFlat(_) => "Show files from subdirectories, too",
+        Rename => "Rename the file under the cursor",
+        GotoFirstSelected => "Go to the first selected file",
+        GotoLastSelected => "Go to the last selected file",
+        NextSelected => "Go to the next selected file",
+        PrevSelected => "Go to the previous selected file",
+        ToggleDetails => "Show or hide file details",
+        LoadAllMeta => "Load the metadata of all files",
+        ToggleDirSize => "Switch directories between sizes and entry counts",
+        JumpToLetter => "Jump to the first file starting with a letter"
+    }
+}
+
+fn tab_description(action: TabAction) -> &'static str {
+    use TabAction::*;
+
+    match action {
+        NewTab => "Open a new tab",
+        CloseTab => "Close the current tab",
+        NextTab => "Go to the next tab",
+        PrevTab => "Go to the previous tab",
+        GotoTab(_) => "Go to a tab by its number",
+        RenameTab => "Give the current tab a name"
+    }
+}
+
+#[test]
+fn test_ranked() {
+    let entries = entries(&KeyBinds::default());
+    let names = |query| ranked(&entries, query).into_iter()
+                                               .map(|i| entries[i].name.as_str())
+                                               .collect::<Vec<_>>();
+
+    assert_eq!(names("").len(), entries.len());
+    assert_eq!(names("togglehidden").first(), Some(&"ToggleHidden"));
+    assert_eq!(names("trash").first(), Some(&"TrashFiles"));
+    // Found by its description
+    assert!(names("archive").contains(&"ExtractArchive"));
+    assert!(!names("").contains(&"CommandPalette"));
+    assert!(!names("").contains(&"GotoTab"));
+}
@@ -2,11 +2,17 @@ use lazy_static;
 use clap;
 
 use std::sync::RwLock;
+use std::path::PathBuf;
 
 use crate::paths;
 
 use crate::fail::{HError, HResult, ErrorLog};
 use crate::keybind::KeyBinds;
+use crate::openers::Openers;
+use crate::files::{SizeFormat, SizeUnits, DateFormat, TrailingSlash};
+use crate::hbox::{Placement, SmallTerminal};
+use crate::file_browser::{ConfirmQuit, EmptyTrash};
+use crate::proclist::AlertOnFailure;
 
 
 #[derive(Clone)]
@@ -16,6 +22,7 @@ struct ArgvConfig {
     show_hidden: Option<bool>,
     icons: Option<bool>,
     graphics: Option<String>,
+    read_only: Option<bool>,
 }
 
 impl ArgvConfig {
@@ -24,7 +31,8 @@ impl ArgvConfig {
             animation: None,
             show_hidden: None,
             icons: None,
-            graphics: None
+            graphics: None,
+            read_only: None
         }
     }
 }
@@ -38,6 +46,7 @@ pub fn set_argv_config(args: clap::ArgMatches) -> HResult<()> {
     let animation = args.is_present("animation-off");
     let show_hidden = args.is_present("show-hidden");
     let icons = args.is_present("icons");
+    let read_only = args.is_present("read-only");
 
     let mut config = ArgvConfig::new();
 
@@ -53,6 +62,10 @@ pub fn set_argv_config(args: clap::ArgMatches) -> HResult<()> {
         config.icons = Some(true)
     }
 
+    if read_only == true {
+        config.read_only = Some(true);
+    }
+
     if let Some(mode) = args.value_of("graphics") {
         if mode == "auto" {
             config.graphics = Some(detect_g_mode());
@@ -76,10 +89,23 @@ fn infuse_argv_config(mut config: Config) -> Config {
     argv_config.show_hidden.map(|val| config.show_hidden = val);
     argv_config.icons.map(|val| config.icons = val);
     argv_config.graphics.map(|val| config.graphics = val);
+    argv_config.read_only.map(|val| config.read_only = val);
 
     config
 }
 
+// Options a project's .hunter/config can't set, since they run commands
+// or decide whether something is run
+const PROJECT_IGNORED: [&str; 9] = ["select_cmd",
+                                    "cd_cmd",
+                                    "media_previewer",
+                                    "editor",
+                                    "editor_line_cmd",
+                                    "diff_cmd",
+                                    "run_executables",
+                                    "confirm_run",
+                                    "dir_config"];
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub animation: bool,
@@ -94,8 +120,59 @@ pub struct Config {
     pub media_previewer: String,
     pub media_previewer_exists: bool,
     pub ratios: Vec::<usize>,
+    pub columns: usize,
+    pub preview_position: Placement,
     pub graphics: String,
+    pub proc_read_delay: u64,
+    pub proc_output_limit: usize,
+    // Entries per minibuffer prompt, 0 turns the history off
+    pub history_size: usize,
+    pub previewer_timeout: u64,
+    // In milliseconds, how long the cursor has to rest before previewing
+    pub preview_delay_ms: u64,
+    pub alert_on_failure: AlertOnFailure,
+    pub dir_config: bool,
+    pub recursive_dir_size: bool,
+    pub editor: Option<String>,
+    // {editor}, {file} and {line} are filled in
+    pub editor_line_cmd: String,
+    pub run_executables: bool,
+    pub run_executables_bg: bool,
+    pub confirm_run: bool,
+    // "off", "on" or "running" to only ask while processes are running
+    pub confirm_quit: ConfirmQuit,
+    // "off", "on" to ask the first time or "always"
+    pub empty_trash_on_quit: EmptyTrash,
+    pub search_wrap: bool,
+    pub size_units: SizeUnits,
+    pub size_decimal: bool,
+    pub size_on_disk: bool,
+    pub remember_offset: bool,
+    pub header_info: String,
+    pub date_format: DateFormat,
+    pub scratch_dir: Option<String>,
+    pub scratch_copy: bool,
+    pub syntax_highlight: bool,
+    // In MB, 0 turns the cache off
+    pub image_cache_size: u64,
+    pub small_terminal: SmallTerminal,
+    // "off", "on" for a slash after directories or "classify" like ls -F
+    pub dir_trailing_slash: TrailingSlash,
+    pub status_line: bool,
+    pub recent_actions_first: bool,
+    // Names given with RenameTab stay after leaving the directory
+    pub keep_tab_names: bool,
+    // Characters of ambiguous width take up two columns
+    pub cjk_width: bool,
+    // Hidden files in directory previews, None is the same as show_hidden
+    pub preview_hidden: Option<bool>,
+    pub diff_cmd: String,
+    // Only set with --read-only, blocks everything changing files
+    pub read_only: bool,
     pub keybinds: KeyBinds,
+    pub openers: Openers,
+    pub preview_filters: Openers,
+    pub load_errors: Vec<HError>,
 }
 
 
@@ -120,28 +197,108 @@ impl Config {
             media_previewer: "hunter-media".to_string(),
             media_previewer_exists: false,
             ratios: vec![20,30,49],
+            columns: 3,
+            preview_position: Placement::Right,
             graphics: detect_g_mode(),
+            proc_read_delay: 100,
+            proc_output_limit: 10 * 1024 * 1024,
+            history_size: 100,
+            previewer_timeout: 10,
+            preview_delay_ms: 0,
+            alert_on_failure: AlertOnFailure::Off,
+            dir_config: false,
+            recursive_dir_size: false,
+            editor: None,
+            editor_line_cmd: "{editor} +{line} {file}".to_string(),
+            run_executables: false,
+            run_executables_bg: false,
+            confirm_run: true,
+            confirm_quit: ConfirmQuit::Off,
+            empty_trash_on_quit: EmptyTrash::Off,
+            search_wrap: false,
+            size_units: SizeUnits::Binary,
+            size_decimal: false,
+            size_on_disk: false,
+            remember_offset: true,
+            header_info: String::new(),
+            date_format: DateFormat::default(),
+            scratch_dir: None,
+            scratch_copy: false,
+            syntax_highlight: false,
+            image_cache_size: 100,
+            small_terminal: SmallTerminal::Collapse,
+            dir_trailing_slash: TrailingSlash::Off,
+            status_line: false,
+            recent_actions_first: false,
+            keep_tab_names: false,
+            cjk_width: false,
+            preview_hidden: None,
+            diff_cmd: "diff -r".to_string(),
+            read_only: false,
             keybinds: KeyBinds::default(),
+            openers: Openers::default(),
+            preview_filters: Openers::default(),
+            load_errors: vec![],
         }
     }
 
     pub fn load() -> HResult<Config> {
-        let config_path = paths::config_path()?;
+        // System-wide, user and project config, later files override
+        let project = paths::project_layer("config");
+        let config_strings = paths::config_layers("config")
+            .into_iter()
+            .map(|path| {
+                std::fs::read_to_string(&path)
+                    .map(|config| (path, config))
+                    .map_err(HError::from)
+            })
+            .filter_map(|config| config.log_and().ok())
+            .collect::<Vec<_>>();
+
+        // Keep track of where each line came from for error messages
+        let lines = config_strings
+            .iter()
+            .flat_map(|(path, config)| {
+                config.lines()
+                      .enumerate()
+                      .map(move |(i, line)| (path, i+1, line))
+            });
+
+        let config = lines.fold(Config::new(), |mut config, (path, n, line)| {
+            // Empty lines and comments are fine
+            if line.trim().is_empty() || line.starts_with("#") {
+                return config;
+            }
 
-        if !config_path.exists() {
-            return Ok(infuse_argv_config(Config::new()));
-        }
+            // Even a trusted project doesn't get to decide what's run
+            if project.as_ref() == Some(path) {
+                match Config::prep_line(line) {
+                    Ok((option, _)) if PROJECT_IGNORED.contains(&option) => {
+                        let file = format!("{}:{}", path.to_string_lossy(), n);
+                        let error = format!("Ignored in project config: {}", option);
+                        HError::config_file_error::<()>(file, error).log();
+                        return config;
+                    }
+                    _ => {}
+                }
+            }
 
-        let config_string = std::fs::read_to_string(config_path)?;
+            let line_error = |config: &mut Config| {
+                let file = format!("{}:{}", path.to_string_lossy(), n);
+                let error = format!("Invalid line: {}", line);
+                HError::config_file_error::<()>(file, error)
+                    .log_and()
+                    .map_err(|e| config.load_errors.push(e))
+                    .ok();
+            };
 
-        let config = config_string.lines().fold(Config::new(), |mut config, line| {
             match Config::prep_line(line) {
                 Ok(("animation", "on")) => config.animation = true,
                 Ok(("animation", "off")) => config.animation = false,
                 Ok(("animation_refresh_frequency", frequency)) => {
                     match frequency.parse::<usize>() {
                         Ok(parsed_freq) => config.animation_refresh_frequency = parsed_freq,
-                        _ => HError::config_error::<Config>(line.to_string()).log()
+                        _ => line_error(&mut config)
                     }
                 }
                 Ok(("show_hidden", "on")) => config.show_hidden = true,
@@ -160,6 +317,14 @@ impl Config {
                 }
                 Ok(("media_autoplay", "on")) => config.media_autoplay = true,
                 Ok(("media_autoplay", "off")) => config.media_autoplay = false,
+                Ok(("columns", columns)) => {
+                    match columns.parse::<usize>() {
+                        Ok(columns) if columns >= 2 && columns <= 4 => {
+                            config.columns = columns;
+                        }
+                        _ => line_error(&mut config)
+                    }
+                }
                 Ok(("media_mute", "on")) => config.media_mute = true,
                 Ok(("media_mute", "off")) => config.media_mute = false,
                 Ok(("media_previewer", cmd)) => {
@@ -175,7 +340,7 @@ impl Config {
                                  .parse().unwrap())
                             .collect();
                         let ratios_sum: usize = ratios.iter().sum();
-                        if ratios.len() == 3 && ratios_sum > 0 &&
+                        if ratios.len() >= 2 && ratios.len() <= 4 && ratios_sum > 0 &&
                             ratios
                             .iter()
                             .filter(|&r| *r > u16::max_value() as usize)
@@ -189,9 +354,139 @@ impl Config {
                     "sixel")) => config.graphics = "sixel".to_string(),
                 Ok(("graphics",
                     "kitty")) => config.graphics = "kitty".to_string(),
+                Ok(("graphics",
+                    "unicode")) => config.graphics = "unicode".to_string(),
                 Ok(("graphics",
                     "auto")) => config.graphics = detect_g_mode(),
-                _ => { HError::config_error::<Config>(line.to_string()).log(); }
+                Ok(("proc_read_delay", delay)) => {
+                    match delay.parse::<u64>() {
+                        Ok(delay) => config.proc_read_delay = delay,
+                        _ => line_error(&mut config)
+                    }
+                }
+                Ok(("alert_on_failure",
+                    "off")) => config.alert_on_failure = AlertOnFailure::Off,
+                Ok(("alert_on_failure",
+                    "bell")) => config.alert_on_failure = AlertOnFailure::Bell,
+                Ok(("alert_on_failure",
+                    "flash")) => config.alert_on_failure = AlertOnFailure::Flash,
+                Ok(("alert_on_failure",
+                    "on")) => config.alert_on_failure = AlertOnFailure::On,
+                Ok(("dir_config", "on")) => config.dir_config = true,
+                Ok(("dir_config", "off")) => config.dir_config = false,
+                Ok(("recursive_dir_size", "on")) => config.recursive_dir_size = true,
+                Ok(("recursive_dir_size", "off")) => config.recursive_dir_size = false,
+                Ok(("editor", "")) => config.editor = None,
+                Ok(("editor", cmd)) => {
+                    let cmd = cmd.to_string();
+                    config.editor = Some(cmd);
+                }
+                Ok(("editor_line_cmd", "")) => line_error(&mut config),
+                Ok(("editor_line_cmd", cmd)) => config.editor_line_cmd = cmd.to_string(),
+                Ok(("run_executables", "off")) => {
+                    config.run_executables = false;
+                    config.run_executables_bg = false;
+                }
+                Ok(("run_executables", "on")) => {
+                    config.run_executables = true;
+                    config.run_executables_bg = false;
+                }
+                Ok(("run_executables", "background")) => {
+                    config.run_executables = true;
+                    config.run_executables_bg = true;
+                }
+                Ok(("confirm_run", "on")) => config.confirm_run = true,
+                Ok(("confirm_run", "off")) => config.confirm_run = false,
+                Ok(("confirm_quit", "off")) => config.confirm_quit = ConfirmQuit::Off,
+                Ok(("confirm_quit", "on")) => config.confirm_quit = ConfirmQuit::On,
+                Ok(("confirm_quit",
+                    "running")) => config.confirm_quit = ConfirmQuit::Running,
+                Ok(("empty_trash_on_quit",
+                    "off")) => config.empty_trash_on_quit = EmptyTrash::Off,
+                Ok(("empty_trash_on_quit",
+                    "on")) => config.empty_trash_on_quit = EmptyTrash::Ask,
+                Ok(("empty_trash_on_quit",
+                    "always")) => config.empty_trash_on_quit = EmptyTrash::Always,
+                Ok(("search_wrap", "on")) => config.search_wrap = true,
+                Ok(("search_wrap", "off")) => config.search_wrap = false,
+                Ok(("size_units", "binary")) => config.size_units = SizeUnits::Binary,
+                Ok(("size_units", "si")) => config.size_units = SizeUnits::Si,
+                Ok(("size_decimal", "on")) => config.size_decimal = true,
+                Ok(("size_decimal", "off")) => config.size_decimal = false,
+                Ok(("size_on_disk", "on")) => config.size_on_disk = true,
+                Ok(("size_on_disk", "off")) => config.size_on_disk = false,
+                Ok(("remember_offset", "on")) => config.remember_offset = true,
+                Ok(("remember_offset", "off")) => config.remember_offset = false,
+                Ok(("header_info", info)) => config.header_info = info.to_string(),
+                Ok(("scratch_dir", "")) => config.scratch_dir = None,
+                Ok(("scratch_dir", dir)) => config.scratch_dir = Some(dir.to_string()),
+                Ok(("scratch_copy", "on")) => config.scratch_copy = true,
+                Ok(("scratch_copy", "off")) => config.scratch_copy = false,
+                Ok(("syntax_highlight", "on")) => config.syntax_highlight = true,
+                Ok(("syntax_highlight", "off")) => config.syntax_highlight = false,
+                Ok(("image_cache_size", size)) => {
+                    match size.parse::<u64>() {
+                        Ok(size) => config.image_cache_size = size,
+                        _ => line_error(&mut config)
+                    }
+                }
+                Ok(("small_terminal",
+                    "collapse")) => config.small_terminal = SmallTerminal::Collapse,
+                Ok(("small_terminal",
+                    "message")) => config.small_terminal = SmallTerminal::Message,
+                Ok(("dir_trailing_slash",
+                    "off")) => config.dir_trailing_slash = TrailingSlash::Off,
+                Ok(("dir_trailing_slash",
+                    "on")) => config.dir_trailing_slash = TrailingSlash::Dirs,
+                Ok(("dir_trailing_slash",
+                    "classify")) => config.dir_trailing_slash = TrailingSlash::Classify,
+                Ok(("preview_position", "right")) => config.preview_position = Placement::Right,
+                Ok(("preview_position", "left")) => config.preview_position = Placement::Left,
+                Ok(("preview_position", "bottom")) => config.preview_position = Placement::Bottom,
+                Ok(("status_line", "on")) => config.status_line = true,
+                Ok(("status_line", "off")) => config.status_line = false,
+                Ok(("recent_actions_first", "on")) => config.recent_actions_first = true,
+                Ok(("recent_actions_first", "off")) => config.recent_actions_first = false,
+                Ok(("keep_tab_names", "on")) => config.keep_tab_names = true,
+                Ok(("keep_tab_names", "off")) => config.keep_tab_names = false,
+                Ok(("cjk_width", "on")) => config.cjk_width = true,
+                Ok(("cjk_width", "off")) => config.cjk_width = false,
+                Ok(("preview_hidden", "same")) => config.preview_hidden = None,
+                Ok(("preview_hidden", "on")) => config.preview_hidden = Some(true),
+                Ok(("preview_hidden", "off")) => config.preview_hidden = Some(false),
+                Ok(("diff_cmd", "")) => line_error(&mut config),
+                Ok(("diff_cmd", cmd)) => config.diff_cmd = cmd.to_string(),
+                Ok(("date_format", format)) => {
+                    match DateFormat::parse(format) {
+                        Some(format) => config.date_format = format,
+                        _ => line_error(&mut config)
+                    }
+                }
+                Ok(("preview_delay_ms", delay)) => {
+                    match delay.parse::<u64>() {
+                        Ok(delay) => config.preview_delay_ms = delay,
+                        _ => line_error(&mut config)
+                    }
+                }
+                Ok(("previewer_timeout", timeout)) => {
+                    match timeout.parse::<u64>() {
+                        Ok(timeout) => config.previewer_timeout = timeout,
+                        _ => line_error(&mut config)
+                    }
+                }
+                Ok(("history_size", size)) => {
+                    match size.parse::<usize>() {
+                        Ok(size) => config.history_size = size,
+                        _ => line_error(&mut config)
+                    }
+                }
+                Ok(("proc_output_limit", limit)) => {
+                    match limit.parse::<usize>() {
+                        Ok(limit) => config.proc_output_limit = limit,
+                        _ => line_error(&mut config)
+                    }
+                }
+                _ => line_error(&mut config)
             }
 
             #[cfg(feature = "img")]
@@ -206,14 +501,26 @@ impl Config {
 
         //use std::iter::Extend;
         KeyBinds::load()
-            .map(|kb| config.keybinds = kb)
+            .map(|(keybinds, mut errors)| {
+                config.keybinds = keybinds;
+                config.load_errors.append(&mut errors);
+            })
             .log();
 
+        let (openers, mut errors) = Openers::load();
+        config.openers = openers;
+        config.load_errors.append(&mut errors);
+
+        let (preview_filters, mut errors) = Openers::load_filters();
+        config.preview_filters = preview_filters;
+        config.load_errors.append(&mut errors);
+
         Ok(config)
     }
 
     fn prep_line<'a>(line: &'a str) -> HResult<(&'a str, &'a str)> {
-        let setting = line.split("=").collect::<Vec<&str>>();
+        // Values can contain "=", like "--color=always" in diff_cmd
+        let setting = line.splitn(2, "=").collect::<Vec<&str>>();
         if setting.len() == 2 {
             Ok((setting[0], setting[1]))
         } else {
@@ -230,12 +537,67 @@ impl Config {
         self.show_hidden
     }
 
+    pub fn preview_hidden(&self) -> bool {
+        self.preview_hidden.unwrap_or(self.show_hidden)
+    }
+
     pub fn media_available(&self) -> bool {
         self.media_previewer_exists
     }
+
+    // Configured ratios if there's one for each column
+    pub fn column_ratios(&self, columns: usize) -> Vec<usize> {
+        match columns {
+            _ if self.ratios.len() == columns => self.ratios.clone(),
+            2 => vec![30, 70],
+            4 => vec![10, 15, 30, 45],
+            _ => vec![20, 30, 49]
+        }
+    }
+
+    pub fn scratch_dir(&self) -> HResult<PathBuf> {
+        match &self.scratch_dir {
+            Some(dir) if dir.starts_with("~/") => Ok(paths::home_path()?.join(&dir[2..])),
+            Some(dir) => Ok(PathBuf::from(dir)),
+            None => paths::scratch_path()
+        }
+    }
+
+    pub fn size_format(&self) -> SizeFormat {
+        SizeFormat {
+            units: self.size_units,
+            decimal: self.size_decimal
+        }
+    }
+}
+
+// Replace or add options in the user config, everything else is left as is
+pub fn set_options(options: &[(&str, String)]) -> HResult<()> {
+    let path = paths::config_path()?;
+    let config = std::fs::read_to_string(&path).unwrap_or(String::new());
+    let mut missing = options.to_vec();
+
+    let mut lines = config.lines()
+        .map(|line| {
+            let key = line.splitn(2, "=").next().unwrap_or("");
+            match missing.iter().position(|(option, _)| *option == key) {
+                Some(i) => {
+                    let (option, value) = missing.remove(i);
+                    format!("{}={}", option, value)
+                }
+                None => line.to_string()
+            }
+        })
+        .collect::<Vec<String>>();
+
+    lines.extend(missing.iter()
+                 .map(|(option, value)| format!("{}={}", option, value)));
+
+    std::fs::write(&path, lines.join("\n") + "\n")?;
+    Ok(())
 }
 
-fn detect_g_mode() -> String {
+pub fn detect_g_mode() -> String {
     let term = std::env::var("TERM").unwrap_or(String::new());
     match term.as_str() {
         "xterm-kitty" => "kitty",
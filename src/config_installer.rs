@@ -2,12 +2,22 @@ use std::fs::*;
 use std::io::Write;
 use std::process::Command;
 use std::ffi::OsStr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::os::unix::ffi::OsStringExt;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::fail::{HError, HResult, ErrorLog};
 use crate::widget::WidgetCore;
 
 
+// Set when the default config was just installed, so the setup can run
+static FIRST_RUN: AtomicBool = AtomicBool::new(false);
+
+pub fn take_first_run() -> bool {
+    FIRST_RUN.swap(false, Ordering::SeqCst)
+}
+
 pub fn ensure_config(core: WidgetCore) -> HResult<()> {
     if has_config()? {
         let previewers_path = crate::paths::previewers_path()?;
@@ -31,8 +41,11 @@ pub fn ensure_config(core: WidgetCore) -> HResult<()> {
     }
 
     let msg = match install_config_all() {
-        Ok(_) => format!("Config installed in: {}",
-                         crate::paths::hunter_path()?.to_string_lossy()),
+        Ok(_) => {
+            FIRST_RUN.store(true, Ordering::SeqCst);
+            format!("Config installed in: {}",
+                         crate::paths::hunter_path()?.to_string_lossy())
+        }
         Err(_) => format!("{}Problems with installation of default configuration! Look inside log.",
                           crate::term::color_red()),
     };
@@ -47,7 +60,7 @@ fn default_config_archive() -> &'static [u8] {
     default_config
 }
 
-fn has_config() -> HResult<bool> {
+pub fn has_config() -> HResult<bool> {
     let config_dir = crate::paths::hunter_path()?;
 
     if config_dir.exists() {
@@ -74,9 +87,189 @@ fn install_config_all() -> HResult<()> {
     extract_archive(config_dir, &archive_path)?;
     delete_archive(archive_path)?;
 
+    record_installed(&crate::paths::previewers_path()?).log();
+    record_installed(&crate::paths::actions_path()?).log();
+
+    Ok(())
+}
+
+// Hashes of the installed defaults, so user modifications can be detected
+fn hashes_path() -> HResult<PathBuf> {
+    Ok(crate::paths::hunter_path()?.join(".default_hashes"))
+}
+
+// Symlinks are copied as is, so only the target matters
+fn file_content(path: &Path) -> HResult<Vec<u8>> {
+    match path.symlink_metadata()?.file_type().is_symlink() {
+        true => Ok(path.read_link()?.into_os_string().into_vec()),
+        false => Ok(std::fs::read(path)?)
+    }
+}
+
+fn file_hash(path: &Path) -> HResult<u64> {
+    let content = file_content(path)?;
+    Ok(crate::hash::fnv1a(crate::hash::FNV_OFFSET, &content))
+}
+
+fn parse_hashes(hashes: &str) -> Vec<(PathBuf, u64)> {
+    hashes.lines()
+       .filter_map(|line| {
+           let mut parts = line.splitn(2, " ");
+           let hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+           let path = PathBuf::from(parts.next()?);
+           Some((path, hash))
+       })
+       .collect()
+}
+
+fn load_hashes() -> HResult<HashMap<PathBuf, u64>> {
+    let hashes = std::fs::read_to_string(hashes_path()?).unwrap_or(String::new());
+    Ok(parse_hashes(&hashes).into_iter().collect())
+}
+
+// Configs installed before .default_hashes existed can still be
+// recognized as unmodified by what earlier releases shipped
+fn was_shipped(key: &Path, hash: u64) -> bool {
+    parse_hashes(include_str!("../config.hashes"))
+        .iter()
+        .any(|(path, shipped)| path == key && *shipped == hash)
+}
+
+fn same_content(a: &Path, b: &Path) -> bool {
+    match (file_content(a), file_content(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false
+    }
+}
+
+fn save_hashes(hashes: &HashMap<PathBuf, u64>) -> HResult<()> {
+    let hashes = hashes.iter()
+        .map(|(path, hash)| format!("{:016x} {}\n", hash, path.to_string_lossy()))
+        .collect::<String>();
+
+    std::fs::write(hashes_path()?, hashes)?;
+    Ok(())
+}
+
+fn record_hashes(dir: &Path, hashes: &mut HashMap<PathBuf, u64>) -> HResult<()> {
+    let hunter_dir = crate::paths::hunter_path()?;
+
+    for file in std::fs::read_dir(dir)? {
+        let file_path = file?.path();
+
+        if file_path.symlink_metadata()?.is_dir() {
+            record_hashes(&file_path, hashes).log();
+        } else {
+            let key = file_path.strip_prefix(&hunter_dir)?.to_path_buf();
+            hashes.insert(key, file_hash(&file_path)?);
+        }
+    }
+
+    Ok(())
+}
+
+fn record_installed(dir: &Path) -> HResult<()> {
+    let mut hashes = load_hashes()?;
+    record_hashes(dir, &mut hashes)?;
+    save_hashes(&hashes)
+}
+
+#[derive(Debug, PartialEq)]
+enum Change {
+    Add,
+    Overwrite,
+    Keep
+}
+
+// Only overwrites files that are still the same as the default installed before,
+// and files that are byte for byte the same as some shipped default
+fn update_unmodified(source: &Path,
+                     target: &Path,
+                     hunter_dir: &Path,
+                     hashes: &mut HashMap<PathBuf, u64>,
+                     changes: &mut Vec<(Change, PathBuf)>,
+                     dry_run: bool) -> HResult<()> {
+    for file in std::fs::read_dir(source)? {
+        let file_path = file?.path();
+        let file_name = file_path.file_name()?;
+        let target_path = target.join(file_name);
+
+        if file_path.symlink_metadata()?.is_dir() {
+            if target_path.exists() {
+                update_unmodified(&file_path,
+                                  &target_path,
+                                  hunter_dir,
+                                  hashes,
+                                  changes,
+                                  dry_run).log();
+            } else {
+                if !dry_run {
+                    copy(&file_path, target)?;
+                    record_hashes(&target_path, hashes).log();
+                }
+                changes.push((Change::Add, target_path));
+            }
+            continue;
+        }
+
+        let key = target_path.strip_prefix(hunter_dir)?.to_path_buf();
+        let new_hash = file_hash(&file_path)?;
+
+        // Already up to date
+        if same_content(&file_path, &target_path) {
+            hashes.insert(key, new_hash);
+            continue;
+        }
+
+        let change = match file_hash(&target_path) {
+            Ok(hash) if hashes.get(&key) == Some(&hash) => Change::Overwrite,
+            Ok(hash) if was_shipped(&key, hash) => Change::Overwrite,
+            Ok(_) => Change::Keep,
+            // Doesn't exist (anymore), so nothing to lose
+            Err(_) => Change::Add
+        };
+
+        if change != Change::Keep && !dry_run {
+            copy(&file_path, &target_path)?;
+            hashes.insert(key, new_hash);
+        }
+
+        changes.push((change, target_path));
+    }
+
     Ok(())
 }
 
+fn update_defaults(dry_run: bool) -> HResult<Vec<(Change, PathBuf)>> {
+    let hunter_dir = crate::paths::hunter_path()?;
+    let mut hashes = load_hashes()?;
+    let mut changes = vec![];
+
+    let archive_path = create_archive()?;
+    extract_archive(Path::new("/tmp"), &archive_path)?;
+
+    update_unmodified(Path::new("/tmp/hunter/previewers"),
+                      &crate::paths::previewers_path()?,
+                      &hunter_dir,
+                      &mut hashes,
+                      &mut changes,
+                      dry_run).log();
+    update_unmodified(Path::new("/tmp/hunter/actions"),
+                      &crate::paths::actions_path()?,
+                      &hunter_dir,
+                      &mut hashes,
+                      &mut changes,
+                      dry_run).log();
+
+    delete_archive(&archive_path)?;
+
+    if !dry_run {
+        save_hashes(&hashes)?;
+    }
+
+    Ok(changes)
+}
+
 fn copy(from: &Path, to: &Path) -> HResult<()> {
     // Uses -a flag to preserve symlinks
     let success = Command::new("cp")
@@ -100,6 +293,7 @@ fn install_config_previewers() -> HResult<()> {
     let archive_path = create_archive()?;
     extract_archive(Path::new("/tmp"), &archive_path)?;
     copy(Path::new("/tmp/hunter/previewers"), &hunter_dir)?;
+    record_installed(&crate::paths::previewers_path()?).log();
     delete_archive(&archive_path)
 }
 
@@ -108,6 +302,7 @@ fn install_config_actions() -> HResult<()> {
     let archive_path = create_archive()?;
     extract_archive(Path::new("/tmp"), &archive_path)?;
     copy(Path::new("/tmp/hunter/actions"), &hunter_dir)?;
+    record_installed(&crate::paths::actions_path()?).log();
     delete_archive(&archive_path)
 }
 
@@ -139,12 +334,26 @@ fn update_actions() -> HResult<()> {
 
 pub fn update_config(core: WidgetCore, force: bool) -> HResult<()> {
     // First install whatever might be missing, makes sure all dirs are there
-    ensure_config(core).log();
+    ensure_config(core.clone()).log();
 
-    // Just overwrite everything except core config/keys with the latest version
+    // Overwrite everything except core config/keys and files modified by the user
     if force {
-        install_config_previewers().log();
-        install_config_actions().log();
+        let skipped = update_defaults(false)?
+            .into_iter()
+            .filter(|(change, _)| *change == Change::Keep)
+            .map(|(_, path)| path)
+            .collect::<Vec<_>>();
+
+        for path in &skipped {
+            HError::log::<()>(&format!("Not updating modified config file: {}",
+                                       path.to_string_lossy())).ok();
+        }
+
+        if skipped.len() > 0 {
+            core.show_status(&format!("Kept {} modified config files, see log",
+                                      skipped.len()))?;
+        }
+
         return Ok(())
     }
 
@@ -153,6 +362,32 @@ pub fn update_config(core: WidgetCore, force: bool) -> HResult<()> {
     Ok(())
 }
 
+// Just lists what updating would do without changing anything
+pub fn update_config_dry_run() -> HResult<()> {
+    if !has_config()? {
+        println!("No configuration installed, everything would be installed in: {}",
+                 crate::paths::hunter_path()?.to_string_lossy());
+        return Ok(());
+    }
+
+    let changes = update_defaults(true)?;
+
+    if changes.len() == 0 {
+        println!("Previewers and actions are up to date");
+    }
+
+    for (change, path) in changes {
+        let change = match change {
+            Change::Add => "add",
+            Change::Overwrite => "overwrite",
+            Change::Keep => "keep (modified)"
+        };
+        println!("{:16} {}", change, path.to_string_lossy());
+    }
+
+    Ok(())
+}
+
 fn update_dir<P: AsRef<Path>>(source: P, target: P) -> HResult<()> {
     for file in std::fs::read_dir(source)? {
         let file_path = file?.path();
@@ -215,3 +450,57 @@ fn delete_archive(archive_path: &str) -> HResult<()> {
         .or_else(|_| HError::log(&format!("Deletion of archive failed! Archive: {}",
                                           archive_path)))
 }
+
+#[test]
+fn test_update_without_hashes() {
+    let tmp = crate::test_dir::TestDir::new("update");
+    let archive = Path::new(env!("CARGO_MANIFEST_DIR")).join("config.tar.gz");
+
+    // An old install without .default_hashes and the new defaults
+    for dir in &["old", "new"] {
+        std::fs::create_dir_all(tmp.join(dir)).unwrap();
+        Command::new("tar")
+            .arg("-C").arg(tmp.join(dir))
+            .arg("-xzf").arg(&archive)
+            .status()
+            .unwrap();
+    }
+
+    let hunter_dir = tmp.join("old/hunter");
+    let definitions = Path::new("previewers/definitions");
+    std::fs::write(tmp.join("new/hunter").join(definitions).join("text"), "new").unwrap();
+    std::fs::write(tmp.join("new/hunter").join(definitions).join("html"), "new").unwrap();
+    std::fs::write(hunter_dir.join(definitions).join("html"), "mine").unwrap();
+
+    let mut hashes = HashMap::new();
+    let mut changes = vec![];
+    update_unmodified(&tmp.join("new/hunter/previewers"),
+                      &hunter_dir.join("previewers"),
+                      &hunter_dir,
+                      &mut hashes,
+                      &mut changes,
+                      true).unwrap();
+    changes.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+    fn count_files(dir: &Path) -> usize {
+        std::fs::read_dir(dir).unwrap().map(|file| {
+            let path = file.unwrap().path();
+            match path.is_dir() {
+                true => count_files(&path),
+                false => 1
+            }
+        }).sum()
+    }
+
+    let shipped = count_files(&tmp.join("new/hunter/previewers"));
+    let tar_hash = file_hash(&tmp.join("new/hunter/previewers/tar")).unwrap();
+
+    assert_eq!(changes,
+               vec![(Change::Keep, hunter_dir.join(definitions).join("html")),
+                    (Change::Overwrite, hunter_dir.join(definitions).join("text"))]);
+    // It's a dry run, so only the files that were already up to date count
+    assert_eq!(hashes.len(), shipped - changes.len());
+    assert_eq!(hashes.get(Path::new("previewers/tar")), Some(&tar_hash));
+    assert_eq!(hashes.get(&definitions.join("text")), None);
+    assert_eq!(hashes.get(&definitions.join("html")), None);
+}
@@ -71,7 +71,7 @@ impl Coordinates {
     }
 
     pub fn ysize_u(&self) -> usize {
-        (self.ysize() - 1) as usize
+        self.ysize().saturating_sub(1) as usize
     }
 
     pub fn ysize(&self) -> u16 {
@@ -95,8 +95,7 @@ impl Coordinates {
     }
 
     pub fn position_u(&self) -> (usize, usize) {
-        let (xpos, ypos) = self.u16position();
-        ((xpos-1) as usize, (ypos-1) as usize)
+        self.position.position_u()
     }
 
     pub fn size(&self) -> &Size {
@@ -108,8 +107,7 @@ impl Coordinates {
     }
 
     pub fn size_u(&self) -> (usize, usize) {
-        let (xsize, ysize) = self.u16size();
-        ((xsize-1) as usize, (ysize-1) as usize)
+        self.size.size_u()
     }
 
     pub fn size_pixels(&self) -> HResult<(usize, usize)> {
@@ -136,9 +134,10 @@ impl Size {
     pub fn size(&self) -> (u16, u16) {
         self.0
     }
+    // Terminals can get tiny, so these are clamped at 0
     pub fn size_u(&self) -> (usize, usize) {
         let (xsize, ysize) = self.0;
-        ((xsize-1) as usize, (ysize-1) as usize)
+        (xsize.saturating_sub(1) as usize, ysize.saturating_sub(1) as usize)
     }
     pub fn xsize(&self) -> u16 {
         (self.0).0
@@ -154,7 +153,7 @@ impl Position {
     }
     pub fn position_u(&self) -> (usize, usize) {
         let (xpos, ypos) = self.0;
-        ((xpos-1) as usize, (ypos-1) as usize)
+        (xpos.saturating_sub(1) as usize, ypos.saturating_sub(1) as usize)
     }
     pub fn x(&self) -> u16 {
         (self.0).0
@@ -163,3 +162,21 @@ impl Position {
         (self.0).1
     }
 }
+
+
+
+#[test]
+fn test_tiny_coordinates() {
+    let coords = Coordinates::new_at(0, 0, 0, 0);
+    assert_eq!(coords.size_u(), (0, 0));
+    assert_eq!(coords.ysize_u(), 0);
+    assert_eq!(coords.position_u(), (0, 0));
+
+    let coords = Coordinates::new_at(1, 1, 1, 1);
+    assert_eq!(coords.size_u(), (0, 0));
+    assert_eq!(coords.position_u(), (0, 0));
+
+    let coords = Coordinates::new_at(80, 24, 1, 2);
+    assert_eq!(coords.size_u(), (79, 23));
+    assert_eq!(coords.position_u(), (0, 1));
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::fail::{HResult, HError};
+
+
+// Applications registered via .desktop files, see the freedesktop.org specs
+#[derive(Debug, Clone)]
+pub struct DesktopEntry {
+    pub id: String,
+    pub name: String,
+    pub exec: String,
+    pub terminal: bool,
+    pub mime_types: Vec<String>
+}
+
+type Groups = HashMap<String, HashMap<String, String>>;
+
+fn data_home() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs_2::home_dir().map(|home| home.join(".local/share")))
+}
+
+fn data_dirs() -> Vec<PathBuf> {
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or("/usr/local/share:/usr/share".to_string());
+
+    data_home()
+        .into_iter()
+        .chain(data_dirs.split(":").map(PathBuf::from))
+        .collect()
+}
+
+// In order of preference, user files first
+fn mimeapps_lists() -> Vec<PathBuf> {
+    let config_dirs = std::env::var("XDG_CONFIG_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or("/etc/xdg".to_string());
+
+    dirs_2::config_dir()
+        .into_iter()
+        .chain(config_dirs.split(":").map(PathBuf::from))
+        .chain(data_dirs().into_iter().map(|dir| dir.join("applications")))
+        .map(|dir| dir.join("mimeapps.list"))
+        .collect()
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(chr) = chars.next() {
+        match (chr, chr == '\\') {
+            (_, true) => match chars.next() {
+                Some('s') => unescaped.push(' '),
+                Some('n') => unescaped.push('\n'),
+                Some('t') => unescaped.push('\t'),
+                Some('r') => unescaped.push('\r'),
+                Some('\\') => unescaped.push('\\'),
+                // Leave it for the Exec parsing
+                Some(chr) => { unescaped.push('\\'); unescaped.push(chr); }
+                None => unescaped.push('\\')
+            }
+            (chr, false) => unescaped.push(chr)
+        }
+    }
+
+    unescaped
+}
+
+fn parse_groups(content: &str) -> Groups {
+    let mut groups = Groups::new();
+    let mut group = String::new();
+
+    for line in content.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with("#") {
+            continue;
+        }
+
+        if line.starts_with("[") && line.ends_with("]") {
+            group = line[1..line.len()-1].to_string();
+            continue;
+        }
+
+        let mut entry = line.splitn(2, "=");
+        if let (Some(key), Some(value)) = (entry.next(), entry.next()) {
+            groups.entry(group.clone())
+                  .or_insert_with(HashMap::new)
+                  .insert(key.trim().to_string(), unescape(value.trim()));
+        }
+    }
+
+    groups
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(";")
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// Exec arguments can be quoted and contain escapes inside of quotes
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut arg = String::new();
+    let mut quoted = false;
+    let mut chars = exec.chars();
+
+    while let Some(chr) = chars.next() {
+        match chr {
+            '"' => quoted = !quoted,
+            '\\' if quoted => chars.next().map(|chr| arg.push(chr)).unwrap_or(()),
+            ' ' if !quoted => {
+                if !arg.is_empty() {
+                    args.push(std::mem::replace(&mut arg, String::new()));
+                }
+            }
+            chr => arg.push(chr)
+        }
+    }
+
+    if !arg.is_empty() {
+        args.push(arg);
+    }
+
+    args
+}
+
+impl DesktopEntry {
+    fn load(id: String, path: &Path) -> Option<DesktopEntry> {
+        let content = std::fs::read_to_string(path).ok()?;
+        let mut groups = parse_groups(&content);
+        let entry = groups.remove("Desktop Entry")?;
+        let is_set = |key| entry.get(key).map(|val| val == "true").unwrap_or(false);
+
+        if entry.get("Type")? != "Application" ||
+            is_set("Hidden") ||
+            is_set("NoDisplay") {
+            return None;
+        }
+
+        Some(DesktopEntry {
+            id: id,
+            name: entry.get("Name")?.clone(),
+            exec: entry.get("Exec")?.clone(),
+            terminal: is_set("Terminal"),
+            mime_types: entry.get("MimeType")
+                             .map(|types| split_list(types))
+                             .unwrap_or(vec![])
+        })
+    }
+
+    // One command, or one per file if the app only takes a single file
+    pub fn commands(&self, files: &[PathBuf]) -> Vec<Vec<OsString>> {
+        let args = split_exec(&self.exec);
+        let single = args.iter().any(|arg| arg == "%f" || arg == "%u");
+        let multi = args.iter().any(|arg| arg == "%F" || arg == "%U");
+
+        let build = |files: &[PathBuf]| {
+            let mut cmd = args.iter()
+                .flat_map(|arg| {
+                    match arg.as_str() {
+                        "%f" | "%u" | "%F" | "%U" => {
+                            files.iter().map(OsString::from).collect()
+                        }
+                        "%c" => vec![OsString::from(&self.name)],
+                        "%i" | "%k" => vec![],
+                        arg => vec![OsString::from(arg.replace("%%", "%"))]
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            // No field codes, files go at the end
+            if !single && !multi {
+                cmd.extend(files.iter().map(OsString::from));
+            }
+
+            cmd
+        };
+
+        match single && !multi {
+            true => files.iter()
+                         .map(|file| build(std::slice::from_ref(file)))
+                         .collect(),
+            false => vec![build(files)]
+        }
+    }
+}
+
+fn collect_entries(dir: &Path,
+                   prefix: &str,
+                   entries: &mut HashMap<String, DesktopEntry>) -> HResult<()> {
+    for file in std::fs::read_dir(dir)? {
+        let path = file?.path();
+        let name = path.file_name()?.to_string_lossy().to_string();
+
+        if path.is_dir() {
+            let prefix = format!("{}{}-", prefix, name);
+            collect_entries(&path, &prefix, entries).ok();
+            continue;
+        }
+
+        if !name.ends_with(".desktop") {
+            continue;
+        }
+
+        // Files from earlier directories take precedence
+        let id = format!("{}{}", prefix, name);
+        if entries.contains_key(&id) {
+            continue;
+        }
+
+        if let Some(entry) = DesktopEntry::load(id.clone(), &path) {
+            entries.insert(id, entry);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn load_entries() -> HashMap<String, DesktopEntry> {
+    let mut entries = HashMap::new();
+
+    for dir in data_dirs() {
+        collect_entries(&dir.join("applications"), "", &mut entries).ok();
+    }
+
+    entries
+}
+
+// Default and added applications first, removed ones are left out
+fn associations(mime: &str) -> (Vec<String>, Vec<String>) {
+    let mut preferred = vec![];
+    let mut removed = vec![];
+
+    for list in mimeapps_lists() {
+        let groups = match std::fs::read_to_string(&list) {
+            Ok(content) => parse_groups(&content),
+            Err(_) => continue
+        };
+
+        let apps = |group: &str| {
+            groups.get(group)
+                  .and_then(|group| group.get(mime))
+                  .map(|apps| split_list(apps))
+                  .unwrap_or(vec![])
+        };
+
+        preferred.extend(apps("Default Applications"));
+        preferred.extend(apps("Added Associations"));
+        removed.extend(apps("Removed Associations"));
+    }
+
+    (preferred, removed)
+}
+
+pub fn apps_for_mime(mime: &str) -> HResult<Vec<DesktopEntry>> {
+    let mut entries = load_entries();
+
+    if entries.len() == 0 {
+        return HError::log("Couldn't find any .desktop files");
+    }
+
+    let (preferred, removed) = associations(mime);
+
+    let mut registered = entries.values()
+        .filter(|entry| entry.mime_types.iter().any(|m| m == mime))
+        .collect::<Vec<_>>();
+    registered.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let ids = preferred.into_iter()
+        .chain(registered.into_iter().map(|entry| entry.id.clone()))
+        .collect::<Vec<_>>();
+
+    let apps = ids.into_iter()
+        .filter(|id| !removed.contains(id))
+        .filter_map(|id| entries.remove(&id))
+        .collect::<Vec<_>>();
+
+    Ok(apps)
+}
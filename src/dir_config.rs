@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use crate::files::{Files, SortBy};
+use crate::fail::{HResult, HError, ErrorLog};
+
+
+// Parsed .hunter files along with their mtime, so changes get picked up
+lazy_static! {
+    static ref DIR_CONFIGS: RwLock<HashMap<PathBuf, (Option<SystemTime>, DirConfig)>>
+        = RwLock::new(HashMap::new());
+}
+
+pub const TEMPLATE: &str = "\
+# Settings for this directory, uncomment to use
+# sort=name
+# reverse=off
+# dirs_first=on
+# show_hidden=off
+# filter=
+# ignore=*.o, *.tmp
+";
+
+pub fn dir_config_path(dir: &Path) -> PathBuf {
+    let mut path = dir.join(".hunter");
+
+    // Projects can have a .hunter/ directory with config/keys in it
+    if path.is_dir() {
+        path.push("dir");
+    }
+
+    path
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DirConfig {
+    pub sort: Option<SortBy>,
+    pub reverse: Option<bool>,
+    pub dirs_first: Option<bool>,
+    pub show_hidden: Option<bool>,
+    pub filter: Option<String>,
+    pub ignore: Vec<String>
+}
+
+impl DirConfig {
+    pub fn load(dir: &Path) -> HResult<DirConfig> {
+        let path = dir_config_path(dir);
+        let mtime = std::fs::metadata(&path)?.modified().ok();
+
+        if let Some((cached_mtime, config)) = DIR_CONFIGS.read()?.get(&path) {
+            if *cached_mtime == mtime {
+                return Ok(config.clone());
+            }
+        }
+
+        let config = DirConfig::parse(&std::fs::read_to_string(&path)?);
+        DIR_CONFIGS.write()?.insert(path, (mtime, config.clone()));
+
+        Ok(config)
+    }
+
+    fn parse(config_string: &str) -> DirConfig {
+        config_string.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with("#"))
+            .fold(DirConfig::default(), |mut config, line| {
+                let mut setting = line.splitn(2, "=");
+
+                match (setting.next(), setting.next()) {
+                    (Some("sort"), Some("name")) => config.sort = Some(SortBy::Name),
+                    (Some("sort"), Some("extension")) => {
+                        config.sort = Some(SortBy::Extension)
+                    }
+                    (Some("sort"), Some("size")) => config.sort = Some(SortBy::Size),
+                    (Some("sort"), Some("mtime")) => config.sort = Some(SortBy::MTime),
+                    (Some("reverse"), Some("on")) => config.reverse = Some(true),
+                    (Some("reverse"), Some("off")) => config.reverse = Some(false),
+                    (Some("dirs_first"), Some("on")) => config.dirs_first = Some(true),
+                    (Some("dirs_first"), Some("off")) => config.dirs_first = Some(false),
+                    (Some("show_hidden"), Some("on")) => config.show_hidden = Some(true),
+                    (Some("show_hidden"), Some("off")) => config.show_hidden = Some(false),
+                    (Some("filter"), Some(filter)) => {
+                        config.filter = Some(filter.to_string());
+                    }
+                    (Some("ignore"), Some(patterns)) => {
+                        let patterns = patterns.split(",")
+                                               .map(|pat| pat.trim())
+                                               .filter(|pat| !pat.is_empty())
+                                               .map(String::from);
+                        config.ignore.extend(patterns);
+                    }
+                    _ => { HError::config_error::<()>(line.to_string()).log(); }
+                }
+
+                config
+            })
+    }
+
+    pub fn apply(&self, files: &mut Files) {
+        self.sort.map(|sort| files.sort = sort);
+        self.reverse.map(|reverse| files.reverse = reverse);
+        self.dirs_first.map(|dirs_first| files.dirs_first = dirs_first);
+        self.show_hidden.map(|show_hidden| files.show_hidden = show_hidden);
+
+        files.ignore = self.ignore.clone();
+
+        // Takes care of recounting files and adding a placeholder if needed
+        let filter = self.filter.clone().or_else(|| files.filter.clone());
+        files.set_filter(filter).log();
+    }
+}
@@ -0,0 +1,366 @@
+use termion::event::Key;
+use parking_lot::Mutex;
+use async_value::Stale;
+
+use std::collections::{HashMap, HashSet};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::listview::{JobResults, Listable, ListView};
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::hash::{hash_file, same_content};
+use crate::keybind::{Acting, Bindings, DuplicateAction, Movement};
+use crate::widget::Widget;
+use crate::dirty::Dirtyable;
+use crate::term;
+
+
+// Files with the same content, sorted by path
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub size: u64,
+    pub hash: u64,
+    pub paths: Vec<PathBuf>
+}
+
+impl Group {
+    // What removing all but one of them frees up
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+// How far the scan has come, the groups are there once it's done
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub hashed: AtomicUsize,
+    pub total: AtomicUsize,
+    pub groups: Mutex<Option<Vec<Group>>>
+}
+
+// Only files of the same size get hashed. Hard links to the same file
+// aren't duplicates, removing one doesn't free anything.
+pub fn find_duplicates(paths: Vec<PathBuf>,
+                       scan: &Scan,
+                       stale: &Stale) -> HResult<Vec<Group>> {
+    let mut inodes = HashSet::new();
+    let mut by_size: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+
+    for path in paths {
+        let meta = match path.symlink_metadata() {
+            Ok(meta) => meta,
+            Err(_) => continue
+        };
+
+        if !meta.is_file() || meta.len() == 0 {
+            continue;
+        }
+
+        if inodes.insert((meta.dev(), meta.ino())) {
+            by_size.entry(meta.len()).or_default().push(path);
+        }
+    }
+
+    by_size.retain(|_, paths| paths.len() > 1);
+
+    let total = by_size.values().map(|paths| paths.len()).sum();
+    scan.total.store(total, Ordering::Relaxed);
+
+    let mut by_hash: HashMap<(u64, u64), Vec<PathBuf>> = HashMap::new();
+
+    for (size, paths) in by_size {
+        for path in paths {
+            match hash_file(&path, stale) {
+                Ok(hash) => by_hash.entry((size, hash)).or_default().push(path),
+                Err(HError::StaleError) => return Err(HError::StaleError),
+                // Unreadable files can't be compared
+                err @ Err(_) => { err.log(); }
+            }
+
+            scan.hashed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let mut groups = vec![];
+
+    for ((size, hash), paths) in by_hash {
+        if paths.len() < 2 { continue; }
+
+        // Same hash usually means same content, but not always
+        let mut same: Vec<Vec<PathBuf>> = vec![];
+
+        'paths: for path in paths {
+            for group in same.iter_mut() {
+                match same_content(&group[0], &path, stale) {
+                    Ok(true) => { group.push(path); continue 'paths; }
+                    Ok(false) => {}
+                    Err(HError::StaleError) => return Err(HError::StaleError),
+                    err @ Err(_) => { err.log(); continue 'paths; }
+                }
+            }
+            same.push(vec![path]);
+        }
+
+        groups.extend(same.into_iter()
+                      .filter(|paths| paths.len() > 1)
+                      .map(|mut paths| {
+                          paths.sort();
+                          Group { size, hash, paths }
+                      }));
+    }
+
+    // The ones taking up the most space first
+    groups.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then(a.paths.cmp(&b.paths)));
+
+    Ok(groups)
+}
+
+
+// What the FileBrowser should do after the popup is closed
+#[derive(Debug, Clone, PartialEq)]
+pub enum DuplicatePick {
+    Goto(PathBuf),
+    // All but the first file of each group
+    SelectCopies(Vec<PathBuf>)
+}
+
+// A group's header line or one of its files
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Line {
+    Group(usize),
+    File(usize, usize)
+}
+
+#[derive(Debug)]
+pub struct DuplicateList {
+    pub scan: std::sync::Arc<Scan>,
+    pub picked: Option<DuplicatePick>,
+    // Paths are shown relative to it
+    dir: PathBuf,
+    groups: Option<Vec<Group>>,
+    lines: Vec<Line>
+}
+
+impl PartialEq for DuplicateList {
+    fn eq(&self, other: &DuplicateList) -> bool {
+        self.dir == other.dir && self.groups == other.groups
+    }
+}
+
+impl DuplicateList {
+    pub fn new(scan: std::sync::Arc<Scan>, dir: PathBuf) -> DuplicateList {
+        DuplicateList {
+            scan: scan,
+            picked: None,
+            dir: dir,
+            groups: None,
+            lines: vec![]
+        }
+    }
+
+    fn take_groups(&mut self) -> bool {
+        if self.groups.is_some() { return false; }
+
+        let groups = match self.scan.groups.lock().take() {
+            Some(groups) => groups,
+            None => return false
+        };
+
+        self.lines = groups.iter()
+            .enumerate()
+            .flat_map(|(i, group)| {
+                std::iter::once(Line::Group(i))
+                    .chain((0..group.paths.len()).map(move |j| Line::File(i, j)))
+            })
+            .collect();
+        self.groups = Some(groups);
+        true
+    }
+}
+
+impl JobResults for DuplicateList {
+    fn is_done(&self) -> bool {
+        self.groups.is_some()
+    }
+}
+
+impl Listable for ListView<DuplicateList> {
+    type Item = ();
+
+    fn len(&self) -> usize {
+        self.content.lines.len()
+    }
+
+    fn render(&self) -> Vec<String> {
+        let groups = match &self.content.groups {
+            Some(groups) => groups,
+            None => return vec![]
+        };
+
+        let (xsize, ysize) = self.core.coordinates.size_u();
+        let size_format = self.core.config().size_format();
+
+        // Only what's on screen, there can be a lot of them
+        self.content
+            .lines
+            .iter()
+            .skip(self.offset)
+            .take(ysize)
+            .map(|line| match *line {
+                Line::Group(i) => {
+                    let group = &groups[i];
+                    let line = format!("{} files of {}, {:016x}",
+                                       group.paths.len(),
+                                       size_format.format(group.size),
+                                       group.hash);
+                    format!("{}{}",
+                            term::highlight_color(),
+                            term::sized_string_u(&line, xsize))
+                }
+                Line::File(i, j) => {
+                    let path = &groups[i].paths[j];
+                    let path = path.strip_prefix(&self.content.dir)
+                                   .unwrap_or(path)
+                                   .to_string_lossy();
+                    format!("{}{}",
+                            term::normal_color(),
+                            term::sized_string_u(&format!("  {}", path), xsize))
+                }
+            })
+            .collect()
+    }
+
+    fn render_header(&self) -> HResult<String> {
+        match &self.content.groups {
+            Some(groups) if groups.is_empty() => Ok(String::from("No duplicates found")),
+            Some(groups) => {
+                let wasted = groups.iter().map(|group| group.wasted()).sum();
+                Ok(format!("Duplicates: {} groups, {} in extra copies",
+                           groups.len(),
+                           self.core.config().size_format().format(wasted)))
+            }
+            None => Ok(format!("Looking for duplicates{} {}/{}",
+                               crate::files::tick_str(),
+                               self.content.scan.hashed.load(Ordering::Relaxed),
+                               self.content.scan.total.load(Ordering::Relaxed)))
+        }
+    }
+
+    fn render_footer(&self) -> HResult<String> {
+        Ok(String::from("Enter: go there, s: select all but the first of each, Esc: close"))
+    }
+
+    fn on_refresh(&mut self) -> HResult<()> {
+        if self.content.take_groups() {
+            self.core.set_dirty();
+        }
+        Ok(())
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        self.do_key(key)?;
+        self.refresh().log();
+        self.draw()
+    }
+}
+
+impl ListView<DuplicateList> {
+    fn pick(&mut self, picked: DuplicatePick) -> HResult<()> {
+        self.content.picked = Some(picked);
+        self.popup_finnished()
+    }
+
+    fn goto_file(&mut self) -> HResult<()> {
+        let groups = match &self.content.groups {
+            Some(groups) => groups,
+            None => return self.core.show_status("Still looking for duplicates")
+        };
+
+        let path = match self.content.lines.get(self.get_selection()) {
+            Some(Line::Group(i)) => groups[*i].paths[0].clone(),
+            Some(Line::File(i, j)) => groups[*i].paths[*j].clone(),
+            None => return Ok(())
+        };
+
+        self.pick(DuplicatePick::Goto(path))
+    }
+
+    fn select_copies(&mut self) -> HResult<()> {
+        let copies = match &self.content.groups {
+            Some(groups) => groups.iter()
+                                  .flat_map(|group| group.paths.iter().skip(1))
+                                  .cloned()
+                                  .collect(),
+            None => return self.core.show_status("Still looking for duplicates")
+        };
+
+        self.pick(DuplicatePick::SelectCopies(copies))
+    }
+}
+
+impl Acting for ListView<DuplicateList> {
+    type Action = DuplicateAction;
+
+    fn search_in(&self) -> Bindings<Self::Action> {
+        self.core.config().keybinds.duplicates
+    }
+
+    fn movement(&mut self, movement: &Movement) -> HResult<()> {
+        use Movement::*;
+
+        // Nothing to move through until the scan is done
+        if self.len() == 0 {
+            return Ok(());
+        }
+
+        match movement {
+            Up(n) => for _ in 0..*n { self.move_up(); },
+            Down(n) => for _ in 0..*n { self.move_down(); },
+            PageUp => self.page_up(),
+            PageDown => self.page_down(),
+            Top => self.move_top(),
+            Bottom => self.move_bottom(),
+            Right => self.goto_file()?,
+            Left => {}
+        }
+
+        Ok(())
+    }
+
+    fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
+        use DuplicateAction::*;
+
+        match action {
+            Close => self.popup_finnished(),
+            Goto => self.goto_file(),
+            SelectCopies => self.select_copies()
+        }
+    }
+}
+
+#[test]
+fn test_find_duplicates() {
+    let dir = crate::test_dir::TestDir::new("dupes");
+    std::fs::create_dir(dir.join("sub")).unwrap();
+
+    std::fs::write(dir.join("a"), "same").unwrap();
+    std::fs::write(dir.join("sub/b"), "same").unwrap();
+    // Same size, different content
+    std::fs::write(dir.join("c"), "diff").unwrap();
+    std::fs::write(dir.join("empty"), "").unwrap();
+    std::fs::write(dir.join("empty2"), "").unwrap();
+    std::fs::hard_link(dir.join("a"), dir.join("link")).unwrap();
+
+    let paths = ["a", "sub/b", "c", "empty", "empty2", "link"]
+        .iter()
+        .map(|name| dir.join(name))
+        .collect();
+    let scan = Scan::default();
+    let groups = find_duplicates(paths, &scan, &Stale::new()).unwrap();
+
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].paths, vec![dir.join("a"), dir.join("sub/b")]);
+    assert_eq!(groups[0].wasted(), 4);
+    assert_eq!(scan.total.load(Ordering::Relaxed), 3);
+}
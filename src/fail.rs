@@ -35,12 +35,16 @@ pub enum HError {
     ChannelRecvTimeout(#[cause] std::sync::mpsc::RecvTimeoutError),
     #[fail(display = "Previewer failed on file: {}", file)]
     PreviewFailed{file: String},
+    #[fail(display = "Previewer timed out on file: {}", _0)]
+    PreviewerTimedOut(String),
     #[fail(display = "StalePreviewer for file: {}", file)]
     StalePreviewError{file: String},
     #[fail(display = "Accessed stale value")]
     StaleError,
     #[fail(display = "Failed: {}", _0)]
     Error(String),
+    #[fail(display = "Invalid filter: {}", _0)]
+    FilterError(String),
     #[fail(display = "Was None!")]
     NoneError,
     #[fail(display = "Async Error: {}", _0)]
@@ -85,6 +89,8 @@ pub enum HError {
     WidgetNoFilesError,
     #[fail(display = "Invalid line in settings file: {}", _0)]
     ConfigLineError(String),
+    #[fail(display = "Error in {}: {}", file, error)]
+    ConfigFileError{ file: String, error: String },
     #[fail(display = "New input in Minibuffer")]
     MiniBufferInputUpdated(String),
     #[fail(display = "Failed to parse into UTF8")]
@@ -101,6 +107,14 @@ pub enum HError {
     KeyBind(KeyBindError),
     #[fail(display = "FileBrowser needs to know about all tab's files to run exec!")]
     FileBrowserNeedTabFiles,
+    #[fail(display = "FileBrowser needs to know about the other tab to {} files!", _0)]
+    FileBrowserNeedTabDirs(crate::file_ops::FileOp),
+    #[fail(display = "FileBrowser needs the files selected in another tab!")]
+    FileBrowserNeedTabSelection,
+    #[fail(display = "FileBrowser needs a new tab to open {:?}!", _0)]
+    FileBrowserNeedNewTab(PathBuf),
+    #[fail(display = "FileBrowser needs the tab view to run {}!", _0)]
+    FileBrowserNeedTabAction(crate::keybind::TabAction),
     #[fail(display = "{}", _0)]
     FileError(crate::files::FileError),
     #[fail(display = "{}", _0)]
@@ -165,6 +179,10 @@ impl HError {
         Err(HError::ConfigLineError(line))
     }
 
+    pub fn config_file_error<T>(file: String, error: String) -> HResult<T> {
+        Err(HError::ConfigFileError{ file: file, error: error })
+    }
+
     pub fn metadata_processed<T>() -> HResult<T> {
         Err(HError::MetadataProcessedError)
     }
@@ -278,6 +296,13 @@ impl From<std::io::Error> for HError {
     }
 }
 
+impl From<regex::Error> for HError {
+    fn from(error: regex::Error) -> Self {
+        let err = HError::FilterError(format!("{}", error));
+        err
+    }
+}
+
 impl From<failure::Error> for HError {
     fn from(error: failure::Error) -> Self {
         let err = HError::Error(format!("{}", error));
@@ -397,6 +422,8 @@ pub enum MimeError {
     NoMimeFound,
     #[fail(display = "Paniced while trying to find MIME type for: {}!", _0)]
     Panic(String),
+    #[fail(display = "{} is a {}, it has no MIME type", _0, _1)]
+    SpecialFile(String, crate::files::FileType),
 }
 
 impl From<MimeError> for HError {
@@ -434,7 +461,9 @@ pub enum KeyBindError {
     #[fail(display = "Couldn't parse as either char or u8: {}", _0)]
     CharOrNumParseError(String),
     #[fail(display = "Wanted {}, but got {}!", _0, _1)]
-    CharOrNumWrongType(String, String)
+    CharOrNumWrongType(String, String),
+    #[fail(display = "Nothing to repeat")]
+    NothingToRepeat
 
 }
 
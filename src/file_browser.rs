@@ -7,29 +7,53 @@ use async_value::Stale;
 
 use std::io::Write;
 use std::sync::Arc;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::ffi::OsString;
 use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::OpenOptionsExt;
 use std::collections::HashSet;
 
-use crate::files::{File, Files};
+use crate::files::{File, Files, Kind};
 use crate::fscache::FsCache;
-use crate::listview::{ListView, FileSource};
+use crate::listview::{ListView, JobResults, FileSource};
 use crate::hbox::HBox;
 use crate::widget::Widget;
 use crate::tabview::{TabView, Tabbable};
 use crate::preview::{Previewer, AsyncWidget};
 use crate::textview::TextView;
-use crate::fail::{HResult, HError, ErrorLog};
+use crate::fail::{HResult, HError, ErrorLog, KeyBindError};
 use crate::widget::{Events, WidgetCore};
 use crate::proclist::ProcView;
-use crate::bookmarks::BMPopup;
+use crate::bookmarks::{BMPopup, Bookmark};
 use crate::term;
 use crate::term::ScreenExt;
 use crate::foldview::LogView;
 use crate::coordinates::Coordinates;
 use crate::dirty::Dirtyable;
 use crate::stats::{FsStat, FsExt};
+use crate::dir_config::DirConfig;
+use crate::file_info::FileInfoView;
+use crate::keybind_editor::KeyBindEditor;
+use crate::frecency::FrecentList;
+use crate::file_ops::{FileJob, FileOp};
+
+// When quitting asks first
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ConfirmQuit {
+    Off,
+    On,
+    // Only while processes or file jobs are running
+    Running
+}
+
+// Whether the trash is emptied when quitting
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EmptyTrash {
+    Off,
+    // Asks the first time
+    Ask,
+    Always
+}
 
 #[derive(PartialEq)]
 pub enum FileBrowserWidgets {
@@ -88,6 +112,10 @@ pub struct FileBrowser {
     pub columns: HBox<FileBrowserWidgets>,
     pub cwd: File,
     pub prev_cwd: Option<File>,
+    // Set with RenameTab, together with the directory it was given in
+    pub tab_name: Option<(String, File)>,
+    // Percent of the space GrowPreview/ShrinkPreview gave to the previewer
+    pub preview_shift: isize,
     core: WidgetCore,
     proc_view: Arc<Mutex<ProcView>>,
     bookmarks: Arc<Mutex<BMPopup>>,
@@ -115,6 +143,9 @@ impl Tabbable for TabView<FileBrowser> {
         tab.bookmarks = bookmarks;
         tab.log_view  = log_view;
         tab.fs_stat = cur_tab.fs_stat.clone();
+        if cur_tab.preview_shift != 0 {
+            tab.resize_preview(cur_tab.preview_shift).log();
+        }
 
         self.push_widget(tab)?;
         self.active = self.widgets.len() - 1;
@@ -140,8 +171,31 @@ impl Tabbable for TabView<FileBrowser> {
         self.goto_tab_(index)
     }
 
+    fn rename_tab(&mut self) -> HResult<()> {
+        use crate::minibuffer::MiniBufferEvent::Empty;
+
+        let name = self.get_tab_names()
+                       .remove(self.active)
+                       .unwrap_or_default();
+
+        // Entering nothing goes back to the directory's name
+        let name = match self.core.minibuffer_prefilled("tab name", &name, name.len()) {
+            Ok(name) => Some(name),
+            Err(HError::MiniBufferEvent(Empty)) => None,
+            Err(_) => return Ok(())
+        };
+
+        let tab = self.active_tab_mut();
+        tab.tab_name = name.map(|name| (name, tab.cwd.clone()));
+        Ok(())
+    }
+
     fn get_tab_names(&self) -> Vec<Option<String>> {
         self.widgets.iter().map(|filebrowser| {
+            if let Some((name, _)) = &filebrowser.tab_name {
+                return Some(name.clone());
+            }
+
             let path = filebrowser.cwd.path();
             let last_dir = path.components().last().unwrap();
             let dir_name = last_dir.as_os_str().to_string_lossy().to_string();
@@ -176,6 +230,37 @@ impl Tabbable for TabView<FileBrowser> {
 
                 self.widgets[self.active].exec_cmd(tab_dirs, selected_files)
             }
+            // Copy/move go to the next tab's directory by default
+            Err(HError::FileBrowserNeedTabDirs(op)) => {
+                let other_dir = match self.widgets.len() {
+                    1 => None,
+                    len => Some(self.widgets[(self.active + 1) % len].cwd.clone())
+                };
+
+                self.widgets[self.active].file_op(op, other_dir)
+            }
+            // Links go to the files selected in the next tab that has some
+            Err(HError::FileBrowserNeedTabSelection) => {
+                let len = self.widgets.len();
+                let sources = (1..len)
+                    .map(|i| &self.widgets[(self.active + i) % len])
+                    .filter_map(|tab| tab.selected_files().ok())
+                    .find(|files| files.len() > 0);
+
+                self.widgets[self.active].symlink_files(sources)
+            }
+            // Tab actions picked from the command palette
+            Err(HError::FileBrowserNeedTabAction(action)) => self.do_action(&action),
+            // Directories are entered, files get selected in their directory
+            Err(HError::FileBrowserNeedNewTab(path)) => {
+                let file = File::new_from_path(&path)?;
+                self.new_tab()?;
+
+                match file.is_dir() {
+                    true => self.active_tab_mut().main_widget_goto(&file),
+                    false => self.active_tab_mut().goto_file(&file)
+                }
+            }
             result @ _ => result
         }
     }
@@ -185,6 +270,9 @@ impl Tabbable for TabView<FileBrowser> {
             .iter()
             .fold(HashSet::new(), |mut dirs, tab| {
                 tab.left_dir().map(|dir| dirs.insert(dir.clone())).ok();
+                tab.grandparent_widget()
+                    .map(|w| dirs.insert(w.content.directory.clone()))
+                    .ok();
                 dirs.insert(tab.cwd.clone());
                 tab.preview_widget()
                     .map(|preview| preview.get_file().map(|file| {
@@ -209,6 +297,9 @@ impl Tabbable for TabView<FileBrowser> {
 
     fn on_config_loaded(&mut self) -> HResult<()> {
         let show_hidden = self.core.config().show_hidden();
+        crate::files::set_dir_bytes(self.core.config().recursive_dir_size);
+        crate::files::set_size_on_disk(self.core.config().size_on_disk);
+        crate::term::set_cjk_width(self.core.config().cjk_width);
 
         for tab in self.widgets.iter_mut() {
             tab.left_async_widget_mut().map(|async_w| {
@@ -239,19 +330,210 @@ impl Tabbable for TabView<FileBrowser> {
                 }).log()
             }).log();
 
+            tab.grandparent_async_widget_mut().map(|async_w| {
+                async_w.widget.on_ready(move |mut w, _| {
+                    w.as_mut()
+                     .map(|w| {
+                         if w.content.show_hidden != show_hidden {
+                             w.content.show_hidden = show_hidden;
+                             w.content.recalculate_len();
+                             w.refresh().log();
+                         }
+                     }).ok();
+                    Ok(())
+                }).log()
+            }).ok();
+
             tab.preview_widget_mut().map(|w| w.config_loaded()).ok();
-            tab.columns.set_ratios(self.core.config().ratios);
+            let ratios = layout_ratios(&self.core.config(),
+                                       tab.columns.widgets.len(),
+                                       tab.preview_shift);
+            tab.columns.set_ratios(ratios);
+
+            let placement = self.core.config().preview_position;
+            if tab.columns.placement != placement {
+                tab.columns.placement = placement;
+                // Nothing draws over where the gaps are now
+                tab.columns.core.clear().log();
+            }
         }
+
+        // Don't let broken config files go unnoticed, errors are in the log
+        if self.core.config().load_errors.len() > 0 {
+            self.core.show_status("Errors in configuration, defaults were used!")?;
+            self.active_tab_mut_().show_log()?;
+        }
+
+        if crate::config_installer::take_first_run() {
+            self.active_tab_mut_().setup_wizard()?;
+        }
+
         Ok(())
     }
+
+    fn on_dump_view(&self) -> HResult<()> {
+        self.active_tab().dump_view()
+    }
+}
+
+
+
+
+
+
+
+
+// Written in the background, it's not worth waiting for
+fn record_visit(dir: &File) {
+    let path = dir.path.clone();
+    std::thread::spawn(move || {
+        crate::frecency::add_visit(&path).log();
+    });
+}
+
+// Most editors understand "+line", like vi, emacs, nano and kakoune, so
+// that's what "line_cmd" is by default. Without a line only the file is
+// passed, and it goes at the end when "line_cmd" has no {file}.
+fn editor_cmd(editor: &str,
+              line_cmd: &str,
+              path: &Path,
+              line: Option<usize>) -> Vec<OsString> {
+    // $EDITOR might contain arguments like "emacsclient -t"
+    let editor = editor.split_whitespace().map(OsString::from);
+
+    let line = match line {
+        Some(line) => line.to_string(),
+        None => return editor.chain(std::iter::once(path.into())).collect()
+    };
+
+    let mut cmd = line_cmd.split_whitespace()
+        .flat_map(|part| match part {
+            "{editor}" => editor.clone().collect(),
+            // Not going through a String keeps odd file names intact
+            "{file}" => vec![path.into()],
+            part => {
+                let part = part.replace("{line}", &line)
+                               .replace("{file}", &path.to_string_lossy());
+                vec![OsString::from(part)]
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if !line_cmd.contains("{file}") {
+        cmd.push(path.into());
+    }
+
+    cmd
+}
+
+// Single quotes keep everything as it is, except for single quotes
+// themselves, which have to end the quoting and get escaped
+fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "/._-+,:@%".contains(c);
+
+    match !arg.is_empty() && arg.chars().all(plain) {
+        true => arg.to_string(),
+        false => format!("'{}'", arg.replace("'", "'\\''"))
+    }
+}
+
+// Where to go when a directory is deleted while hunter is in it
+fn nearest_existing_dir(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|dir| dir.is_dir())
 }
 
+// The cursor goes where the deleted directory was, on the entry after it
+fn deleted_cwd_target(gone: &Path) -> Option<(PathBuf, Option<PathBuf>)> {
+    let dir = nearest_existing_dir(gone)?;
+    let deleted = gone.strip_prefix(dir).ok()?.iter().next()?;
+
+    let mut entries = std::fs::read_dir(dir).ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let selection = entries.iter()
+        .find(|path| path.file_name() > Some(deleted))
+        .or(entries.last())
+        .cloned();
+
+    Some((dir.to_path_buf(), selection))
+}
 
+// Moves "shift" percent of the space to or from the previewer, the other
+// columns keep their proportions
+fn shift_preview(ratios: Vec<usize>, shift: isize) -> Vec<usize> {
+    let (preview, others) = match ratios.split_last() {
+        Some((&preview, others)) if preview > 0 && shift != 0 => (preview, others),
+        _ => return ratios
+    };
+
+    let others_sum: usize = others.iter().sum();
+    let sum = others_sum + preview;
+    if others_sum == 0 { return ratios; }
+
+    // In thousandths, so the others don't lose too much to rounding
+    let preview = (preview * 1000 / sum) as isize + shift * 10;
+    let preview = preview.max(1).min(999) as usize;
+
+    // A column getting 0 would count as hidden
+    others.iter()
+          .map(|&ratio| match ratio {
+              0 => 0,
+              _ => (ratio * (1000 - preview) / others_sum).max(1)
+          })
+          .chain(std::iter::once(preview))
+          .collect()
+}
 
+// Ratios for all widgets, a hidden previewer gets 0
+fn layout_ratios(config: &crate::config::Config,
+                 widget_count: usize,
+                 preview_shift: isize) -> Vec<usize> {
+    let ratios = match (widget_count, config.columns) {
+        (4, _) => config.column_ratios(4),
+        (_, 2) => {
+            let mut ratios = config.column_ratios(2);
+            ratios.push(0);
+            ratios
+        }
+        _ => config.column_ratios(3)
+    };
 
+    shift_preview(ratios, preview_shift)
+}
 
+// A file list for a parent directory, or an empty one above /
+fn dir_column(core: WidgetCore,
+              dir: Option<PathBuf>,
+              cache: FsCache) -> FileBrowserWidgets {
+    if let Some(dir) = dir {
+        let widget = AsyncWidget::new(&core.clone(), move |stale| {
+            let dir = File::new_from_path(&dir)?;
+            let source = FileSource::Path(dir);
+            ListView::builder(core, source)
+                .with_cache(cache)
+                .with_stale(stale.clone())
+                .build()
+        });
+        FileBrowserWidgets::FileList(widget)
+    } else {
+        let mut widget = AsyncWidget::new(&core.clone(), move |_| {
+            let files = Files::default();
+            let source = FileSource::Files(files);
+            ListView::builder(core, source).build()
+        });
 
+        widget.widget.on_ready(move |_, stale| {
+            // To stop from drawing empty placeholder
+            stale.set_stale()?;
+            Ok(())
+        }).log();
 
+        FileBrowserWidgets::FileList(widget)
+    }
+}
 
 impl FileBrowser {
     pub fn new(core: &WidgetCore, cache: Option<FsCache>) -> HResult<FileBrowser> {
@@ -259,16 +541,22 @@ impl FileBrowser {
 
         let cwd = std::env::current_dir().unwrap();
         let mut core_m = core.clone();
-        let mut core_l = core.clone();
         let mut core_p = core.clone();
 
+        // The grandparent needs one more widget, two columns just hide the previewer
+        let widget_count = match core.config().columns {
+            4 => 4,
+            _ => 3
+        };
+        let offset = widget_count - 3;
+
         let mut columns = HBox::new(core);
-        columns.set_ratios(core.config().ratios);
+        columns.set_ratios(layout_ratios(&core.config(), widget_count, 0));
+        columns.placement = core.config().preview_position;
         let list_coords = columns.calculate_coordinates()?;
 
-        core_l.coordinates = list_coords[0].clone();
-        core_m.coordinates = list_coords[1].clone();
-        core_p.coordinates = list_coords[2].clone();
+        core_m.coordinates = list_coords[offset + 1].clone();
+        core_p.coordinates = list_coords[offset + 2].clone();
 
         let main_path = cwd.ancestors()
                            .take(1)
@@ -277,6 +565,19 @@ impl FileBrowser {
                            }).last()?;
         let left_path = main_path.parent().map(|p| p.to_path_buf());
 
+        if offset == 1 {
+            let mut core_g = core.clone();
+            core_g.coordinates = list_coords[0].clone();
+            let grandparent_path = main_path.parent()
+                                            .and_then(|path| path.parent())
+                                            .map(|path| path.to_path_buf());
+            columns.push_widget(dir_column(core_g, grandparent_path, fs_cache.clone()));
+        }
+
+        let mut core_l = core.clone();
+        core_l.coordinates = list_coords[offset].clone();
+        columns.push_widget(dir_column(core_l, left_path, fs_cache.clone()));
+
         let cache = fs_cache.clone();
         let main_widget = AsyncWidget::new(&core, move |stale| {
             let dir = File::new_from_path(&main_path)?;
@@ -287,40 +588,11 @@ impl FileBrowser {
                 .build()
         });
 
-        let cache = fs_cache.clone();
-        if let Some(left_path) = left_path {
-            let left_widget = AsyncWidget::new(&core_l.clone(), move |stale| {
-                let dir = File::new_from_path(&left_path)?;
-                let source = FileSource::Path(dir);
-                ListView::builder(core_l, source)
-                    .with_cache(cache)
-                    .with_stale(stale.clone())
-                    .build()
-            });
-            let left_widget = FileBrowserWidgets::FileList(left_widget);
-            columns.push_widget(left_widget);
-        } else {
-            let mut left_widget = AsyncWidget::new(&core_l.clone(), move |_| {
-                let files = Files::default();
-                let source = FileSource::Files(files);
-                ListView::builder(core_l, source).build()
-            });
-
-            left_widget.widget.on_ready(move |_, stale| {
-                // To stop from drawing empty placeholder
-                stale.set_stale()?;
-                Ok(())
-            }).log();
-
-            let left_widget = FileBrowserWidgets::FileList(left_widget);
-            columns.push_widget(left_widget);
-        }
-
         let previewer = Previewer::new(&core_p, fs_cache.clone());
 
         columns.push_widget(FileBrowserWidgets::FileList(main_widget));
         columns.push_widget(FileBrowserWidgets::Previewer(previewer));
-        columns.set_active(1).log();
+        columns.set_active(offset + 1).log();
         columns.refresh().log();
 
 
@@ -336,6 +608,8 @@ impl FileBrowser {
         Ok(FileBrowser { columns: columns,
                          cwd: cwd,
                          prev_cwd: None,
+                         tab_name: None,
+                         preview_shift: 0,
                          core: core.clone(),
                          proc_view: Arc::new(Mutex::new(proc_view)),
                          bookmarks: Arc::new(Mutex::new(bookmarks)),
@@ -349,24 +623,21 @@ impl FileBrowser {
 
         if file.is_dir() {
             let dir = file;
-            match dir.is_readable() {
-                Ok(true) => {},
-                Ok(false) => {
-                    let status =
-                        format!("{}Stop right there, cowboy! Check your permisions!",
-                                term::color_red());
-                    self.core.show_status(&status).log();
-                    return Ok(());
-                }
-                err @ Err(_) => err.log()
+            if !self.check_listable(&dir)? {
+                return Ok(());
             }
             self.preview_widget_mut()?.set_stale().log();
             self.preview_widget_mut()?.cancel_animation().log();
             let previewer_files = self.preview_widget_mut()?.take_files().ok();
             let main_files = self.take_main_files().ok();
+            let left_files = match self.grandparent_widget().is_ok() {
+                true => self.take_left_files().ok(),
+                false => None
+            };
 
             self.prev_cwd = Some(self.cwd.clone());
             self.cwd = dir.clone();
+            record_visit(&self.cwd);
 
             let cache = self.fs_cache.clone();
             self.main_async_widget_mut()?.change_to(move |stale, core| {
@@ -395,32 +666,119 @@ impl FileBrowser {
                     .with_stale(stale.clone())
                     .build()
                 }).log();
-        } else {
-            self.preview_widget_mut().map(|preview| {
-                preview.cancel_animation().log();
-            }).log();
-            self.core.get_sender().send(Events::InputEnabled(false))?;
-            self.core.screen.suspend().log();
 
-            let status = std::process::Command::new("xdg-open")
-                .args(file.path.file_name())
-                .status();
+            self.grandparent_goto(left_files).log();
+        } else if file.is_special() {
+            let status = format!("{}Can't open \"{}\", it's a {}",
+                                 term::color_red(),
+                                 file.name,
+                                 file.file_type());
+            self.core.show_status(&status).log();
+        } else if self.core.config().read_only {
+            let status = format!("Opening \"{}\" is disabled in read-only mode", file.name);
+            self.core.show_status(&status).log();
+        } else if file.is_executable() && self.core.config().run_executables {
+            self.run_executable(&file)?;
+        } else if let Some(cmd) = self.core.config().openers.find(&file) {
+            self.open_with_cmd(&file, &cmd)?;
+        } else {
+            self.xdg_open(&file)?;
+        }
 
-            self.core.screen.activate().log();
-            self.core.clear().log();
+        Ok(())
+    }
 
-            self.core.get_sender().send(Events::InputEnabled(true))?;
+    pub fn run_executable(&mut self, file: &File) -> HResult<()> {
+        let config = self.core.config();
 
-            match status {
-                Ok(status) =>
-                    self.core.show_status(&format!("\"{}\" exited with {}",
-                                                   "xdg-open", status)).log(),
-                Err(err) =>
-                    self.core.show_status(&format!("Can't run this \"{}\": {}",
-                                                   "xdg-open", err)).log()
+        if config.confirm_run {
+            let query = format!("Run \"{}\"? (y/n)", file.name);
+            let answer = self.setup_question(&query, "n")?;
+            if !answer.to_lowercase().starts_with("y") {
+                return Ok(());
             }
         }
 
+        if config.run_executables_bg {
+            return self.open_bg();
+        }
+
+        self.preview_widget_mut().map(|preview| {
+            preview.cancel_animation().log();
+        }).log();
+        self.core.get_sender().send(Events::InputEnabled(false))?;
+        self.core.screen.suspend().log();
+
+        let status = std::process::Command::new(&file.path)
+            .current_dir(&self.cwd.path)
+            .status();
+
+        // Otherwise the output is gone before it can be read
+        if status.is_ok() {
+            println!("\nPress Enter to return to hunter");
+            std::io::stdin().read_line(&mut String::new()).ok();
+        }
+
+        self.core.screen.activate().log();
+        self.core.clear().log();
+
+        self.core.get_sender().send(Events::InputEnabled(true))?;
+
+        match status {
+            Ok(status) =>
+                self.core.show_status(&format!("\"{}\" exited with {}",
+                                               file.name, status)).log(),
+            Err(err) =>
+                self.core.show_status(&format!("Can't run this \"{}\": {}",
+                                               file.name, err)).log()
+        }
+
+        Ok(())
+    }
+
+    pub fn xdg_open(&mut self, file: &File) -> HResult<()> {
+        self.open_with_cmd(file, "xdg-open")
+    }
+
+    pub fn xdg_open_selected(&mut self) -> HResult<()> {
+        let file = self.selected_file()?;
+
+        match file.is_dir() || file.is_special() {
+            true => self.enter_dir(),
+            false => self.xdg_open(&file)
+        }
+    }
+
+    // Runs through sh with the file as $1, so openers can use $EDITOR
+    pub fn open_with_cmd(&mut self, file: &File, cmd: &str) -> HResult<()> {
+        self.preview_widget_mut().map(|preview| {
+            preview.cancel_animation().log();
+        }).log();
+        self.core.get_sender().send(Events::InputEnabled(false))?;
+        self.core.screen.suspend().log();
+
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{} \"$1\"", cmd))
+            .arg("hunter")
+            .arg(&file.path)
+            .current_dir(&self.cwd.path)
+            .status();
+
+        self.core.screen.activate().log();
+        self.core.clear().log();
+
+        self.core.get_sender().send(Events::InputEnabled(true))?;
+
+        match status {
+            Ok(status) =>
+                self.core.show_status(&format!("\"{}\" exited with {}",
+                                               cmd, status)).log(),
+            Err(err) =>
+                self.core.show_status(&format!("Can't run this \"{}\": {}",
+                                               cmd, err)).log()
+        }
+
         Ok(())
     }
 
@@ -491,6 +849,10 @@ impl FileBrowser {
     }
 
     pub fn main_widget_goto(&mut self, dir: &File) -> HResult<()> {
+        if !self.check_listable(dir)? {
+            return Ok(());
+        }
+
         self.preview_widget_mut()
             .map(|p| p.set_stale())
             .ok();
@@ -500,6 +862,7 @@ impl FileBrowser {
 
         self.prev_cwd = Some(self.cwd.clone());
         self.cwd = dir.clone();
+        record_visit(&self.cwd);
         let file_source = FileSource::Path(self.cwd.clone());
 
         let main_async_widget = self.main_async_widget_mut()?;
@@ -521,11 +884,28 @@ impl FileBrowser {
             }).log();
         }
 
+        self.grandparent_goto(None).log();
 
 
         Ok(())
     }
 
+    // Going into a directory that can't be listed would just show an empty
+    // column, so the cwd stays where it is and the reason is shown instead
+    fn check_listable(&self, dir: &File) -> HResult<bool> {
+        match std::fs::read_dir(&dir.path) {
+            Ok(_) => Ok(true),
+            Err(err) => {
+                let status = format!("{}Can't open {}: {}",
+                                     term::color_red(),
+                                     dir.path.to_string_lossy(),
+                                     err);
+                self.core.show_status(&status)?;
+                Ok(false)
+            }
+        }
+    }
+
     pub fn left_widget_goto(&mut self, dir: &File) -> HResult<()> {
         // Check if we're in the correct directory already and return
         // if we are
@@ -574,8 +954,16 @@ impl FileBrowser {
                     .build()
             }).log();
 
+            // With four columns the grandparent already has the files
+            let grandparent_files = self.take_grandparent_files().ok();
+
             if let Ok(left_dir) = new_cwd.parent_as_file() {
-                let file_source = FileSource::Path(left_dir);
+                let file_source = match grandparent_files {
+                    Some(files) if files.directory == left_dir => {
+                        FileSource::Files(files)
+                    }
+                    _ => FileSource::Path(left_dir)
+                };
                 let cache = self.fs_cache.clone();
                 self.left_async_widget_mut()?.change_to(move |stale, core| {
                     ListView::builder(core, file_source)
@@ -598,6 +986,8 @@ impl FileBrowser {
                 }).log()
             }
 
+            self.grandparent_goto(None).log();
+
 
             if let Ok(preview_files) = preview_files {
                 self.preview_widget_mut().map(|preview| {
@@ -611,10 +1001,21 @@ impl FileBrowser {
         self.refresh()
     }
 
+    // Going there makes the current directory the previous one, so doing
+    // it again comes back here, like "cd -"
     pub fn goto_prev_cwd(&mut self) -> HResult<()> {
-        let prev_cwd = self.prev_cwd.take()?;
-        self.main_widget_goto(&prev_cwd)?;
-        Ok(())
+        let prev_cwd = match self.prev_cwd.clone() {
+            Some(prev_cwd) => prev_cwd,
+            None => return self.core.show_status("No previous directory")
+        };
+
+        if !prev_cwd.path.is_dir() {
+            let msg = format!("{} doesn't exist anymore",
+                              prev_cwd.path.to_string_lossy());
+            return self.core.show_status(&msg);
+        }
+
+        self.main_widget_goto(&prev_cwd)
     }
 
     pub fn go_home(&mut self) -> HResult<()> {
@@ -623,7 +1024,7 @@ impl FileBrowser {
         self.main_widget_goto(&home)
     }
 
-    fn get_boomark(&mut self) -> HResult<String> {
+    fn get_boomark(&mut self) -> HResult<Bookmark> {
         let cwd = &match self.prev_cwd.as_ref() {
             Some(cwd) => cwd,
             None => &self.cwd
@@ -670,107 +1071,534 @@ impl FileBrowser {
     }
 
     pub fn goto_bookmark(&mut self) -> HResult<()> {
-        let path = self.get_boomark()?;
-        let path = File::new_from_path(&PathBuf::from(path))?;
-        self.main_widget_goto(&path)?;
-        Ok(())
-    }
+        let bookmark = self.get_boomark()?;
+        let dir = File::new_from_path(&PathBuf::from(&bookmark.path))?;
 
-    pub fn add_bookmark(&mut self) -> HResult<()> {
-        let cwd = self.cwd.path.to_string_lossy().to_string();
-        let coords = &self.core.coordinates;
+        if let Some(selection) = bookmark.selection {
+            let file = File::new_from_path(&dir.path.join(selection))?;
+            self.fs_cache.set_selection(dir.clone(), file).log();
+        }
 
-        self.bookmarks
-            .lock()
-            .set_coordinates(&coords)
-            .log();
-        self.bookmarks
-            .lock()
-            .add(&cwd)?;
+        self.main_widget_goto(&dir)?;
         Ok(())
     }
 
-    pub fn set_title(&self) -> HResult<()> {
-        let path = self.cwd.short_string();
+    // Links or copies the files into a bookmarked directory without going there
+    pub fn file_to_bookmark(&mut self, copy: bool) -> HResult<()> {
+        let sources = self.op_files()?;
+        let bookmark = self.get_boomark()?;
+        let dir = PathBuf::from(&bookmark.path);
 
-        self.core.screen()?.set_title(&path)?;
-        Ok(())
-    }
+        if !dir.is_dir() {
+            let msg = format!("Bookmark {} isn't a directory", bookmark.path);
+            return self.core.show_status(&msg);
+        }
 
-    pub fn update_preview(&mut self) -> HResult<()> {
-        if !self.main_async_widget_mut()?.ready() { return Ok(()) }
-        if self.main_widget()?
-            .content
-            .len() == 0 {
-                self.preview_widget_mut()?.set_stale().log();
-                return Ok(());
-            }
+        if copy {
+            let job = FileJob::new(FileOp::Copy, sources, Some(dir));
+            return self.proc_view.lock().run_file_job(job);
+        }
 
-        let file = self.selected_file()?;
+        let mut linked = 0;
+        let mut existing = vec![];
 
-        // Don't even call previewer on empty files to save CPU cycles
-        match (file.is_dir(), file.calculate_size()) {
-            (false, Ok((size, unit))) => if size == 0 && unit == "" {
-                self.preview_widget_mut()?.set_stale().log();
-                return Ok(());
-            },
-            _ => {}
+        for source in &sources {
+            let name = source.file_name()?;
+            let link = dir.join(name);
+
+            match crate::file_ops::symlink(source, &link, true) {
+                Ok(()) => linked += 1,
+                Err(_) if link.symlink_metadata().is_ok() => {
+                    existing.push(name.to_string_lossy().to_string())
+                }
+                Err(e) => return Err(e)
+            }
         }
 
-        let preview = self.preview_widget_mut()?;
-        preview.set_file(&file).log();
-        Ok(())
+        let msg = match existing.len() {
+            0 => format!("Linked {} file(s) into {}", linked, bookmark.path),
+            _ => format!("Linked {} file(s) into {}, already there: {}",
+                         linked,
+                         bookmark.path,
+                         existing.join(", "))
+        };
+        self.core.show_status(&msg)
     }
 
-    pub fn set_left_selection(&mut self) -> HResult<()> {
-        if self.cwd.parent().is_none() { return Ok(()) }
-        if !self.left_async_widget_mut()?.ready() { return Ok(()) }
+    // The header_info template with {depth}, {dev}, {free} and {total} filled in
+    fn header_info(&self) -> String {
+        let template = self.core.config().header_info;
+        if template.is_empty() { return template; }
 
-        let selection = self.cwd()?.clone();
+        // The root directory is at depth 0
+        let depth = self.cwd.path.components().count().saturating_sub(1);
 
-        // Saves doing iteration to find file's position
-        if let Some(ref current_selection) =  self.left_widget()?.current_item {
-            if current_selection.name == selection.name {
-                return Ok(());
-            }
-        }
+        let (dev, free, total) = self.fs_stat
+            .read()
+            .find_fs(&self.cwd.path)
+            .map(|fs| {
+                let dev = fs.get_dev().unwrap_or_default();
+                (dev.trim_end_matches(": ").to_string(), fs.get_free(), fs.get_total())
+            })
+            .unwrap_or_default();
 
-        self.left_widget_mut()?.select_file(&selection);
+        let info = template.replace("{depth}", &depth.to_string())
+                           .replace("{dev}", &dev)
+                           .replace("{free}", &free)
+                           .replace("{total}", &total);
 
-        let selected_file = self.left_widget()?.selected_file();
-        self.cwd.parent_as_file()
-                .map(|dir| {
-                    self.fs_cache
-                        .set_selection(dir.clone(), selected_file.clone())
-                }).log();
+        format!("{}  {}", crate::term::header_color(), info)
+    }
 
+    pub fn goto_frecent(&mut self) -> HResult<()> {
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
 
-        Ok(())
-    }
+        let mut view = ListView::new(&self.core, FrecentList::new()?);
 
-    pub fn take_main_files(&mut self) -> HResult<Files> {
-        let w = self.main_widget_mut()?;
-        let files = std::mem::take(&mut w.content);
-        w.content.len = 0;
-        Ok(files)
-    }
+        loop {
+            match view.popup() {
+                Err(HError::RefreshParent) => continue,
+                Err(HError::TerminalResizedError) |
+                Err(HError::WidgetResizedError) => {
+                    self.resize().log();
+                    view.set_coordinates(&self.core.coordinates).log();
+                }
+                _ => break
+            }
+        }
 
-    pub fn take_left_files(&mut self) -> HResult<Files> {
-        let w = self.left_widget_mut()?;
-        let files = std::mem::take(&mut w.content);
-        w.content.len = 0;
-        Ok(files)
+        match view.content.picked.take() {
+            Some(path) => self.main_widget_goto(&File::new_from_path(&path)?),
+            None => Ok(())
+        }
     }
 
-    pub fn get_files(&self) -> HResult<&Files> {
-        Ok(&self.main_widget()?.content)
-    }
+    pub fn show_mounts(&mut self) -> HResult<()> {
+        use crate::mounts::{MountList, MountPick};
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+        self.fs_stat.write().refresh().log();
+
+        let mounts = MountList::new(&self.fs_stat.read());
+        let mut view = ListView::new(&self.core, mounts);
+
+        loop {
+            match view.popup() {
+                Err(HError::RefreshParent) => continue,
+                Err(HError::TerminalResizedError) |
+                Err(HError::WidgetResizedError) => {
+                    self.resize().log();
+                    view.set_coordinates(&self.core.coordinates).log();
+                }
+                _ => break
+            }
+        }
+
+        match view.content.picked.take() {
+            Some(MountPick::Goto(path)) => self.main_widget_goto(&File::new_from_path(&path)?),
+            Some(MountPick::Mount(dev)) => self.udisksctl("mount", dev),
+            Some(MountPick::Unmount(dev)) => self.udisksctl("unmount", dev),
+            None => Ok(())
+        }
+    }
+
+    // Compares the files in the list, so in the flat view the ones in
+    // subdirectories are included
+    pub fn find_duplicates(&mut self) -> HResult<()> {
+        use crate::duplicates::{DuplicateList, DuplicatePick, Scan};
+
+        let paths = self.main_widget()?
+            .content
+            .iter_files()
+            .filter(|file| !file.is_dir())
+            .map(|file| file.path.clone())
+            .collect::<Vec<_>>();
+
+        let scan = Arc::new(Scan::default());
+        let dir = self.cwd.path.clone();
+        let list = DuplicateList::new(scan.clone(), dir);
+
+        let job = move |stale: &Stale| {
+            match crate::duplicates::find_duplicates(paths, &scan, stale) {
+                Ok(groups) => *scan.groups.lock() = Some(groups),
+                Err(HError::StaleError) => {}
+                err @ Err(_) => { err.log(); }
+            }
+        };
+
+        let mut view = match self.job_popup(list, job)? {
+            Some(view) => view,
+            None => return self.core.show_status("Stopped looking for duplicates")
+        };
+
+        match view.content.picked.take() {
+            Some(DuplicatePick::Goto(path)) => self.goto_file(&File::new_from_path(&path)?),
+            Some(DuplicatePick::SelectCopies(copies)) => {
+                let copies = copies.into_iter().collect::<HashSet<_>>();
+                let main_widget = self.main_widget_mut()?;
+
+                for file in main_widget.content.iter_files_mut() {
+                    file.selected = copies.contains(&file.path);
+                }
+
+                main_widget.content.set_dirty();
+                main_widget.refresh().log();
+                self.save_tab_settings()?;
+                self.core.show_status(&format!("Selected {} copies, the first file of each group is left out",
+                                               copies.len()))
+            }
+            None => Ok(())
+        }
+    }
+
+    // Runs job in the background while the popup shows what it found so
+    // far. Closing the popup before the job is done makes it stale.
+    fn job_popup<T>(&mut self,
+                    list: T,
+                    job: impl FnOnce(&Stale) + Send + 'static)
+                    -> HResult<Option<ListView<T>>>
+    where
+        T: JobResults,
+        ListView<T>: crate::listview::Listable
+    {
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let stale = Stale::new();
+
+        {
+            let stale = stale.clone();
+            let sender = self.core.get_sender();
+
+            std::thread::spawn(move || {
+                let _ticker = crate::files::Ticker::start_ticking(sender.clone());
+                job(&stale);
+                sender.send(Events::WidgetReady).ok();
+            });
+        }
+
+        let mut view = ListView::new(&self.core, list);
+
+        loop {
+            match view.popup() {
+                Err(HError::RefreshParent) => continue,
+                Err(HError::TerminalResizedError) |
+                Err(HError::WidgetResizedError) => {
+                    self.resize().log();
+                    view.set_coordinates(&self.core.coordinates).log();
+                }
+                _ => break
+            }
+        }
+
+        match view.content.is_done() {
+            true => Ok(Some(view)),
+            false => {
+                stale.set_stale()?;
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn grep_open(&mut self) -> HResult<()> {
+        use crate::grep::{GrepList, Search};
+
+        let pattern = match self.core.minibuffer("grep") {
+            Ok(pattern) => pattern,
+            Err(HError::MiniBufferEvent(_)) => return Ok(()),
+            Err(e) => return Err(e)
+        };
+
+        let dir = self.cwd.path.clone();
+        let rg = match crate::grep::spawn_rg(&dir, &pattern) {
+            Ok(rg) => rg,
+            Err(err) => {
+                let msg = format!("Can't run rg: {}", err);
+                self.core.show_status(&msg)?;
+                return HError::log(&msg);
+            }
+        };
+        let search = Arc::new(Search::new(rg));
+        let list = GrepList::new(search.clone(), pattern, dir.clone());
+
+        let job = {
+            let search = search.clone();
+            move |stale: &Stale| {
+                match crate::grep::read_matches(&dir, &search, stale) {
+                    Err(HError::StaleError) => {}
+                    result => *search.matches.lock() = Some(result)
+                }
+            }
+        };
+
+        let view = self.job_popup(list, job);
+        // Whether it's done or not, rg is of no use anymore
+        search.kill();
+
+        let mut view = match view? {
+            Some(view) => view,
+            None => return self.core.show_status("Stopped searching")
+        };
+
+        match view.content.picked.take() {
+            Some(found) => {
+                self.edit_file_at(&found.path, Some(found.line))?;
+                self.preview_widget_mut()?.reload();
+                Ok(())
+            }
+            None => Ok(())
+        }
+    }
+
+    // Runs in the process list, so errors like missing permissions show up there
+    fn udisksctl(&mut self, action: &str, dev: String) -> HResult<()> {
+        let cmd = crate::proclist::Cmd {
+            cmd: OsString::from("udisksctl"),
+            short_cmd: Some(format!("udisksctl {} {}", action, dev)),
+            args: Some(vec![OsString::from(action),
+                            OsString::from("-b"),
+                            OsString::from(dev)]),
+            vars: None,
+            cwd: self.cwd.clone(),
+            cwd_files: None,
+            tab_files: None,
+            tab_paths: None
+        };
+
+        self.proc_view
+            .lock()
+            .run_proc_raw(cmd)
+    }
+
+    pub fn add_bookmark(&mut self) -> HResult<()> {
+        let cwd = self.cwd.path.to_string_lossy().to_string();
+        let selection = self.main_widget()
+                            .map(|widget| widget.selected_file())
+                            .ok()
+                            .filter(|file| file.kind != Kind::Placeholder)
+                            .map(|file| file.name.clone());
+        let coords = &self.core.coordinates;
+
+        self.bookmarks
+            .lock()
+            .set_coordinates(&coords)
+            .log();
+        self.bookmarks
+            .lock()
+            .add(&cwd, selection)?;
+        Ok(())
+    }
+
+    // The name was given to the directory, not the tab
+    fn forget_tab_name(&mut self) {
+        let moved = match &self.tab_name {
+            Some((_, dir)) => dir != &self.cwd,
+            None => return
+        };
+
+        if moved && !self.core.config().keep_tab_names {
+            self.tab_name = None;
+        }
+    }
+
+    pub fn set_title(&self) -> HResult<()> {
+        let path = self.cwd.short_string();
+
+        self.core.screen()?.set_title(&path)?;
+        Ok(())
+    }
+
+    pub fn update_preview(&mut self) -> HResult<()> {
+        // Nothing to do when it's hidden
+        if self.columns.is_hidden(self.preview_index()) { return Ok(()) }
+        if self.preview_widget().map(|p| p.pinned().is_some()).unwrap_or(false) {
+            return Ok(())
+        }
+        self.load_preview()
+    }
+
+    pub fn toggle_pin_preview(&mut self) -> HResult<()> {
+        let pinned = self.preview_widget_mut()?
+                         .toggle_pin()
+                         .map(|file| file.name.clone());
+
+        match pinned {
+            Some(name) => self.core.show_status(&format!("Pinned preview of {}", name)),
+            None => {
+                self.update_preview().log();
+                self.core.show_status("Preview follows the selection again")
+            }
+        }
+    }
+
+    pub fn toggle_preview_hidden(&mut self) -> HResult<()> {
+        let show_hidden = self.preview_widget_mut()?.toggle_hidden();
+        self.core.show_status(&format!("Preview shows hidden files: {}", show_hidden))
+    }
+
+    fn load_preview(&mut self) -> HResult<()> {
+        if !self.main_async_widget_mut()?.ready() { return Ok(()) }
+        if self.main_widget()?
+            .content
+            .len() == 0 {
+                self.preview_widget_mut()?.set_stale().log();
+                return Ok(());
+            }
+
+        let file = self.selected_file()?;
+
+        if file.is_dir() && crate::files::dir_bytes() {
+            let stale = self.main_widget()?
+                .content
+                .stale
+                .clone()
+                .unwrap_or_else(Stale::new);
+            file.calculate_recursive_size(self.core.get_sender(), stale).log();
+        }
+
+        // Don't even call previewer on empty files to save CPU cycles
+        let empty = file.meta()
+                        .map(|meta| meta.as_ref().map(|meta| meta.len() == 0))
+                        .flatten()
+                        .unwrap_or(false);
+        if !file.is_dir() && empty {
+            self.preview_widget_mut()?.set_stale().log();
+            return Ok(());
+        }
+
+        let preview = self.preview_widget_mut()?;
+        preview.set_file(&file).log();
+        Ok(())
+    }
+
+    pub fn set_left_selection(&mut self) -> HResult<()> {
+        if self.cwd.parent().is_none() { return Ok(()) }
+        if !self.left_async_widget_mut()?.ready() { return Ok(()) }
+
+        let selection = self.cwd()?.clone();
+
+        // Saves doing iteration to find file's position
+        if let Some(ref current_selection) =  self.left_widget()?.current_item {
+            if current_selection.name == selection.name {
+                return Ok(());
+            }
+        }
+
+        self.left_widget_mut()?.select_file(&selection);
+
+        let selected_file = self.left_widget()?.selected_file();
+        self.cwd.parent_as_file()
+                .map(|dir| {
+                    self.fs_cache
+                        .set_selection(dir.clone(), selected_file.clone())
+                }).log();
+
+
+        Ok(())
+    }
+
+    // Selects the left column's directory, if there are four columns
+    pub fn set_grandparent_selection(&mut self) -> HResult<()> {
+        if self.grandparent_widget().is_err() { return Ok(()) }
+        let selection = match self.cwd.parent_as_file() {
+            Ok(dir) if dir.parent().is_some() => dir,
+            _ => return Ok(())
+        };
+
+        let widget = self.grandparent_widget_mut()?;
+        if let Some(ref current_selection) = widget.current_item {
+            if current_selection.name == selection.name {
+                return Ok(());
+            }
+        }
+
+        widget.select_file(&selection);
+        Ok(())
+    }
+
+    // Keeps the grandparent column in sync. Files taken from the left
+    // column are used if they're from the right directory.
+    fn grandparent_goto(&mut self, files: Option<Files>) -> HResult<()> {
+        if self.columns.widgets.len() < 4 { return Ok(()) }
+
+        let dir = self.cwd.grand_parent();
+        let cache = self.fs_cache.clone();
+
+        let dir = match dir {
+            Some(dir) => File::new_from_path(&dir)?,
+            None => {
+                // Just place a dummy in the column
+                let widget = self.grandparent_async_widget_mut()?;
+                widget.change_to(move |_, core| {
+                    let files = Files::default();
+                    let source = FileSource::Files(files);
+                    ListView::builder(core, source).build()
+                }).log();
+
+                widget.widget.on_ready(move |_, stale| {
+                    // To stop from drawing empty placeholder
+                    stale.set_stale()?;
+                    Ok(())
+                }).log();
+                return Ok(());
+            }
+        };
+
+        let file_source = match files {
+            Some(files) if files.directory == dir => FileSource::Files(files),
+            _ => {
+                // Already there
+                if let Ok(widget) = self.grandparent_widget() {
+                    if widget.content.directory == dir {
+                        return Ok(());
+                    }
+                }
+                FileSource::Path(dir)
+            }
+        };
+
+        self.grandparent_async_widget_mut()?.change_to(move |stale, core| {
+            ListView::builder(core, file_source)
+                .with_cache(cache)
+                .with_stale(stale.clone())
+                .build()
+        }).log();
+
+        Ok(())
+    }
+
+    pub fn take_main_files(&mut self) -> HResult<Files> {
+        let w = self.main_widget_mut()?;
+        let files = std::mem::take(&mut w.content);
+        w.content.len = 0;
+        Ok(files)
+    }
+
+    pub fn take_grandparent_files(&mut self) -> HResult<Files> {
+        let w = self.grandparent_widget_mut()?;
+        let files = std::mem::take(&mut w.content);
+        w.content.len = 0;
+        Ok(files)
+    }
+
+    pub fn take_left_files(&mut self) -> HResult<Files> {
+        let w = self.left_widget_mut()?;
+        let files = std::mem::take(&mut w.content);
+        w.content.len = 0;
+        Ok(files)
+    }
+
+    pub fn get_files(&self) -> HResult<&Files> {
+        Ok(&self.main_widget()?.content)
+    }
 
     pub fn get_left_files(&self) -> HResult<&Files> {
         Ok(&self.left_widget()?.content)
     }
 
     pub fn save_selected_file(&self) -> HResult<()> {
+        let offset = self.main_widget()?.offset;
+        self.fs_cache.set_offset(self.cwd.clone(), offset)?;
+
         self.selected_file()
             .map(|f| self.fs_cache.set_selection(self.cwd.clone(),
                                                  f))?
@@ -782,7 +1610,8 @@ impl FileBrowser {
         if self.main_widget()?.content.len() > 0 {
             let files = self.get_files()?;
             let selected_file = self.selected_file().ok();
-            self.fs_cache.save_settings(files, selected_file).log();
+            let offset = self.main_widget()?.offset;
+            self.fs_cache.save_settings(files, selected_file, offset).log();
         }
 
         Ok(())
@@ -793,6 +1622,28 @@ impl FileBrowser {
         Ok(&self.cwd)
     }
 
+    // Something else might have deleted the directory, moving up is better
+    // than showing a list of files that are gone
+    pub fn leave_deleted_cwd(&mut self) -> HResult<()> {
+        if self.cwd.path.is_dir() {
+            return Ok(());
+        }
+
+        let gone = self.cwd.path.clone();
+        let (dir, selection) = deleted_cwd_target(&gone)?;
+        let dir = File::new_from_path(&dir)?;
+
+        match selection {
+            Some(selection) => self.goto_file(&File::new_from_path(&selection)?)?,
+            None => self.main_widget_goto(&dir)?
+        }
+
+        let msg = format!("{} was deleted, went to {}",
+                          gone.to_string_lossy(),
+                          dir.path.to_string_lossy());
+        self.core.show_status(&msg)
+    }
+
     pub fn set_cwd(&mut self) -> HResult<()> {
         let cwd = self.cwd()?;
         std::env::set_current_dir(&cwd.path)?;
@@ -849,7 +1700,22 @@ impl FileBrowser {
         widget
     }
 
-    pub fn left_async_widget_mut(&mut self) -> HResult<&mut AsyncWidget<ListView<Files>>> {
+    // Four columns have the grandparent first, which shifts everything else
+    fn left_index(&self) -> usize {
+        self.columns.widgets.len().saturating_sub(3)
+    }
+
+    fn main_index(&self) -> usize {
+        self.left_index() + 1
+    }
+
+    fn preview_index(&self) -> usize {
+        self.left_index() + 2
+    }
+
+    pub fn grandparent_async_widget_mut(&mut self) -> HResult<&mut AsyncWidget<ListView<Files>>> {
+        if self.left_index() == 0 { return HError::no_widget(); }
+
         let widget = match self.columns.widgets.get_mut(0)? {
             FileBrowserWidgets::FileList(filelist) => filelist,
             _ => { return HError::wrong_widget("previewer", "filelist"); }
@@ -857,7 +1723,9 @@ impl FileBrowser {
         Ok(widget)
     }
 
-    pub fn left_widget(&self) -> HResult<&ListView<Files>> {
+    pub fn grandparent_widget(&self) -> HResult<&ListView<Files>> {
+        if self.left_index() == 0 { return HError::no_widget(); }
+
         let widget = match self.columns.widgets.get(0)? {
             FileBrowserWidgets::FileList(filelist) => filelist.widget(),
             _ => { return HError::wrong_widget("previewer", "filelist"); }
@@ -865,8 +1733,30 @@ impl FileBrowser {
         widget
     }
 
+    pub fn grandparent_widget_mut(&mut self) -> HResult<&mut ListView<Files>> {
+        self.grandparent_async_widget_mut()?.widget_mut()
+    }
+
+    pub fn left_async_widget_mut(&mut self) -> HResult<&mut AsyncWidget<ListView<Files>>> {
+        let index = self.left_index();
+        let widget = match self.columns.widgets.get_mut(index)? {
+            FileBrowserWidgets::FileList(filelist) => filelist,
+            _ => { return HError::wrong_widget("previewer", "filelist"); }
+        };
+        Ok(widget)
+    }
+
+    pub fn left_widget(&self) -> HResult<&ListView<Files>> {
+        let widget = match self.columns.widgets.get(self.left_index())? {
+            FileBrowserWidgets::FileList(filelist) => filelist.widget(),
+            _ => { return HError::wrong_widget("previewer", "filelist"); }
+        };
+        widget
+    }
+
     pub fn left_widget_mut(&mut self) -> HResult<&mut ListView<Files>> {
-        let widget = match self.columns.widgets.get_mut(0)? {
+        let index = self.left_index();
+        let widget = match self.columns.widgets.get_mut(index)? {
             FileBrowserWidgets::FileList(filelist) => filelist.widget_mut(),
             _ => { return HError::wrong_widget("previewer", "filelist"); }
         };
@@ -874,14 +1764,15 @@ impl FileBrowser {
     }
 
     pub fn preview_widget(&self) -> HResult<&Previewer> {
-        match self.columns.widgets.get(2)? {
+        match self.columns.widgets.get(self.preview_index())? {
             FileBrowserWidgets::Previewer(previewer) => Ok(previewer),
             _ => { return HError::wrong_widget("filelist", "previewer"); }
         }
     }
 
     pub fn preview_widget_mut(&mut self) -> HResult<&mut Previewer> {
-        match self.columns.widgets.get_mut(2)? {
+        let index = self.preview_index();
+        match self.columns.widgets.get_mut(index)? {
             FileBrowserWidgets::Previewer(previewer) => Ok(previewer),
             _ => { return HError::wrong_widget("filelist", "previewer"); }
         }
@@ -894,41 +1785,148 @@ impl FileBrowser {
     }
 
     fn activate_main_widget(&mut self) {
-        const MAIN_INDEX: usize = 1;
+        let main_index = self.main_index();
         self.columns
-            .set_active(MAIN_INDEX)
+            .set_active(main_index)
             .log();
     }
 
     fn activate_preview_widget(&mut self) {
-        const PREVIEW_INDEX: usize = 2;
+        let preview_index = self.preview_index();
+        self.columns
+            .set_active(preview_index)
+            .log();
+    }
+
+    pub fn toggle_colums(&mut self) {
+        self.cancel_preview_animation();
+        self.activate_main_widget();
+        self.columns
+            .toggle_zoom()
+            .log();
+    }
+
+    pub fn zoom_preview(&mut self) {
+        // Hidden with two columns, so it might not be up to date
+        if self.columns.is_hidden(self.preview_index()) {
+            self.load_preview().log();
+        }
+
+        self.cancel_preview_animation();
+        self.activate_preview_widget();
+        self.preview_widget_mut()
+            .map(|preview| {
+                preview.reload_text();
+            }).log();
+
         self.columns
-            .set_active(PREVIEW_INDEX)
+            .toggle_zoom()
             .log();
     }
 
-    pub fn toggle_colums(&mut self) {
-        self.cancel_preview_animation();
-        self.activate_main_widget();
-        self.columns
-            .toggle_zoom()
-            .log();
+    // Changes are in steps of this many percent of the space
+    const PREVIEW_STEP: isize = 5;
+
+    pub fn grow_preview(&mut self) -> HResult<()> {
+        self.resize_preview(self.preview_shift + Self::PREVIEW_STEP)
+    }
+
+    pub fn shrink_preview(&mut self) -> HResult<()> {
+        self.resize_preview(self.preview_shift - Self::PREVIEW_STEP)
+    }
+
+    pub fn reset_layout(&mut self) -> HResult<()> {
+        // The file list gets the keys again, too
+        if self.columns.zoom_active {
+            self.toggle_colums();
+        }
+
+        self.resize_preview(0)?;
+        self.core.show_status("Layout reset")
+    }
+
+    fn resize_preview(&mut self, shift: isize) -> HResult<()> {
+        if self.columns.is_hidden(self.preview_index()) {
+            return self.core.show_status("The preview is hidden");
+        }
+
+        let ratios = layout_ratios(&self.core.config(), self.columns.widgets.len(), shift);
+
+        // Shrinking is fine as long as it makes things better
+        let shrinking = shift.abs() < self.preview_shift.abs();
+        if !self.columns.fits_min_sizes(&ratios) && !shrinking {
+            let size = if shift > self.preview_shift { "bigger" } else { "smaller" };
+            return self.core.show_status(&format!("The preview can't get any {}", size));
+        }
+
+        self.preview_shift = shift;
+        self.columns.set_ratios(ratios);
+        self.columns.core.clear().log();
+        self.columns.resize_children()?;
+        self.core.screen()?.clear().log();
+        self.core.set_dirty();
+        Ok(())
+    }
+
+    // Unless confirm_quit is off, ForceQuit doesn't ask
+    fn confirm_quit(&mut self) -> HResult<bool> {
+        let running = self.proc_view.lock().running_count();
+        let ask = match self.core.config().confirm_quit {
+            ConfirmQuit::On => true,
+            ConfirmQuit::Running => running > 0,
+            ConfirmQuit::Off => false
+        };
+
+        if !ask { return Ok(true) }
+
+        let query = match running {
+            0 => String::from("Quit?"),
+            1 => String::from("Quit? 1 process is still running"),
+            _ => format!("Quit? {} processes are still running", running)
+        };
+
+        let answer = self.setup_question(&format!("{} (y/n)", query), "n")?;
+        Ok(answer.to_lowercase().starts_with("y"))
+    }
+
+    // With empty_trash_on_quit=on it asks the first time, yes saves "always"
+    fn empty_trash_on_quit(&mut self, ask: bool) -> HResult<()> {
+        if crate::file_ops::trash_is_empty() { return Ok(()) }
+
+        match self.core.config().empty_trash_on_quit {
+            EmptyTrash::Always => {}
+            EmptyTrash::Ask if ask => {
+                let query = "Empty the trash now and whenever hunter quits? (y/n)";
+                let answer = self.setup_question(query, "n")?;
+                if !answer.to_lowercase().starts_with("y") {
+                    return Ok(());
+                }
+
+                crate::config::set_options(&[("empty_trash_on_quit",
+                                              String::from("always"))]).log();
+            }
+            EmptyTrash::Ask | EmptyTrash::Off => return Ok(())
+        }
+
+        crate::file_ops::empty_trash().log();
+        Ok(())
     }
 
-    pub fn zoom_preview(&mut self) {
-        self.cancel_preview_animation();
-        self.activate_preview_widget();
-        self.preview_widget_mut()
-            .map(|preview| {
-                preview.reload_text();
-            }).log();
+    pub fn quit(&mut self) -> HResult<()> {
+        if !self.confirm_quit()? { return Ok(()) }
+        self.empty_trash_on_quit(true)?;
+        HError::quit()
+    }
 
-        self.columns
-            .toggle_zoom()
-            .log();
+    pub fn force_quit(&mut self) -> HResult<()> {
+        self.empty_trash_on_quit(false)?;
+        HError::quit()
     }
 
-    pub fn quit_with_dir(&self) -> HResult<()> {
+    pub fn quit_with_dir(&mut self) -> HResult<()> {
+        if !self.confirm_quit()? { return Ok(()) }
+        self.empty_trash_on_quit(true)?;
+
         let cwd = self.cwd()?.clone().path;
         let selected_file = self.selected_file()?;
         let selected_file = selected_file.path.to_string_lossy();
@@ -1002,7 +2000,8 @@ impl FileBrowser {
                                 continue;
                             }
 
-                            if input.ends_with('/') {
+                            // A leading "/" is a regex filter, where "/" can't navigate
+                            if input.ends_with('/') && !input.starts_with('/') {
                                 match input.as_str() {
                                     "../" => {
                                         dir_restore(self,
@@ -1087,6 +2086,8 @@ impl FileBrowser {
                             // Explicitly selected
                             selected_file = Some(self.selected_file()?);
                         }
+                        // Only the file list search asks for that
+                        SearchSubdirs(_) => {}
                     }
                 },
                 _ => {  }
@@ -1230,80 +2231,657 @@ impl FileBrowser {
         self.core.clear().log();
         self.core.get_sender().send(Events::InputEnabled(true))?;
 
-        match cmd_result {
-            Ok(cmd_result) => {
-                if cmd_result.status.success() {
-                    let cwd = &self.cwd.path;
+        match cmd_result {
+            Ok(cmd_result) => {
+                if cmd_result.status.success() {
+                    let cwd = &self.cwd.path;
+
+                    let path_string = OsString::from_vec(cmd_result.stdout);
+                    let path_string = path_string.trim_end("\n");
+                    let path = PathBuf::from(path_string);
+                    let path = if path.is_absolute() {
+                        path
+                    } else {
+                        cwd.join(path)
+                    };
+
+                    if path.exists() {
+                        if path.is_dir() {
+                            let dir = File::new_from_path(&path)?;
+                            self.main_widget_goto(&dir).log();
+                        }
+                        else {
+                            let msg = format!("Can't access path: {}!",
+                                              path.to_string_lossy());
+                            self.core.show_status(&msg).log();
+                        }
+
+                    } else {
+                        self.core.show_status("External program failed!").log();
+                    }
+                }
+            }
+            Err(_) => self.core.show_status("Can't run external program!").log()
+        }
+
+        Ok(())
+    }
+
+
+    fn exec_cmd(&mut self,
+                tab_dirs: Vec<File>,
+                tab_files: Vec<Vec<File>>) -> HResult<()> {
+        let cwd = self.cwd()?.clone();
+        let selected_file = self.selected_file().ok();
+        let selected_files = self.selected_files().ok();
+
+        let cwd_files = selected_files.map(|selected_files| {
+            if selected_files.len() == 0 {
+                if selected_file.is_some() {
+                    vec![selected_file.unwrap()]
+                } else {
+                    selected_files
+                }
+            } else {
+                selected_files
+            }
+        });
+
+        // InsertSelection puts in the same files $s stands for
+        let insertion = cwd_files.iter()
+                                 .flatten()
+                                 .map(|file| shell_quote(&file.path.to_string_lossy()))
+                                 .collect::<Vec<_>>()
+                                 .join(" ");
+
+        let cmd = self.core.minibuffer_with_insertion("exec", insertion)?.to_string();
+
+        let cmd = crate::proclist::Cmd {
+            cmd: OsString::from(cmd),
+            short_cmd: None,
+            args: None,
+            vars: None,
+            cwd: cwd,
+            cwd_files: cwd_files,
+            tab_files: Some(tab_files),
+            tab_paths: Some(tab_dirs)
+        };
+
+        self.proc_view
+            .lock()
+            .run_proc_subshell(cmd)?;
+
+        Ok(())
+    }
+
+    pub fn diff_files(&mut self) -> HResult<()> {
+        let files = self.selected_files()?;
+
+        if files.len() != 2 {
+            let msg = format!("Select two files or directories to diff, not {}",
+                              files.len());
+            return self.core.show_status(&msg);
+        }
+
+        if files[0].is_dir() != files[1].is_dir() {
+            return self.core.show_status("Can't diff a file with a directory!");
+        }
+
+        // The paths go where $0 and $1 are, or at the end
+        let mut cmd = self.core.config().diff_cmd;
+        if !cmd.contains("$0") {
+            cmd += " $0 $1";
+        }
+
+        let cmd = crate::proclist::Cmd {
+            cmd: OsString::from(cmd),
+            short_cmd: None,
+            args: None,
+            vars: None,
+            cwd: self.cwd()?.clone(),
+            cwd_files: None,
+            tab_files: None,
+            tab_paths: Some(files)
+        };
+
+        {
+            let mut proc_view = self.proc_view.lock();
+            proc_view.run_proc_subshell(cmd)?;
+            proc_view.keep_colors_of_last();
+            proc_view.select_last();
+        }
+
+        self.show_procview()
+    }
+
+    pub fn extract_archive(&mut self) -> HResult<()> {
+        let cwd = self.cwd()?.clone();
+        let archive = self.selected_file()?;
+        let mime = archive.get_mime()?;
+
+        // Everything goes into a new directory named after the archive
+        let stem = crate::archive::archive_stem(&archive.name);
+        let dest = crate::archive::unused_path(cwd.path.join(stem));
+        let dest_name = PathBuf::from(dest.file_name()?);
+
+        let args = match crate::archive::extract_cmd(&mime,
+                                                     &archive.strip_prefix(&cwd),
+                                                     &dest_name) {
+            Ok(args) => args,
+            Err(HError::Error(msg)) => return self.core.show_status(&msg),
+            Err(e) => return Err(e)
+        };
+
+        std::fs::create_dir(&dest)?;
+        self.run_archive_cmd(args, cwd, Some(&dest_name))
+    }
+
+    pub fn compress_files(&mut self) -> HResult<()> {
+        let cwd = self.cwd()?.clone();
+        let files = self.op_files()?
+            .into_iter()
+            .map(|path| path.strip_prefix(&cwd.path)
+                            .map(|path| path.to_path_buf())
+                            .unwrap_or(path))
+            .collect::<Vec<_>>();
+
+        let query = format!("Pack {} file(s) into", files.len());
+        let name = match self.core.minibuffer(&query) {
+            Ok(name) => name.trim().to_string(),
+            Err(HError::MiniBufferEvent(_)) => return Ok(()),
+            Err(e) => return Err(e)
+        };
+
+        if cwd.path.join(&name).symlink_metadata().is_ok() {
+            return self.core.show_status(&format!("{} already exists", name));
+        }
+
+        let args = match crate::archive::compress_cmd(Path::new(&name), &files) {
+            Ok(args) => args,
+            Err(HError::Error(msg)) => return self.core.show_status(&msg),
+            Err(e) => return Err(e)
+        };
+
+        self.run_archive_cmd(args, cwd, None)
+    }
+
+    // With a dest it's removed again if the command fails
+    fn run_archive_cmd(&mut self,
+                       args: Vec<OsString>,
+                       cwd: File,
+                       dest: Option<&Path>) -> HResult<()> {
+        let short_cmd = args.iter()
+                            .map(|arg| arg.to_string_lossy())
+                            .collect::<Vec<_>>()
+                            .join(" ");
+
+        let mut args = match dest {
+            Some(dest) => crate::archive::remove_on_failure(args, dest),
+            None => args
+        };
+
+        let cmd = crate::proclist::Cmd {
+            cmd: args.remove(0),
+            short_cmd: Some(short_cmd),
+            args: Some(args),
+            vars: None,
+            cwd: cwd,
+            cwd_files: None,
+            tab_files: None,
+            tab_paths: None
+        };
+
+        self.proc_view
+            .lock()
+            .run_proc_raw(cmd)
+    }
+
+    fn op_files(&self) -> HResult<Vec<PathBuf>> {
+        let mut files = self.selected_files()?;
+
+        if files.len() == 0 {
+            files.push(self.selected_file()?);
+        }
+
+        Ok(files.into_iter().map(|file| file.path).collect())
+    }
+
+    pub fn file_op(&mut self, op: FileOp, other_dir: Option<File>) -> HResult<()> {
+        use crate::minibuffer::MiniBufferEvent::*;
+
+        let sources = self.op_files()?;
+        let query = format!("{} {} file(s) to", op, sources.len());
+        let default = other_dir.map(|dir| dir.path.to_string_lossy().to_string());
+
+        let target = loop {
+            let query = match &default {
+                Some(default) => format!("{} [{}]", query, default),
+                None => query.clone()
+            };
+
+            match self.core.minibuffer(&query) {
+                Ok(target) => break target,
+                Err(HError::MiniBufferEvent(Empty)) if default.is_some() => {
+                    break default?
+                }
+                Err(HError::MiniBufferEvent(_)) => return Ok(()),
+                // Widgets finishing loading interrupt the minibuffer
+                Err(HError::RefreshParent) => {
+                    self.refresh().log();
+                    self.draw().log();
+                }
+                Err(e) => return Err(e)
+            }
+        };
+
+        // Relative to the current directory
+        let target = self.cwd.path.join(target.trim());
+        if !target.is_dir() {
+            let msg = format!("Can't {} files to {}, not a directory",
+                              op,
+                              target.to_string_lossy());
+            return self.core.show_status(&msg);
+        }
+
+        let job = FileJob::new(op, sources, Some(target));
+        self.proc_view.lock().run_file_job(job)
+    }
+
+    pub fn delete_files(&mut self) -> HResult<()> {
+        let sources = self.op_files()?;
+        let query = match sources.len() {
+            1 => format!("Delete \"{}\"? (y/n)", sources[0].to_string_lossy()),
+            n => format!("Delete {} files? (y/n)", n)
+        };
+
+        let answer = self.setup_question(&query, "n")?;
+        if !answer.to_lowercase().starts_with("y") {
+            return Ok(());
+        }
+
+        let job = FileJob::new(FileOp::Delete, sources, None);
+        self.proc_view.lock().run_file_job(job)
+    }
+
+    pub fn trash_files(&mut self) -> HResult<()> {
+        let sources = self.op_files()?;
+        let job = FileJob::new(FileOp::Trash, sources, None);
+        self.proc_view.lock().run_file_job(job)
+    }
+
+    pub fn symlink_files(&mut self, sources: Option<Vec<File>>) -> HResult<()> {
+        let sources = match sources {
+            Some(sources) => sources,
+            None => {
+                let msg = "Select the files to link to in another tab first";
+                return self.core.show_status(msg);
+            }
+        };
+
+        let query = format!("Link to {} file(s) with relative paths? (y/n)",
+                            sources.len());
+        let relative = self.setup_question(&query, "n")?
+                           .to_lowercase()
+                           .starts_with("y");
+
+        let mut failed = 0;
+        for source in &sources {
+            let link = self.cwd.path.join(source.path.file_name()?);
+            crate::file_ops::symlink(&source.path, &link, relative)
+                .map_err(|err| { failed += 1; err })
+                .log();
+        }
+
+        let msg = match failed {
+            0 => format!("Created {} symlink(s)", sources.len()),
+            n => format!("Failed to create {} of {} symlinks, see log", n, sources.len())
+        };
+        self.core.show_status(&msg)
+    }
+
+    pub fn create_symlink(&mut self) -> HResult<()> {
+        use crate::minibuffer::MiniBufferEvent::*;
+
+        let file = self.selected_file()?;
+        let query = format!("Link to {} as", file.name);
+
+        let name = loop {
+            match self.core.minibuffer(&query) {
+                Ok(name) => break name,
+                Err(HError::MiniBufferEvent(Empty)) |
+                Err(HError::MiniBufferEvent(Cancelled)) => return Ok(()),
+                // Widgets finishing loading interrupt the minibuffer
+                Err(HError::RefreshParent) => {
+                    self.refresh().log();
+                    self.draw().log();
+                }
+                Err(e) => return Err(e)
+            }
+        };
+
+        // Relative to the current directory, a directory gets the link put into it
+        let mut link = self.cwd.path.join(name.trim());
+        if link.is_dir() {
+            link.push(file.path.file_name()?);
+        }
+
+        crate::file_ops::symlink(&file.path, &link, true)?;
+
+        let msg = format!("Created {}", link.to_string_lossy());
+        self.core.show_status(&msg)
+    }
+
+    // One name per line, changed lines get renamed, emptied ones deleted
+    pub fn bulk_rename(&mut self) -> HResult<()> {
+        let sources = self.op_files()?;
+        let names = sources.iter()
+            .map(|source| {
+                source.strip_prefix(&self.cwd.path)
+                      .unwrap_or(source)
+                      .to_string_lossy()
+                      .to_string()
+            })
+            .collect::<Vec<String>>();
+
+        if names.iter().any(|name| name.contains('\n')) {
+            return self.core.show_status("Can't bulk rename names containing newlines");
+        }
+
+        let content = names.iter()
+            .map(|name| format!("{}\n", name))
+            .collect::<String>();
+
+        // Only readable by us and never following something placed there before
+        let mut i = 0;
+        let (tmp_path, mut tmp_file) = loop {
+            let path = std::env::temp_dir()
+                .join(format!("hunter-rename-{}-{}", std::process::id(), i));
+
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path) {
+                    Ok(file) => break (path, file),
+                    Err(ref err) if err.kind() == std::io::ErrorKind::AlreadyExists => i += 1,
+                    Err(err) => return Err(err)?
+                }
+        };
+        tmp_file.write_all(content.as_bytes())?;
+        drop(tmp_file);
+
+        let edited = self.edit_file(&tmp_path)
+            .and_then(|_| Ok(std::fs::read_to_string(&tmp_path)?));
+        std::fs::remove_file(&tmp_path).ok();
+        let edited = edited?;
+
+        let lines = edited.lines().collect::<Vec<&str>>();
+        if lines.len() != names.len() {
+            let msg = format!("Got {} lines for {} files, nothing renamed",
+                              lines.len(),
+                              names.len());
+            return self.core.show_status(&msg);
+        }
+
+        let mut renames = vec![];
+        let mut deletions = vec![];
+
+        for ((source, name), line) in sources.iter().zip(&names).zip(lines) {
+            match line.trim() {
+                "" => deletions.push(source.clone()),
+                _ if line == name => {}
+                _ => renames.push((source.clone(), self.cwd.path.join(line)))
+            }
+        }
+
+        let mut renamed = vec![];
+        let mut failed = 0;
+
+        for (source, target) in renames {
+            // A dangling symlink doesn't "exist", but would still be replaced
+            let result = match std::fs::symlink_metadata(&target).is_ok() ||
+                               renamed.iter().any(|(_, done)| done == &target) {
+                true => HError::log(&format!("Not renaming {}, {} exists",
+                                             source.to_string_lossy(),
+                                             target.to_string_lossy())),
+                false => std::fs::rename(&source, &target).or_else(|err| {
+                    HError::log(&format!("Can't rename {} to {}: {}",
+                                         source.to_string_lossy(),
+                                         target.to_string_lossy(),
+                                         err))
+                })
+            };
+
+            match result {
+                Ok(_) => renamed.push((source, target)),
+                Err(_) => failed += 1
+            }
+        }
+
+        if renamed.len() > 0 {
+            let (sources, dests) = renamed.iter().cloned().unzip();
+            crate::file_ops::push_history(crate::file_ops::FileOpRecord {
+                op: FileOp::Move,
+                sources: sources,
+                dests: dests
+            });
+        }
+
+        let msg = match failed {
+            0 => format!("Renamed {} file(s)", renamed.len()),
+            n => format!("Renamed {} file(s), {} failed, see log", renamed.len(), n)
+        };
+        self.core.show_status(&msg)?;
+
+        if deletions.len() > 0 {
+            let query = format!("Delete {} file(s) with emptied lines? (y/n)",
+                                deletions.len());
+            let answer = self.setup_question(&query, "n")?;
+            if answer.to_lowercase().starts_with("y") {
+                let job = FileJob::new(FileOp::Delete, deletions, None);
+                self.proc_view.lock().run_file_job(job)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Links to files from all over the place, or copies with scratch_copy=on
+    pub fn add_to_scratch(&mut self) -> HResult<()> {
+        let sources = self.op_files()?;
+        let config = self.core.config();
+        let dir = config.scratch_dir()?;
+        std::fs::create_dir_all(&dir)?;
+
+        if config.scratch_copy {
+            let job = FileJob::new(FileOp::Copy, sources, Some(dir));
+            return self.proc_view.lock().run_file_job(job);
+        }
+
+        let mut failed = 0;
+        for source in &sources {
+            let link = dir.join(source.file_name()?);
+            crate::file_ops::symlink(source, &link, false)
+                .map_err(|err| { failed += 1; err })
+                .log();
+        }
+
+        let msg = match failed {
+            0 => format!("Added {} file(s) to {}", sources.len(), dir.to_string_lossy()),
+            n => format!("Failed to add {} of {} files, see log", n, sources.len())
+        };
+        self.core.show_status(&msg)
+    }
+
+    pub fn goto_scratch(&mut self) -> HResult<()> {
+        let dir = self.core.config().scratch_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        self.main_widget_goto(&File::new_from_path(&dir)?)
+    }
+
+    // Previewers, actions and keys can be edited from hunter itself
+    pub fn goto_config_dir(&mut self) -> HResult<()> {
+        // Without the trailing "/" it's the same path as when going there by hand
+        let dir = crate::paths::hunter_path()?
+                      .components()
+                      .collect::<PathBuf>();
+        std::fs::create_dir_all(&dir)?;
+        self.main_widget_goto(&File::new_from_path(&dir)?)
+    }
+
+    // From the command line, applied when the first directory is loaded.
+    // A file in a subdirectory is selected there.
+    pub fn start_with(&mut self,
+                      select: Option<String>,
+                      filter: Option<String>) -> HResult<()> {
+        if let Some(name) = select {
+            let path = self.cwd.path.join(&name);
+            if path.symlink_metadata().is_err() {
+                return HError::log(&format!("Can't select {}, it doesn't exist", name));
+            }
+
+            let file = File::new_from_path(&path)?;
+            match path.parent() == Some(&self.cwd.path) {
+                true => self.main_async_widget_mut()?
+                            .widget
+                            .on_ready(move |w, _| {
+                                w?.select_file(&file);
+                                Ok(())
+                            })?,
+                false => self.goto_file(&file)?
+            }
+        }
+
+        if let Some(filter) = filter {
+            self.main_async_widget_mut()?
+                .widget
+                .on_ready(move |w, _| {
+                    let view = w?;
+                    let selected_file = view.clone_selected_file();
+                    view.content.set_filter(Some(filter)).log();
+                    view.select_file(&selected_file);
+                    Ok(())
+                })?;
+        }
+
+        Ok(())
+    }
+
+    pub fn goto_file(&mut self, file: &File) -> HResult<()> {
+        let file = file.clone();
+        let dir = file.parent_as_file()?;
+
+        self.main_widget_goto(&dir)?;
+        self.main_async_widget_mut()?
+            .widget
+            .on_ready(move |w, _| {
+                w?.select_file(&file);
+                Ok(())
+            })?;
+
+        Ok(())
+    }
+
+    // Ends up where the link really points, not in the path of the link,
+    // with the target selected in its directory
+    pub fn follow_link(&mut self) -> HResult<()> {
+        let file = self.selected_file()?;
+
+        if file.target.is_none() {
+            return self.core.show_status("Not a symlink");
+        }
+
+        let target = match std::fs::canonicalize(&file.path) {
+            Ok(target) => target,
+            Err(err) => {
+                return self.core.show_status(&format!("Can't follow broken link: {}", err))
+            }
+        };
 
-                    let path_string = OsString::from_vec(cmd_result.stdout);
-                    let path_string = path_string.trim_end("\n");
-                    let path = PathBuf::from(path_string);
-                    let path = if path.is_absolute() {
-                        path
-                    } else {
-                        cwd.join(path)
-                    };
+        self.goto_file(&File::new_from_path(&target)?)?;
 
-                    if path.exists() {
-                        if path.is_dir() {
-                            let dir = File::new_from_path(&path)?;
-                            self.main_widget_goto(&dir).log();
-                        }
-                        else {
-                            let msg = format!("Can't access path: {}!",
-                                              path.to_string_lossy());
-                            self.core.show_status(&msg).log();
-                        }
+        let msg = format!("Followed link to {}", target.to_string_lossy());
+        self.core.show_status(&msg)
+    }
 
-                    } else {
-                        self.core.show_status("External program failed!").log();
-                    }
-                }
+    // The search in subdirectories runs in the background
+    fn take_search_result(&mut self) -> HResult<()> {
+        let found = match self.main_widget_mut()?.content.take_subdir_match() {
+            Some(found) => found,
+            None => return Ok(())
+        };
+
+        match found {
+            (_, Some(path)) => self.goto_search_result(&path),
+            (search, None) => {
+                let msg = format!("No match for \"{}\" in subdirectories", search);
+                self.core.show_status(&msg)
             }
-            Err(_) => self.core.show_status("Can't run external program!").log()
         }
+    }
 
-        Ok(())
+    pub fn goto_search_result(&mut self, path: &Path) -> HResult<()> {
+        let file = File::new_from_path(path)?;
+        let dir = file.parent_as_file()?;
+        self.goto_file(&file)?;
+
+        let msg = format!("Found in {}", dir.path.to_string_lossy());
+        self.core.show_status(&msg)
     }
 
+    // Only actions on the file list/browser itself can be repeated
+    pub fn repeat_action(&mut self) -> HResult<()> {
+        let result = match crate::keybind::last_action_section() {
+            Some("filelist") => self.main_widget_mut()?.repeat_last_action(),
+            Some("filebrowser") => self.repeat_last_action(),
+            _ => Err(KeyBindError::NothingToRepeat)?
+        };
 
-    fn exec_cmd(&mut self,
-                tab_dirs: Vec<File>,
-                tab_files: Vec<Vec<File>>) -> HResult<()> {
-        let cwd = self.cwd()?.clone();
-        let selected_file = self.selected_file().ok();
-        let selected_files = self.selected_files().ok();
+        match result {
+            Err(HError::KeyBind(KeyBindError::NothingToRepeat)) => {
+                self.core.show_status("Nothing to repeat")
+            }
+            result => result
+        }
+    }
 
-        let cmd = self.core.minibuffer("exec")?.to_string();
+    pub fn undo(&mut self) -> HResult<()> {
+        use crate::file_ops::Recording;
 
-        let cwd_files = selected_files.map(|selected_files| {
-            if selected_files.len() == 0 {
-                if selected_file.is_some() {
-                    vec![selected_file.unwrap()]
-                } else {
-                    selected_files
+        let record = match crate::file_ops::pop_history() {
+            Some(record) => record,
+            None => return self.core.show_status("Nothing to undo")
+        };
+
+        match record.undo_job(Recording::Undo) {
+            Ok(job) => self.proc_view.lock().run_file_job(job),
+            // Deletions are dropped, so the operation before can be undone
+            Err(err) => {
+                if record.op != FileOp::Delete {
+                    // Put back without forgetting what can be redone
+                    crate::file_ops::push_record(record, Recording::Redo);
                 }
-            } else {
-                selected_files
+                self.core.show_status(&format!("Can't undo: {}", err))
             }
-        });
+        }
+    }
 
-        let cmd = crate::proclist::Cmd {
-            cmd: OsString::from(cmd),
-            short_cmd: None,
-            args: None,
-            vars: None,
-            cwd: cwd,
-            cwd_files: cwd_files,
-            tab_files: Some(tab_files),
-            tab_paths: Some(tab_dirs)
-        };
+    pub fn redo(&mut self) -> HResult<()> {
+        use crate::file_ops::Recording;
 
-        self.proc_view
-            .lock()
-            .run_proc_subshell(cmd)?;
+        let record = match crate::file_ops::pop_redo() {
+            Some(record) => record,
+            None => return self.core.show_status("Nothing to redo")
+        };
 
-        Ok(())
+        // Reversing the undo does the operation again
+        match record.undo_job(Recording::Redo) {
+            Ok(job) => self.proc_view.lock().run_file_job(job),
+            Err(err) => {
+                crate::file_ops::push_record(record, Recording::Undo);
+                self.core.show_status(&format!("Can't redo: {}", err))
+            }
+        }
     }
 
     pub fn run_subshell(&mut self) -> HResult<()> {
@@ -1334,6 +2912,162 @@ impl FileBrowser {
         Ok(())
     }
 
+    fn default_editor() -> String {
+        std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or("vi".into())
+    }
+
+    pub fn edit_file(&mut self, path: &Path) -> HResult<()> {
+        self.edit_file_at(path, None)
+    }
+
+    pub fn edit_file_at(&mut self, path: &Path, line: Option<usize>) -> HResult<()> {
+        let config = self.core.config();
+        let editor = config.editor.unwrap_or_else(FileBrowser::default_editor);
+        let cmd = editor_cmd(&editor, &config.editor_line_cmd, path, line);
+        let (editor_cmd, editor_args) = cmd.split_first()?;
+
+        self.core.get_sender().send(Events::InputEnabled(false))?;
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+        self.core.screen.suspend().log();
+
+        let status = std::process::Command::new(editor_cmd)
+            .args(editor_args)
+            .status();
+
+        self.core.screen.activate().log();
+        self.core.clear().log();
+
+        self.core.get_sender().send(Events::InputEnabled(true))?;
+
+        match status {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => {
+                let msg = format!("\"{}\" exited with {}", editor, status);
+                self.core.show_status(&msg)?;
+                HError::log(&msg)
+            }
+            Err(err) => {
+                let msg = format!("Can't run editor \"{}\": {}", editor, err);
+                self.core.show_status(&msg)?;
+                HError::log(&msg)
+            }
+        }
+    }
+
+    fn setup_question(&mut self, query: &str, default: &str) -> HResult<String> {
+        use crate::minibuffer::MiniBufferEvent::*;
+
+        match self.query_minibuffer(&format!("{} [{}]", query, default)) {
+            Ok(answer) => Ok(answer.trim().to_string()),
+            Err(HError::MiniBufferEvent(Empty)) => Ok(default.to_string()),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn setup_questions(&mut self) -> HResult<Vec<(&'static str, String)>> {
+        let on_off = |answer: String| match answer.to_lowercase().starts_with("y") {
+            true => "on".to_string(),
+            false => "off".to_string()
+        };
+
+        let mut options = vec![];
+
+        let icons = self.setup_question("Show icons? Needs a nerd font (y/n)", "n")?;
+        options.push(("icons", on_off(icons)));
+
+        let mut modes = vec!["auto", "kitty", "unicode"];
+        if cfg!(feature = "sixel") {
+            modes.insert(2, "sixel");
+        }
+
+        let query = format!("Graphics ({})", modes.join("/"));
+        let graphics = loop {
+            let mode = self.setup_question(&query, "auto")?;
+            match modes.contains(&mode.as_str()) {
+                true => break mode,
+                false => self.core.show_status(&format!("Unknown mode: {}", mode))?
+            }
+        };
+        options.push(("graphics", graphics));
+
+        // Only save it if it's not just what's set in the environment
+        let default_editor = FileBrowser::default_editor();
+        let editor = self.setup_question("Editor", &default_editor)?;
+        if editor != default_editor {
+            options.push(("editor", editor));
+        }
+
+        if self.core.config().media_available() {
+            let autoplay = self.setup_question("Autoplay media previews? (y/n)", "n")?;
+            options.push(("media_autoplay", on_off(autoplay)));
+
+            let mute = self.setup_question("Mute media previews? (y/n)", "n")?;
+            options.push(("media_mute", on_off(mute)));
+        }
+
+        Ok(options)
+    }
+
+    pub fn setup_wizard(&mut self) -> HResult<()> {
+        self.core.show_status("Welcome to hunter! Press Enter for defaults, Esc to skip setup")?;
+
+        match self.setup_questions() {
+            Ok(options) => {
+                crate::config::set_options(&options)?;
+                self.core.reload_config();
+
+                let path = crate::paths::config_path()?;
+                self.core.show_status(&format!("Configuration saved in: {}",
+                                               path.to_string_lossy()))
+            }
+            Err(HError::MiniBufferEvent(_)) => {
+                self.core.show_status("Setup skipped, using default configuration")
+            }
+            Err(e) => Err(e)
+        }
+    }
+
+    // Starts where the text preview is scrolled to
+    pub fn edit_selected_file(&mut self) -> HResult<()> {
+        let file = self.selected_file()?;
+
+        if file.is_dir() {
+            return self.core.show_status("Can't edit a directory");
+        }
+
+        let line = self.preview_widget()
+            .ok()
+            .filter(|preview| preview.get_file() == Some(&file))
+            .and_then(|preview| preview.text_line());
+
+        self.edit_file_at(&file.path, line)?;
+        self.preview_widget_mut()?.reload();
+        Ok(())
+    }
+
+    pub fn edit_dir_config(&mut self) -> HResult<()> {
+        let path = crate::dir_config::dir_config_path(&self.cwd.path);
+
+        if !path.exists() {
+            std::fs::write(&path, crate::dir_config::TEMPLATE)?;
+        }
+
+        self.edit_file(&path)?;
+
+        if !self.core.config().dir_config {
+            self.core.show_status("Set dir_config=on to use per-directory settings")?;
+            return Ok(());
+        }
+
+        let dir_config = DirConfig::load(&self.cwd.path)?;
+        self.main_widget_mut()?.apply_dir_config(&dir_config);
+        self.core.show_status(&format!("Applied settings from: {}",
+                                       path.to_string_lossy()))
+    }
+
     pub fn show_procview(&mut self) -> HResult<()> {
         self.preview_widget().map(|preview| preview.cancel_animation()).log();
         let procview = self.proc_view.clone();
@@ -1371,6 +3105,91 @@ impl FileBrowser {
         Ok(())
     }
 
+    pub fn show_file_info(&mut self) -> HResult<()> {
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let file = self.selected_file()?;
+        let mut info = FileInfoView::new(&self.core, &file)?;
+
+        loop {
+            match info.popup() {
+                Err(HError::RefreshParent) => continue,
+                Err(HError::TerminalResizedError) |
+                Err(HError::WidgetResizedError) => {
+                    self.resize().log();
+                    info.set_coordinates(&self.core.coordinates).log();
+                }
+                _ => break
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn edit_keybindings(&mut self) -> HResult<()> {
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let mut editor = KeyBindEditor::new(&self.core)?;
+
+        loop {
+            match editor.popup() {
+                Err(HError::RefreshParent) => continue,
+                Err(HError::TerminalResizedError) |
+                Err(HError::WidgetResizedError) => {
+                    self.resize().log();
+                    editor.set_coordinates(&self.core.coordinates).log();
+                }
+                _ => break
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn command_palette(&mut self) -> HResult<()> {
+        use crate::minibuffer::MiniBufferEvent::*;
+        use crate::command_palette::{self, PaletteAction};
+
+        self.preview_widget().map(|preview| preview.cancel_animation()).log();
+
+        let entries = command_palette::entries(&self.core.config().keybinds);
+        let mut ranked = command_palette::ranked(&entries, "");
+
+        let chosen = loop {
+            let list = command_palette::render(&entries, &ranked, &self.core.coordinates);
+            self.core.write_to_screen(&list).log();
+
+            match self.core.minibuffer_continuous("action") {
+                Err(HError::MiniBufferEvent(NewInput(input))) => {
+                    ranked = command_palette::ranked(&entries, &input);
+                }
+                Ok(input) | Err(HError::MiniBufferEvent(Done(input))) => {
+                    ranked = command_palette::ranked(&entries, &input);
+                    break ranked.first().map(|&i| entries[i].action);
+                }
+                Err(HError::RefreshParent) => {
+                    self.refresh().log();
+                    self.draw().log();
+                }
+                _ => break None
+            }
+        };
+
+        self.core.minibuffer_clear().log();
+        self.core.clear().log();
+
+        match chosen {
+            Some(PaletteAction::FileBrowser(action)) => self.do_action(&action),
+            Some(PaletteAction::FileList(action)) => {
+                self.main_widget_mut()?.do_action(&action)?;
+                self.save_tab_settings()
+            }
+            // Only the tab view can do these
+            Some(PaletteAction::Tab(action)) => Err(HError::FileBrowserNeedTabAction(action)),
+            None => Ok(())
+        }
+    }
+
     pub fn quick_action(&self) -> HResult<()> {
         let files = self.selected_files()?;
         let files = if files.len() > 0 { files }
@@ -1387,6 +3206,35 @@ impl FileBrowser {
         Ok(())
     }
 
+    pub fn run_last_quick_action(&self) -> HResult<()> {
+        let files = self.selected_files()?;
+        let files = if files.len() > 0 { files }
+        else { vec![self.selected_file()?.clone()] };
+
+        let core = self.preview_widget()?.get_core()?.clone();
+        let proc_view = self.proc_view.clone();
+
+        crate::quick_actions::run_last(files, core, proc_view)
+    }
+
+    pub fn open_with(&mut self) -> HResult<()> {
+        let files = self.selected_files()?;
+        let files = if files.len() > 0 { files }
+        else { vec![self.selected_file()?.clone()] };
+
+        let core = self.preview_widget()?.get_core()?.clone();
+        let proc_view = self.proc_view.clone();
+
+        // Without any registered applications just leave it to xdg-open
+        match crate::quick_actions::open_with(files, core, proc_view)? {
+            true => Ok(()),
+            false => {
+                let file = self.selected_file()?;
+                self.xdg_open(&file)
+            }
+        }
+    }
+
     pub fn get_footer(&self) -> HResult<String> {
         let xsize = self.get_coordinates()?.xsize();
         let ypos = self.get_coordinates()?.position().y();
@@ -1396,11 +3244,32 @@ impl FileBrowser {
         let permissions = file.pretty_print_permissions().unwrap_or("NOPERMS".into());
         let user = file.pretty_user().unwrap_or("NOUSER".into());
         let group = file.pretty_group().unwrap_or("NOGROUP".into());
-        let mtime = file.pretty_mtime().unwrap_or("NOMTIME".into());
+        let mtime = file.pretty_mtime(&self.core.config().date_format)
+                        .unwrap_or("NOMTIME".into());
         let target = if let Some(target) = &file.target {
             "--> ".to_string() + &target.short_string()
         } else { "".to_string() };
 
+        let size_format = self.core.config().size_format();
+
+        // Walking the directory can take a while
+        let size = match (file.is_dir(), crate::files::dir_bytes()) {
+            // The length and what the file takes up on disk can differ a lot
+            (false, _) => file.meta()
+                .and_then(|meta| {
+                    let meta = meta.as_ref()?;
+                    Some(format!("{} ({} on disk) ",
+                                 size_format.format(meta.len()),
+                                 size_format.format(crate::files::disk_size(meta))))
+                })
+                .unwrap_or_default(),
+            (true, bytes) => match file.calculate_size(size_format) {
+                Ok(size) if bytes => format!("{} ", size),
+                Ok(size) => format!("{} entries ", size),
+                Err(_) => format!("{} ", crate::files::tick_str())
+            }
+        };
+
         let main_widget = self.main_widget()?;
         let selection = main_widget.get_selection() + 1;
         let file_count = main_widget.content.len();
@@ -1410,7 +3279,15 @@ impl FileBrowser {
                                  selection,
                                  file_count,
                                  digits = digits);
-        let count_xpos = xsize - file_count.len() as u16;
+        let file_count = match &main_widget.content.meta_progress {
+            Some(progress) => format!("{}meta {}/{} | {}",
+                                      crate::files::tick_str(),
+                                      progress.done.load(std::sync::atomic::Ordering::Relaxed),
+                                      progress.total,
+                                      file_count),
+            None => file_count
+        };
+        let count_xpos = xsize.saturating_sub(term::string_len(&file_count) as u16);
         let count_ypos = ypos + self.get_coordinates()?.ysize();
 
         let fs = self.fs_stat
@@ -1421,23 +3298,31 @@ impl FileBrowser {
         let dev = fs.get_dev().unwrap_or(String::from(""));
         let free_space = fs.get_free();
         let total_space = fs.get_total();
-        let space = format!("{}{} / {}",
+        // Only once there's something in it
+        let trash = crate::file_ops::trash_size(self.core.get_sender())
+            .filter(|&size| size > 0)
+            .map(|size| format!("Trash: {} | ", size_format.format(size)))
+            .unwrap_or_default();
+
+        let space = format!("{}{}{} / {}",
+                            trash,
                             dev,
                             free_space,
                             total_space);
 
-        let space_xpos = count_xpos - space.len() as u16 - 5; // - 3;
+        let space_xpos = count_xpos.saturating_sub(term::string_len(&space) as u16 + 5); // - 3;
 
-        let status = format!("{} {}:{} {}{} {}{}",
+        let status = format!("{} {}:{} {}{}{} {}{}",
                              permissions,
                              user,
                              group,
                              crate::term::header_color(),
+                             size,
                              mtime,
                              crate::term::color_yellow(),
                              target
         );
-        let status = crate::term::sized_string_u(&status, (xsize-1) as usize);
+        let status = crate::term::sized_string_u(&status, xsize.saturating_sub(1) as usize);
 
         let status = format!("{}{}{}{}{}{} | {}",
                              status,
@@ -1460,6 +3345,14 @@ impl Widget for FileBrowser {
         Ok(&mut self.core)
     }
 
+    #[cfg(feature = "dump-view")]
+    fn dump_view(&self) -> HResult<()> {
+        let widget = self.main_widget()?;
+        let selection = widget.selected_file();
+
+        crate::view_dump::ViewDump::new(&widget.content, Some(selection)).write()
+    }
+
     fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
         self.core.coordinates = coordinates.clone();
         self.columns.set_coordinates(&coordinates).log();
@@ -1503,7 +3396,24 @@ impl Widget for FileBrowser {
         if &path == "/" { path.pop(); }
 
 
-        let pretty_path = format!("{}/{}{}", path, &color, name );
+        // Otherwise nothing tells that the preview is of another file
+        let pinned = match self.preview_widget().ok().and_then(|preview| preview.pinned()) {
+            Some(pinned) => format!("{}  [pinned: {}]", crate::term::header_color(), pinned.name),
+            None => String::new()
+        };
+
+        let read_only = match self.core.config().read_only {
+            true => format!("{}  [read-only]", crate::term::header_color()),
+            false => String::new()
+        };
+
+        let pretty_path = format!("{}/{}{}{}{}{}",
+                                  path,
+                                  &color,
+                                  name,
+                                  self.header_info(),
+                                  pinned,
+                                  read_only);
         let sized_path = crate::term::sized_string(&pretty_path, xsize);
         Ok(sized_path.to_string())
     }
@@ -1515,21 +3425,27 @@ impl Widget for FileBrowser {
                              .lock();
         let status = status.as_mut()
                            .take();
-        let active = self.columns
-                         .active
-                         .unwrap_or(1);
+        let preview_active = self.columns.active == Some(self.preview_index());
 
-        match (status, active) {
+        match (status, preview_active) {
             (Some(status), _) => Ok(term::sized_string_u(&status, xsize)),
-            (_,            2) => self.preview_widget()?.render_footer(),
+            (_,         true) => self.preview_widget()?.render_footer(),
             _                 => self.get_footer(),
         }
     }
 
     fn refresh(&mut self) -> HResult<()> {
         self.set_title().log();
+        self.forget_tab_name();
+        // Only the main column shows details, it's a new list after moving
+        self.main_widget_mut()
+            .map(|main| main.details = crate::listview::show_details())
+            .ok();
+        self.leave_deleted_cwd().log();
+        self.take_search_result().log();
         self.columns.refresh().log();
         self.set_left_selection().log();
+        self.set_grandparent_selection().log();
         self.set_cwd().log();
         if !self.columns.zoom_active { self.update_preview().log(); }
         self.columns.refresh().log();
@@ -1544,23 +3460,40 @@ impl Widget for FileBrowser {
         // Special handling for preview zoom
         let binds = self.search_in();
         let action = binds.get(key);
+        let main_index = self.main_index();
+        let preview_index = self.preview_index();
 
         match (action, self.columns.active) {
-            (Some(FileBrowserAction::ZoomPreview), Some(2)) => {
+            (Some(FileBrowserAction::ZoomPreview), Some(i)) if i == preview_index => {
                 self.toggle_colums();
                 return Ok(());
             }
-            (Some(FileBrowserAction::ZoomPreview), Some(1)) => {
+            (Some(FileBrowserAction::ZoomPreview), Some(i)) if i == main_index => {
                 self.zoom_preview();
                 return Ok(());
             }
-            (_, Some(2)) => {
+            (Some(FileBrowserAction::EditFile), Some(i)) if i == preview_index => {
+                self.edit_selected_file()?;
+                return Ok(());
+            }
+            (Some(FileBrowserAction::ResetLayout), Some(i)) if i == preview_index => {
+                self.reset_layout()?;
+                return Ok(());
+            }
+            (_, Some(i)) if i == preview_index => {
                 self.columns.active_widget_mut()?.on_key(key)?;
                 return Ok(());
             }
             _ => {}
         }
 
+        // Scroll text previews without switching to the preview column
+        if let Some(action) = self.core.config().keybinds.preview.get(key) {
+            if self.preview_widget_mut()?.scroll_text(action) {
+                return Ok(());
+            }
+        }
+
         match self.do_key(key) {
             Err(HError::WidgetUndefinedKeyError{..}) => {
                 match self.main_widget_mut()?.on_key(key) {
@@ -1611,8 +3544,15 @@ impl Acting for FileBrowser {
 
     fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
         use FileBrowserAction::*;
+
+        if action.is_mutating() && self.core.config().read_only {
+            return self.core.show_status(&format!("{} is disabled in read-only mode",
+                                                  action));
+        }
+
         match action {
-            Quit => HError::quit()?,
+            Quit => self.quit()?,
+            ForceQuit => self.force_quit()?,
             QuitWithDir => self.quit_with_dir()?,
             LeftColumnDown => self.move_down_left_widget()?,
             LeftColumnUp => self.move_up_left_widget()?,
@@ -1624,14 +3564,55 @@ impl Acting for FileBrowser {
             GotoPrevCwd => self.goto_prev_cwd()?,
             ShowBookmarks => self.goto_bookmark()?,
             AddBookmark => self.add_bookmark()?,
+            GotoFrecent => self.goto_frecent()?,
             ShowProcesses => self.show_procview()?,
             ShowLog => self.show_log()?,
             ShowQuickActions => self.quick_action()?,
+            RunLastQuickAction => self.run_last_quick_action()?,
+            Diff => self.diff_files()?,
+            ExtractArchive => self.extract_archive()?,
+            CompressFiles => self.compress_files()?,
+            LinkToBookmark => self.file_to_bookmark(false)?,
+            CopyToBookmark => self.file_to_bookmark(true)?,
+            ShowMounts => self.show_mounts()?,
+            // Tab implementation creates the tab
+            OpenInNewTab => Err(HError::FileBrowserNeedNewTab(self.selected_file()?.path))?,
             RunSubshell => self.run_subshell()?,
             ToggleColumns => self.toggle_colums(),
             ZoomPreview => self.zoom_preview(),
             // Tab implementation needs to call exec_cmd because ALL files are needed
-            ExecCmd => Err(HError::FileBrowserNeedTabFiles)?
+            ExecCmd => Err(HError::FileBrowserNeedTabFiles)?,
+            EditDirConfig => self.edit_dir_config()?,
+            OpenWith => self.open_with()?,
+            FileInfo => self.show_file_info()?,
+            // Tab implementation knows the destination
+            CopyFiles => Err(HError::FileBrowserNeedTabDirs(FileOp::Copy))?,
+            MoveFiles => Err(HError::FileBrowserNeedTabDirs(FileOp::Move))?,
+            DeleteFiles => self.delete_files()?,
+            TrashFiles => self.trash_files()?,
+            Undo => self.undo()?,
+            Redo => self.redo()?,
+            RepeatLastAction => self.repeat_action()?,
+            SymlinkFiles => Err(HError::FileBrowserNeedTabSelection)?,
+            CreateSymlink => self.create_symlink()?,
+            BulkRename => self.bulk_rename()?,
+            AddToScratch => self.add_to_scratch()?,
+            XdgOpen => self.xdg_open_selected()?,
+            GotoScratch => self.goto_scratch()?,
+            RecordMacro => self.core.get_sender().send(Events::RecordMacro)?,
+            PlayMacro(times) => self.core.get_sender().send(Events::PlayMacro(*times))?,
+            EditKeyBindings => self.edit_keybindings()?,
+            EditFile => self.edit_selected_file()?,
+            CommandPalette => self.command_palette()?,
+            GotoConfigDir => self.goto_config_dir()?,
+            FindDuplicates => self.find_duplicates()?,
+            GrepOpen => self.grep_open()?,
+            FollowLink => self.follow_link()?,
+            PinPreview => self.toggle_pin_preview()?,
+            GrowPreview => self.grow_preview()?,
+            ShrinkPreview => self.shrink_preview()?,
+            ResetLayout => self.reset_layout()?,
+            TogglePreviewHidden => self.toggle_preview_hidden()?
         }
         Ok(())
     }
@@ -1646,3 +3627,81 @@ impl PartialEq for FileBrowser {
         }
     }
 }
+
+
+#[test]
+fn test_nearest_existing_dir() {
+    let tmp = crate::test_dir::TestDir::new("nearest");
+    let cwd = tmp.join("cwd");
+    std::fs::create_dir_all(cwd.join("subdir")).unwrap();
+
+    assert_eq!(nearest_existing_dir(&cwd), Some(cwd.as_path()));
+
+    std::fs::remove_dir_all(&cwd).unwrap();
+
+    assert_eq!(nearest_existing_dir(&cwd.join("subdir")), Some(&*tmp));
+    assert_eq!(nearest_existing_dir(&cwd), Some(&*tmp));
+}
+
+#[test]
+fn test_deleted_cwd_target() {
+    let dir = crate::test_dir::TestDir::new("cwd");
+    let tmp = dir.to_path_buf();
+    let cwd = tmp.join("cwd");
+    std::fs::create_dir_all(cwd.join("subdir")).unwrap();
+    std::fs::create_dir_all(tmp.join("a")).unwrap();
+    std::fs::write(tmp.join("z"), "").unwrap();
+
+    std::fs::remove_dir_all(&cwd).unwrap();
+
+    // Up to the parent, with the entry after the deleted one selected
+    assert_eq!(deleted_cwd_target(&cwd), Some((tmp.clone(), Some(tmp.join("z")))));
+    assert_eq!(deleted_cwd_target(&cwd.join("subdir")),
+               Some((tmp.clone(), Some(tmp.join("z")))));
+
+    // The last one when it was at the end
+    std::fs::remove_file(tmp.join("z")).unwrap();
+    assert_eq!(deleted_cwd_target(&cwd), Some((tmp.clone(), Some(tmp.join("a")))));
+
+    std::fs::remove_dir_all(tmp.join("a")).unwrap();
+    assert_eq!(deleted_cwd_target(&cwd), Some((tmp.clone(), None)));
+}
+
+#[test]
+fn test_editor_cmd() {
+    let path = Path::new("/tmp/foo bar");
+    let cmd = |editor, line_cmd, line| {
+        editor_cmd(editor, line_cmd, path, line)
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(cmd("emacsclient -t", "{editor} +{line} {file}", Some(42)),
+               vec!["emacsclient", "-t", "+42", "/tmp/foo bar"]);
+    assert_eq!(cmd("code", "code --goto {file}:{line}", Some(3)),
+               vec!["code", "--goto", "/tmp/foo bar:3"]);
+    assert_eq!(cmd("vi", "{editor} +{line}", Some(1)),
+               vec!["vi", "+1", "/tmp/foo bar"]);
+    assert_eq!(cmd("vi", "{editor} +{line} {file}", None),
+               vec!["vi", "/tmp/foo bar"]);
+}
+
+#[test]
+fn test_shell_quote() {
+    assert_eq!(shell_quote("/tmp/foo.txt"), "/tmp/foo.txt");
+    assert_eq!(shell_quote("/tmp/foo bar"), "'/tmp/foo bar'");
+    assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    assert_eq!(shell_quote("$HOME"), "'$HOME'");
+    assert_eq!(shell_quote(""), "''");
+}
+
+#[test]
+fn test_shift_preview() {
+    assert_eq!(shift_preview(vec![20, 30, 50], 0), vec![20, 30, 50]);
+    assert_eq!(shift_preview(vec![20, 30, 50], 10), vec![160, 240, 600]);
+    assert_eq!(shift_preview(vec![20, 30, 50], -10), vec![240, 360, 400]);
+    // Never all of it, and a hidden preview stays hidden
+    assert_eq!(shift_preview(vec![20, 30, 50], 100), vec![1, 1, 999]);
+    assert_eq!(shift_preview(vec![40, 60, 0], 10), vec![40, 60, 0]);
+}
@@ -0,0 +1,495 @@
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::os::unix::ffi::OsStrExt;
+use std::ffi::CString;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime};
+
+use termion::event::Key;
+use chrono::TimeZone;
+
+use crate::files::{File, SizeFormat};
+use crate::textview::TextView;
+use crate::widget::{Widget, WidgetCore};
+use crate::coordinates::Coordinates;
+use crate::fail::{HResult, HError};
+use crate::config::Config;
+
+
+// Don't let huge directory trees block the UI forever
+const DIR_SIZE_TIMEOUT: Duration = Duration::from_secs(2);
+const MEDIA_INFO_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub struct FileInfoView {
+    core: WidgetCore,
+    textview: TextView,
+    file: File
+}
+
+impl FileInfoView {
+    pub fn new(core: &WidgetCore, file: &File) -> HResult<FileInfoView> {
+        let mut textview = TextView::new_blank(core);
+        textview.set_lines(file_info(file, &core.config())?)?;
+
+        Ok(FileInfoView {
+            core: core.clone(),
+            textview: textview,
+            file: file.clone()
+        })
+    }
+}
+
+fn line(label: &str, value: impl std::fmt::Display) -> String {
+    format!("{}{:14}{}{}",
+            crate::term::highlight_color(),
+            label,
+            crate::term::reset(),
+            value)
+}
+
+// The exact number of bytes goes along with the short size
+fn size_with_bytes(size: u64, size_format: SizeFormat) -> String {
+    match size_format.format(size) {
+        short if short == size.to_string() => format!("{} bytes", size),
+        short => format!("{} ({} bytes)", short, size)
+    }
+}
+
+fn pretty_time(secs: i64, nsecs: i64) -> String {
+    let time = chrono::Local.timestamp(secs, nsecs as u32);
+    time.format("%F %T%.f %z").to_string()
+}
+
+fn type_name(meta: &std::fs::Metadata) -> &'static str {
+    let file_type = meta.file_type();
+
+    if file_type.is_symlink() { "symbolic link" }
+    else if file_type.is_dir() { "directory" }
+    else if file_type.is_fifo() { "named pipe" }
+    else if file_type.is_socket() { "socket" }
+    else if file_type.is_char_device() { "character device" }
+    else if file_type.is_block_device() { "block device" }
+    else { "regular file" }
+}
+
+// Like ls -l, including setuid/setgid/sticky bits
+pub fn mode_string(meta: &std::fs::Metadata) -> String {
+    let mode = meta.mode();
+    let file_type = meta.file_type();
+
+    let kind = if file_type.is_symlink() { 'l' }
+    else if file_type.is_dir() { 'd' }
+    else if file_type.is_fifo() { 'p' }
+    else if file_type.is_socket() { 's' }
+    else if file_type.is_char_device() { 'c' }
+    else if file_type.is_block_device() { 'b' }
+    else { '-' };
+
+    let bit = |mask: u32, chr: char| if mode & mask != 0 { chr } else { '-' };
+    let exec = |mask: u32, special: u32, set: char, unset: char| {
+        match (mode & mask != 0, mode & special != 0) {
+            (true, true) => set,
+            (false, true) => unset,
+            (true, false) => 'x',
+            (false, false) => '-'
+        }
+    };
+
+    let mut perms = String::new();
+    perms.push(kind);
+    perms.push(bit(0o400, 'r'));
+    perms.push(bit(0o200, 'w'));
+    perms.push(exec(0o100, 0o4000, 's', 'S'));
+    perms.push(bit(0o040, 'r'));
+    perms.push(bit(0o020, 'w'));
+    perms.push(exec(0o010, 0o2000, 's', 'S'));
+    perms.push(bit(0o004, 'r'));
+    perms.push(bit(0o002, 'w'));
+    perms.push(exec(0o001, 0o1000, 't', 'T'));
+
+    format!("{} ({:04o})", perms, mode & 0o7777)
+}
+
+// Returns size, number of files and if it finished in time
+fn dir_size(dir: &Path, deadline: Instant) -> (u64, usize, bool) {
+    let mut size = 0;
+    let mut files = 0;
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        if Instant::now() > deadline {
+            return (size, files, false);
+        }
+
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let meta = match entry.path().symlink_metadata() {
+                Ok(meta) => meta,
+                Err(_) => continue
+            };
+
+            size += meta.len();
+            files += 1;
+
+            if meta.is_dir() {
+                dirs.push(entry.path());
+            }
+        }
+    }
+
+    (size, files, true)
+}
+
+// major:minor, encoded the same way glibc does
+fn dev_numbers(dev: u64) -> String {
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+    format!("{}:{}", major, minor)
+}
+
+pub fn xattrs(path: &Path) -> HResult<Vec<(String, Vec<u8>)>> {
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| HError::Error(e.to_string()))?;
+
+    let len = unsafe {
+        libc::llistxattr(c_path.as_ptr(), std::ptr::null_mut(), 0)
+    };
+
+    if len < 0 {
+        return Err(std::io::Error::last_os_error())?;
+    }
+
+    let mut names = vec![0u8; len as usize];
+    let len = unsafe {
+        libc::llistxattr(c_path.as_ptr(),
+                         names.as_mut_ptr() as *mut libc::c_char,
+                         names.len())
+    };
+
+    if len < 0 {
+        return Err(std::io::Error::last_os_error())?;
+    }
+
+    names.truncate(len as usize);
+
+    let xattrs = names.split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+        .filter_map(|name| {
+            let c_name = CString::new(name).ok()?;
+
+            let len = unsafe {
+                libc::lgetxattr(c_path.as_ptr(), c_name.as_ptr(), std::ptr::null_mut(), 0)
+            };
+            if len < 0 { return None; }
+
+            let mut value = vec![0u8; len as usize];
+            let len = unsafe {
+                libc::lgetxattr(c_path.as_ptr(),
+                                c_name.as_ptr(),
+                                value.as_mut_ptr() as *mut libc::c_void,
+                                value.len())
+            };
+            if len < 0 { return None; }
+            value.truncate(len as usize);
+
+            Some((String::from_utf8_lossy(name).to_string(), value))
+        })
+        .collect();
+
+    Ok(xattrs)
+}
+
+fn pretty_xattr(value: &[u8]) -> String {
+    let text = value.iter()
+        .take_while(|&&byte| byte != 0)
+        .all(|&byte| byte.is_ascii_graphic() || byte == b' ');
+
+    match text {
+        true => String::from_utf8_lossy(value)
+            .trim_end_matches('\0')
+            .to_string(),
+        false => value.iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>()
+    }
+}
+
+fn be16(bytes: &[u8]) -> u32 {
+    (bytes[0] as u32) << 8 | bytes[1] as u32
+}
+
+fn le16(bytes: &[u8]) -> u32 {
+    (bytes[1] as u32) << 8 | bytes[0] as u32
+}
+
+fn be32(bytes: &[u8]) -> u32 {
+    be16(&bytes[0..2]) << 16 | be16(&bytes[2..4])
+}
+
+fn le32(bytes: &[u8]) -> u32 {
+    le16(&bytes[2..4]) << 16 | le16(&bytes[0..2])
+}
+
+// JPEG has the size in the SOF segment, which can come after big EXIF data
+fn jpeg_info(file: &mut std::fs::File) -> Option<(u32, u32, String)> {
+    let mut marker = [0u8; 4];
+    file.seek(SeekFrom::Start(2)).ok()?;
+
+    loop {
+        file.read_exact(&mut marker).ok()?;
+        if marker[0] != 0xFF { return None; }
+
+        let len = be16(&marker[2..4]) as i64;
+
+        match marker[1] {
+            0xC0..=0xCF if marker[1] != 0xC4 &&
+                           marker[1] != 0xC8 &&
+                           marker[1] != 0xCC => {
+                let mut sof = [0u8; 6];
+                file.read_exact(&mut sof).ok()?;
+
+                let color = match sof[5] {
+                    1 => "grayscale",
+                    3 => "YCbCr",
+                    4 => "CMYK",
+                    _ => "unknown colors"
+                };
+
+                return Some((be16(&sof[3..5]),
+                             be16(&sof[1..3]),
+                             format!("{}, {} bit", color, sof[0])));
+            }
+            _ => { file.seek(SeekFrom::Current(len - 2)).ok()?; }
+        }
+    }
+}
+
+// Only reads the header, so it's fast even for huge images
+pub fn image_info(path: &Path) -> Option<(u32, u32, String)> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut header = [0u8; 32];
+    file.read_exact(&mut header).ok()?;
+
+    match &header {
+        h if h.starts_with(b"\x89PNG\r\n\x1a\n") => {
+            let color = match h[25] {
+                0 => "grayscale",
+                2 => "RGB",
+                3 => "indexed",
+                4 => "grayscale with alpha",
+                6 => "RGBA",
+                _ => "unknown colors"
+            };
+            Some((be32(&h[16..20]), be32(&h[20..24]), format!("{}, {} bit", color, h[24])))
+        }
+        h if h.starts_with(b"GIF8") => {
+            let colors = match h[10] & 0x80 {
+                0 => String::from("indexed"),
+                _ => format!("indexed, {} colors", 2 << (h[10] & 0x7))
+            };
+            Some((le16(&h[6..8]), le16(&h[8..10]), colors))
+        }
+        h if h.starts_with(b"BM") => {
+            let height = (le32(&h[22..26]) as i32).abs() as u32;
+            Some((le32(&h[18..22]), height, format!("{} bit", le16(&h[28..30]))))
+        }
+        h if h.starts_with(&[0xFF, 0xD8]) => jpeg_info(&mut file),
+        h if h.starts_with(b"RIFF") && &h[8..12] == b"WEBP" => {
+            match &h[12..16] {
+                b"VP8 " => Some((le16(&h[26..28]) & 0x3fff,
+                                 le16(&h[28..30]) & 0x3fff,
+                                 String::from("lossy"))),
+                b"VP8L" => {
+                    let bits = le32(&h[21..25]);
+                    Some(((bits & 0x3fff) + 1,
+                          ((bits >> 14) & 0x3fff) + 1,
+                          String::from("lossless")))
+                }
+                b"VP8X" => {
+                    let width = le32(&h[24..28]) & 0xffffff;
+                    let height = le32(&h[27..31]) & 0xffffff;
+                    Some((width + 1, height + 1, String::from("extended")))
+                }
+                _ => None
+            }
+        }
+        _ => None
+    }
+}
+
+// Asks the media previewer, so hunter itself doesn't need gstreamer
+fn media_info(path: &Path, previewer: &str) -> Vec<String> {
+    let child = Command::new(previewer)
+        .args(&["0", "0", "0", "0", "0", "info", "false", "false", "unicode"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(_) => return vec![]
+    };
+
+    let deadline = Instant::now() + MEDIA_INFO_TIMEOUT;
+    while let Ok(None) = child.try_wait() {
+        if Instant::now() > deadline {
+            child.kill().ok();
+            child.wait().ok();
+            return vec![line("Media", "timed out reading media info")];
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    let mut output = String::new();
+    child.stdout
+        .take()
+        .map(|mut stdout| stdout.read_to_string(&mut output).ok());
+
+    output.lines()
+        .filter_map(|info| {
+            let mut info = info.splitn(2, ": ");
+            Some(line(info.next()?, info.next()?))
+        })
+        .collect()
+}
+
+pub fn file_info(file: &File, config: &Config) -> HResult<Vec<String>> {
+    let meta = file.path.symlink_metadata()?;
+    let mut lines = vec![];
+
+    lines.push(line("Path", file.path.to_string_lossy()));
+    lines.push(line("Type", type_name(&meta)));
+
+    if meta.file_type().is_symlink() {
+        let target = std::fs::read_link(&file.path)?;
+        let broken = match file.path.exists() {
+            true => "",
+            false => " (broken)"
+        };
+        lines.push(line("Target", format!("{}{}", target.to_string_lossy(), broken)));
+    }
+
+    // Reading special files can hang or crash the mime detection
+    let file_type = meta.file_type();
+    if file.path.is_file() {
+        if let Ok(mime) = file.get_mime() {
+            lines.push(line("MIME", &mime));
+
+            match mime.type_().as_str() {
+                "image" => {
+                    if let Some((width, height, color)) = image_info(&file.path) {
+                        lines.push(line("Dimensions", format!("{}x{}", width, height)));
+                        lines.push(line("Color", color));
+                    }
+                }
+                "video" | "audio" if config.media_available() => {
+                    lines.extend(media_info(&file.path, &config.media_previewer));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let size_format = config.size_format();
+    lines.push(line("Size", size_with_bytes(meta.len(), size_format)));
+    if !meta.is_dir() {
+        let disk_size = crate::files::disk_size(&meta);
+        lines.push(line("On disk", size_with_bytes(disk_size, size_format)));
+    }
+
+    if meta.is_dir() {
+        let deadline = Instant::now() + DIR_SIZE_TIMEOUT;
+        let (size, files, done) = dir_size(&file.path, deadline);
+        let partial = match done {
+            true => "",
+            false => "at least "
+        };
+        lines.push(line("Content",
+                        format!("{}{} in {} files",
+                                partial,
+                                size_with_bytes(size, size_format),
+                                files)));
+    }
+
+    let user = users::get_user_by_uid(meta.uid())
+        .map(|user| user.name().to_string_lossy().to_string())
+        .unwrap_or(String::from("?"));
+    let group = users::get_group_by_gid(meta.gid())
+        .map(|group| group.name().to_string_lossy().to_string())
+        .unwrap_or(String::from("?"));
+
+    lines.push(line("Permissions", mode_string(&meta)));
+    lines.push(line("Owner", format!("{} ({})", user, meta.uid())));
+    lines.push(line("Group", format!("{} ({})", group, meta.gid())));
+
+    lines.push(line("Accessed", pretty_time(meta.atime(), meta.atime_nsec())));
+    lines.push(line("Modified", pretty_time(meta.mtime(), meta.mtime_nsec())));
+    lines.push(line("Changed", pretty_time(meta.ctime(), meta.ctime_nsec())));
+
+    let born = meta.created()
+        .ok()
+        .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|time| pretty_time(time.as_secs() as i64, time.subsec_nanos() as i64))
+        .unwrap_or(String::from("unavailable"));
+    lines.push(line("Born", born));
+
+    lines.push(line("Inode", meta.ino()));
+    lines.push(line("Links", meta.nlink()));
+    lines.push(line("Device", dev_numbers(meta.dev())));
+
+    if file_type.is_char_device() || file_type.is_block_device() {
+        lines.push(line("Device type", dev_numbers(meta.rdev())));
+    }
+
+    match xattrs(&file.path) {
+        Ok(ref xattrs) if xattrs.len() > 0 => {
+            lines.push(String::new());
+            lines.push(line("Extended attributes", ""));
+            for (name, value) in xattrs {
+                lines.push(format!("  {} = {}", name, pretty_xattr(value)));
+            }
+        }
+        Ok(_) => lines.push(line("Xattrs", "none")),
+        Err(err) => lines.push(line("Xattrs", format!("unavailable: {}", err)))
+    }
+
+    Ok(lines)
+}
+
+impl Widget for FileInfoView {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        self.core.coordinates = coordinates.clone();
+        self.textview.set_coordinates(coordinates)
+    }
+    fn render_header(&self) -> HResult<String> {
+        Ok(format!("File info: {}", self.file.short_string()))
+    }
+    fn render_footer(&self) -> HResult<String> {
+        self.textview.render_footer()
+    }
+    fn refresh(&mut self) -> HResult<()> {
+        self.textview.refresh()
+    }
+    fn get_drawlist(&self) -> HResult<String> {
+        self.textview.get_drawlist()
+    }
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        match key {
+            Key::Esc | Key::Ctrl('c') | Key::Char('q') => HError::popup_finnished(),
+            _ => self.textview.on_key(key)
+        }
+    }
+}
@@ -0,0 +1,564 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+
+use parking_lot::Mutex;
+use async_value::Stale;
+
+use crate::fail::{HResult, HError};
+use crate::files::File;
+use crate::widget::Events;
+
+
+const BUFFER_SIZE: usize = 128 * 1024;
+const UNDO_LIMIT: usize = 100;
+
+// Finished jobs, newest last
+lazy_static! {
+    static ref HISTORY: Mutex<Vec<FileOpRecord>> = Mutex::new(vec![]);
+    // Undone jobs, until something new happens
+    static ref REDO: Mutex<Vec<FileOpRecord>> = Mutex::new(vec![]);
+    // Size of the home trash, made again after jobs put things in or out
+    static ref TRASH_SIZE: Mutex<Option<Arc<(AtomicBool, AtomicUsize)>>> = Mutex::new(None);
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FileOp {
+    Copy,
+    Move,
+    Delete,
+    Trash,
+    // Moves files out of the trash, removing their .trashinfo
+    Restore
+}
+
+impl std::fmt::Display for FileOp {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let text = match self {
+            FileOp::Copy => "copy",
+            FileOp::Move => "move",
+            FileOp::Delete => "delete",
+            FileOp::Trash => "trash",
+            FileOp::Restore => "restore",
+        };
+        write!(formatter, "{}", text)
+    }
+}
+
+// Bytes for copy/move/trash, number of files for delete
+fn total_size(path: &Path, op: FileOp) -> u64 {
+    let meta = match path.symlink_metadata() {
+        Ok(meta) => meta,
+        Err(_) => return 0
+    };
+
+    let own_size = match op {
+        FileOp::Delete => 1,
+        _ if meta.is_file() => meta.len(),
+        _ => 0
+    };
+
+    if !meta.is_dir() {
+        return own_size;
+    }
+
+    fs::read_dir(path)
+        .map(|entries| {
+            entries.filter_map(|entry| entry.ok())
+                   .map(|entry| total_size(&entry.path(), op))
+                   .sum::<u64>()
+        })
+        .unwrap_or(0) + own_size
+}
+
+// Which list a job's record goes on when it's done
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Recording {
+    New,
+    Undo,
+    Redo
+}
+
+// What a job did, enough to reverse it. For each path in sources the
+// path it ended up at is in dests, which is empty for deletions.
+#[derive(Debug, Clone)]
+pub struct FileOpRecord {
+    pub op: FileOp,
+    pub sources: Vec<PathBuf>,
+    pub dests: Vec<PathBuf>
+}
+
+impl FileOpRecord {
+    // The job putting things back. Its record goes on the redo list when
+    // undoing, and back on the undo list when redoing.
+    pub fn undo_job(&self, recording: Recording) -> HResult<FileJob> {
+        let (op, sources, dests) = match self.op {
+            FileOp::Delete => {
+                Err(HError::Error(String::from("Deleted files can't be restored")))?
+            }
+            FileOp::Copy => (FileOp::Trash, &self.dests, vec![]),
+            FileOp::Move => (FileOp::Move, &self.dests, self.sources.clone()),
+            FileOp::Trash => (FileOp::Restore, &self.dests, self.sources.clone()),
+            FileOp::Restore => (FileOp::Trash, &self.dests, vec![])
+        };
+
+        for source in sources {
+            if source.symlink_metadata().is_err() {
+                return Err(HError::Error(format!("{} is gone",
+                                                 source.to_string_lossy())));
+            }
+        }
+
+        for dest in &dests {
+            if dest.symlink_metadata().is_ok() {
+                return Err(HError::Error(format!("{} exists, it would be overwritten",
+                                                 dest.to_string_lossy())));
+            }
+        }
+
+        let mut job = FileJob::new(op, sources.clone(), None).with_dests(dests);
+        job.recording = recording;
+        Ok(job)
+    }
+}
+
+fn push_limited(records: &mut Vec<FileOpRecord>, record: FileOpRecord) {
+    records.push(record);
+
+    if records.len() > UNDO_LIMIT {
+        records.remove(0);
+    }
+}
+
+// Something new happened, so what was undone can't be redone anymore
+pub fn push_history(record: FileOpRecord) {
+    REDO.lock().clear();
+    push_limited(&mut HISTORY.lock(), record);
+}
+
+pub fn push_record(record: FileOpRecord, recording: Recording) {
+    match recording {
+        Recording::New => push_history(record),
+        Recording::Undo => push_limited(&mut REDO.lock(), record),
+        Recording::Redo => push_limited(&mut HISTORY.lock(), record)
+    }
+}
+
+pub fn pop_history() -> Option<FileOpRecord> {
+    HISTORY.lock().pop()
+}
+
+pub fn pop_redo() -> Option<FileOpRecord> {
+    REDO.lock().pop()
+}
+
+// Walks the trash in the background the first time, None until that's done
+pub fn trash_size(sender: Sender<Events>) -> Option<u64> {
+    let mut trash_size = TRASH_SIZE.lock();
+
+    if let Some(size) = trash_size.as_ref() {
+        return match size.0.load(Ordering::Relaxed) {
+            true => Some(size.1.load(Ordering::Relaxed) as u64),
+            false => None
+        };
+    }
+
+    let size: Arc<(AtomicBool, AtomicUsize)> = Arc::default();
+    *trash_size = Some(size.clone());
+
+    std::thread::spawn(move || {
+        crate::files::start_ticking(sender);
+
+        let files = crate::paths::trash_path().map(|trash| trash.join("files"));
+        let bytes = files.and_then(|files| crate::files::recursive_size(&files,
+                                                                        &Stale::new()))
+                         .unwrap_or(0);
+        size.1.store(bytes, Ordering::Relaxed);
+        size.0.store(true, Ordering::Relaxed);
+
+        crate::files::stop_ticking();
+    });
+
+    None
+}
+
+pub fn trash_is_empty() -> bool {
+    crate::paths::trash_path()
+        .and_then(|trash| Ok(fs::read_dir(trash.join("files"))?))
+        .map(|mut files| files.next().is_none())
+        .unwrap_or(true)
+}
+
+pub fn forget_trash_size() {
+    TRASH_SIZE.lock().take();
+}
+
+// Deletes everything in the home trash for good, with the .trashinfo files
+pub fn empty_trash() -> HResult<()> {
+    let result = remove_trash_contents();
+
+    // Even when it failed some of it can be gone
+    forget_trash_size();
+    result
+}
+
+fn remove_trash_contents() -> HResult<()> {
+    let trash = crate::paths::trash_path()?;
+
+    for dir in &["files", "info"] {
+        let entries = match fs::read_dir(trash.join(dir)) {
+            Ok(entries) => entries,
+            Err(_) => continue
+        };
+
+        for entry in entries {
+            let entry = entry?;
+            match entry.file_type()?.is_dir() {
+                true => fs::remove_dir_all(entry.path())?,
+                false => fs::remove_file(entry.path())?
+            }
+        }
+    }
+
+    // Cached sizes of trashed directories, from the spec
+    fs::remove_file(trash.join("directorysizes")).ok();
+
+    Ok(())
+}
+
+// A copy/move/delete/trash running in the process list
+pub struct FileJob {
+    pub op: FileOp,
+    pub sources: Vec<PathBuf>,
+    pub target: Option<PathBuf>,
+    // Where each source goes, instead of into target
+    pub dests: Vec<PathBuf>,
+    pub progress: Arc<Mutex<(u64, u64)>>,
+    pub cancel: Arc<AtomicBool>,
+    pub output: Arc<Mutex<String>>,
+    pub recording: Recording
+}
+
+impl FileJob {
+    pub fn new(op: FileOp, sources: Vec<PathBuf>, target: Option<PathBuf>) -> FileJob {
+        FileJob {
+            op: op,
+            sources: sources,
+            target: target,
+            dests: vec![],
+            progress: Arc::new(Mutex::new((0, 0))),
+            cancel: Arc::new(AtomicBool::new(false)),
+            output: Arc::new(Mutex::new(String::new())),
+            recording: Recording::New
+        }
+    }
+
+    pub fn with_dests(mut self, dests: Vec<PathBuf>) -> FileJob {
+        self.dests = dests;
+        self
+    }
+
+    pub fn describe(&self) -> String {
+        let what = match self.sources.len() {
+            1 => self.sources[0].to_string_lossy().to_string(),
+            n => format!("{} files", n)
+        };
+
+        match &self.target {
+            Some(target) => format!("{} {} to {}",
+                                    self.op,
+                                    what,
+                                    target.to_string_lossy()),
+            None => format!("{} {}", self.op, what)
+        }
+    }
+
+    fn log(&self, msg: String) {
+        let mut output = self.output.lock();
+        output.push_str(&msg);
+        output.push('\n');
+    }
+
+    fn check_cancelled(&self) -> HResult<()> {
+        match self.cancel.load(Ordering::Relaxed) {
+            true => Err(HError::Error(String::from("Cancelled"))),
+            false => Ok(())
+        }
+    }
+
+    fn advance(&self, amount: u64, report: &mut dyn FnMut(u64, u64)) {
+        let mut progress = self.progress.lock();
+        progress.0 += amount;
+        report(progress.0, progress.1);
+    }
+
+    // Returns true if everything went fine, errors are written to the output
+    pub fn run(&self, report: &mut dyn FnMut(u64, u64)) -> bool {
+        let total = self.sources
+            .iter()
+            .map(|source| total_size(source, self.op))
+            .sum();
+        self.progress.lock().1 = total;
+
+        let mut success = true;
+        let mut record = FileOpRecord {
+            op: self.op,
+            sources: vec![],
+            dests: vec![]
+        };
+
+        for (i, source) in self.sources.iter().enumerate() {
+            match self.run_one(source, self.dests.get(i), report) {
+                Ok(dest) => {
+                    record.sources.push(source.clone());
+                    record.dests.extend(dest);
+                }
+                Err(err) => {
+                    self.log(format!("Failed to {} {}: {}",
+                                     self.op,
+                                     source.to_string_lossy(),
+                                     err));
+                    success = false;
+                }
+            }
+
+            if self.check_cancelled().is_err() {
+                self.log(String::from("Cancelled!"));
+                success = false;
+                break;
+            }
+        }
+
+        // Only what actually happened can be undone
+        if record.sources.len() > 0 {
+            push_record(record, self.recording);
+        }
+
+        match self.op {
+            FileOp::Trash | FileOp::Restore => forget_trash_size(),
+            _ => {}
+        }
+
+        success
+    }
+
+    // Returns where the file ended up, unless it was deleted
+    fn run_one(&self,
+               source: &Path,
+               dest: Option<&PathBuf>,
+               report: &mut dyn FnMut(u64, u64)) -> HResult<Option<PathBuf>> {
+        if self.op == FileOp::Trash {
+            let dest = self.trash(&File::new_from_path(source)?, report)?;
+            self.log(format!("trash {} -> {}",
+                             source.to_string_lossy(),
+                             dest.to_string_lossy()));
+            return Ok(Some(dest));
+        }
+
+        let dest = match (dest, &self.target) {
+            (Some(dest), _) => dest.clone(),
+            (None, Some(target)) => target.join(source.file_name()?),
+            (None, None) => {
+                self.delete(source, report)?;
+                self.log(format!("delete {}", source.to_string_lossy()));
+                return Ok(None);
+            }
+        };
+
+        if dest.symlink_metadata().is_ok() {
+            return Err(HError::Error(format!("{} already exists",
+                                             dest.to_string_lossy())));
+        }
+
+        if dest.starts_with(source) {
+            return Err(HError::Error(String::from("Can't put a directory into itself")));
+        }
+
+        match self.op {
+            FileOp::Copy => self.copy(source, &dest, report)?,
+            FileOp::Move => self.move_path(source, &dest, report)?,
+            FileOp::Restore => {
+                // The original directory might be gone by now
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                self.move_path(source, &dest, report)?;
+                fs::remove_file(trash_info_path(source)?).ok();
+            }
+            FileOp::Delete | FileOp::Trash => unreachable!()
+        }
+
+        self.log(format!("{} {} -> {}",
+                         self.op,
+                         source.to_string_lossy(),
+                         dest.to_string_lossy()));
+        Ok(Some(dest))
+    }
+
+    fn move_path(&self,
+                 source: &Path,
+                 dest: &Path,
+                 report: &mut dyn FnMut(u64, u64)) -> HResult<()> {
+        match fs::rename(source, dest) {
+            Ok(_) => self.advance(total_size(dest, self.op), report),
+            // Different filesystem, so copy and then remove
+            Err(ref err) if err.raw_os_error() == Some(libc::EXDEV) => {
+                self.copy(source, dest, report)?;
+                remove_path(source)?;
+            }
+            Err(err) => Err(err)?
+        }
+        Ok(())
+    }
+
+    // The info file is removed again if the file can't be moved
+    pub fn trash(&self,
+                 file: &File,
+                 report: &mut dyn FnMut(u64, u64)) -> HResult<PathBuf> {
+        let (info_path, dest) = file.trash_info()?;
+
+        match self.move_path(&file.path, &dest, report) {
+            Ok(_) => Ok(dest),
+            Err(err) => {
+                fs::remove_file(&info_path).ok();
+                Err(err)
+            }
+        }
+    }
+
+    fn copy(&self,
+            source: &Path,
+            dest: &Path,
+            report: &mut dyn FnMut(u64, u64)) -> HResult<()> {
+        self.check_cancelled()?;
+
+        let meta = source.symlink_metadata()?;
+        let file_type = meta.file_type();
+
+        if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(source)?, dest)?;
+        } else if file_type.is_dir() {
+            fs::create_dir(dest)?;
+            for entry in fs::read_dir(source)? {
+                let entry = entry?.path();
+                self.copy(&entry, &dest.join(entry.file_name()?), report)?;
+            }
+            fs::set_permissions(dest, meta.permissions())?;
+        } else if file_type.is_file() {
+            self.copy_file(source, dest, report)?;
+            fs::set_permissions(dest, meta.permissions())?;
+        } else {
+            self.log(format!("Skipping special file {}", source.to_string_lossy()));
+        }
+
+        Ok(())
+    }
+
+    // Read/write loop instead of fs::copy, so progress can be shown
+    fn copy_file(&self,
+                 source: &Path,
+                 dest: &Path,
+                 report: &mut dyn FnMut(u64, u64)) -> HResult<()> {
+        let mut source = fs::File::open(source)?;
+        let mut dest_file = fs::File::create(dest)?;
+        let mut buffer = vec![0; BUFFER_SIZE];
+
+        loop {
+            if let Err(err) = self.check_cancelled() {
+                drop(dest_file);
+                fs::remove_file(dest).ok();
+                return Err(err);
+            }
+
+            let len = source.read(&mut buffer)?;
+            if len == 0 { break; }
+
+            dest_file.write_all(&buffer[..len])?;
+            self.advance(len as u64, report);
+        }
+
+        Ok(())
+    }
+
+    fn delete(&self, path: &Path, report: &mut dyn FnMut(u64, u64)) -> HResult<()> {
+        self.check_cancelled()?;
+
+        let meta = path.symlink_metadata()?;
+
+        if meta.is_dir() {
+            for entry in fs::read_dir(path)? {
+                self.delete(&entry?.path(), report)?;
+            }
+            fs::remove_dir(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+
+        self.advance(1, report);
+        Ok(())
+    }
+}
+
+// Path to "path" from inside "dir", both absolute
+pub fn relative_path(path: &Path, dir: &Path) -> PathBuf {
+    let path_parts = path.components().collect::<Vec<_>>();
+    let dir_parts = dir.components().collect::<Vec<_>>();
+    let common = path_parts.iter()
+                           .zip(&dir_parts)
+                           .take_while(|(a, b)| a == b)
+                           .count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..dir_parts.len() {
+        relative.push("..");
+    }
+    for part in &path_parts[common..] {
+        relative.push(part);
+    }
+    relative
+}
+
+// Relative links keep working when moved together with what they point to
+pub fn symlink(target: &Path, link: &Path, relative: bool) -> HResult<()> {
+    if link.symlink_metadata().is_ok() {
+        return Err(HError::Error(format!("{} already exists",
+                                         link.to_string_lossy())));
+    }
+
+    let target = match relative {
+        true => relative_path(target, link.parent()?),
+        false => target.to_path_buf()
+    };
+
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+// Trash/files/name has its info in Trash/info/name.trashinfo
+fn trash_info_path(trashed: &Path) -> HResult<PathBuf> {
+    let trash = trashed.parent()?.parent()?;
+    let mut info_name = trashed.file_name()?.to_os_string();
+    info_name.push(".trashinfo");
+    Ok(trash.join("info").join(info_name))
+}
+
+fn remove_path(path: &Path) -> HResult<()> {
+    match path.symlink_metadata()?.is_dir() {
+        true => fs::remove_dir_all(path)?,
+        false => fs::remove_file(path)?
+    }
+    Ok(())
+}
+
+#[test]
+fn test_relative_path() {
+    let path = |path: &str| PathBuf::from(path);
+
+    assert_eq!(relative_path(&path("/a/b/file"), &path("/a/b")), path("file"));
+    assert_eq!(relative_path(&path("/a/b/file"), &path("/a/c/d")), path("../../b/file"));
+    assert_eq!(relative_path(&path("/a/file"), &path("/a/b")), path("../file"));
+    assert_eq!(relative_path(&path("/file"), &path("/a/b")), path("../../file"));
+}
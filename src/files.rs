@@ -44,8 +44,22 @@ lazy_static! {
     static ref ICONS: Icons = Icons::new();
     static ref IOTICK_CLIENTS: AtomicUsize = AtomicUsize::default();
     static ref IOTICK: AtomicUsize = AtomicUsize::default();
+    // Recursive sizes in bytes, kept apart from the entry counts
+    static ref RECURSIVE_SIZES: RwLock<HashMap<PathBuf, Arc<(AtomicBool, AtomicUsize)>>>
+        = RwLock::new(HashMap::new());
+    // Walking big trees takes a while, so only a few run at once
+    static ref SIZE_POOL: ThreadPool = ThreadPoolBuilder::new()
+        .num_threads(2)
+        .thread_name(|i| format!("hunter_sizethread_{}", i))
+        .build()
+        .unwrap();
 }
 
+// Whether directories show their recursive size instead of the entry count
+static DIR_BYTES: AtomicBool = AtomicBool::new(false);
+// Whether files are shown and sorted by the space they take up on disk
+static SIZE_ON_DISK: AtomicBool = AtomicBool::new(false);
+
 pub fn tick_str() -> &'static str {
     // Using mod 5 for that nice nonlinear look
     match IOTICK.load(Ordering::Relaxed) % 5 {
@@ -96,12 +110,333 @@ pub fn stop_ticking() {
     IOTICK_CLIENTS.fetch_sub(1, Ordering::Relaxed);
 }
 
+pub fn dir_bytes() -> bool {
+    DIR_BYTES.load(Ordering::Relaxed)
+}
+
+pub fn set_dir_bytes(bytes: bool) {
+    DIR_BYTES.store(bytes, Ordering::Relaxed);
+}
+
+pub fn toggle_dir_bytes() -> bool {
+    !DIR_BYTES.fetch_xor(true, Ordering::Relaxed)
+}
+
+pub fn set_size_on_disk(on_disk: bool) {
+    SIZE_ON_DISK.store(on_disk, Ordering::Relaxed);
+}
+
+// Sparse files and compressed filesystems use less than the file's length
+pub fn disk_size(meta: &Metadata) -> u64 {
+    meta.blocks() * 512
+}
+
+fn shown_size(meta: &Metadata) -> u64 {
+    match SIZE_ON_DISK.load(Ordering::Relaxed) {
+        true => disk_size(meta),
+        false => meta.size()
+    }
+}
+
+fn recursive_size_slot(path: &Path) -> Option<Arc<(AtomicBool, AtomicUsize)>> {
+    RECURSIVE_SIZES.read()
+                   .ok()?
+                   .get(path)
+                   .cloned()
+}
+
+// "." and ".." don't count, hidden files only when they're shown
+fn count_entries(dir: &mut Dir, show_hidden: bool) -> usize {
+    dir.iter()
+       .filter_map(|entry| entry.ok())
+       .filter(|entry| {
+           let name = entry.file_name().to_bytes();
+           name != b"." && name != b".." && (show_hidden || !name.starts_with(b"."))
+       })
+       .count()
+}
+
+// Changing anything below a directory changes its size, and everything
+// inside a removed or renamed directory is gone as well
+pub fn forget_recursive_sizes(path: &Path) {
+    if let Ok(mut sizes) = RECURSIVE_SIZES.write() {
+        sizes.retain(|dir, _| !path.starts_with(dir) && !dir.starts_with(path));
+    }
+}
+
+pub fn forget_all_recursive_sizes() {
+    if let Ok(mut sizes) = RECURSIVE_SIZES.write() {
+        sizes.clear();
+    }
+}
+
+// Doesn't follow symlinks, so links to / don't count the whole disk
+pub fn recursive_size(path: &Path, stale: &Stale) -> HResult<usize> {
+    if stale.is_stale()? {
+        return Err(HError::StaleError);
+    }
+
+    let meta = match std::fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(_) => return Ok(0)
+    };
+
+    if !meta.is_dir() {
+        return Ok(meta.len() as usize);
+    }
+
+    match std::fs::read_dir(path) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok())
+                              .map(|entry| recursive_size(&entry.path(), stale))
+                              .sum(),
+        Err(_) => Ok(0)
+    }
+}
+
+// Background jobs on a listing stop when it's left or when they're
+// cancelled themselves
+fn any_stale(stale: &[Stale]) -> HResult<bool> {
+    for stale in stale {
+        if stale.is_stale()? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// Subdirectory contents up to depth levels down, for the flat view.
+// Unreadable directories are just left out.
+fn read_subdirs(mut dirs: Vec<PathBuf>,
+                depth: usize,
+                show_hidden: bool,
+                stale: &[Stale]) -> HResult<Vec<File>> {
+    let subdirs = |files: &[File]| {
+        files.iter()
+             // Symlinks could lead into a loop
+             .filter(|f| f.is_dir() && f.target.is_none())
+             .filter(|f| show_hidden || !f.name.starts_with("."))
+             .map(|f| f.path.clone())
+             .collect::<Vec<_>>()
+    };
+
+    let mut found = vec![];
+
+    for _ in 0..depth {
+        let mut next_dirs = vec![];
+
+        for dir in dirs {
+            if any_stale(stale)? {
+                return HError::stale();
+            }
+
+            // Dropping Files sets its Stale, so this needs its own
+            if let Ok(mut files) = Files::new_from_path_cancellable(&dir, Stale::new()) {
+                let mut files = std::mem::take(&mut files.files);
+                next_dirs.extend(subdirs(&files));
+                found.append(&mut files);
+            }
+        }
+
+        dirs = next_dirs;
+    }
+
+    Ok(found)
+}
+
+// Breadth first, so the match closest to "dir" is found. Only looks below
+// "dir", ignoring case like the search in the file list does. Symlinked
+// directories aren't followed.
+pub fn find_in_subdirs(dir: &Path,
+                       search: &str,
+                       show_hidden: bool,
+                       stale: &[Stale]) -> HResult<Option<PathBuf>> {
+    let search = search.to_lowercase();
+
+    let entries = |dir: &Path| -> Vec<(PathBuf, bool)> {
+        let mut entries = std::fs::read_dir(dir)
+            .map(|entries| {
+                entries.filter_map(|entry| entry.ok())
+                       .filter(|entry| {
+                           show_hidden ||
+                               !entry.file_name().to_string_lossy().starts_with(".")
+                       })
+                       .map(|entry| {
+                           let is_dir = entry.file_type()
+                                             .map(|kind| kind.is_dir())
+                                             .unwrap_or(false);
+                           (entry.path(), is_dir)
+                       })
+                       .collect::<Vec<_>>()
+            })
+            .unwrap_or(vec![]);
+        entries.sort();
+        entries
+    };
+
+    let subdirs = |entries: Vec<(PathBuf, bool)>| {
+        entries.into_iter()
+               .filter(|(_, is_dir)| *is_dir)
+               .map(|(path, _)| path)
+               .collect::<Vec<_>>()
+    };
+
+    let mut dirs = subdirs(entries(dir));
+
+    while !dirs.is_empty() {
+        let mut next_dirs = vec![];
+
+        for dir in dirs {
+            if any_stale(stale)? {
+                return HError::stale();
+            }
+
+            let entries = entries(&dir);
+            let found = entries.iter().find(|(path, _)| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_lowercase().contains(&search))
+                    .unwrap_or(false)
+            });
+
+            if let Some((path, _)) = found {
+                return Ok(Some(path.clone()));
+            }
+
+            next_dirs.extend(subdirs(entries));
+        }
+
+        dirs = next_dirs;
+    }
+
+    Ok(None)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SizeUnits {
+    Binary,
+    Si
+}
+
+// How sizes are shown in the file list and the status bar
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SizeFormat {
+    pub units: SizeUnits,
+    pub decimal: bool
+}
+
+impl SizeFormat {
+    pub fn format(&self, size: u64) -> String {
+        let base = match self.units {
+            SizeUnits::Binary => 1024,
+            SizeUnits::Si => 1000
+        };
+
+        let mut whole = size;
+        let mut unit = 0;
+        while whole > base {
+            whole /= base;
+            unit += 1;
+        }
+
+        let unit_str = match (unit, self.units) {
+            (0, _) => "",
+            (1, SizeUnits::Binary) => " KB",
+            (1, SizeUnits::Si) => " kB",
+            (2, _) => " MB",
+            (3, _) => " GB",
+            (4, _) => " TB",
+            (5, _) => " wtf are you doing",
+            _ => "",
+        };
+
+        match self.decimal && unit > 0 {
+            true => {
+                let short = size as f64 / (base as f64).powi(unit);
+                format!("{:.1}{}", short, unit_str)
+            }
+            false => format!("{}{}", whole, unit_str)
+        }
+    }
+}
+
+// What's put after names in the file lists
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TrailingSlash {
+    Off,
+    // Only a "/" after directories
+    Dirs,
+    // Like ls -F
+    Classify
+}
+
+// How modification times are shown in the status bar
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateFormat {
+    Strftime(String),
+    Relative
+}
+
+impl DateFormat {
+    pub fn default() -> DateFormat {
+        DateFormat::Strftime(String::from("%F %R"))
+    }
+
+    // Catch invalid formats here, chrono would panic when formatting
+    pub fn parse(format: &str) -> Option<DateFormat> {
+        use chrono::format::{StrftimeItems, Item};
+
+        match format {
+            "relative" => Some(DateFormat::Relative),
+            _ if StrftimeItems::new(format).any(|item| item == Item::Error) => None,
+            _ => Some(DateFormat::Strftime(format.to_string()))
+        }
+    }
+
+    pub fn format(&self, time: chrono::DateTime<chrono::Local>) -> String {
+        self.format_at(time, chrono::Local::now())
+    }
+
+    pub fn format_at(&self,
+                     time: chrono::DateTime<chrono::Local>,
+                     now: chrono::DateTime<chrono::Local>) -> String {
+        let format = match self {
+            DateFormat::Strftime(format) => format,
+            DateFormat::Relative => return relative_time(time, now)
+        };
+
+        time.format(format).to_string()
+    }
+}
+
+fn relative_time(time: chrono::DateTime<chrono::Local>,
+                 now: chrono::DateTime<chrono::Local>) -> String {
+    let ago = |n: i64, unit: &str| match n {
+        1 => format!("1 {} ago", unit),
+        n => format!("{} {}s ago", n, unit)
+    };
+
+    let secs = (now - time).num_seconds();
+    let days = (now.naive_local().date() - time.naive_local().date()).num_days();
+
+    match secs {
+        // Clock skew or files from the future
+        _ if secs < 0 => time.format("%F %R").to_string(),
+        _ if secs < 60 => String::from("just now"),
+        _ if secs < 60 * 60 => ago(secs / 60, "minute"),
+        _ if days == 0 => ago(secs / (60 * 60), "hour"),
+        _ if days == 1 => String::from("yesterday"),
+        _ if days < 7 => ago(days, "day"),
+        _ if days < 30 => ago(days / 7, "week"),
+        _ if days < 365 => ago(days / 30, "month"),
+        _ => ago(days / 365, "year")
+    }
+}
+
 #[derive(Fail, Debug, Clone)]
 pub enum FileError {
     #[fail(display = "Metadata still pending!")]
     MetaPending,
-    #[fail(display = "Couldn't open directory! Error: {}", _0)]
-    OpenDir(#[cause] nix::Error),
+    #[fail(display = "Couldn't open {:?}: {}", _0, _1)]
+    OpenDir(PathBuf, #[cause] nix::Error),
     #[fail(display = "Couldn't read files! Error: {}", _0)]
     ReadFiles(#[cause] nix::Error),
     #[fail(display = "Had problems with getdents64 in directory: {}", _0)]
@@ -223,7 +558,7 @@ impl RefreshPackage {
         for event in events.into_iter().stop_stale(stale.clone()) {
             match event {
                 Create(mut file) => {
-                    let job = file.prepare_meta_job(cache);
+                    let job = file.prepare_meta_job(cache, files.show_hidden);
                     job.map(|j| jobs.push(j));
                     new_files.push(file);
                 }
@@ -311,8 +646,13 @@ pub struct Files {
     pub dirs_first: bool,
     pub reverse: bool,
     pub show_hidden: bool,
+    // What show_hidden was when directories were last counted
+    counted_hidden: bool,
     pub filter: Option<String>,
     pub filter_selected: bool,
+    pub ignore: Vec<String>,
+    // Depth of subdirectories shown in the same list
+    pub flat: Option<usize>,
     pub dirty: DirtyBit,
     #[derivative(Debug="ignore")]
     #[derivative(PartialEq="ignore")]
@@ -325,7 +665,57 @@ pub struct Files {
     #[derivative(Debug="ignore")]
     #[derivative(PartialEq="ignore")]
     #[derivative(Hash="ignore")]
-    pub stale: Option<Stale>
+    pub stale: Option<Stale>,
+    #[derivative(Debug="ignore")]
+    #[derivative(PartialEq="ignore")]
+    #[derivative(Hash="ignore")]
+    pub meta_progress: Option<Arc<MetaProgress>>,
+    #[derivative(Debug="ignore")]
+    #[derivative(PartialEq="ignore")]
+    #[derivative(Hash="ignore")]
+    pub flattening: Option<Flattening>,
+    #[derivative(Debug="ignore")]
+    #[derivative(PartialEq="ignore")]
+    #[derivative(Hash="ignore")]
+    pub subdir_search: Option<SubdirSearch>
+}
+
+// The files for the flat view while they're still being read
+#[derive(Debug, Clone)]
+pub struct Flattening {
+    pub depth: usize,
+    stale: Stale,
+    files: Arc<RwLock<Option<Vec<File>>>>
+}
+
+// A search through the subdirectories that's still running
+#[derive(Debug, Clone)]
+pub struct SubdirSearch {
+    pub search: String,
+    stale: Stale,
+    found: Arc<RwLock<Option<Option<PathBuf>>>>
+}
+
+// How far load_all_meta has come, shown in the footer
+#[derive(Debug, Default)]
+pub struct MetaProgress {
+    pub done: AtomicUsize,
+    pub total: usize
+}
+
+impl MetaProgress {
+    pub fn is_done(&self) -> bool {
+        self.done.load(Ordering::Relaxed) >= self.total
+    }
+}
+
+// Counts a job as done when it's dropped, however it returns
+struct JobDone(Arc<MetaProgress>);
+
+impl Drop for JobDone {
+    fn drop(&mut self) {
+        self.0.done.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 impl Index<usize> for Files {
@@ -365,12 +755,18 @@ impl Default for Files {
             dirs_first: true,
             reverse: false,
             show_hidden: false,
+            counted_hidden: false,
             filter: None,
             filter_selected: false,
+            ignore: vec![],
+            flat: None,
             dirty: DirtyBit::new(),
             jobs: vec![],
             cache: None,
-            stale: None
+            stale: None,
+            meta_progress: None,
+            flattening: None,
+            subdir_search: None
         }
     }
 }
@@ -615,7 +1011,7 @@ impl Files {
         let dir  = Dir::open(path.clone(),
                              OFlag::O_DIRECTORY,
                              Mode::empty())
-            .map_err(|e| FileError::OpenDir(e))?;
+            .map_err(|e| FileError::OpenDir(path.to_path_buf(), e))?;
 
         let direntries = from_getdents(dir.as_raw_fd(), path, &nonhidden)?;
 
@@ -644,7 +1040,7 @@ impl Files {
         let mut dir = Dir::open(path.clone(),
                                 OFlag::O_DIRECTORY,
                                 Mode::empty())
-            .map_err(|e| FileError::OpenDir(e))?;
+            .map_err(|e| FileError::OpenDir(path.to_path_buf(), e))?;
 
         let dirfd = dir.as_raw_fd();
 
@@ -676,6 +1072,12 @@ impl Files {
     }
 
     pub fn enqueue_jobs(&mut self, n: usize) {
+        // Directories are counted differently now, so all of them again
+        if self.dirsizes_outdated() {
+            self.counted_hidden = self.show_hidden;
+            self.meta_upto = None;
+        }
+
         let from = self.meta_upto.unwrap_or(0);
         self.meta_upto = Some(from + n);
 
@@ -683,20 +1085,70 @@ impl Files {
             Some(cache) => cache,
             None => return
         };
+        let show_hidden = self.show_hidden;
 
         let mut jobs = self.iter_files_mut()
                            .collect::<Vec<&mut File>>()
                            .into_par_iter()
                            .skip(from)
                            .take(n)
-                           .filter_map(|f| f.prepare_meta_job(&cache))
+                           .filter_map(|f| f.prepare_meta_job(&cache, show_hidden))
                            .collect::<Vec<_>>();
 
         self.jobs.append(&mut jobs);
     }
 
+    pub fn dirsizes_outdated(&self) -> bool {
+        self.counted_hidden != self.show_hidden
+    }
+
+    // Only files near the screen get their metadata loaded normally, but
+    // sorting by size or mtime needs it for all of them
+    pub fn load_all_meta(&mut self, sender: Sender<Events>) {
+        if self.meta_progress.is_some() { return; }
+
+        self.meta_upto = Some(0);
+        self.enqueue_jobs(self.files.len());
+
+        let progress = Arc::new(MetaProgress {
+            done: AtomicUsize::new(0),
+            total: self.jobs.len()
+        });
+        self.meta_progress = Some(progress.clone());
+        self.run_jobs_with_progress(sender, Some(progress));
+    }
+
+    pub fn meta_incomplete(&self) -> bool {
+        self.meta_upto.unwrap_or(0) < self.files.len()
+    }
+
+    pub fn sort_needs_meta(&self) -> bool {
+        match self.sort {
+            SortBy::Size | SortBy::MTime => true,
+            _ => false
+        }
+    }
+
+    // True once load_all_meta is done
+    pub fn take_meta_loaded(&mut self) -> bool {
+        match &self.meta_progress {
+            Some(progress) if progress.is_done() => {
+                self.meta_progress = None;
+                true
+            }
+            _ => false
+        }
+    }
+
     pub fn run_jobs(&mut self, sender: Sender<Events>) {
+        self.run_jobs_with_progress(sender, None);
+    }
+
+    fn run_jobs_with_progress(&mut self,
+                              sender: Sender<Events>,
+                              progress: Option<Arc<MetaProgress>>) {
         let jobs = std::mem::take(&mut self.jobs);
+        let show_hidden = self.show_hidden;
         let stale = self.stale
                         .clone()
                         .unwrap_or_else(Stale::new);
@@ -713,7 +1165,10 @@ impl Files {
                 for (path, mslot, dirsize) in jobs.into_iter()
                                                   .stop_stale(stale.clone())
                 {
+                    let progress = progress.clone();
                     s.spawn_fifo(move |_| {
+                        let _done = progress.map(JobDone);
+
                         if let Some(mslot) = mslot {
                             if let Ok(meta) = std::fs::symlink_metadata(&path) {
                                 *mslot.write().unwrap() = Some(meta);
@@ -724,8 +1179,8 @@ impl Files {
                             let size = Dir::open(&path,
                                                  OFlag::O_DIRECTORY,
                                                  Mode::empty())
-                                .map(|mut d| d.iter().count())
-                                .map_err(|e| FileError::OpenDir(e))
+                                .map(|mut d| count_entries(&mut d, show_hidden))
+                                .map_err(|e| FileError::OpenDir(path.clone(), e))
                                 .log_and()
                                 .unwrap_or(0);
 
@@ -741,9 +1196,37 @@ impl Files {
     }
 
     pub fn recalculate_len(&mut self) {
+        self.load_filter_meta();
         self.len = self.par_iter_files().count();
     }
 
+    // Metadata is usually only loaded for files on screen, but filtering
+    // by it needs it for all of them. Files it's missing for don't match.
+    fn load_filter_meta(&mut self) {
+        let needs_meta = self.filter
+            .as_ref()
+            .and_then(|filter| FileFilter::new(filter).ok())
+            .map(|filter| filter.needs_meta())
+            .unwrap_or(false);
+
+        if !needs_meta { return; }
+
+        self.files
+            .par_iter_mut()
+            .filter(|file| file.kind != Kind::Placeholder && file.meta().is_none())
+            .for_each(|file| {
+                let meta = match std::fs::symlink_metadata(&file.path) {
+                    Ok(meta) => meta,
+                    Err(_) => return
+                };
+
+                match &file.meta {
+                    Some(slot) => { slot.write().map(|mut slot| *slot = Some(meta)).ok(); }
+                    None => file.meta = Some(Arc::new(RwLock::new(Some(meta))))
+                }
+            });
+    }
+
     pub fn get_file_mut(&mut self, index: usize) -> Option<&mut File> {
         // Need actual length of self.files for this
         let hidden_in_between = self.files_in_between(index, self.files.len());
@@ -813,16 +1296,19 @@ impl Files {
 
     #[allow(trivial_bounds)]
     pub fn filter_fn(&self) -> impl Fn(&File) -> bool + 'static {
-        let filter = self.filter.clone();
+        let filter = self.filter
+            .as_ref()
+            .and_then(|filter| FileFilter::new(filter).ok());
         let filter_selected = self.filter_selected;
         let show_hidden = self.show_hidden;
+        let ignore = self.ignore.clone();
 
         move |f| {
             f.kind == Kind::Placeholder ||
-                !(filter.is_some() &&
-                  !f.name.contains(filter.as_ref().unwrap())) &&
+                filter.as_ref().map(|filter| filter.matches(f)).unwrap_or(true) &&
                 (!filter_selected || f.selected) &&
-                !(!show_hidden && f.name.starts_with("."))
+                !(!show_hidden && f.name.starts_with(".")) &&
+                !ignore.iter().any(|pattern| glob_match(pattern, &f.name))
         }
     }
 
@@ -843,13 +1329,38 @@ impl Files {
 
 
         let reverse = self.reverse;
+        let flat = self.flat.is_some();
         let namecmp = move |a: &File, b: &File| {
             let (a, b) = match reverse {
                 true => (b, a),
                 false => (a, b),
             };
 
-            compare(&a.name, &b.name)
+            // Names aren't unique in a flattened list, keep directories together
+            match flat {
+                true => compare(&a.path.to_string_lossy(), &b.path.to_string_lossy()),
+                false => compare(&a.name, &b.name)
+            }
+        };
+
+        let reverse = self.reverse;
+        let extcmp = move |a: &File, b: &File| {
+            let (a, b) = match reverse {
+                true => (b, a),
+                false => (a, b),
+            };
+
+            let extension = |file: &File| {
+                file.path
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_lowercase())
+            };
+
+            // Files without extension come first, sorted by name
+            match extension(a).cmp(&extension(b)) {
+                Equal => compare(&a.name, &b.name),
+                ord @ _ => ord
+            }
         };
 
         let reverse = self.reverse;
@@ -863,9 +1374,9 @@ impl Files {
                 (Some(a_meta), Some(b_meta)) => {
                     let a_meta = a_meta.as_ref().unwrap();
                     let b_meta = b_meta.as_ref().unwrap();
-                    match a_meta.size() == b_meta.size() {
+                    match shown_size(a_meta) == shown_size(b_meta) {
                         true => compare(&b.name, &a.name),
-                        false => b_meta.size().cmp(&a_meta.size())
+                        false => shown_size(b_meta).cmp(&shown_size(a_meta))
                     }
                 }
                 _ => Equal
@@ -900,6 +1411,12 @@ impl Files {
                     ord @ _ => ord
                 }
             },
+            SortBy::Extension => {
+                match dircmp(a, b) {
+                    Equal => extcmp(a, b),
+                    ord @ _ => ord
+                }
+            }
             SortBy::Size => {
                 match dircmp(a, b) {
                     Equal => sizecmp(a, b),
@@ -922,9 +1439,132 @@ impl Files {
             .par_sort_unstable_by(sort);
     }
 
+    // Reads the contents of subdirectories up to depth levels down in the
+    // background, take_flattened adds them to the list once they're there
+    pub fn flatten(&mut self, depth: usize, sender: Sender<Events>) {
+        self.unflatten();
+        self.cancel_flatten();
+
+        let show_hidden = self.show_hidden;
+        let dirs = self.files
+            .iter()
+            .filter(|f| f.is_dir() && f.target.is_none())
+            .filter(|f| show_hidden || !f.name.starts_with("."))
+            .map(|f| f.path.clone())
+            .collect::<Vec<_>>();
+
+        let flattening = Flattening {
+            depth: depth,
+            stale: Stale::new(),
+            files: Arc::default()
+        };
+        let stale = vec![self.stale.clone().unwrap_or_else(Stale::new),
+                         flattening.stale.clone()];
+        let slot = flattening.files.clone();
+        self.flattening = Some(flattening);
+
+        std::thread::spawn(move || {
+            let _ticker = Ticker::start_ticking(sender.clone());
+
+            if let Ok(files) = read_subdirs(dirs, depth, show_hidden, &stale) {
+                *slot.write().unwrap() = Some(files);
+                sender.send(Events::WidgetReady).ok();
+            }
+        });
+    }
+
+    pub fn cancel_flatten(&mut self) {
+        if let Some(flattening) = self.flattening.take() {
+            flattening.stale.set_stale().ok();
+        }
+    }
+
+    // True once the subdirectories are read and in the list
+    pub fn take_flattened(&mut self) -> bool {
+        let files = match &self.flattening {
+            Some(flattening) => flattening.files.write().unwrap().take(),
+            None => return false
+        };
+
+        let mut files = match files {
+            Some(files) => files,
+            None => return false
+        };
+
+        let depth = self.flattening.take().map(|f| f.depth).unwrap_or(0);
+
+        self.remove_placeholder();
+        self.files.append(&mut files);
+        self.flat = Some(depth);
+        self.meta_upto = None;
+        self.sort();
+        self.recalculate_len();
+        self.set_dirty();
+        true
+    }
+
+    // Looks for a match below the directory in the background, found in
+    // take_subdir_match once it's done
+    pub fn search_subdirs(&mut self, search: &str, sender: Sender<Events>) {
+        self.cancel_subdir_search();
+
+        let search_job = SubdirSearch {
+            search: search.to_string(),
+            stale: Stale::new(),
+            found: Arc::default()
+        };
+        let stale = vec![self.stale.clone().unwrap_or_else(Stale::new),
+                         search_job.stale.clone()];
+        let slot = search_job.found.clone();
+        let dir = self.directory.path.clone();
+        let search = search.to_string();
+        let show_hidden = self.show_hidden;
+        self.subdir_search = Some(search_job);
+
+        std::thread::spawn(move || {
+            let _ticker = Ticker::start_ticking(sender.clone());
+
+            if let Ok(found) = find_in_subdirs(&dir, &search, show_hidden, &stale) {
+                *slot.write().unwrap() = Some(found);
+                sender.send(Events::WidgetReady).ok();
+            }
+        });
+    }
+
+    pub fn cancel_subdir_search(&mut self) {
+        if let Some(search) = self.subdir_search.take() {
+            search.stale.set_stale().ok();
+        }
+    }
+
+    // The search and what it found, once it's done
+    pub fn take_subdir_match(&mut self) -> Option<(String, Option<PathBuf>)> {
+        let found = self.subdir_search
+                        .as_ref()?
+                        .found
+                        .write()
+                        .unwrap()
+                        .take()?;
+        let search = self.subdir_search.take()?.search;
+        Some((search, found))
+    }
+
+    pub fn unflatten(&mut self) {
+        if self.flat.take().is_none() {
+            return;
+        }
+
+        let dir = self.directory.path.clone();
+        self.files.retain(|f| f.parent() == Some(&dir));
+        self.sort();
+        self.recalculate_len();
+        self.set_dirty();
+    }
+
     pub fn cycle_sort(&mut self) {
         self.sort = match self.sort {
-            SortBy::Name => SortBy::Size,
+            SortBy::Name => SortBy::Extension,
+            SortBy::Extension => SortBy::Size,
             SortBy::Size => SortBy::MTime,
             SortBy::MTime => SortBy::Name,
         };
@@ -1022,13 +1662,13 @@ impl Files {
 
     pub fn find_file(&self, file: &File) -> Option<usize> {
         let comp = self.sorter();
-        let pos = self.files
-            .binary_search_by(|probe| comp(probe, file))
-            .ok()?;
-
-        debug_assert_eq!(file.path, self.files[pos].path);
 
-        Some(pos)
+        // Metadata coming in after sorting can leave the files out of
+        // order until they're sorted again, so this can miss
+        match self.files.binary_search_by(|probe| comp(probe, file)) {
+            Ok(pos) if self.files[pos].path == file.path => Some(pos),
+            _ => self.files.iter().position(|f| f.path == file.path)
+        }
     }
 
     pub fn find_file_with_name(&self, name: &str) -> Option<&File> {
@@ -1040,8 +1680,16 @@ impl Files {
         self.iter_files_mut().find(|file| file.path == path)
     }
 
-    pub fn set_filter(&mut self, filter: Option<String>) {
-        self.filter = filter;
+    // Broken regexes leave the previous filter in place
+    pub fn set_filter(&mut self, filter: Option<String>) -> HResult<()> {
+        let valid = match &filter {
+            Some(filter) => FileFilter::new(filter).map(|_| ()),
+            None => Ok(())
+        };
+
+        if valid.is_ok() {
+            self.filter = filter;
+        }
 
         // Do this first, so we know len() == 0 needs a placeholder
         self.remove_placeholder();
@@ -1055,6 +1703,7 @@ impl Files {
         }
 
         self.set_dirty();
+        valid
     }
 
     pub fn get_filter(&self) -> Option<String> {
@@ -1082,10 +1731,241 @@ pub enum Kind {
     Placeholder
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileType {
+    Regular,
+    Directory,
+    Fifo,
+    Socket,
+    CharDevice,
+    BlockDevice,
+    Unknown
+}
+
+impl FileType {
+    fn from_mode(mode: u32) -> FileType {
+        match mode & libc::S_IFMT as u32 {
+            m if m == libc::S_IFREG as u32 => FileType::Regular,
+            m if m == libc::S_IFDIR as u32 => FileType::Directory,
+            m if m == libc::S_IFIFO as u32 => FileType::Fifo,
+            m if m == libc::S_IFSOCK as u32 => FileType::Socket,
+            m if m == libc::S_IFCHR as u32 => FileType::CharDevice,
+            m if m == libc::S_IFBLK as u32 => FileType::BlockDevice,
+            _ => FileType::Unknown
+        }
+    }
+
+    // Reading these blocks or makes no sense, so they can't be opened/previewed
+    pub fn is_special(&self) -> bool {
+        match self {
+            FileType::Fifo |
+            FileType::Socket |
+            FileType::CharDevice |
+            FileType::BlockDevice => true,
+            _ => false
+        }
+    }
+}
+
+impl std::fmt::Display for FileType {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let text = match self {
+            FileType::Regular => "regular file",
+            FileType::Directory => "directory",
+            FileType::Fifo => "named pipe",
+            FileType::Socket => "socket",
+            FileType::CharDevice => "character device",
+            FileType::BlockDevice => "block device",
+            FileType::Unknown => "unknown file type"
+        };
+        write!(formatter, "{}", text)
+    }
+}
+
+// Filters starting with "/" are regexes, anything else is matched
+// case-insensitively. Used by the file list filter and turbo-cd alike.
+#[derive(Debug, Clone)]
+pub enum NameFilter {
+    Text(String),
+    Regex(regex::Regex)
+}
+
+impl NameFilter {
+    pub fn new(filter: &str) -> HResult<NameFilter> {
+        match filter.starts_with("/") {
+            true => Ok(NameFilter::Regex(regex::Regex::new(&filter[1..])?)),
+            false => Ok(NameFilter::Text(filter.to_lowercase()))
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NameFilter::Text(text) => name.to_lowercase().contains(text),
+            NameFilter::Regex(regex) => regex.is_match(name)
+        }
+    }
+}
+
+// Conditions on the metadata, like ">1M" for files bigger than a megabyte,
+// "<10k" for smaller than ten kilobytes or "@7d" for modified in the last
+// seven days. Units are k/m/g/t for sizes and s/m/h/d/w for times.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MetaFilter {
+    LargerThan(u64),
+    SmallerThan(u64),
+    ModifiedWithin(std::time::Duration)
+}
+
+impl MetaFilter {
+    pub fn parse(word: &str) -> Option<MetaFilter> {
+        let rest = word.get(1..)?;
+        let split = rest.find(|c: char| !c.is_ascii_digit() && c != '.')
+                        .unwrap_or(rest.len());
+        let (num, unit) = rest.split_at(split);
+        let num = num.parse::<f64>().ok()?;
+
+        let size = || {
+            let factor: u64 = match unit.to_lowercase().as_str() {
+                "" | "b" => 1,
+                "k" => 1 << 10,
+                "m" => 1 << 20,
+                "g" => 1 << 30,
+                "t" => 1 << 40,
+                _ => return None
+            };
+            Some((num * factor as f64) as u64)
+        };
+
+        match word.chars().next()? {
+            '>' => Some(MetaFilter::LargerThan(size()?)),
+            '<' => Some(MetaFilter::SmallerThan(size()?)),
+            '@' => {
+                let factor = match unit {
+                    "s" => 1,
+                    "m" => 60,
+                    "h" => 60 * 60,
+                    "" | "d" => 24 * 60 * 60,
+                    "w" => 7 * 24 * 60 * 60,
+                    _ => return None
+                };
+                let secs = (num * factor as f64) as u64;
+                Some(MetaFilter::ModifiedWithin(std::time::Duration::from_secs(secs)))
+            }
+            _ => None
+        }
+    }
+
+    // Directories have no size to speak of, so they never match sizes
+    pub fn matches(&self, meta: &Metadata) -> bool {
+        match self {
+            MetaFilter::LargerThan(size) => meta.is_file() && meta.len() > *size,
+            MetaFilter::SmallerThan(size) => meta.is_file() && meta.len() < *size,
+            MetaFilter::ModifiedWithin(duration) => {
+                meta.modified()
+                    .map(|mtime| match mtime.elapsed() {
+                        Ok(elapsed) => elapsed <= *duration,
+                        // Modified in the future
+                        Err(_) => true
+                    })
+                    .unwrap_or(false)
+            }
+        }
+    }
+}
+
+// The whole filter, words that are metadata conditions are taken out and
+// the rest is matched against the name. Everything has to match.
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    name: Option<NameFilter>,
+    meta: Vec<MetaFilter>
+}
+
+impl FileFilter {
+    pub fn new(filter: &str) -> HResult<FileFilter> {
+        let mut meta = vec![];
+        let name = filter.split(' ')
+            .filter(|word| match MetaFilter::parse(word) {
+                Some(meta_filter) => { meta.push(meta_filter); false }
+                None => true
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let name = match name.is_empty() {
+            true => None,
+            false => Some(NameFilter::new(&name)?)
+        };
+
+        Ok(FileFilter {
+            name: name,
+            meta: meta
+        })
+    }
+
+    pub fn needs_meta(&self) -> bool {
+        self.meta.len() > 0
+    }
+
+    pub fn matches(&self, file: &File) -> bool {
+        let name_matches = self.name
+            .as_ref()
+            .map(|name| name.matches(&file.name))
+            .unwrap_or(true);
+
+        if !name_matches { return false; }
+        if self.meta.len() == 0 { return true; }
+
+        let meta = match file.meta() {
+            Some(meta) => meta,
+            None => return false
+        };
+
+        match meta.as_ref() {
+            Some(meta) => self.meta.iter().all(|filter| filter.matches(meta)),
+            None => false
+        }
+    }
+}
+
+// Shell-style matching with "*" and "?" wildcards
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let name = name.chars().collect::<Vec<char>>();
+
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&ch) if ch == '?' || ch == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // Let the last "*" eat one more char and try again
+                Some((star_p, star_n)) => {
+                    backtrack = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false
+            }
+        }
+    }
+
+    pattern[p..].iter().all(|&ch| ch == '*')
+}
+
 impl std::fmt::Display for SortBy {
     fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         let text = match self {
             SortBy::Name => "name",
+            SortBy::Extension => "extension",
             SortBy::Size => "size",
             SortBy::MTime => "mtime",
         };
@@ -1093,9 +1973,22 @@ impl std::fmt::Display for SortBy {
     }
 }
 
+impl SortBy {
+    pub fn from_name(name: &str) -> Option<SortBy> {
+        match name {
+            "name" => Some(SortBy::Name),
+            "extension" => Some(SortBy::Extension),
+            "size" => Some(SortBy::Size),
+            "mtime" => Some(SortBy::MTime),
+            _ => None
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum SortBy {
     Name,
+    Extension,
     Size,
     MTime,
 }
@@ -1252,10 +2145,131 @@ impl File {
         Ok(())
     }
 
+    // Moves the file into the trash and returns where it ended up
+    pub fn trash(&self) -> HResult<PathBuf> {
+        use crate::file_ops::{FileJob, FileOp};
+
+        let job = FileJob::new(FileOp::Trash, vec![self.path.clone()], None);
+        job.trash(self, &mut |_, _| {})
+    }
+
+    // Writes the .trashinfo file and returns its path along with the path
+    // the file has to be moved to. Creating the info file exclusively
+    // reserves the name, so "name", "name.2", ... are tried until one is free.
+    pub fn trash_info(&self) -> HResult<(PathBuf, PathBuf)> {
+        use std::io::Write;
+        use std::os::unix::ffi::OsStrExt;
+
+        let trash = crate::paths::trash_path()?;
+        let files_dir = trash.join("files");
+        let info_dir = trash.join("info");
+        std::fs::create_dir_all(&files_dir)?;
+        std::fs::create_dir_all(&info_dir)?;
+
+        let path = match self.path.is_absolute() {
+            true => self.path.clone(),
+            false => std::env::current_dir()?.join(&self.path)
+        };
+        let name = path.file_name()?;
+
+        // Like an URI, so everything besides unreserved characters is escaped
+        let encoded_path = path.as_os_str()
+            .as_bytes()
+            .iter()
+            .map(|&byte| match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' |
+                b'-' | b'_' | b'.' | b'~' | b'/' => (byte as char).to_string(),
+                _ => format!("%{:02X}", byte)
+            })
+            .collect::<String>();
+
+        let date = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S");
+
+        for n in 1.. {
+            let mut trash_name = name.to_os_string();
+            if n > 1 {
+                trash_name.push(format!(".{}", n));
+            }
+
+            let dest = files_dir.join(&trash_name);
+            trash_name.push(".trashinfo");
+            let info_path = info_dir.join(&trash_name);
+
+            if dest.symlink_metadata().is_ok() {
+                continue;
+            }
+
+            let info = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&info_path);
+
+            match info {
+                Ok(mut info) => {
+                    let written = write!(info,
+                                         "[Trash Info]\nPath={}\nDeletionDate={}\n",
+                                         encoded_path,
+                                         date);
+                    if let Err(err) = written {
+                        std::fs::remove_file(&info_path).ok();
+                        return Err(err)?;
+                    }
+                    return Ok((info_path, dest));
+                }
+                Err(ref err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err)?
+            }
+        }
+
+        unreachable!()
+    }
+
     pub fn set_dirsize(&mut self, dirsize: Arc<(AtomicBool, AtomicUsize)>) {
         self.dirsize = Some(dirsize);
     }
 
+    // Expensive, so only done for directories on screen and kept until
+    // something inside changes. Stops once the listing is stale.
+    pub fn calculate_recursive_size(&self,
+                                    sender: Sender<Events>,
+                                    stale: Stale) -> HResult<()> {
+        let dirsize: Arc<(AtomicBool, AtomicUsize)> = Arc::default();
+
+        {
+            let mut sizes = RECURSIVE_SIZES.write()?;
+            if sizes.contains_key(&self.path) {
+                return Ok(());
+            }
+            sizes.insert(self.path.clone(), dirsize.clone());
+        }
+
+        let path = self.path.clone();
+
+        SIZE_POOL.spawn(move || {
+            start_ticking(sender);
+
+            match recursive_size(&path, &stale) {
+                Ok(size) => {
+                    dirsize.1.store(size, Ordering::Relaxed);
+                    dirsize.0.store(true, Ordering::Relaxed);
+                }
+                // Walked again when it's shown the next time
+                Err(_) => {
+                    if let Ok(mut sizes) = RECURSIVE_SIZES.write() {
+                        if sizes.get(&path).map(|size| Arc::ptr_eq(size, &dirsize))
+                                           == Some(true) {
+                            sizes.remove(&path);
+                        }
+                    }
+                }
+            }
+
+            stop_ticking();
+        });
+
+        Ok(())
+    }
+
     pub fn refresh_meta_job(&mut self) -> Job {
         let meta = self.meta
             .as_ref()
@@ -1269,7 +2283,7 @@ impl File {
         (self.path.clone(), Some(meta), None)
     }
 
-    pub fn prepare_meta_job(&mut self, cache: &FsCache) -> Option<Job> {
+    pub fn prepare_meta_job(&mut self, cache: &FsCache, show_hidden: bool) -> Option<Job> {
         let mslot = match self.meta {
             Some(_) => None,
             None => {
@@ -1279,12 +2293,11 @@ impl File {
             }
         };
 
-        let dslot = match self.dirsize {
-            None if self.is_dir() => {
-                let dslot = match cache.get_dirsize(self) {
-                    Some(dslot) => dslot,
-                    None => cache.make_dirsize(self)
-                };
+        // Counted again when it has the slot for the other setting
+        let dslot = match (&self.dirsize, cache.get_dirsize(self, show_hidden)) {
+            (Some(dirsize), Some(dslot)) if Arc::ptr_eq(dirsize, &dslot) => None,
+            (_, dslot) if self.is_dir() => {
+                let dslot = dslot.unwrap_or_else(|| cache.make_dirsize(self, show_hidden));
                 self.set_dirsize(dslot.clone());
                 Some(dslot)
             }
@@ -1328,44 +2341,45 @@ impl File {
         }
     }
 
-    pub fn calculate_size(&self) -> HResult<(usize, &str)> {
+    pub fn calculate_size(&self, format: SizeFormat) -> HResult<String> {
         if self.is_dir() {
-            let size = match self.dirsize {
+            let dirsize = match dir_bytes() {
+                true => recursive_size_slot(&self.path),
+                false => self.dirsize.clone()
+            };
+
+            let size = match dirsize {
                 Some(ref dirsize) => {
                     let (ref ready, ref size) = **dirsize;
                     if ready.load(Ordering::Relaxed) == true {
-                        (size.load(Ordering::Relaxed), "")
+                        let size = size.load(Ordering::Relaxed);
+                        match dir_bytes() {
+                            true => format.format(size as u64),
+                            false => size.to_string()
+                        }
                     } else {
                         return Err(FileError::MetaPending)?;
                     }
                 },
-                None => (0, ""),
+                None if dir_bytes() => return Err(FileError::MetaPending)?,
+                None => String::from("0"),
             };
 
             return Ok(size);
         }
 
 
-        let mut unit = 0;
-        let mut size = match self.meta() {
-            Some(meta) => meta.as_ref().unwrap().size(),
+        let size = match self.meta() {
+            Some(meta) => shown_size(meta.as_ref().unwrap()),
             None => return Err(FileError::MetaPending)?
         };
-        while size > 1024 {
-            size /= 1024;
-            unit += 1;
-        }
-        let unit = match unit {
-            0 => "",
-            1 => " KB",
-            2 => " MB",
-            3 => " GB",
-            4 => " TB",
-            5 => " wtf are you doing",
-            _ => "",
-        };
 
-        Ok((size as usize, unit))
+        Ok(format.format(size))
+    }
+
+    pub fn disk_size(&self) -> Option<u64> {
+        let meta = self.meta()?;
+        meta.as_ref().map(disk_size)
     }
 
     // Sadly tree_magic tends to panic (in unwraps a None) when called
@@ -1377,6 +2391,12 @@ impl File {
         use std::panic;
         use crate::fail::MimeError;
 
+        // tree_magic would block forever reading from a pipe
+        if self.is_special() {
+            return Err(MimeError::SpecialFile(self.name.clone(),
+                                              self.file_type()))?;
+        }
+
         if let Some(ext) = self.path.extension() {
             let mime = mime_guess::from_ext(&ext.to_string_lossy()).first();
             if mime.is_some() {
@@ -1411,9 +2431,9 @@ impl File {
 
     pub fn is_filtered(&self, filter: &str, filter_selected: bool) -> bool {
         self.kind == Kind::Placeholder ||
-            !(// filter.is_some() &&
-              !self.name.contains(filter// .as_ref().unwrap()
-              )) &&
+            NameFilter::new(filter)
+                .map(|filter| filter.matches(&self.name))
+                .unwrap_or(false) &&
             (!filter_selected || self.selected)
     }
 
@@ -1571,6 +2591,50 @@ impl File {
         }
     }
 
+    // Follows symlinks, a link to a pipe is just as bad
+    pub fn file_type(&self) -> FileType {
+        let mode = match self.meta() {
+            Some(meta) => meta.as_ref().map(|meta| meta.mode()),
+            None => std::fs::metadata(&self.path).ok().map(|meta| meta.mode())
+        };
+
+        mode.map(|mode| FileType::from_mode(mode as u32))
+            .unwrap_or(FileType::Unknown)
+    }
+
+    pub fn is_special(&self) -> bool {
+        self.file_type().is_special()
+    }
+
+    // Like ls -F, files whose metadata isn't loaded yet get nothing
+    pub fn type_indicator(&self) -> Option<char> {
+        if self.target.is_some() { return Some('@') }
+        if self.is_dir() { return Some('/') }
+
+        let meta = self.meta()?;
+        let mode = meta.as_ref()?.mode();
+
+        match FileType::from_mode(mode) {
+            FileType::Fifo => Some('|'),
+            FileType::Socket => Some('='),
+            FileType::Regular if mode & 0o111 != 0 => Some('*'),
+            _ => None
+        }
+    }
+
+    pub fn is_executable(&self) -> bool {
+        let mode = match self.meta() {
+            Some(meta) => meta.as_ref().map(|meta| meta.mode()),
+            // Metadata might still be loading
+            None => std::fs::metadata(&self.path).ok().map(|meta| meta.mode())
+        };
+
+        match mode {
+            Some(mode) => !self.is_dir() && mode & 0o111 != 0,
+            None => false
+        }
+    }
+
     pub fn pretty_print_permissions(&self) -> HResult<String> {
         let meta = self.meta()?;
         let meta = meta.as_ref()?;
@@ -1626,13 +2690,13 @@ impl File {
         Some(format!("{}{}", color, file_group.name().to_string_lossy()))
     }
 
-    pub fn pretty_mtime(&self) -> Option<String> {
+    pub fn pretty_mtime(&self, format: &DateFormat) -> Option<String> {
         let meta = self.meta()?;
         let meta = meta.as_ref()?;
 
         let time: chrono::DateTime<chrono::Local>
             = chrono::Local.timestamp(meta.mtime(), 0);
-        Some(time.format("%F %R").to_string())
+        Some(format.format(time))
     }
 
     pub fn icon(&self) -> &'static str {
@@ -1676,3 +2740,58 @@ impl Drop for Ticker {
         }
     }
 }
+
+#[test]
+fn test_meta_filter_parse() {
+    use std::time::Duration;
+
+    assert_eq!(MetaFilter::parse(">1M"), Some(MetaFilter::LargerThan(1 << 20)));
+    assert_eq!(MetaFilter::parse("<10k"), Some(MetaFilter::SmallerThan(10 << 10)));
+    assert_eq!(MetaFilter::parse(">1.5k"), Some(MetaFilter::LargerThan(1536)));
+    assert_eq!(MetaFilter::parse(">100"), Some(MetaFilter::LargerThan(100)));
+    assert_eq!(MetaFilter::parse("@7d"),
+               Some(MetaFilter::ModifiedWithin(Duration::from_secs(7 * 24 * 60 * 60))));
+    assert_eq!(MetaFilter::parse("@2h"),
+               Some(MetaFilter::ModifiedWithin(Duration::from_secs(2 * 60 * 60))));
+
+    assert_eq!(MetaFilter::parse(">"), None);
+    assert_eq!(MetaFilter::parse(">1x"), None);
+    assert_eq!(MetaFilter::parse("@home"), None);
+    assert_eq!(MetaFilter::parse("foo"), None);
+}
+
+#[test]
+fn test_date_format() {
+    let now = chrono::Local.ymd(2020, 6, 15).and_hms(12, 0, 0);
+    let relative = DateFormat::Relative;
+    let format = |time| relative.format_at(time, now);
+
+    assert_eq!(format(chrono::Local.ymd(2020, 6, 15).and_hms(11, 59, 30)), "just now");
+    assert_eq!(format(chrono::Local.ymd(2020, 6, 15).and_hms(11, 59, 0)), "1 minute ago");
+    assert_eq!(format(chrono::Local.ymd(2020, 6, 15).and_hms(9, 0, 0)), "3 hours ago");
+    assert_eq!(format(chrono::Local.ymd(2020, 6, 14).and_hms(20, 0, 0)), "yesterday");
+    assert_eq!(format(chrono::Local.ymd(2020, 6, 11).and_hms(12, 0, 0)), "4 days ago");
+    assert_eq!(format(chrono::Local.ymd(2020, 5, 1).and_hms(12, 0, 0)), "1 month ago");
+    assert_eq!(format(chrono::Local.ymd(2017, 6, 1).and_hms(12, 0, 0)), "3 years ago");
+
+    assert_eq!(DateFormat::parse("%d %b %Y").map(|f| f.format_at(now, now)),
+               Some(String::from("15 Jun 2020")));
+    assert_eq!(DateFormat::parse("relative"), Some(DateFormat::Relative));
+    assert_eq!(DateFormat::parse("%Q"), None);
+}
+
+#[test]
+fn test_count_entries() {
+    let dir = crate::test_dir::TestDir::new("count");
+    std::fs::create_dir(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("file"), "").unwrap();
+    std::fs::write(dir.join(".hidden"), "").unwrap();
+
+    let count = |show_hidden| {
+        let mut dir = Dir::open(&*dir, OFlag::O_DIRECTORY, Mode::empty()).unwrap();
+        count_entries(&mut dir, show_hidden)
+    };
+
+    assert_eq!(count(false), 2);
+    assert_eq!(count(true), 3);
+}
@@ -0,0 +1,257 @@
+use termion::event::Key;
+
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::listview::{Listable, ListView};
+use crate::widget::Widget;
+use crate::fail::{HResult, ErrorLog};
+use crate::term;
+
+
+const MAX_VISITS: usize = 1000;
+
+// Read from disk on first use, written back after every change
+lazy_static! {
+    static ref VISITS: Mutex<Option<Vec<Visit>>> = Mutex::new(None);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visit {
+    pub path: PathBuf,
+    pub count: u64,
+    // Seconds since the epoch
+    pub last: u64
+}
+
+impl Visit {
+    // Same weighting as z: visits in the last hour count four times as
+    // much, in the last day twice, older ones less and less
+    pub fn score(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.last);
+        let weight = match age {
+            _ if age < 60 * 60 => 4.0,
+            _ if age < 24 * 60 * 60 => 2.0,
+            _ if age < 7 * 24 * 60 * 60 => 0.5,
+            _ => 0.25
+        };
+
+        self.count as f64 * weight
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+fn sort_by_score(visits: &mut Vec<Visit>) {
+    let now = now();
+    visits.sort_by(|a, b| {
+        b.score(now)
+         .partial_cmp(&a.score(now))
+         .unwrap_or(Ordering::Equal)
+    });
+}
+
+// One "count:last:path" per line
+fn load() -> HResult<Vec<Visit>> {
+    let content = std::fs::read_to_string(crate::paths::frecency_path()?)?;
+
+    let visits = content.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ":");
+            let count = parts.next()?.parse().ok()?;
+            let last = parts.next()?.parse().ok()?;
+            let path = PathBuf::from(parts.next()?);
+
+            Some(Visit {
+                path: path,
+                count: count,
+                last: last
+            })
+        })
+        .collect();
+
+    Ok(visits)
+}
+
+fn save(visits: &[Visit]) -> HResult<()> {
+    let content = visits.iter()
+        .map(|visit| format!("{}:{}:{}\n",
+                             visit.count,
+                             visit.last,
+                             visit.path.to_string_lossy()))
+        .collect::<String>();
+
+    std::fs::write(crate::paths::frecency_path()?, content)?;
+    Ok(())
+}
+
+fn with_visits<T>(fun: impl FnOnce(&mut Vec<Visit>) -> T) -> HResult<T> {
+    let mut visits = VISITS.lock()?;
+
+    if visits.is_none() {
+        *visits = Some(load().unwrap_or_else(|_| vec![]));
+    }
+
+    Ok(fun(visits.as_mut()?))
+}
+
+pub fn add_visit(path: &Path) -> HResult<()> {
+    let now = now();
+
+    with_visits(|visits| {
+        match visits.iter_mut().find(|visit| visit.path == path) {
+            Some(visit) => {
+                visit.count += 1;
+                visit.last = now;
+            }
+            None => visits.push(Visit {
+                path: path.to_path_buf(),
+                count: 1,
+                last: now
+            })
+        }
+
+        // Forget the least interesting ones
+        if visits.len() > MAX_VISITS {
+            sort_by_score(visits);
+            visits.truncate(MAX_VISITS);
+        }
+
+        save(visits)
+    })?
+}
+
+// Best first. Directories that are gone by now are dropped here.
+pub fn ranked_visits() -> HResult<Vec<Visit>> {
+    with_visits(|visits| {
+        let len = visits.len();
+        visits.retain(|visit| visit.path.is_dir());
+
+        if visits.len() != len {
+            save(visits).log();
+        }
+
+        let mut ranked = visits.clone();
+        sort_by_score(&mut ranked);
+        ranked
+    })
+}
+
+
+// Ranked list of visited directories, typing narrows it down
+#[derive(Debug, PartialEq)]
+pub struct FrecentList {
+    visits: Vec<Visit>,
+    matches: Vec<usize>,
+    query: String,
+    pub picked: Option<PathBuf>
+}
+
+impl FrecentList {
+    pub fn new() -> HResult<FrecentList> {
+        let visits = ranked_visits()?;
+
+        Ok(FrecentList {
+            matches: (0..visits.len()).collect(),
+            visits: visits,
+            query: String::new(),
+            picked: None
+        })
+    }
+
+    fn update_matches(&mut self) {
+        let query = self.query.to_lowercase();
+        let visits = &self.visits;
+
+        self.matches = (0..visits.len())
+            .filter(|&i| {
+                visits[i].path
+                         .to_string_lossy()
+                         .to_lowercase()
+                         .contains(&query)
+            })
+            .collect();
+    }
+}
+
+impl Listable for ListView<FrecentList> {
+    type Item = ();
+
+    fn len(&self) -> usize {
+        self.content.matches.len()
+    }
+
+    fn render(&self) -> Vec<String> {
+        let (xsize, ysize) = self.core.coordinates.size_u();
+        let visits = &self.content.visits;
+
+        self.content
+            .matches
+            .iter()
+            .skip(self.offset)
+            .take(ysize)
+            .map(|&i| {
+                let path = visits[i].path.to_string_lossy();
+                format!("{}{}",
+                        term::normal_color(),
+                        term::sized_string_u(&path, xsize))
+            })
+            .collect()
+    }
+
+    fn render_header(&self) -> HResult<String> {
+        match self.content.query.is_empty() {
+            true => Ok(String::from("Frequently visited directories")),
+            false => Ok(format!("Visited directories matching: {}",
+                                self.content.query))
+        }
+    }
+
+    fn render_footer(&self) -> HResult<String> {
+        Ok(String::from("Type to search, Enter: go there, Esc: close"))
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        match key {
+            Key::Esc | Key::Ctrl('c') => return self.popup_finnished(),
+            Key::Char('\n') => return self.pick(),
+            Key::Up | Key::Ctrl('p') => self.move_up(),
+            Key::Down | Key::Ctrl('n') => self.move_down(),
+            Key::PageUp => self.page_up(),
+            Key::PageDown => self.page_down(),
+            Key::Backspace => {
+                self.content.query.pop();
+                self.content.update_matches();
+                self.set_selection(0);
+            }
+            Key::Char(chr) => {
+                self.content.query.push(chr);
+                self.content.update_matches();
+                self.set_selection(0);
+            }
+            _ => {}
+        }
+
+        self.refresh().log();
+        self.draw()
+    }
+}
+
+impl ListView<FrecentList> {
+    fn pick(&mut self) -> HResult<()> {
+        let list = &self.content;
+        let picked = list.matches
+            .get(self.get_selection())
+            .map(|&i| list.visits[i].path.clone());
+
+        self.content.picked = picked;
+        self.popup_finnished()
+    }
+}
@@ -22,7 +22,8 @@ pub struct DirSettings {
     reverse: bool,
     show_hidden: bool,
     filter: Option<String>,
-    filter_selected: bool
+    filter_selected: bool,
+    ignore: Vec<String>
 }
 
 impl DirSettings {
@@ -33,14 +34,110 @@ impl DirSettings {
             reverse: false,
             show_hidden: true,
             filter: None,
-            filter_selected: false
+            filter_selected: false,
+            ignore: vec![]
         }
     }
 }
 
+// Sort order is kept across sessions, everything else only per session
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SortSettings {
+    pub sort: SortBy,
+    pub reverse: bool,
+    pub dirs_first: bool
+}
+
+impl SortSettings {
+    // Same as a fresh Files
+    fn default() -> SortSettings {
+        SortSettings {
+            sort: SortBy::Name,
+            reverse: false,
+            dirs_first: true
+        }
+    }
+
+    fn from_files(files: &Files) -> SortSettings {
+        SortSettings {
+            sort: files.sort,
+            reverse: files.reverse,
+            dirs_first: files.dirs_first
+        }
+    }
+
+    pub fn apply(&self, files: &mut Files) {
+        files.sort = self.sort;
+        files.reverse = self.reverse;
+        files.dirs_first = self.dirs_first;
+    }
+}
+
+// Bump this when the format changes, files with other versions are ignored
+const SORT_SETTINGS_VERSION: &str = "hunter sort settings v1";
+
+fn load_sort_settings() -> HResult<HashMap<PathBuf, SortSettings>> {
+    let path = crate::paths::sort_settings_path()?;
+    if !path.exists() { return Ok(HashMap::new()) }
+
+    let content = std::fs::read_to_string(&path)?;
+    let mut lines = content.lines();
+
+    if lines.next() != Some(SORT_SETTINGS_VERSION) {
+        return HError::log(&format!("Ignoring {}, unknown format",
+                                    path.to_string_lossy()));
+    }
+
+    let on = |flag: &str| match flag {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None
+    };
+
+    // The path comes last, so it can contain anything except newlines
+    let settings = lines
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, "\t");
+            let sort = SortBy::from_name(fields.next()?)?;
+            let reverse = on(fields.next()?)?;
+            let dirs_first = on(fields.next()?)?;
+            let dir = PathBuf::from(fields.next()?);
+
+            Some((dir, SortSettings { sort, reverse, dirs_first }))
+        })
+        .collect();
+
+    Ok(settings)
+}
+
+fn save_sort_settings(settings: &HashMap<PathBuf, SortSettings>) -> HResult<()> {
+    let path = crate::paths::sort_settings_path()?;
+    let on = |flag: bool| match flag {
+        true => "on",
+        false => "off"
+    };
+
+    let mut content = format!("{}\n", SORT_SETTINGS_VERSION);
+    for (dir, settings) in settings {
+        let dir = dir.to_string_lossy();
+        if dir.contains("\n") { continue; }
+
+        content += &format!("{}\t{}\t{}\t{}\n",
+                            settings.sort,
+                            on(settings.reverse),
+                            on(settings.dirs_first),
+                            dir);
+    }
+
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct TabSettings {
     selection: Option<File>,
+    // How far the list was scrolled down
+    offset: usize,
     multi_selections: Vec<File>,
     dir_settings: DirSettings,
 }
@@ -49,6 +146,7 @@ impl TabSettings {
     fn new() -> TabSettings {
         TabSettings {
             selection: None,
+            offset: 0,
             multi_selections: vec![],
             dir_settings: DirSettings::new()
         }
@@ -127,9 +225,10 @@ use std::sync::atomic::{AtomicBool, AtomicUsize};
 #[derive(Clone)]
 pub struct FsCache {
     files: Arc<RwLock<HashMap<File, Files>>>,
-    dirsizes: Arc<RwLock<HashMap<PathBuf, HashMap<PathBuf,
-                                                  Arc<(AtomicBool, AtomicUsize)>>>>>,
+    dirsizes: Arc<RwLock<HashMap<(PathBuf, bool), HashMap<PathBuf,
+                                                          Arc<(AtomicBool, AtomicUsize)>>>>>,
     pub tab_settings: Arc<RwLock<HashMap<File, TabSettings>>>,
+    sort_settings: Arc<RwLock<HashMap<PathBuf, SortSettings>>>,
     watched_dirs: Arc<RwLock<HashSet<File>>>,
     watcher: Arc<RwLock<RecommendedWatcher>>,
     fs_event_dispatcher: FsEventDispatcher
@@ -146,6 +245,8 @@ impl FsCache {
             files: Arc::new(RwLock::new(HashMap::new())),
             dirsizes: Arc::new(RwLock::new(HashMap::new())),
             tab_settings: Arc::new(RwLock::new(HashMap::new())),
+            sort_settings: Arc::new(RwLock::new(load_sort_settings()
+                                                .unwrap_or_else(|_| HashMap::new()))),
             watched_dirs: Arc::new(RwLock::new(HashSet::new())),
             watcher: Arc::new(RwLock::new(watcher)),
             fs_event_dispatcher: FsEventDispatcher::new()
@@ -209,6 +310,22 @@ impl FsCache {
            .clone())
     }
 
+    pub fn get_offset(&self, dir: &File) -> HResult<usize> {
+        Ok(self.tab_settings
+           .read()?
+           .get(&dir)?
+           .offset)
+    }
+
+    pub fn set_offset(&self, dir: File, offset: usize) -> HResult<()> {
+        self.tab_settings.write()
+            .map(|mut settings| {
+                let setting = settings.entry(dir).or_insert(TabSettings::new());
+                setting.offset = offset;
+            })?;
+        Ok(())
+    }
+
     pub fn set_selection(&self, dir: File, selection: File) -> HResult<()> {
         self.tab_settings.write()
             .map(|mut settings| {
@@ -218,25 +335,77 @@ impl FsCache {
         Ok(())
     }
 
-    pub fn save_settings(&self, files: &Files, selection: Option<File>) -> HResult<()> {
+    pub fn save_settings(&self,
+                         files: &Files,
+                         selection: Option<File>,
+                         offset: usize) -> HResult<()> {
         let dir = files.directory.clone();
-        let tab_settings = FsCache::extract_tab_settings(&files, selection);
+        let tab_settings = FsCache::extract_tab_settings(&files, selection, offset);
         self.tab_settings.write()?.insert(dir, tab_settings);
+        self.save_sort_settings(&files).log();
         Ok(())
     }
 
+    pub fn get_sort_settings(&self, dir: &File) -> Option<SortSettings> {
+        self.sort_settings
+            .read()
+            .ok()?
+            .get(&dir.path)
+            .cloned()
+    }
+
+    // Only writes to disk when the sort order actually changed
+    fn save_sort_settings(&self, files: &Files) -> HResult<()> {
+        let dir = files.directory.path.clone();
+        let settings = SortSettings::from_files(files);
+        let mut sort_settings = self.sort_settings.write()?;
+
+        match sort_settings.get(&dir) {
+            Some(saved) if *saved == settings => return Ok(()),
+            None if settings == SortSettings::default() => return Ok(()),
+            _ => {}
+        }
+
+        match settings == SortSettings::default() {
+            true => sort_settings.remove(&dir),
+            false => sort_settings.insert(dir, settings)
+        };
+
+        // The writer holds the lock and saves what's there when it runs, so
+        // a slower thread can't overwrite newer settings with older ones
+        let sort_settings = self.sort_settings.clone();
+        let save = move || -> HResult<()> {
+            let sort_settings = sort_settings.write()?;
+            save_sort_settings(&sort_settings)
+        };
+        std::thread::spawn(move || save().log());
+
+        Ok(())
+    }
+
+    pub fn has_settings(&self, dir: &File) -> bool {
+        self.tab_settings
+            .read()
+            .map(|settings| settings.contains_key(dir))
+            .unwrap_or(false)
+    }
+
     pub fn is_cached(&self, dir: &File) -> HResult<bool> {
         Ok(self.files.read()?.contains_key(dir))
     }
 
-    pub fn get_dirsize(&self, dir: &File) -> Option<Arc<(AtomicBool, AtomicUsize)>> {
+    // Hidden files are only counted when they're shown, so the two ways of
+    // counting each have their own slots
+    pub fn get_dirsize(&self,
+                       dir: &File,
+                       show_hidden: bool) -> Option<Arc<(AtomicBool, AtomicUsize)>> {
         let parent_dir = dir.parent()
                             .unwrap_or_else(|| Path::new("/"));
 
         self.dirsizes
             .read()
             .unwrap()
-            .get(parent_dir)
+            .get(&(parent_dir.to_path_buf(), show_hidden))
             .map(|parent_map| {
                 parent_map.get(&dir.path)
             })
@@ -244,50 +413,20 @@ impl FsCache {
             .cloned()
     }
 
-    pub fn make_dirsize(&self, dir: &File) -> Arc<(AtomicBool, AtomicUsize)> {
+    pub fn make_dirsize(&self,
+                        dir: &File,
+                        show_hidden: bool) -> Arc<(AtomicBool, AtomicUsize)> {
         let parent_dir = dir.parent()
                             .unwrap_or_else(|| Path::new("/"));
-        let dir = dir.path.as_path();
-
-        let mut dirsizes = self.dirsizes
-                               .write()
-                               .unwrap();
-
-        match dirsizes.contains_key(parent_dir) {
-            false => {
-                let mut dir_map = HashMap::new();
-                let ready = AtomicBool::new(false);
-                let size = AtomicUsize::default();
-                let dirsize = Arc::new((ready, size));
-                dir_map.insert(dir.to_path_buf(),
-                               dirsize);
-                dirsizes.insert(parent_dir.to_path_buf(),
-                                dir_map);
-
-                return dirsizes.get(parent_dir)
-                               .unwrap()
-                               .get(dir)
-                               .unwrap()
-                               .clone();
-            }
-            true => {
-                let pmap = dirsizes.get_mut(parent_dir).unwrap();
-
-                match pmap.get(dir) {
-                    Some(dirsize) => dirsize.clone(),
-                    None => {
-                        let ready = AtomicBool::new(false);
-                        let size = AtomicUsize::default();
-                        let dirsize = Arc::new((ready, size));
-                        pmap.insert(dir.to_path_buf(), dirsize);
-
-                        return pmap.get(dir)
-                                   .unwrap()
-                                   .clone()
-                    }
-                }
-            }
-        }
+
+        self.dirsizes
+            .write()
+            .unwrap()
+            .entry((parent_dir.to_path_buf(), show_hidden))
+            .or_default()
+            .entry(dir.path.clone())
+            .or_default()
+            .clone()
     }
 
     pub fn watch_only(&self, open_dirs: HashSet<File>) -> HResult<()> {
@@ -342,6 +481,7 @@ impl FsCache {
             files.reverse = tab_settings.dir_settings.reverse;
             files.show_hidden = tab_settings.dir_settings.show_hidden;
             files.filter = tab_settings.dir_settings.filter.clone();
+            files.ignore = tab_settings.dir_settings.ignore.clone();
 
             if tab_settings.multi_selections.len() > 0 {
                 for file in &mut files.files {
@@ -371,7 +511,8 @@ impl FsCache {
 
         if files.show_hidden != tab_settings.dir_settings.show_hidden ||
             files.filter != tab_settings.dir_settings.filter ||
-            files.filter_selected != tab_settings.dir_settings.filter_selected {
+            files.filter_selected != tab_settings.dir_settings.filter_selected ||
+            files.ignore != tab_settings.dir_settings.ignore {
                 files.recalculate_len();
             }
 
@@ -381,6 +522,7 @@ impl FsCache {
         files.show_hidden = tab_settings.dir_settings.show_hidden;
         files.filter = tab_settings.dir_settings.filter.clone();
         files.filter_selected = tab_settings.dir_settings.filter_selected;
+        files.ignore = tab_settings.dir_settings.ignore.clone();
 
 
 
@@ -408,9 +550,12 @@ impl FsCache {
     }
 
 
-    fn extract_tab_settings(files: &Files, selection: Option<File>) -> TabSettings {
+    fn extract_tab_settings(files: &Files,
+                            selection: Option<File>,
+                            offset: usize) -> TabSettings {
         TabSettings {
             selection: selection,
+            offset: offset,
             multi_selections: files.get_selected().into_iter().cloned().collect(),
             dir_settings: DirSettings {
                 sort: files.sort,
@@ -418,7 +563,8 @@ impl FsCache {
                 reverse: files.reverse,
                 show_hidden: files.show_hidden,
                 filter: files.filter.clone(),
-                filter_selected: files.filter_selected
+                filter_selected: files.filter_selected,
+                ignore: files.ignore.clone()
             }
         }
     }
@@ -495,6 +641,11 @@ fn watch_fs(rx_fs_events: Receiver<DebouncedEvent>,
         let transform_event =
             move |event: DebouncedEvent| -> HResult<(File, FsEvent)> {
                 let path = event.get_source_path()?;
+                crate::files::forget_recursive_sizes(path);
+                if let DebouncedEvent::Rename(_, new_path) = &event {
+                    crate::files::forget_recursive_sizes(new_path);
+                }
+
                 let dirpath = path.parent()
                     .map(|path| path)
                     .unwrap_or(std::path::Path::new("/"));
@@ -0,0 +1,365 @@
+use termion::event::Key;
+use parking_lot::Mutex;
+use async_value::Stale;
+
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::listview::{JobResults, Listable, ListView};
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::keybind::{Acting, Bindings, GrepAction, Movement};
+use crate::widget::Widget;
+use crate::dirty::Dirtyable;
+use crate::term;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub path: PathBuf,
+    pub line: usize,
+    pub text: String
+}
+
+// "path:line:column:text", the path can contain colons itself, so the
+// first ":line:column:" decides where it ends
+pub fn parse_vimgrep(dir: &Path, line: &str) -> Option<Match> {
+    // The line number and how long "line:column:" is
+    let numbers = |rest: &str| -> Option<(usize, usize)> {
+        let mut parts = rest.splitn(3, ':');
+        let line = parts.next()?;
+        let column = parts.next()?;
+        parts.next()?;
+
+        column.parse::<usize>().ok()?;
+        Some((line.parse().ok()?, line.len() + column.len() + 2))
+    };
+
+    line.match_indices(':')
+        .find_map(|(pos, _)| {
+            let (number, skip) = numbers(&line[pos+1..])?;
+            let path = line[..pos].trim_start_matches("./");
+
+            Some(Match {
+                path: dir.join(path),
+                line: number,
+                text: line[pos+1+skip..].to_string()
+            })
+        })
+}
+
+// Started here, so a missing rg shows up before the popup opens
+pub fn spawn_rg(dir: &Path, pattern: &str) -> std::io::Result<Child> {
+    Command::new("rg")
+        .args(&["--vimgrep", "--color", "never", "--", pattern, "."])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+}
+
+// How far the search has come, the matches are there once it's done
+#[derive(Debug)]
+pub struct Search {
+    pub found: AtomicUsize,
+    pub matches: Mutex<Option<HResult<Vec<Match>>>>,
+    rg: Mutex<Child>
+}
+
+impl Search {
+    pub fn new(rg: Child) -> Search {
+        Search {
+            found: AtomicUsize::new(0),
+            matches: Mutex::new(None),
+            rg: Mutex::new(rg)
+        }
+    }
+
+    // rg can take a long time before it prints the next match, so it's
+    // killed right away instead of when it does
+    pub fn kill(&self) {
+        let mut rg = self.rg.lock();
+        rg.kill().ok();
+        rg.wait().ok();
+    }
+}
+
+pub fn read_matches(dir: &Path,
+                    search: &Search,
+                    stale: &Stale) -> HResult<Vec<Match>> {
+    let stdout = search.rg.lock().stdout.take()?;
+    let mut matches = vec![];
+
+    for line in BufReader::new(stdout).lines() {
+        if stale.is_stale()? {
+            search.kill();
+            return Err(HError::StaleError);
+        }
+
+        // Lines that aren't valid UTF-8 are skipped
+        if let Some(found) = line.ok().and_then(|line| parse_vimgrep(dir, &line)) {
+            matches.push(found);
+            search.found.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    // 1 is for no matches, 2 for errors, which also happens when only a
+    // few files couldn't be read
+    match search.rg.lock().wait()?.code() {
+        Some(2) if matches.is_empty() => HError::log("rg failed, is the pattern valid?"),
+        _ => Ok(matches)
+    }
+}
+
+
+#[derive(Debug)]
+pub struct GrepList {
+    pub search: Arc<Search>,
+    pub picked: Option<Match>,
+    pattern: String,
+    // Paths are shown relative to it
+    dir: PathBuf,
+    matches: Option<Vec<Match>>,
+    error: Option<String>,
+    filter: Option<String>,
+    // Positions of the matches passing the filter
+    shown: Vec<usize>
+}
+
+impl PartialEq for GrepList {
+    fn eq(&self, other: &GrepList) -> bool {
+        self.pattern == other.pattern &&
+            self.dir == other.dir &&
+            self.matches == other.matches
+    }
+}
+
+impl GrepList {
+    pub fn new(search: Arc<Search>, pattern: String, dir: PathBuf) -> GrepList {
+        GrepList {
+            search: search,
+            picked: None,
+            pattern: pattern,
+            dir: dir,
+            matches: None,
+            error: None,
+            filter: None,
+            shown: vec![]
+        }
+    }
+
+    fn line(&self, found: &Match) -> String {
+        let path = found.path.strip_prefix(&self.dir)
+                             .unwrap_or(&found.path)
+                             .to_string_lossy();
+        format!("{}:{}: {}", path, found.line, found.text.trim())
+    }
+
+    fn update_shown(&mut self) {
+        let matches = match &self.matches {
+            Some(matches) => matches,
+            None => return
+        };
+
+        let filter = self.filter.as_ref().map(|filter| filter.to_lowercase());
+
+        self.shown = matches.iter()
+            .enumerate()
+            .filter(|(_, found)| match &filter {
+                Some(filter) => self.line(found).to_lowercase().contains(filter),
+                None => true
+            })
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    fn take_matches(&mut self) -> bool {
+        if self.is_done() { return false; }
+
+        match self.search.matches.lock().take() {
+            Some(Ok(matches)) => self.matches = Some(matches),
+            Some(Err(err)) => self.error = Some(err.to_string()),
+            None => return false
+        }
+
+        self.update_shown();
+        true
+    }
+}
+
+impl JobResults for GrepList {
+    fn is_done(&self) -> bool {
+        self.matches.is_some() || self.error.is_some()
+    }
+}
+
+impl Listable for ListView<GrepList> {
+    type Item = ();
+
+    fn len(&self) -> usize {
+        self.content.shown.len()
+    }
+
+    fn render(&self) -> Vec<String> {
+        let matches = match &self.content.matches {
+            Some(matches) => matches,
+            None => return vec![]
+        };
+
+        let (xsize, ysize) = self.core.coordinates.size_u();
+
+        // Only what's on screen, there can be a lot of them
+        self.content
+            .shown
+            .iter()
+            .skip(self.offset)
+            .take(ysize)
+            .map(|&i| {
+                let line = self.content.line(&matches[i]);
+                format!("{}{}",
+                        term::normal_color(),
+                        term::sized_string_u(&line, xsize))
+            })
+            .collect()
+    }
+
+    fn render_header(&self) -> HResult<String> {
+        let pattern = &self.content.pattern;
+
+        let header = match (&self.content.matches, &self.content.error) {
+            (_, Some(err)) => format!("Searching for \"{}\" failed: {}", pattern, err),
+            (Some(matches), _) if matches.is_empty() => format!("No matches for \"{}\"",
+                                                                pattern),
+            (Some(matches), _) => format!("{} matches for \"{}\"",
+                                          matches.len(),
+                                          pattern),
+            (None, None) => format!("Searching for \"{}\"{} {}",
+                                    pattern,
+                                    crate::files::tick_str(),
+                                    self.content.search.found.load(Ordering::Relaxed))
+        };
+
+        match &self.content.filter {
+            Some(filter) => Ok(format!("{}, {} shown with filter \"{}\"",
+                                       header,
+                                       self.content.shown.len(),
+                                       filter)),
+            None => Ok(header)
+        }
+    }
+
+    fn render_footer(&self) -> HResult<String> {
+        Ok(String::from("Enter: edit there, C-f: filter, Esc: close"))
+    }
+
+    fn on_refresh(&mut self) -> HResult<()> {
+        if self.content.take_matches() {
+            self.core.set_dirty();
+        }
+        Ok(())
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        self.do_key(key)?;
+        self.refresh().log();
+        self.draw()
+    }
+}
+
+impl ListView<GrepList> {
+    fn edit_match(&mut self) -> HResult<()> {
+        let found = self.content
+            .shown
+            .get(self.get_selection())
+            .and_then(|&i| self.content.matches.as_ref()?.get(i))
+            .cloned();
+
+        match found {
+            Some(found) => {
+                self.content.picked = Some(found);
+                self.popup_finnished()
+            }
+            None => Ok(())
+        }
+    }
+
+    // The minibuffer takes over input, so the popup has to be restarted
+    fn query_filter(&mut self) -> HResult<()> {
+        let mut filter = self.core.minibuffer("filter");
+
+        // New matches coming in interrupt the minibuffer
+        while let Err(HError::RefreshParent) = filter {
+            self.refresh().log();
+            self.draw().log();
+            filter = self.core.minibuffer_resume();
+        }
+
+        self.content.filter = filter.ok().filter(|filter| !filter.is_empty());
+        self.content.update_shown();
+        self.move_top();
+        HError::widget_resized()
+    }
+}
+
+impl Acting for ListView<GrepList> {
+    type Action = GrepAction;
+
+    fn search_in(&self) -> Bindings<Self::Action> {
+        self.core.config().keybinds.grep
+    }
+
+    fn movement(&mut self, movement: &Movement) -> HResult<()> {
+        use Movement::*;
+
+        // Nothing to move through until the search is done
+        if self.len() == 0 {
+            return Ok(());
+        }
+
+        match movement {
+            Up(n) => for _ in 0..*n { self.move_up(); },
+            Down(n) => for _ in 0..*n { self.move_down(); },
+            PageUp => self.page_up(),
+            PageDown => self.page_down(),
+            Top => self.move_top(),
+            Bottom => self.move_bottom(),
+            Right => self.edit_match()?,
+            Left => {}
+        }
+
+        Ok(())
+    }
+
+    fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
+        use GrepAction::*;
+
+        match action {
+            Close => self.popup_finnished(),
+            Edit => self.edit_match(),
+            Filter => self.query_filter()
+        }
+    }
+}
+
+#[test]
+fn test_parse_vimgrep() {
+    let dir = Path::new("/dir");
+
+    assert_eq!(parse_vimgrep(dir, "./src/main.rs:12:5:    fn main() {"),
+               Some(Match {
+                   path: PathBuf::from("/dir/src/main.rs"),
+                   line: 12,
+                   text: String::from("    fn main() {")
+               }));
+    // Colons in the path and the text
+    assert_eq!(parse_vimgrep(dir, "./a:b:3:1:x: y"),
+               Some(Match {
+                   path: PathBuf::from("/dir/a:b"),
+                   line: 3,
+                   text: String::from("x: y")
+               }));
+    assert_eq!(parse_vimgrep(dir, "no match here"), None);
+}
@@ -0,0 +1,89 @@
+use async_value::Stale;
+
+use std::io::Read;
+use std::path::Path;
+
+use crate::fail::{HResult, HError};
+
+
+// FNV-1a, since std's hasher isn't guaranteed to stay the same
+pub const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+pub fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Read in chunks, files can be a lot bigger than memory
+pub fn hash_file(path: &Path, stale: &Stale) -> HResult<u64> {
+    let mut file = std::fs::File::open(path)?;
+    let mut buffer = vec![0; 128 * 1024];
+    let mut hash = FNV_OFFSET;
+
+    loop {
+        if stale.is_stale()? {
+            return Err(HError::StaleError);
+        }
+
+        match file.read(&mut buffer)? {
+            0 => return Ok(hash),
+            len => hash = fnv1a(hash, &buffer[..len])
+        }
+    }
+}
+
+// Fills the buffer as far as the file goes, read() can return less
+fn read_full(file: &mut std::fs::File, buffer: &mut [u8]) -> HResult<usize> {
+    let mut len = 0;
+
+    while len < buffer.len() {
+        match file.read(&mut buffer[len..])? {
+            0 => break,
+            read => len += read
+        }
+    }
+
+    Ok(len)
+}
+
+// A matching hash could still be a collision, this makes sure
+pub fn same_content(a: &Path, b: &Path, stale: &Stale) -> HResult<bool> {
+    let mut file_a = std::fs::File::open(a)?;
+    let mut file_b = std::fs::File::open(b)?;
+    let mut buffer_a = vec![0; 128 * 1024];
+    let mut buffer_b = vec![0; 128 * 1024];
+
+    loop {
+        if stale.is_stale()? {
+            return Err(HError::StaleError);
+        }
+
+        let len_a = read_full(&mut file_a, &mut buffer_a)?;
+        let len_b = read_full(&mut file_b, &mut buffer_b)?;
+
+        if buffer_a[..len_a] != buffer_b[..len_b] {
+            return Ok(false);
+        }
+
+        if len_a == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+#[test]
+fn test_same_content() {
+    let dir = crate::test_dir::TestDir::new("hash");
+
+    // Bigger than one buffer, only the very end differs
+    let mut content = vec![b'x'; 300 * 1024];
+    std::fs::write(dir.join("a"), &content).unwrap();
+    std::fs::write(dir.join("b"), &content).unwrap();
+    content.push(b'y');
+    std::fs::write(dir.join("c"), &content).unwrap();
+
+    let stale = Stale::new();
+    assert!(same_content(&dir.join("a"), &dir.join("b"), &stale).unwrap());
+    assert!(!same_content(&dir.join("a"), &dir.join("c"), &stale).unwrap());
+}
@@ -3,6 +3,133 @@ use termion::event::{Event};
 use crate::widget::{Widget, WidgetCore};
 use crate::coordinates::{Coordinates, Size, Position};
 use crate::fail::{HResult, HError, ErrorLog};
+use crate::term;
+
+
+// Columns narrower than this aren't of much use, so when they don't all
+// fit anymore only the active widget is shown
+pub const MIN_COLUMN_XSIZE: usize = 10;
+// The same for the lines of a widget below the others
+pub const MIN_ROW_YSIZE: usize = 3;
+
+// Widths of the shown columns with a gap of one between them, or None if
+// the box is too small to fit them all
+pub fn column_sizes(ratios: &[usize], box_xsize: usize) -> Option<Vec<usize>> {
+    let ratios = ratios.iter().filter(|&&r| r > 0).collect::<Vec<_>>();
+    let gaps = ratios.len().saturating_sub(1);
+
+    if ratios.len() * MIN_COLUMN_XSIZE + gaps > box_xsize {
+        return None;
+    }
+
+    let ratios_sum: usize = ratios.iter().copied().sum();
+
+    let mut sizes = ratios.iter()
+                          .map(|&&r| (r as f64 * box_xsize as f64 / ratios_sum as f64).round() as usize)
+                          .map(|r| if r < MIN_COLUMN_XSIZE { MIN_COLUMN_XSIZE } else { r })
+                          .collect::<Vec<_>>();
+
+    let mut sizes_sum: usize = sizes.iter().sum();
+
+    // Can't go below the minimum, since that would have fit
+    while sizes_sum + gaps > box_xsize {
+        let sizes_max = sizes.iter()
+                             .position(|&r| r == *sizes.iter().max().unwrap())
+                             .unwrap();
+        sizes[sizes_max] -= 1;
+        sizes_sum -= 1;
+    }
+
+    Some(sizes)
+}
+
+// Columns in "box_coords" with a gap of one between them. Hidden widgets get
+// "whole", so zooming into them works.
+fn side_by_side(box_coords: &Coordinates,
+                ratios: &[usize],
+                whole: &Coordinates) -> Vec<Coordinates> {
+    let box_xsize = box_coords.xsize();
+    let box_ysize = box_coords.ysize();
+    let box_top = box_coords.top().y();
+    let hidden = ratios.iter().map(|&r| r == 0).collect::<Vec<_>>();
+
+    // Too small for all of them, so they all get the whole box
+    let ratios = match column_sizes(ratios, box_xsize as usize) {
+        Some(sizes) => sizes,
+        None => return ratios.iter()
+                             .map(|_| whole.clone())
+                             .collect()
+    };
+
+    let coords = ratios.iter().fold(Vec::<Coordinates>::new(), |mut coords, ratio| {
+        let len = coords.len();
+        let gap = if len == ratios.len() { 0 } else { 1 };
+
+        let widget_xsize = *ratio as u16;
+        let widget_xpos = if len == 0 {
+            box_coords.top().x()
+        } else {
+            let prev_coords = coords.last().unwrap();
+            let prev_xsize = prev_coords.xsize();
+            let prev_xpos = prev_coords.position().x();
+
+            prev_xsize + prev_xpos + gap
+        };
+
+        coords.push(Coordinates {
+            size: Size((widget_xsize,
+                        box_ysize)),
+            position: Position((widget_xpos,
+                                box_top))
+        });
+        coords
+    });
+
+    let mut coords = coords.into_iter();
+    hidden.into_iter()
+          .map(|hidden| match hidden {
+              true => whole.clone(),
+              false => coords.next()
+                             .unwrap_or_else(|| whole.clone())
+          })
+          .collect()
+}
+
+// Where the last widget goes, the others are always side by side. Below
+// them it gets its ratio of the height and the whole width.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Placement {
+    Right,
+    Left,
+    Bottom
+}
+
+// What's shown when not all columns fit
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SmallTerminal {
+    // Only the active widget, like when zooming in
+    Collapse,
+    // "Terminal too small"
+    Message
+}
+
+// Heights of the upper and the lower part with a gap of one between them,
+// or None if one of them wouldn't get any
+pub fn split_ysize(upper_ratio: usize,
+                   lower_ratio: usize,
+                   box_ysize: usize) -> Option<(usize, usize)> {
+    let ratios_sum = upper_ratio + lower_ratio;
+    if ratios_sum == 0 { return None; }
+
+    let ysize = box_ysize.checked_sub(1)?;
+    let lower = (lower_ratio as f64 * ysize as f64 / ratios_sum as f64).round() as usize;
+    let upper = ysize.checked_sub(lower)?;
+
+    match upper > 0 && lower > 0 {
+        true => Some((upper, lower)),
+        false => None
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct HBox<T: Widget> {
@@ -11,6 +138,7 @@ pub struct HBox<T: Widget> {
     pub ratios: Option<Vec<usize>>,
     pub zoom_active: bool,
     pub active: Option<usize>,
+    pub placement: Placement,
 }
 
 
@@ -20,7 +148,8 @@ impl<T> HBox<T> where T: Widget + PartialEq {
                widgets: vec![],
                ratios: None,
                zoom_active: false,
-               active: None
+               active: None,
+               placement: Placement::Right
          }
     }
 
@@ -29,7 +158,7 @@ impl<T> HBox<T> where T: Widget + PartialEq {
         let len = self.widgets.len();
         if len == 0 { return Ok(()) }
 
-        if self.zoom_active {
+        if self.zoom_active || self.is_too_small() {
             let coords = self.core.coordinates.clone();
             self.active_widget_mut()?.set_coordinates(&coords).log();
             return Ok(());
@@ -81,67 +210,138 @@ impl<T> HBox<T> where T: Widget + PartialEq {
         self.ratios = Some(ratios);
     }
 
-    pub fn calculate_equal_ratios(&self) -> HResult<Vec<usize>> {
-        let len = self.widgets.len();
-        if len == 0 { return HError::no_widget(); }
-
-        let ratios = (0..len).map(|_| 100 / len).collect();
-        Ok(ratios)
+    // Widgets with a ratio of 0 aren't shown
+    pub fn is_hidden(&self, index: usize) -> bool {
+        self.ratios
+            .as_ref()
+            .and_then(|ratios| ratios.get(index))
+            == Some(&0)
     }
 
-    pub fn calculate_coordinates(&self) -> HResult<Vec<Coordinates>> {
-        let box_coords = self.get_coordinates()?;
-        let box_xsize = box_coords.xsize();
-        let box_ysize = box_coords.ysize();
-        let box_top = box_coords.top().y();
+    fn get_ratios(&self) -> HResult<Vec<usize>> {
+        match self.ratios.clone() {
+            Some(ratios) => Ok(ratios),
+            None => self.calculate_equal_ratios()
+        }
+    }
 
-        let ratios = match self.ratios.clone() {
-            Some(ratios) => ratios,
-            None => self.calculate_equal_ratios()?
+    pub fn is_too_small(&self) -> bool {
+        let coords = &self.core.coordinates;
+        let ratios = match self.get_ratios() {
+            Ok(ratios) => ratios,
+            Err(_) => return false
         };
 
-        let ratios_sum: usize = ratios.iter().sum();
+        if coords.ysize() == 0 { return true; }
 
-        let mut ratios = ratios.iter()
-                               .map(|&r| (r as f64 * box_xsize as f64 / ratios_sum as f64).round() as usize)
-                               .map(|r| if r < 10 { 10 } else { r })
-                               .collect::<Vec<_>>();
+        match self.bottom_split(&ratios) {
+            Some((upper, lower)) => {
+                split_ysize(upper.iter().sum(), lower, coords.ysize() as usize).is_none() ||
+                    column_sizes(upper, coords.xsize() as usize).is_none()
+            }
+            None => column_sizes(&ratios, coords.xsize() as usize).is_none()
+        }
+    }
 
-        let mut ratios_sum: usize = ratios.iter().sum();
+    // Whether "ratios" leave every shown widget at least the minimum size,
+    // column_sizes would make them bigger than their ratio instead
+    pub fn fits_min_sizes(&self, ratios: &[usize]) -> bool {
+        let (xsize, ysize) = self.core.coordinates.size_u();
+        let fits = |ratios: &[usize], size: usize, min: usize| {
+            let sum: usize = ratios.iter().sum();
+            sum > 0 && ratios.iter()
+                             .filter(|&&ratio| ratio > 0)
+                             .all(|&ratio| ratio * size / sum >= min)
+        };
 
-        while ratios_sum + ratios.len() > box_xsize as usize + 1 {
-            let ratios_max = ratios.iter()
-                                   .position(|&r| r == *ratios.iter().max().unwrap())
-                                   .unwrap();
-            ratios[ratios_max] = ratios[ratios_max] - 1;
-            ratios_sum -= 1;
+        match self.bottom_split(ratios) {
+            Some((upper, lower)) => fits(&[upper.iter().sum(), lower], ysize, MIN_ROW_YSIZE) &&
+                fits(upper, xsize, MIN_COLUMN_XSIZE),
+            None => fits(ratios, xsize, MIN_COLUMN_XSIZE)
         }
+    }
 
-        let coords = ratios.iter().fold(Vec::<Coordinates>::new(), |mut coords, ratio| {
-            let len = coords.len();
-            let gap = if len == ratios.len() { 0 } else { 1 };
+    // The ratios of the widgets on top and the one of the last widget, if
+    // it's shown below them
+    fn bottom_split<'a>(&self, ratios: &'a [usize]) -> Option<(&'a [usize], usize)> {
+        match (self.placement, ratios.split_last()) {
+            (Placement::Bottom, Some((&lower, upper))) if lower > 0 => Some((upper, lower)),
+            _ => None
+        }
+    }
 
-            let widget_xsize = *ratio as u16;
-            let widget_xpos = if len == 0 {
-                box_coords.top().x()
-            } else {
-                let prev_coords = coords.last().unwrap();
-                let prev_xsize = prev_coords.xsize();
-                let prev_xpos = prev_coords.position().x();
+    // Shown instead of the active widget with SmallTerminal::Message
+    fn render_too_small(&self) -> HResult<String> {
+        let coords = self.get_coordinates()?;
+        let (xpos, ypos) = coords.u16position();
+        let (xsize, ysize) = coords.u16size();
+        if ysize == 0 { return Ok(String::new()); }
+
+        let message = term::sized_string("Terminal too small", xsize);
+        let message_xpos = xpos + (xsize - message.len() as u16) / 2;
+        let message_ypos = ypos + ysize / 2;
+
+        let clear = (ypos..ypos+ysize)
+            .map(|y| format!("{}{:xsize$}",
+                             term::goto_xy(xpos, y),
+                             "",
+                             xsize = xsize as usize))
+            .collect::<String>();
+
+        Ok(format!("{}{}{}{}{}",
+                   clear,
+                   term::goto_xy(message_xpos, message_ypos),
+                   term::color_red(),
+                   message,
+                   term::reset()))
+    }
 
-                prev_xsize + prev_xpos + gap
+    pub fn calculate_equal_ratios(&self) -> HResult<Vec<usize>> {
+        let len = self.widgets.len();
+        if len == 0 { return HError::no_widget(); }
+
+        let ratios = (0..len).map(|_| 100 / len).collect();
+        Ok(ratios)
+    }
+
+    pub fn calculate_coordinates(&self) -> HResult<Vec<Coordinates>> {
+        let box_coords = self.get_coordinates()?;
+        let mut ratios = self.get_ratios()?;
+
+        if let Some((upper, lower)) = self.bottom_split(&ratios) {
+            let (xpos, ypos) = box_coords.u16position();
+            let xsize = box_coords.xsize();
+            let ysize = box_coords.ysize() as usize;
+
+            // Too small for all of them, so they all get the whole box
+            let (upper_ysize, lower_ysize) = match split_ysize(upper.iter().sum(),
+                                                               lower,
+                                                               ysize) {
+                Some(sizes) => sizes,
+                None => return Ok(ratios.iter()
+                                        .map(|_| box_coords.clone())
+                                        .collect())
             };
 
-            coords.push(Coordinates {
-                size: Size((widget_xsize,
-                            box_ysize)),
-                position: Position((widget_xpos,
-                                    box_top))
-            });
-            coords
-        });
+            let upper_coords = Coordinates::new_at(xsize, upper_ysize as u16, xpos, ypos);
+            let lower_coords = Coordinates::new_at(xsize,
+                                                   lower_ysize as u16,
+                                                   xpos,
+                                                   ypos + upper_ysize as u16 + 1);
+
+            let mut coords = side_by_side(&upper_coords, upper, &box_coords);
+            coords.push(lower_coords);
+            return Ok(coords);
+        }
+
+        if self.placement == Placement::Left && ratios.len() > 0 {
+            ratios.rotate_right(1);
+            let mut coords = side_by_side(box_coords, &ratios, box_coords);
+            coords.rotate_left(1);
+            return Ok(coords);
+        }
 
-        Ok(coords)
+        Ok(side_by_side(box_coords, &ratios, box_coords))
     }
 
     pub fn set_active(&mut self, i: usize) -> HResult<()> {
@@ -182,26 +382,40 @@ impl<T> Widget for HBox<T> where T: Widget + PartialEq {
     }
 
     fn refresh(&mut self) -> HResult<()> {
-        if self.zoom_active {
+        if self.zoom_active || self.is_too_small() {
+            self.resize_children().log();
             self.active_widget_mut()?.refresh().log();
             return Ok(());
         }
 
         self.resize_children().log();
-        for child in &mut self.widgets {
-            child.refresh().log();
+
+        let hidden = (0..self.widgets.len()).map(|i| self.is_hidden(i))
+                                            .collect::<Vec<_>>();
+        for (child, hidden) in self.widgets.iter_mut().zip(hidden) {
+            if !hidden {
+                child.refresh().log();
+            }
         }
         Ok(())
     }
 
     fn get_drawlist(&self) -> HResult<String> {
-        if self.zoom_active {
+        if self.is_too_small() && self.core.config().small_terminal == SmallTerminal::Message {
+            return self.render_too_small();
+        }
+
+        if self.zoom_active || self.is_too_small() {
             return self.active_widget()?.get_drawlist();
         }
 
-        Ok(self.widgets.iter().map(|child| {
-            child.get_drawlist().log_and().unwrap_or_else(|_| String::new())
-        }).collect())
+        Ok(self.widgets
+               .iter()
+               .enumerate()
+               .filter(|(i, _)| !self.is_hidden(*i))
+               .map(|(_, child)| {
+                   child.get_drawlist().log_and().unwrap_or_else(|_| String::new())
+               }).collect())
     }
 
     fn on_event(&mut self, event: Event) -> HResult<()> {
@@ -213,3 +427,31 @@ impl<T> Widget for HBox<T> where T: Widget + PartialEq {
         self.active_widget_mut()?.on_key(key)
     }
 }
+
+
+
+#[test]
+fn test_column_sizes() {
+    let ratios = vec![20, 30, 49];
+
+    assert_eq!(column_sizes(&ratios, 0), None);
+    assert_eq!(column_sizes(&ratios, 1), None);
+    assert_eq!(column_sizes(&ratios, 31), None);
+    assert_eq!(column_sizes(&ratios, 32), Some(vec![10, 10, 10]));
+
+    let sizes = column_sizes(&ratios, 100).unwrap();
+    assert_eq!(sizes.iter().sum::<usize>() + 2, 100);
+
+    // Hidden columns don't take up any space
+    assert_eq!(column_sizes(&[0, 50, 50], 21), Some(vec![10, 10]));
+    assert_eq!(column_sizes(&[0, 0, 100], 5), None);
+    assert_eq!(column_sizes(&[], 0), Some(vec![]));
+}
+
+#[test]
+fn test_split_ysize() {
+    assert_eq!(split_ysize(50, 50, 21), Some((10, 10)));
+    assert_eq!(split_ysize(50, 49, 40), Some((20, 19)));
+    assert_eq!(split_ysize(50, 50, 2), None);
+    assert_eq!(split_ysize(0, 0, 40), None);
+}
@@ -0,0 +1,69 @@
+use syntect::highlighting::{HighlightIterator, HighlightState, Highlighter, Theme,
+                            ThemeSet};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+use syntect::util::as_24_bit_terminal_escaped;
+
+use std::path::Path;
+
+use crate::fail::{HResult, HError};
+
+
+// Loading these takes a while, so it's only done once
+lazy_static! {
+    static ref SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEME: Theme = ThemeSet::load_defaults()
+        .themes
+        .remove("base16-ocean.dark")
+        .unwrap_or_default();
+}
+
+// Only the extension is used, the MIME type of source files is too vague
+pub fn find_syntax(path: &Path) -> Option<&'static SyntaxReference> {
+    let ext = path.extension()?.to_str()?;
+
+    SYNTAXES.find_syntax_by_extension(ext)
+            .filter(|syntax| syntax.name != "Plain Text")
+}
+
+// Lines have to be highlighted from the start of the file, since things
+// like comments can span several of them. The state is kept between calls,
+// so only the lines that get shown need to be highlighted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineHighlighter {
+    parse_state: ParseState,
+    highlight_state: HighlightState,
+}
+
+impl LineHighlighter {
+    pub fn new(path: &Path) -> HResult<LineHighlighter> {
+        let syntax = find_syntax(path)
+            .ok_or_else(|| HError::Error(format!("No syntax for {}",
+                                                 path.to_string_lossy())))?;
+        let highlighter = Highlighter::new(&THEME);
+
+        Ok(LineHighlighter {
+            parse_state: ParseState::new(syntax),
+            highlight_state: HighlightState::new(&highlighter,
+                                                 ScopeStack::new())
+        })
+    }
+
+    // Has to be called with the lines in order, without skipping any
+    pub fn highlight(&mut self, lines: &mut [String]) {
+        let highlighter = Highlighter::new(&THEME);
+
+        for line in lines {
+            // The syntaxes expect lines to end with a newline
+            let text = format!("{}\n", line);
+            let ops = self.parse_state.parse_line(&text, &SYNTAXES);
+            let ranges = HighlightIterator::new(&mut self.highlight_state,
+                                                &ops,
+                                                &text,
+                                                &highlighter)
+                .collect::<Vec<_>>();
+            let colored = as_24_bit_terminal_escaped(&ranges, false);
+
+            *line = colored.replacen("\n", "", 1);
+        }
+    }
+}
@@ -112,7 +112,10 @@ fn main() -> MResult<()> {
                                      mute),
 
             #[cfg(feature = "video")]
-            _ => { panic!("Available types: video/image/audio") }
+            "info" => media_info(path),
+
+            #[cfg(feature = "video")]
+            _ => { panic!("Available types: video/image/audio/info") }
 
             #[cfg(not(feature = "video"))]
             _ => { panic!("Available type: image") }
@@ -243,6 +246,60 @@ fn video_preview(path: &String,
     Ok(())
 }
 
+// Prints "Label: value" lines for hunter's file info
+#[cfg(feature = "video")]
+fn media_info(path: &String) -> MResult<()> {
+    use gstreamer::tags::{AudioCodec, VideoCodec};
+
+    let gst = Gstreamer::new(path)?;
+
+    // Waits for prerolling, after that duration, caps and tags are known
+    gst.pause()?;
+    gst.get_state();
+
+    let duration = gst.duration();
+    println!("Duration: {:02}:{:02}:{:02}",
+             duration / 3600,
+             duration / 60 % 60,
+             duration % 60);
+
+    let size = gst.appsink
+        .get_static_pad("sink")
+        .and_then(|pad| pad.get_current_caps())
+        .and_then(|caps| {
+            let caps = caps.get_structure(0)?;
+            let width = caps.get::<i32>("width")?;
+            let height = caps.get::<i32>("height")?;
+            Some((width, height))
+        });
+
+    if let Some((width, height)) = size {
+        println!("Dimensions: {}x{}", width, height);
+    }
+
+    let tags = |signal: &str| {
+        gst.player
+            .emit(signal, &[&0i32])
+            .ok()
+            .and_then(|tags| tags)
+            .and_then(|tags| tags.get::<gstreamer::TagList>())
+    };
+
+    if let Some(tags) = tags("get-video-tags") {
+        tags.get::<VideoCodec>()
+            .and_then(|codec| codec.get().map(String::from))
+            .map(|codec| println!("Video codec: {}", codec));
+    }
+
+    if let Some(tags) = tags("get-audio-tags") {
+        tags.get::<AudioCodec>()
+            .and_then(|codec| codec.get().map(String::from))
+            .map(|codec| println!("Audio codec: {}", codec));
+    }
+
+    gst.stop()
+}
+
 #[cfg(feature = "video")]
 fn read_keys(gst: Gstreamer,
              renderer: Option<Arc<RwLock<Renderer>>>) -> MResult<()> {
@@ -0,0 +1,112 @@
+use nix::sys::stat::utimes;
+use nix::sys::time::{TimeVal, TimeValLike};
+
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::fail::{HResult, HError, ErrorLog};
+use crate::hash::{fnv1a, FNV_OFFSET};
+
+
+// Rendered image previews are kept on disk, so looking at an image again
+// doesn't need hunter-media. Entries are named "<path>-<version>", both
+// hashed. The version covers everything that changes the output: the
+// image's mtime and size, the size of the preview and the graphics mode.
+pub struct CacheKey {
+    path: u64,
+    version: u64
+}
+
+impl CacheKey {
+    pub fn new(file: &Path, settings: &[String]) -> HResult<CacheKey> {
+        let meta = std::fs::metadata(file)?;
+
+        let mut version = FNV_OFFSET;
+        version = fnv1a(version, &meta.mtime().to_le_bytes());
+        version = fnv1a(version, &meta.mtime_nsec().to_le_bytes());
+        version = fnv1a(version, &meta.len().to_le_bytes());
+        // Separated, so "1" "23" and "12" "3" don't end up the same
+        for setting in settings {
+            version = fnv1a(version, setting.as_bytes());
+            version = fnv1a(version, &[0]);
+        }
+
+        Ok(CacheKey {
+            path: fnv1a(FNV_OFFSET, file.as_os_str().as_bytes()),
+            version: version
+        })
+    }
+
+    fn prefix(&self) -> String {
+        format!("{:016x}-", self.path)
+    }
+
+    fn name(&self) -> String {
+        format!("{}{:016x}", self.prefix(), self.version)
+    }
+}
+
+pub fn get(key: &CacheKey) -> Option<Vec<String>> {
+    let path = crate::paths::img_cache_path().ok()?.join(key.name());
+    let content = std::fs::read_to_string(&path).ok()?;
+
+    // The mtime of an entry is when it was last used
+    let now = now();
+    utimes(&path, &now, &now).ok();
+
+    Some(content.lines().map(String::from).collect())
+}
+
+// Entries for older versions of the image are removed, then the least
+// recently used ones until the cache fits into max_size bytes again
+pub fn put(key: &CacheKey, lines: &[String], max_size: u64) -> HResult<()> {
+    let dir = crate::paths::img_cache_path()?;
+    std::fs::create_dir_all(&dir)?;
+
+    let prefix = key.prefix();
+    let mut entries = std::fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if name.starts_with(&prefix) {
+                std::fs::remove_file(entry.path()).ok();
+                return None;
+            }
+
+            let meta = entry.metadata().ok()?;
+            Some((meta.modified().ok()?, meta.len(), entry.path()))
+        })
+        .collect::<Vec<_>>();
+
+    let content = lines.join("\n");
+    std::fs::write(dir.join(key.name()), &content)?;
+
+    let mut size = entries.iter()
+        .map(|(_, len, _)| len)
+        .sum::<u64>() + content.len() as u64;
+
+    entries.sort_by_key(|(used, _, _)| *used);
+
+    for (_, len, path) in entries {
+        if size <= max_size { break; }
+
+        std::fs::remove_file(&path)
+            .map(|_| size -= len)
+            .map_err(HError::from)
+            .log();
+    }
+
+    Ok(())
+}
+
+fn now() -> TimeVal {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0);
+
+    TimeVal::seconds(secs as i64)
+}
@@ -5,8 +5,9 @@ use std::sync::atomic::{AtomicU32, Ordering};
 
 use crate::widget::{Widget, WidgetCore};
 use crate::coordinates::Coordinates;
-use crate::fail::{HResult, ErrorCause};
+use crate::fail::{HResult, ErrorCause, ErrorLog};
 use crate::mediaview::MediaError;
+use crate::img_cache::CacheKey;
 
 
 
@@ -20,6 +21,8 @@ pub struct ImgView {
     pub core: WidgetCore,
     pub buffer: Vec<String>,
     pub file: Option<PathBuf>,
+    // Off for images made by graphical previewers, they're thrown away
+    pub cache: bool,
 }
 
 impl ImgView {
@@ -28,6 +31,20 @@ impl ImgView {
             core: core,
             buffer: vec![],
             file: Some(file.to_path_buf()),
+            cache: true,
+        };
+
+        view.encode_file()?;
+        Ok(view)
+    }
+
+    // For the output of graphical previewers, which isn't cached
+    pub fn new_from_previewer(core: WidgetCore, file: &Path) -> HResult<ImgView> {
+        let mut view = ImgView {
+            core: core,
+            buffer: vec![],
+            file: Some(file.to_path_buf()),
+            cache: false,
         };
 
         view.encode_file()?;
@@ -43,6 +60,18 @@ impl ImgView {
         let media_previewer = self.core.config().media_previewer;
         let g_mode = self.core.config().graphics;
 
+        let cache_key = self.cache_key(file, vec![xsize.to_string(),
+                                                  ysize.to_string(),
+                                                  xpix.to_string(),
+                                                  ypix.to_string(),
+                                                  cell_ratio.to_string(),
+                                                  g_mode.clone()]);
+
+        if let Some(lines) = cache_key.as_ref().and_then(crate::img_cache::get) {
+            self.buffer = lines;
+            return Ok(());
+        }
+
         let mut previewer = Command::new(&media_previewer)
             .arg(format!("{}", (xsize+1)))
             .arg(format!("{}", (ysize+1)))
@@ -100,11 +129,28 @@ impl ImgView {
             }
         }
 
+        if let Some(key) = cache_key {
+            let max_size = self.core.config().image_cache_size * 1024 * 1024;
+            crate::img_cache::put(&key, &output, max_size).log();
+        }
+
         self.buffer = output;
 
         Ok(())
     }
 
+    // Kitty gets a path to a temporary file instead of the image itself
+    fn cache_key(&self, file: &Path, settings: Vec<String>) -> Option<CacheKey> {
+        let g_mode = self.core.config().graphics;
+        let cache_size = self.core.config().image_cache_size;
+
+        if !self.cache || cache_size == 0 || g_mode == "kitty" {
+            return None;
+        }
+
+        CacheKey::new(file, &settings).ok()
+    }
+
     pub fn set_image_data(&mut self, img_data: Vec<String>) {
         self.buffer = img_data;
     }
@@ -2,8 +2,10 @@ use termion::event::Key;
 use ini::Ini;
 use strum::IntoEnumIterator;
 
+use std::any::Any;
 use std::collections::HashMap;
 use std::default::Default;
+use std::sync::Mutex;
 use std::str::FromStr;
 use std::fmt::{Display, Debug};
 
@@ -14,6 +16,36 @@ use crate::widget::Widget;
 pub type KbResult<T> = Result<T, KeyBindError>;
 
 
+// The last action of each section, for repeating it. The section the very
+// last action came from is kept, too.
+lazy_static! {
+    static ref LAST_ACTIONS: Mutex<HashMap<&'static str, Box<dyn Any + Send>>>
+        = Mutex::new(HashMap::new());
+    static ref LAST_SECTION: Mutex<Option<&'static str>> = Mutex::new(None);
+}
+
+fn remember_action<T>(action: T)
+where
+    T: BindingSection + Send + 'static,
+    Bindings<T>: Default
+{
+    if !action.repeatable() {
+        return;
+    }
+
+    LAST_ACTIONS.lock()
+                .map(|mut actions| actions.insert(T::section(), Box::new(action)))
+                .ok();
+    LAST_SECTION.lock()
+                .map(|mut section| *section = Some(T::section()))
+                .ok();
+}
+
+pub fn last_action_section() -> Option<&'static str> {
+    LAST_SECTION.lock().ok().and_then(|section| *section)
+}
+
+
 #[derive(Clone, Debug)]
 pub struct Bindings<T>(HashMap<AnyKey, T>);
 
@@ -32,6 +64,10 @@ impl<T> Bindings<T> {
     pub fn new() -> Self {
         Bindings(HashMap::new())
     }
+
+    pub fn merge(&mut self, other: Bindings<T>) {
+        self.0.extend(other.0);
+    }
 }
 
 
@@ -40,7 +76,7 @@ impl<T> Bindings<T> {
 pub trait Acting
 where
     Self: Widget,
-    Self::Action: BindingSection + Debug,
+    Self::Action: BindingSection + Debug + Send + 'static,
     Bindings<Self::Action>: Default,
 {
     type Action;
@@ -73,16 +109,30 @@ where
         let bindings = self.search_in();
 
         if let Some(action) = bindings.get(key) {
+            remember_action(*action);
             return self.do_action(action)
         } else if let Some(any_key) = gkey.any() {
             if let Some(action) = bindings.get(any_key) {
                 let action = action.insert_key_param(key);
+                remember_action(action);
                 return self.do_action(&action);
             }
         }
 
         HError::undefined_key(key)
     }
+
+    fn repeat_last_action(&mut self) -> HResult<()> {
+        let action = LAST_ACTIONS.lock()?
+            .get(Self::Action::section())
+            .and_then(|action| action.downcast_ref::<Self::Action>())
+            .cloned();
+
+        match action {
+            Some(action) => self.do_action(&action),
+            None => Err(KeyBindError::NothingToRepeat)?
+        }
+    }
 }
 
 
@@ -99,6 +149,10 @@ pub struct KeyBinds {
     pub fold: Bindings<FoldAction>,
     pub log: Bindings<LogAction>,
     pub quickaction: Bindings<QuickActionAction>,
+    pub mounts: Bindings<MountAction>,
+    pub preview: Bindings<PreviewAction>,
+    pub duplicates: Bindings<DuplicateAction>,
+    pub grep: Bindings<GrepAction>,
 }
 
 impl Default for KeyBinds {
@@ -114,31 +168,53 @@ impl Default for KeyBinds {
             minibuffer: Bindings::default(),
             fold: Bindings::default(),
             log: Bindings::default(),
-            quickaction: Bindings::default()
+            quickaction: Bindings::default(),
+            mounts: Bindings::default(),
+            preview: Bindings::default(),
+            duplicates: Bindings::default(),
+            grep: Bindings::default()
         }
     }
 }
 
 
 impl KeyBinds {
-    pub fn load() -> HResult<KeyBinds> {
-        let bindings_path = crate::paths::bindings_path()?;
-        let ini = Ini::load_from_file_noescape(bindings_path)
-            .map_err(KeyBindError::from)?;
-
-        let movement = Movement::load_section(&ini);
-        let filebrowser = FileBrowserAction::load_section(&ini);
-        let filelist = FileListAction::load_section(&ini);
-        let tab = TabAction::load_section(&ini);
-        let media = MediaAction::load_section(&ini);
-        let bookmark = BookmarkAction::load_section(&ini);
-        let process = ProcessAction::load_section(&ini);
-        let minibuffer = MiniBufferAction::load_section(&ini);
-        let fold = FoldAction::load_section(&ini);
-        let log = LogAction::load_section(&ini);
-        let quickaction = QuickActionAction::load_section(&ini);
-
-        Ok(KeyBinds {
+    // Also returns errors of files that couldn't be parsed at all
+    pub fn load() -> HResult<(KeyBinds, Vec<HError>)> {
+        let mut errors = vec![];
+
+        // System-wide, user and project bindings, in that order
+        let inis = crate::paths::config_layers("keys")
+            .into_iter()
+            .map(|path| {
+                Ini::load_from_file_noescape(&path)
+                    .or_else(|e| {
+                        let file = path.to_string_lossy().to_string();
+                        HError::config_file_error(file, e.to_string())
+                    })
+                    .log_and()
+                    .map_err(|e| errors.push(e))
+            })
+            .filter_map(|ini| ini.ok())
+            .collect::<Vec<Ini>>();
+
+        let movement = Movement::load_section(&inis);
+        let filebrowser = FileBrowserAction::load_section(&inis);
+        let filelist = FileListAction::load_section(&inis);
+        let tab = TabAction::load_section(&inis);
+        let media = MediaAction::load_section(&inis);
+        let bookmark = BookmarkAction::load_section(&inis);
+        let process = ProcessAction::load_section(&inis);
+        let minibuffer = MiniBufferAction::load_section(&inis);
+        let fold = FoldAction::load_section(&inis);
+        let log = LogAction::load_section(&inis);
+        let quickaction = QuickActionAction::load_section(&inis);
+        let mounts = MountAction::load_section(&inis);
+        let preview = PreviewAction::load_section(&inis);
+        let duplicates = DuplicateAction::load_section(&inis);
+        let grep = GrepAction::load_section(&inis);
+
+        let keybinds = KeyBinds {
             movement,
             filebrowser,
             filelist,
@@ -149,9 +225,171 @@ impl KeyBinds {
             minibuffer,
             fold,
             log,
-            quickaction
-        })
+            quickaction,
+            mounts,
+            preview,
+            duplicates,
+            grep
+        };
+
+        Ok((keybinds, errors))
+    }
+
+    // Every section in the order of the keys file
+    pub fn entries(&self) -> Vec<BindingEntry> {
+        let mut entries = Movement::entries(&self.movement);
+        entries.extend(FileBrowserAction::entries(&self.filebrowser));
+        entries.extend(FileListAction::entries(&self.filelist));
+        entries.extend(TabAction::entries(&self.tab));
+        entries.extend(MediaAction::entries(&self.media));
+        entries.extend(BookmarkAction::entries(&self.bookmark));
+        entries.extend(ProcessAction::entries(&self.process));
+        entries.extend(MiniBufferAction::entries(&self.minibuffer));
+        entries.extend(FoldAction::entries(&self.fold));
+        entries.extend(LogAction::entries(&self.log));
+        entries.extend(QuickActionAction::entries(&self.quickaction));
+        entries.extend(MountAction::entries(&self.mounts));
+        entries.extend(PreviewAction::entries(&self.preview));
+        entries.extend(DuplicateAction::entries(&self.duplicates));
+        entries.extend(GrepAction::entries(&self.grep));
+        entries
+    }
+}
+
+// An action with all its keys, like a line in the keys file
+#[derive(Clone, Debug)]
+pub struct BindingEntry {
+    pub section: &'static str,
+    pub action: String,
+    pub keys: Vec<AnyKey>
+}
+
+impl BindingEntry {
+    // Actions getting their parameter from the key can't have a single key
+    pub fn editable(&self) -> bool {
+        !self.keys.iter().any(|key| key.is_wildcard())
+    }
+
+    pub fn config_line(&self) -> String {
+        let keys = self.keys
+                       .iter()
+                       .map(|key| key.config_string())
+                       .collect::<Vec<_>>();
+        format!("{}={}", self.action, keys.join(", "))
+    }
+}
+
+fn action_string(action: &impl Debug, wildcard: bool) -> String {
+    // Goto('a') in the keys file is Goto(a)
+    let action = format!("{:?}", action).replace("'", "");
+
+    match (wildcard, action.find("(")) {
+        (true, Some(pos)) => format!("{}(_)", &action[..pos]),
+        _ => action
+    }
+}
+
+// Writes the changed bindings to the user's keys file. Everything else,
+// including bindings from the system-wide and project keys files and
+// comments, is left as is.
+pub fn save_bindings(section: &str, changed: &[BindingEntry]) -> HResult<()> {
+    let path = crate::paths::bindings_path()?;
+    let keys = std::fs::read_to_string(&path).unwrap_or(String::new());
+    let header = format!("[{}]", section);
+
+    // The first file defining a section replaces the defaults, so a new
+    // one in the user file needs them unless a system-wide file has it
+    let lower_defines = crate::paths::user_config_layers("keys")
+        .iter()
+        .filter(|layer| **layer != path)
+        .filter_map(|layer| std::fs::read_to_string(layer).ok())
+        .any(|lower| lower.lines().any(|line| line.trim() == header));
+
+    let defaults = match lower_defines {
+        true => vec![],
+        false => KeyBinds::default().entries()
+    };
+
+    let keys = update_section(&keys, section, changed, &defaults);
+    std::fs::write(&path, keys)?;
+    Ok(())
+}
+
+// Replaces the lines of the changed actions in "section", or adds them to it.
+// A missing section is created from "defaults" with the changes applied.
+fn update_section(keys: &str,
+                  section: &str,
+                  changed: &[BindingEntry],
+                  defaults: &[BindingEntry]) -> String {
+    let header = format!("[{}]", section);
+    let changed = changed.iter()
+                         .filter(|entry| entry.section == section)
+                         .collect::<Vec<_>>();
+    let changed_line = |action: &str| {
+        changed.iter()
+               .find(|entry| entry.action == action)
+               .map(|entry| match entry.keys.len() {
+                   0 => None,
+                   _ => Some(entry.config_line())
+               })
+    };
+
+    let mut lines: Vec<String> = vec![];
+    let mut written = vec![];
+    let mut section_end = None;
+    let mut in_section = false;
+
+    for line in keys.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("[") {
+            in_section = trimmed == header;
+        } else if in_section {
+            let action = trimmed.split("=").next().unwrap_or("").trim();
+
+            if let Some(new_line) = changed_line(action) {
+                written.push(action.to_string());
+                lines.extend(new_line);
+                section_end = Some(lines.len());
+                continue;
+            }
+        }
+
+        lines.push(line.to_string());
+
+        // Blank lines and comments at the end belong to what follows
+        if in_section && !trimmed.is_empty() && !trimmed.starts_with("#") {
+            section_end = Some(lines.len());
+        }
+    }
+
+    let missing = changed.iter()
+                         .filter(|entry| !written.contains(&entry.action))
+                         .filter(|entry| entry.keys.len() > 0)
+                         .map(|entry| entry.config_line());
+
+    match section_end {
+        Some(end) => {
+            let missing = missing.collect::<Vec<_>>();
+            lines.splice(end..end, missing);
+        }
+        None => {
+            if lines.len() > 0 {
+                lines.push(String::new());
+            }
+            lines.push(header);
+            lines.extend(defaults.iter()
+                                 .filter(|entry| entry.section == section)
+                                 .filter(|entry| !changed.iter().any(|changed| {
+                                     changed.action == entry.action
+                                 }))
+                                 .filter(|entry| entry.keys.len() > 0)
+                                 .map(|entry| entry.config_line()));
+            lines.extend(missing);
+        }
     }
+
+    lines.join("\n") + "\n"
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -185,6 +423,26 @@ impl Display for AnyKey {
 }
 
 impl AnyKey {
+    pub fn is_wildcard(&self) -> bool {
+        match self {
+            AnyKey::Key(_) => false,
+            _ => true
+        }
+    }
+
+    // Same format the keys file uses
+    pub fn config_string(&self) -> String {
+        use termion::event::Key::*;
+
+        match self {
+            AnyKey::Key(Char(' ')) => String::from("Space"),
+            AnyKey::Key(Char('\t')) => String::from("Tab"),
+            AnyKey::Key(Char('\n')) => String::from("Enter"),
+            AnyKey::Key(Char('_')) => String::from("\\_"),
+            key => key.to_string()
+        }
+    }
+
     pub fn any(&self) -> Option<AnyKey> {
         use AnyKey::*;
         use termion::event::Key::*;
@@ -262,7 +520,11 @@ impl FromStr for AnyKey {
         } else if let Ok(key) = key.parse() {
             Ok(Key(Char(key)))
         } else {
-            let parts = key.split('-').collect::<Vec<&str>>();
+            // The key can be a - itself, like in M--
+            let parts = match key.strip_suffix("--") {
+                Some(modifier) => vec![modifier, "-"],
+                None => key.split('-').collect::<Vec<&str>>()
+            };
 
             let (modifier, maybe_key) = if parts.len() > 2 {
                 // Something is wrong if there are more parts
@@ -362,6 +624,60 @@ where
 {
     fn section() -> &'static str;
 
+    // Repeating the action that repeats would loop
+    fn repeatable(&self) -> bool {
+        true
+    }
+
+    // Actions that aren't bound to anything are included, too
+    fn entries(bindings: &Bindings<Self>) -> Vec<BindingEntry>
+    where
+        Self: IntoEnumIterator,
+        <Self as IntoEnumIterator>::Iterator: Iterator<Item=Self>
+    {
+        let mut entries: Vec<BindingEntry> = vec![];
+
+        let mut bound = bindings.0.iter().collect::<Vec<_>>();
+        bound.sort_by_key(|(key, _)| key.config_string());
+
+        for (key, action) in bound {
+            let name = action_string(action, key.is_wildcard());
+
+            match entries.iter_mut().find(|entry| entry.action == name) {
+                Some(entry) => entry.keys.push(*key),
+                None => entries.push(BindingEntry {
+                    section: Self::section(),
+                    action: name,
+                    keys: vec![*key]
+                })
+            }
+        }
+
+        // Parameters of unbound actions are unknown
+        for action in Self::iter() {
+            let name = action.to_string();
+            let bound = entries.iter().any(|entry| {
+                entry.action.split("(").next() == Some(&name)
+            });
+
+            if !bound && format!("{:?}", action) == name {
+                entries.push(BindingEntry {
+                    section: Self::section(),
+                    action: name,
+                    keys: vec![]
+                });
+            }
+        }
+
+        let order = Self::iter().map(|action| action.to_string()).collect::<Vec<_>>();
+        entries.sort_by_key(|entry| {
+            let name = entry.action.split("(").next().unwrap_or("");
+            (order.iter().position(|action| action == name), entry.action.clone())
+        });
+
+        entries
+    }
+
     fn process_action_str(action_str: &str) -> (&str, Option<CharOrNum>) {
         // Could be something like Up(10) for going up 10 times
         action_str.rfind("(")
@@ -434,10 +750,22 @@ where
         Ok(bindings)
     }
 
-    fn load_section(ini: &Ini) -> Bindings<Self> {
-        Self::parse_section(ini)
-            .log_and()
-            .unwrap_or_else(|_| Bindings::default())
+    // The first file defining a section replaces the defaults, following
+    // files only add to or override individual keys of that section
+    fn load_section(inis: &[Ini]) -> Bindings<Self> {
+        inis.iter()
+            .filter(|ini| ini.section(Some(Self::section())).is_some())
+            .filter_map(|ini| Self::parse_section(ini).log_and().ok())
+            .fold(None, |layered: Option<Bindings<Self>>, bindings| {
+                match layered {
+                    Some(mut layered) => {
+                        layered.merge(bindings);
+                        Some(layered)
+                    }
+                    None => Some(bindings)
+                }
+            })
+            .unwrap_or_else(|| Bindings::default())
     }
 }
 
@@ -472,17 +800,57 @@ pub enum FileBrowserAction {
     EnterDirExternal,
     Quit,
     QuitWithDir,
+    ForceQuit,
     RunInBackground,
     GotoPrevCwd,
     ShowBookmarks,
     AddBookmark,
+    GotoFrecent,
+    OpenInNewTab,
+    ShowMounts,
     ShowProcesses,
     ShowLog,
     ShowQuickActions,
+    RunLastQuickAction,
     RunSubshell,
     ToggleColumns,
     ZoomPreview,
-    ExecCmd
+    ExecCmd,
+    EditDirConfig,
+    OpenWith,
+    XdgOpen,
+    FileInfo,
+    CopyFiles,
+    MoveFiles,
+    DeleteFiles,
+    TrashFiles,
+    SymlinkFiles,
+    CreateSymlink,
+    BulkRename,
+    AddToScratch,
+    GotoScratch,
+    Undo,
+    Redo,
+    RepeatLastAction,
+    RecordMacro,
+    PlayMacro(usize),
+    EditKeyBindings,
+    EditFile,
+    Diff,
+    ExtractArchive,
+    CompressFiles,
+    LinkToBookmark,
+    CopyToBookmark,
+    CommandPalette,
+    GotoConfigDir,
+    FindDuplicates,
+    GrepOpen,
+    FollowLink,
+    PinPreview,
+    GrowPreview,
+    ShrinkPreview,
+    ResetLayout,
+    TogglePreviewHidden
 }
 
 
@@ -490,12 +858,13 @@ pub enum FileBrowserAction {
 #[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
 pub enum FileListAction {
     Search,
-    SearchNext,
-    SearchPrev,
+    SearchNext(usize),
+    SearchPrev(usize),
     Filter,
     Select,
     InvertSelection,
     ClearSelection,
+    SelectGlob,
     FilterSelection,
     ToggleTag,
     ToggleHidden,
@@ -504,6 +873,17 @@ pub enum FileListAction {
     ToNextMtime,
     ToPrevMtime,
     ToggleDirsFirst,
+    YankPaths,
+    ToggleFlat(usize),
+    Rename,
+    GotoFirstSelected,
+    GotoLastSelected,
+    NextSelected,
+    PrevSelected,
+    ToggleDetails,
+    LoadAllMeta,
+    ToggleDirSize,
+    JumpToLetter,
 }
 
 
@@ -515,6 +895,7 @@ pub enum TabAction {
     NextTab,
     PrevTab,
     GotoTab(usize),
+    RenameTab,
 }
 
 
@@ -549,7 +930,26 @@ pub enum ProcessAction {
     ScrollOutputPageDown,
     ScrollOutputPageUp,
     ScrollOutputBottom,
-    ScrollOutputTop
+    ScrollOutputTop,
+    ToggleDetails,
+    ToggleColors,
+    YankOutput,
+    SaveOutput
+}
+
+impl ProcessAction {
+    // Disabled with --read-only
+    pub fn is_mutating(&self) -> bool {
+        use ProcessAction::*;
+
+        match self {
+            Kill | SaveOutput => true,
+
+            Close | Remove | FollowOutput | ScrollOutputDown | ScrollOutputUp |
+            ScrollOutputPageDown | ScrollOutputPageUp | ScrollOutputBottom |
+            ScrollOutputTop | ToggleDetails | ToggleColors | YankOutput => false
+        }
+    }
 }
 
 
@@ -570,7 +970,9 @@ pub enum MiniBufferAction {
     ClearLine,
     DeleteWord,
     CursorToStart,
-    CursorToEnd
+    CursorToEnd,
+    SearchSubdirs,
+    InsertSelection
 }
 
 #[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
@@ -589,6 +991,50 @@ pub enum QuickActionAction {
     SelectOrRun(char)
 }
 
+#[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
+pub enum MountAction {
+    Close,
+    Goto,
+    Mount,
+    Unmount
+}
+
+impl MountAction {
+    // Disabled with --read-only
+    pub fn is_mutating(&self) -> bool {
+        use MountAction::*;
+
+        match self {
+            Mount | Unmount => true,
+            Close | Goto => false
+        }
+    }
+}
+
+#[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
+pub enum DuplicateAction {
+    Close,
+    Goto,
+    SelectCopies
+}
+
+#[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
+pub enum GrepAction {
+    Close,
+    Edit,
+    Filter
+}
+
+#[derive(EnumString, EnumIter, Copy, Clone, Display, Debug)]
+pub enum PreviewAction {
+    ScrollDown,
+    ScrollUp,
+    PageDown,
+    PageUp,
+    Top,
+    Bottom
+}
+
 
 
 
@@ -678,17 +1124,57 @@ impl Default for Bindings<FileBrowserAction> {
                 EnterDirExternal => Char('/'),
                 Quit => Char('q'),
                 QuitWithDir => Char('Q'),
+                ForceQuit => Alt('q'),
                 RunInBackground => Char('F'),
                 GotoPrevCwd => Char('-'),
                 ShowBookmarks => Char('`'),
                 AddBookmark => Char('b'),
+                GotoFrecent => Char('Z'),
+                OpenInNewTab => Alt('t'),
+                ShowMounts => Alt('d'),
                 ShowProcesses => Char('w'),
                 ShowLog => Char('l'),
                 ShowQuickActions => Char('a'),
+                RunLastQuickAction => Char('A'),
                 RunSubshell => Char('z'),
                 ToggleColumns => Char('c'),
                 ZoomPreview => Char('C'),
-                ExecCmd => Char('!')
+                ExecCmd => Char('!'),
+                EditDirConfig => Char('E'),
+                OpenWith => Char('o'),
+                XdgOpen => Alt('o'),
+                FileInfo => Char('i'),
+                CopyFiles => Char('p'),
+                MoveFiles => Char('m'),
+                DeleteFiles => Char('D'),
+                TrashFiles => Char('X'),
+                SymlinkFiles => Char('S'),
+                CreateSymlink => Char('L'),
+                BulkRename => Alt('R'),
+                AddToScratch => Char('+'),
+                GotoScratch => Char('='),
+                Undo => Char('u'),
+                Redo => Ctrl('r'),
+                RepeatLastAction => Char('.'),
+                RecordMacro => Alt('r'),
+                PlayMacro(_) => Char('@'),
+                EditKeyBindings => Alt('k'),
+                EditFile => Char('e'),
+                Diff => Alt('D'),
+                ExtractArchive => Alt('x'),
+                CompressFiles => Alt('a'),
+                LinkToBookmark => Alt('b'),
+                CopyToBookmark => Alt('B'),
+                CommandPalette => Char(':'),
+                GotoConfigDir => Alt('h'),
+                FindDuplicates => Alt('u'),
+                GrepOpen => Alt('g'),
+                FollowLink => Alt('f'),
+                PinPreview => Alt('z'),
+                GrowPreview => Alt('+'),
+                ShrinkPreview => Alt('-'),
+                ResetLayout => Alt('='),
+                TogglePreviewHidden => Alt('H')
             };
 
             filebrowser.insert(key, action.as_default());
@@ -702,6 +1188,59 @@ impl BindingSection for FileBrowserAction {
     fn section() -> &'static str {
         "filebrowser"
     }
+
+    fn repeatable(&self) -> bool {
+        match self {
+            FileBrowserAction::RepeatLastAction => false,
+            FileBrowserAction::RecordMacro => false,
+            _ => true
+        }
+    }
+
+    fn insert_config_param(self, param: CharOrNum) -> Self {
+        use FileBrowserAction::*;
+
+        match self {
+            PlayMacro(_) => PlayMacro(param.num_or(1)),
+            _ => self
+        }
+    }
+
+    fn as_default(self) -> Self {
+        use FileBrowserAction::*;
+
+        match self {
+            PlayMacro(_) => PlayMacro(1),
+            _ => self
+        }
+    }
+}
+
+impl FileBrowserAction {
+    // What --read-only blocks, everything changing files or running
+    // commands. Listed one by one, so new actions have to be decided on.
+    pub fn is_mutating(&self) -> bool {
+        use FileBrowserAction::*;
+
+        match self {
+            RunSubshell | ExecCmd | ShowQuickActions | RunLastQuickAction |
+            EditDirConfig | EditFile | GrepOpen | EditKeyBindings |
+            CopyFiles | MoveFiles | DeleteFiles | TrashFiles | Undo | Redo |
+            SymlinkFiles | CreateSymlink | BulkRename | AddToScratch |
+            ExtractArchive | CompressFiles | LinkToBookmark | CopyToBookmark |
+            RunInBackground | OpenWith | XdgOpen | SelectExternal |
+            EnterDirExternal | Diff => true,
+
+            LeftColumnDown | LeftColumnUp | GotoHome | TurboCd | Quit |
+            QuitWithDir | ForceQuit | GotoPrevCwd |
+            ShowBookmarks | AddBookmark | GotoFrecent | OpenInNewTab | ShowMounts |
+            ShowProcesses | ShowLog | ToggleColumns | ZoomPreview |
+            FileInfo | GotoScratch | RepeatLastAction | RecordMacro |
+            PlayMacro(_) | CommandPalette | GotoConfigDir | FindDuplicates |
+            FollowLink | PinPreview | GrowPreview | ShrinkPreview | ResetLayout |
+            TogglePreviewHidden => false
+        }
+    }
 }
 
 impl Default for Bindings<FileListAction> {
@@ -714,12 +1253,13 @@ impl Default for Bindings<FileListAction> {
         for action in FileListAction::iter() {
             let key = match action {
                 Search => Ctrl('s'),
-                SearchNext => Alt('s'),
-                SearchPrev => Alt('S'),
+                SearchNext(_) => Alt('s'),
+                SearchPrev(_) => Alt('S'),
                 Filter => Ctrl('f'),
                 Select => Char(' '),
                 InvertSelection => Char('v'),
                 ClearSelection => Char('V'),
+                SelectGlob => Char('*'),
                 FilterSelection => Alt('V'),
                 ToggleTag => Char('t'),
                 ToggleHidden => Char('h'),
@@ -727,7 +1267,18 @@ impl Default for Bindings<FileListAction> {
                 CycleSort => Char('s'),
                 ToNextMtime => Char('K'),
                 ToPrevMtime => Char('k'),
-                ToggleDirsFirst => Char('d')
+                ToggleDirsFirst => Char('d'),
+                YankPaths => Char('y'),
+                ToggleFlat(_) => Char('T'),
+                Rename => Char('R'),
+                GotoFirstSelected => Char('{'),
+                GotoLastSelected => Char('}'),
+                NextSelected => Char(')'),
+                PrevSelected => Char('('),
+                ToggleDetails => Char('I'),
+                LoadAllMeta => Alt('l'),
+                ToggleDirSize => Alt('c'),
+                JumpToLetter => Alt('i')
             };
 
             filelist.insert(key, action.as_default());
@@ -741,6 +1292,46 @@ impl BindingSection for FileListAction {
     fn section() -> &'static str {
         "filelist"
     }
+
+    fn insert_config_param(self, param: CharOrNum) -> Self {
+        use FileListAction::*;
+
+        match self {
+            SearchNext(_) => SearchNext(param.num_or(1)),
+            SearchPrev(_) => SearchPrev(param.num_or(1)),
+            ToggleFlat(_) => ToggleFlat(param.num_or(2)),
+            _ => self
+        }
+    }
+
+    fn as_default(self) -> Self {
+        use FileListAction::*;
+
+        match self {
+            SearchNext(_) => SearchNext(1),
+            SearchPrev(_) => SearchPrev(1),
+            ToggleFlat(_) => ToggleFlat(2),
+            _ => self
+        }
+    }
+}
+
+impl FileListAction {
+    // Same as for FileBrowserAction, only renaming changes anything here
+    pub fn is_mutating(&self) -> bool {
+        use FileListAction::*;
+
+        match self {
+            Rename => true,
+
+            Search | SearchNext(_) | SearchPrev(_) | Filter | Select |
+            InvertSelection | ClearSelection | SelectGlob | FilterSelection |
+            ToggleTag | ToggleHidden | ReverseSort | CycleSort | ToNextMtime |
+            ToPrevMtime | ToggleDirsFirst | YankPaths | ToggleFlat(_) |
+            GotoFirstSelected | GotoLastSelected | NextSelected | PrevSelected |
+            ToggleDetails | LoadAllMeta | ToggleDirSize | JumpToLetter => false
+        }
+    }
 }
 
 impl Default for Bindings<TabAction> {
@@ -756,7 +1347,8 @@ impl Default for Bindings<TabAction> {
                 NextTab => Char('\t').into(),
                 PrevTab => BackTab.into(),
                 CloseTab => Ctrl('w').into(),
-                GotoTab(_) => AnyKey::AnyF
+                GotoTab(_) => AnyKey::AnyF,
+                RenameTab => Alt('T').into()
             };
 
             tab.insert(key, action.as_default());
@@ -888,7 +1480,11 @@ impl Default for Bindings<ProcessAction> {
                 ScrollOutputPageDown => Ctrl('v'),
                 ScrollOutputPageUp => Ctrl('V'),
                 ScrollOutputBottom => Char('>'),
-                ScrollOutputTop => Ctrl('<')
+                ScrollOutputTop => Ctrl('<'),
+                ToggleDetails => Char('D'),
+                ToggleColors => Char('c'),
+                YankOutput => Char('y'),
+                SaveOutput => Char('s')
             };
 
             process.insert(key, action.as_default());
@@ -929,7 +1525,9 @@ impl Default for Bindings<MiniBufferAction> {
                 ClearLine => Ctrl('u').into(),
                 DeleteWord => Ctrl('h').into(),
                 CursorToStart => Ctrl('a').into(),
-                CursorToEnd => Ctrl('e').into()
+                CursorToEnd => Ctrl('e').into(),
+                SearchSubdirs => Ctrl('s').into(),
+                InsertSelection => Ctrl('t').into()
         };
 
             minibuffer.insert(key, action.as_default());
@@ -1083,6 +1681,126 @@ impl BindingSection for QuickActionAction {
     }
 }
 
+impl Default for Bindings<MountAction> {
+    fn default() -> Self {
+        use Key::*;
+        use MountAction::*;
+
+        let mut mounts = Bindings::new();
+
+        for action in MountAction::iter() {
+            let key = match action {
+                Close => Alt('d'),
+                Goto => Char('\n'),
+                Mount => Char('m'),
+                Unmount => Char('u')
+            };
+
+            mounts.insert(key, action.as_default());
+        }
+
+        mounts.insert(Ctrl('c'), Close);
+        mounts.insert(Esc, Close);
+
+        mounts
+    }
+}
+
+impl BindingSection for MountAction {
+    fn section() -> &'static str {
+        "mounts"
+    }
+}
+
+impl Default for Bindings<PreviewAction> {
+    fn default() -> Self {
+        use PreviewAction::*;
+
+        let mut preview = Bindings::new();
+
+        for action in PreviewAction::iter() {
+            let key = match action {
+                ScrollDown => Key::Alt('J'),
+                ScrollUp => Key::Alt('K'),
+                PageDown => Key::Alt('N'),
+                PageUp => Key::Alt('P'),
+                Top => Key::Alt('{'),
+                Bottom => Key::Alt('}')
+            };
+
+            preview.insert(key, action.as_default());
+        }
+
+        preview
+    }
+}
+
+impl BindingSection for PreviewAction {
+    fn section() -> &'static str {
+        "preview"
+    }
+}
+
+impl Default for Bindings<DuplicateAction> {
+    fn default() -> Self {
+        use Key::*;
+        use DuplicateAction::*;
+
+        let mut duplicates = Bindings::new();
+
+        for action in DuplicateAction::iter() {
+            let key = match action {
+                Close => Char('q'),
+                Goto => Char('\n'),
+                SelectCopies => Char('s')
+            };
+
+            duplicates.insert(key, action.as_default());
+        }
+
+        duplicates.insert(Ctrl('c'), Close);
+        duplicates.insert(Esc, Close);
+
+        duplicates
+    }
+}
+
+impl BindingSection for DuplicateAction {
+    fn section() -> &'static str {
+        "duplicates"
+    }
+}
+
+impl Default for Bindings<GrepAction> {
+    fn default() -> Self {
+        use Key::*;
+        use GrepAction::*;
+
+        let mut grep = Bindings::new();
+
+        for action in GrepAction::iter() {
+            let key = match action {
+                Close => Char('q'),
+                Edit => Char('\n'),
+                Filter => Ctrl('f')
+            };
+
+            grep.insert(key, action.as_default());
+        }
+
+        grep.insert(Ctrl('c'), Close);
+        grep.insert(Esc, Close);
+
+        grep
+    }
+}
+
+impl BindingSection for GrepAction {
+    fn section() -> &'static str {
+        "grep"
+    }
+}
+
 
 #[test]
 fn test_keyparse() {
@@ -1094,3 +1812,26 @@ fn test_keyparse() {
         dbg!(parsed).ok();
     }
 }
+
+#[test]
+fn test_update_section() {
+    let entry = |action: &str, keys: &[&str]| BindingEntry {
+        section: "filelist",
+        action: action.to_string(),
+        keys: keys.iter().map(|key| key.parse().unwrap()).collect()
+    };
+    let keys = "[filebrowser]\nQuit=q\n\n[filelist]\n# Mine\nSelect=x\nSearch=s\n\n# Other\n[tab]\nNewTab=t\n";
+
+    // Only the changed lines, comments and other sections stay
+    let changed = [entry("Select", &["y"]), entry("Search", &[]), entry("Cut", &["c"])];
+    assert_eq!(update_section(keys, "filelist", &changed, &[]),
+               "[filebrowser]\nQuit=q\n\n[filelist]\n# Mine\nSelect=y\nCut=c\n\n# Other\n[tab]\nNewTab=t\n");
+
+    // A new section gets the defaults, unless a lower layer has it
+    let defaults = [entry("Select", &["Space"]), entry("Cut", &["d"])];
+    let changed = [entry("Cut", &["c"])];
+    assert_eq!(update_section("[tab]\nNewTab=t\n", "filelist", &changed, &defaults),
+               "[tab]\nNewTab=t\n\n[filelist]\nSelect=Space\nCut=c\n");
+    assert_eq!(update_section("", "filelist", &changed, &[]),
+               "[filelist]\nCut=c\n");
+}
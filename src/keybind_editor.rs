@@ -0,0 +1,214 @@
+use termion::event::Key;
+
+use crate::keybind::{AnyKey, BindingEntry};
+use crate::textview::TextView;
+use crate::widget::{Widget, WidgetCore};
+use crate::coordinates::Coordinates;
+use crate::fail::{HResult, HError};
+
+
+// Lists all bindings, Enter waits for a new key for the selected action
+pub struct KeyBindEditor {
+    core: WidgetCore,
+    textview: TextView,
+    entries: Vec<BindingEntry>,
+    selection: usize,
+    waiting_for_key: bool,
+    // Key bound to something else, pressing it again replaces that binding
+    conflict: Option<AnyKey>
+}
+
+impl KeyBindEditor {
+    pub fn new(core: &WidgetCore) -> HResult<KeyBindEditor> {
+        let entries = core.config().keybinds.entries();
+
+        let mut editor = KeyBindEditor {
+            core: core.clone(),
+            textview: TextView::new_blank(core),
+            entries: entries,
+            selection: 0,
+            waiting_for_key: false,
+            conflict: None
+        };
+
+        editor.update_lines()?;
+        Ok(editor)
+    }
+
+    fn update_lines(&mut self) -> HResult<()> {
+        let lines = self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let keys = entry.keys
+                                .iter()
+                                .map(|key| key.config_string())
+                                .collect::<Vec<_>>()
+                                .join(", ");
+
+                let (start, end) = match i == self.selection {
+                    true => (crate::term::invert(), crate::term::reset()),
+                    false => (String::new(), String::new())
+                };
+
+                format!("{}{:14}{:24}{}{}",
+                        start,
+                        entry.section,
+                        entry.action,
+                        keys,
+                        end)
+            })
+            .collect();
+
+        self.textview.set_lines(lines)?;
+
+        // Keep the selection visible
+        let ysize = self.core.coordinates.ysize_u();
+        if self.selection < self.textview.offset {
+            self.textview.offset = self.selection;
+        } else if self.selection >= self.textview.offset + ysize {
+            self.textview.offset = self.selection + 1 - ysize;
+        }
+
+        Ok(())
+    }
+
+    fn move_selection(&mut self, amount: isize) -> HResult<()> {
+        let last = self.entries.len().saturating_sub(1) as isize;
+        let selection = self.selection as isize + amount;
+        self.selection = selection.max(0).min(last) as usize;
+        self.update_lines()
+    }
+
+    fn start_rebind(&mut self) -> HResult<()> {
+        let entry = &self.entries[self.selection];
+
+        if !entry.editable() {
+            let msg = format!("{} gets its parameter from the key, edit the keys file",
+                              entry.action);
+            return self.core.show_status(&msg);
+        }
+
+        self.waiting_for_key = true;
+        self.conflict = None;
+        self.core.show_status(&format!("Press the new key for {} (Esc cancels)",
+                                       entry.action))
+    }
+
+    // Movement is checked first in most places, so it counts as conflict, too
+    fn conflicts(&self, key: AnyKey) -> Vec<usize> {
+        let section = self.entries[self.selection].section;
+
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != self.selection)
+            .filter(|(_, entry)| {
+                entry.section == section || entry.section == "movement"
+            })
+            .filter(|(_, entry)| entry.keys.contains(&key))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn bind_key(&mut self, key: Key) -> HResult<()> {
+        let key = AnyKey::from(key);
+        let conflicts = self.conflicts(key);
+
+        if conflicts.len() > 0 && self.conflict != Some(key) {
+            let bound_to = conflicts.iter()
+                .map(|&i| format!("{} [{}]",
+                                  self.entries[i].action,
+                                  self.entries[i].section))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            self.conflict = Some(key);
+            return self.core.show_status(&format!("{} is bound to {}, press it again to replace",
+                                                  key.config_string(),
+                                                  bound_to));
+        }
+
+        self.waiting_for_key = false;
+        self.conflict = None;
+
+        let mut changed = vec![self.selection];
+        for i in conflicts {
+            self.entries[i].keys.retain(|k| *k != key);
+            changed.push(i);
+        }
+        self.entries[self.selection].keys = vec![key];
+
+        let changed = changed.into_iter()
+                             .map(|i| self.entries[i].clone())
+                             .collect::<Vec<_>>();
+        let mut sections = changed.iter()
+                                  .map(|entry| entry.section)
+                                  .collect::<Vec<_>>();
+        sections.sort();
+        sections.dedup();
+        for section in sections {
+            crate::keybind::save_bindings(section, &changed)?;
+        }
+
+        // Takes effect right away
+        self.core.reload_config();
+        self.update_lines()?;
+
+        let path = crate::paths::bindings_path()?;
+        self.core.show_status(&format!("Bound {} to {}, saved in: {}",
+                                       self.entries[self.selection].action,
+                                       key.config_string(),
+                                       path.to_string_lossy()))
+    }
+}
+
+impl Widget for KeyBindEditor {
+    fn get_core(&self) -> HResult<&WidgetCore> {
+        Ok(&self.core)
+    }
+    fn get_core_mut(&mut self) -> HResult<&mut WidgetCore> {
+        Ok(&mut self.core)
+    }
+    fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
+        self.core.coordinates = coordinates.clone();
+        self.textview.set_coordinates(coordinates)?;
+        self.update_lines()
+    }
+    fn render_header(&self) -> HResult<String> {
+        Ok(String::from("Key bindings"))
+    }
+    fn render_footer(&self) -> HResult<String> {
+        Ok(String::from("Enter: change key, Esc/q: close"))
+    }
+    fn refresh(&mut self) -> HResult<()> {
+        self.textview.refresh()
+    }
+    fn get_drawlist(&self) -> HResult<String> {
+        self.textview.get_drawlist()
+    }
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        if self.waiting_for_key {
+            return match key {
+                Key::Esc => {
+                    self.waiting_for_key = false;
+                    self.conflict = None;
+                    self.core.show_status("Cancelled")
+                }
+                _ => self.bind_key(key)
+            }
+        }
+
+        let page = self.core.coordinates.ysize() as isize;
+
+        match key {
+            Key::Esc | Key::Ctrl('c') | Key::Char('q') => HError::popup_finnished(),
+            Key::Up | Key::Char('k') => self.move_selection(-1),
+            Key::Down | Key::Char('j') => self.move_selection(1),
+            Key::PageUp => self.move_selection(-page),
+            Key::PageDown => self.move_selection(page),
+            Key::Char('\n') => self.start_rebind(),
+            _ => Ok(())
+        }
+    }
+}
@@ -1,18 +1,19 @@
 use std::fmt::Debug;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use termion::event::Key;
-use unicode_width::UnicodeWidthStr;
 use rayon::prelude::*;
 
 use async_value::Stale;
 
-use crate::files::{File, Files};
+use crate::files::{File, Files, Kind, SizeFormat, DateFormat, TrailingSlash};
 use crate::fail::{HResult, HError, ErrorLog};
 use crate::term;
 use crate::widget::{Widget, WidgetCore};
 use crate::dirty::Dirtyable;
 use crate::fscache::FsCache;
+use crate::dir_config::DirConfig;
 
 
 pub trait Listable {
@@ -26,9 +27,109 @@ pub trait Listable {
     fn on_key(&mut self, _key: Key) -> HResult<()> { Ok(()) }
 }
 
+// Lists filled in by a job running in the background, like the duplicates
+// or grep results
+pub trait JobResults {
+    fn is_done(&self) -> bool;
+}
+
 use crate::keybind::{Acting, Bindings, FileListAction, Movement};
 
 
+// Toggled in the main file list, which is rebuilt on every directory change
+static SHOW_DETAILS: AtomicBool = AtomicBool::new(false);
+
+pub fn show_details() -> bool {
+    SHOW_DETAILS.load(Ordering::Relaxed)
+}
+
+// How many files start with each letter, made again when the files change
+#[derive(Clone, Debug, PartialEq)]
+struct LetterIndex {
+    len: usize,
+    filter: Option<String>,
+    show_hidden: bool,
+    buckets: Vec<(char, usize)>
+}
+
+fn first_letter(name: &str) -> Option<char> {
+    name.chars()
+        .next()
+        .and_then(|letter| letter.to_lowercase().next())
+}
+
+fn letter_buckets<'a>(names: impl Iterator<Item=&'a str>) -> Vec<(char, usize)> {
+    let mut buckets = std::collections::BTreeMap::new();
+
+    for letter in names.filter_map(first_letter) {
+        *buckets.entry(letter).or_insert(0) += 1;
+    }
+
+    buckets.into_iter().collect()
+}
+
+// Width of the permissions like "rwxr-xr-x"
+const PERMISSIONS_WIDTH: usize = 9;
+
+// Widths of the detail columns, as wide as their widest value on screen so
+// they line up. Columns that don't fit are left out.
+#[derive(Clone, Debug)]
+struct DetailColumns {
+    permissions: bool,
+    mtime: Option<usize>,
+    size: usize
+}
+
+impl DetailColumns {
+    fn width(&self) -> usize {
+        let permissions = match self.permissions {
+            true => PERMISSIONS_WIDTH + 1,
+            false => 0
+        };
+        let mtime = self.mtime.map(|width| width + 1).unwrap_or(0);
+
+        permissions + mtime + self.size
+    }
+
+    fn render(&self, file: &File, size: &str, date_format: &DateFormat) -> String {
+        let mut details = String::new();
+
+        if self.permissions {
+            match file.pretty_print_permissions() {
+                Ok(permissions) => {
+                    details += &permissions;
+                    details += &term::highlight_color();
+                    details += " ";
+                }
+                Err(_) => details += &" ".repeat(PERMISSIONS_WIDTH + 1)
+            }
+        }
+
+        if let Some(width) = self.mtime {
+            let mtime = file.pretty_mtime(date_format).unwrap_or(String::new());
+            let padding = width.saturating_sub(term::str_width(&mtime));
+            details += &format!("{}{} ", mtime, " ".repeat(padding));
+        }
+
+        let padding = self.size.saturating_sub(term::str_width(&size));
+        details += &format!("{}{}", " ".repeat(padding), size);
+        details
+    }
+}
+
+fn size_string(file: &File, size_format: SizeFormat) -> String {
+    use crate::files::FileError;
+
+    match file.calculate_size(size_format) {
+        Ok(size) => size,
+        Err(HError::FileError(FileError::MetaPending)) => {
+            String::from(crate::files::tick_str())
+        },
+        Err(_) => String::from("ERR")
+    }
+}
+
+
 impl Acting for ListView<Files> {
     type Action=FileListAction;
 
@@ -61,14 +162,20 @@ impl Acting for ListView<Files> {
     fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
         use FileListAction::*;
 
+        if action.is_mutating() && self.core.config().read_only {
+            return self.core.show_status(&format!("{} is disabled in read-only mode",
+                                                  action));
+        }
+
         match action {
             Search => self.search_file()?,
-            SearchNext => self.search_next()?,
-            SearchPrev => self.search_prev()?,
+            SearchNext(count) => self.search_next(*count)?,
+            SearchPrev(count) => self.search_prev(*count)?,
             Filter => self.filter()?,
             Select => self.multi_select_file(),
             InvertSelection => self.invert_selection(),
             ClearSelection => self.clear_selections(),
+            SelectGlob => self.select_glob()?,
             FilterSelection => self.toggle_filter_selected(),
             ToggleTag => self.toggle_tag()?,
             ToggleHidden => self.toggle_hidden(),
@@ -77,6 +184,17 @@ impl Acting for ListView<Files> {
             ToNextMtime => self.select_next_mtime(),
             ToPrevMtime => self.select_prev_mtime(),
             ToggleDirsFirst => self.toggle_dirs_first(),
+            YankPaths => self.yank_paths()?,
+            ToggleFlat(depth) => self.toggle_flat(*depth)?,
+            Rename => self.rename()?,
+            GotoFirstSelected => self.goto_selected(true)?,
+            GotoLastSelected => self.goto_selected(false)?,
+            NextSelected => self.cycle_selected(true)?,
+            PrevSelected => self.cycle_selected(false)?,
+            ToggleDetails => self.toggle_details(),
+            LoadAllMeta => self.load_all_meta(),
+            ToggleDirSize => self.toggle_dir_size(),
+            JumpToLetter => self.jump_to_letter()?,
         }
 
         Ok(())
@@ -108,10 +226,16 @@ impl Listable for ListView<Files> {
             self.content.len = 1;
         }
 
+        // Files without metadata would end up in the wrong place
+        if self.content.sort_needs_meta() && self.content.meta_incomplete() {
+            let sender = self.core.get_sender();
+            self.content.load_all_meta(sender);
+        }
+
         let meta_upto = self.content.meta_upto.unwrap_or(0);
         let ysize = self.core.coordinates.ysize_u();
 
-        if  self.offset + ysize >= meta_upto {
+        if  self.offset + ysize >= meta_upto || self.content.dirsizes_outdated() {
             let sender = self.core.get_sender();
             let njobs = self.offset + ysize;
 
@@ -121,7 +245,35 @@ impl Listable for ListView<Files> {
 
         self.refresh_files().log();
 
+        if crate::files::dir_bytes() {
+            let sender = self.core.get_sender();
+            let stale = self.content.stale.clone().unwrap_or_else(Stale::new);
+            self.visible_files()
+                .filter(|file| file.is_dir())
+                .for_each(|file| file.calculate_recursive_size(sender.clone(),
+                                                               stale.clone()).log());
+        }
+
+        if self.content.flattening.is_some() {
+            let file = self.clone_selected_file();
+            if self.content.take_flattened() {
+                self.select_file(&file);
+                let depth = self.content.flat.unwrap_or(0);
+                self.core.show_status(&format!("Showing files {} levels deep",
+                                               depth)).log();
+                self.core.set_dirty();
+            }
+        }
+
+        if self.content.take_meta_loaded() && self.content.sort_needs_meta() {
+            let file = self.clone_selected_file();
+            self.content.sort();
+            self.select_file(&file);
+            self.core.set_dirty();
+        }
+
         if self.content.is_dirty() {
+            self.letter_index = None;
             self.content.set_clean();
             self.core.set_dirty();
         }
@@ -146,6 +298,11 @@ where
     pub core: WidgetCore,
     seeking: bool,
     searching: Option<String>,
+    // Gets selected as soon as it shows up, like a renamed file
+    pending_selection: Option<<ListView<T> as Listable>::Item>,
+    // Show permissions and mtime, too
+    pub details: bool,
+    letter_index: Option<LetterIndex>,
 }
 
 impl<T> ListView<T>
@@ -161,7 +318,10 @@ where
             offset: 0,
             core: core.clone(),
             seeking: false,
-            searching: None
+            searching: None,
+            pending_selection: None,
+            details: false,
+            letter_index: None
         };
         view.on_new().log();
         view
@@ -236,6 +396,14 @@ where
         self.selection = position;
     }
 
+    // Scrolls to "offset", as far as the selection stays visible
+    pub fn set_offset(&mut self, offset: usize) {
+        let ysize = self.get_coordinates().unwrap().ysize_u();
+        let lowest = (self.selection + 1).saturating_sub(ysize);
+
+        self.offset = offset.min(self.selection).max(lowest);
+    }
+
 }
 
 #[derive(PartialEq)]
@@ -253,6 +421,7 @@ pub struct FileListBuilder {
     stale: Option<Stale>,
     meta_upto: usize,
     meta_all: bool,
+    show_hidden: Option<bool>,
 }
 
 impl FileListBuilder {
@@ -265,6 +434,7 @@ impl FileListBuilder {
             stale: None,
             meta_upto: 0,
             meta_all: false,
+            show_hidden: None,
         }
     }
 
@@ -293,6 +463,12 @@ impl FileListBuilder {
         self
     }
 
+    // Instead of the show_hidden option
+    pub fn show_hidden(mut self, show_hidden: bool) -> Self {
+        self.show_hidden = Some(show_hidden);
+        self
+    }
+
     pub fn build(mut self) -> HResult<ListView<Files>> {
         let c = &self.cache;
         let s = self.stale.clone();
@@ -322,23 +498,54 @@ impl FileListBuilder {
             }?;
 
         // Check/set hidden flag and recalculate number of files if it's different
-        if !files.show_hidden == cfg.show_hidden() {
-            files.show_hidden = cfg.show_hidden();
+        let show_hidden = self.show_hidden.unwrap_or(cfg.show_hidden());
+        if files.show_hidden != show_hidden {
+            files.show_hidden = show_hidden;
             files.recalculate_len();
         }
 
+        // Apply .hunter file, unless settings were changed in this directory
+        if !nosort && cfg.dir_config {
+            let has_settings = c.as_ref()
+                                .map(|c| c.has_settings(&files.directory))
+                                .unwrap_or(false);
+
+            if !has_settings {
+                DirConfig::load(&files.directory.path)
+                    .map(|dir_config| dir_config.apply(&mut files))
+                    .ok();
+            }
+        }
+
+        // Sort order from earlier sessions, other dirs keep the default
+        if !nosort {
+            c.as_ref()
+             .filter(|c| !c.has_settings(&files.directory))
+             .and_then(|c| c.get_sort_settings(&files.directory))
+             .map(|sort_settings| sort_settings.apply(&mut files));
+        }
+
         // TODO: Fix sorting so it works with lazy/partial sorting
         if !nosort {
             files.sort();
         }
 
         let mut view = ListView::new(&core, files);
+        // on_new goes by the option
+        view.content.show_hidden = show_hidden;
 
         selected_file
             .or_else(|| c.as_ref()
                      .and_then(|c| c.get_selection(&view.content.directory).ok()))
             .map(|f| view.select_file(&f));
 
+        // Otherwise the selection just ends up at the bottom of the screen
+        if cfg.remember_offset {
+            c.as_ref()
+             .and_then(|c| c.get_offset(&view.content.directory).ok())
+             .map(|offset| view.set_offset(offset));
+        }
+
         self.stale.map(|s| view.content.stale = Some(s));
         self.cache.map(|c| view.content.cache = Some(c));
         view.content.set_clean();
@@ -468,6 +675,13 @@ impl ListView<Files>
                                        self.content.sort)).log();
     }
 
+    // Sorted again once it's loaded, in on_refresh
+    fn load_all_meta(&mut self) {
+        let sender = self.core.get_sender();
+        self.content.load_all_meta(sender);
+        self.refresh().log();
+    }
+
     fn select_next_mtime(&mut self) {
         let file = self.clone_selected_file();
         let dir_settings = self.content.dirs_first;
@@ -531,6 +745,36 @@ impl ListView<Files>
                                         self.content.show_hidden)).log();
     }
 
+    pub fn toggle_details(&mut self) {
+        let details = !show_details();
+        SHOW_DETAILS.store(details, Ordering::Relaxed);
+        self.details = details;
+        self.core.show_status(&format!("Showing details: {}", details)).log();
+    }
+
+    fn toggle_dir_size(&mut self) {
+        let bytes = crate::files::toggle_dir_bytes();
+        // Sizes can be outdated by changes deeper down that aren't watched
+        if bytes {
+            crate::files::forget_all_recursive_sizes();
+        }
+        let shown = match bytes {
+            true => "size",
+            false => "entries"
+        };
+        self.core.show_status(&format!("Directories show: {}", shown)).log();
+        self.refresh().log();
+    }
+
+    pub fn apply_dir_config(&mut self, dir_config: &DirConfig) {
+        let file = self.clone_selected_file();
+        dir_config.apply(&mut self.content);
+        self.content.sort();
+        self.select_file(&file);
+        self.refresh().log();
+        self.core.clear().log();
+    }
+
     fn toggle_dirs_first(&mut self) {
         let file = self.clone_selected_file();
         self.content.dirs_first = !self.content.dirs_first;
@@ -541,6 +785,46 @@ impl ListView<Files>
                                         self.content.dirs_first)).log();
     }
 
+    fn toggle_flat(&mut self, depth: usize) -> HResult<()> {
+        let mut file = self.clone_selected_file();
+
+        if self.content.flattening.is_some() {
+            self.content.cancel_flatten();
+            return self.core.show_status("Stopped reading subdirectories");
+        }
+
+        match self.content.flat {
+            Some(_) => {
+                self.content.unflatten();
+
+                // Select the directory the file was in
+                let dir = self.content.directory.path.clone();
+                let top = file.path
+                              .ancestors()
+                              .find(|path| path.parent() == Some(&dir))
+                              .map(|path| path.to_path_buf());
+                if let Some(top) = top {
+                    self.content
+                        .find_file_with_path(&top)
+                        .map(|top| file = top.clone());
+                }
+
+                self.core.show_status("Showing single directory").log();
+            }
+            None => {
+                let sender = self.core.get_sender();
+                self.content.flatten(depth, sender);
+                self.core.show_status("Reading subdirectories...").log();
+                return Ok(());
+            }
+        }
+
+        self.select_file(&file);
+        self.core.clear().log();
+        self.refresh().log();
+        Ok(())
+    }
+
     fn multi_select_file(&mut self) {
         self.selected_file_mut().toggle_selection();
 
@@ -563,6 +847,23 @@ impl ListView<Files>
         }
     }
 
+    pub fn yank_paths(&mut self) -> HResult<()> {
+        let mut paths = self.content
+            .get_selected()
+            .map(|file| file.path.to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+
+        if paths.len() == 0 {
+            paths.push(self.selected_file().path.to_string_lossy().to_string());
+        }
+
+        crate::clipboard::copy_with_osc52(&self.core, &paths.join("\n"))?;
+
+        self.core.show_status(&format!("Copied {} path(s) to clipboard",
+                                       paths.len())).log();
+        Ok(())
+    }
+
     pub fn invert_selection(&mut self) {
         for file in self.content.iter_files_mut() {
             file.toggle_selection();
@@ -577,6 +878,112 @@ impl ListView<Files>
         self.refresh().log();
     }
 
+    fn rename(&mut self) -> HResult<()> {
+        let file = self.clone_selected_file();
+        if file.kind == Kind::Placeholder { return Ok(()) }
+
+        let name = file.path.file_name()?.to_string_lossy().to_string();
+
+        // Cursor goes before the extension, dotfiles don't have one
+        let position = match name.rfind('.') {
+            Some(0) | None => name.len(),
+            Some(pos) => pos
+        };
+
+        let mut answer = self.core.minibuffer_prefilled("rename", &name, position);
+        let new_name = loop {
+            match answer {
+                Ok(new_name) => break new_name,
+                // Widgets finishing loading interrupt the minibuffer
+                Err(HError::RefreshParent) => {
+                    self.refresh().log();
+                    self.draw().log();
+                    answer = self.core.minibuffer_resume();
+                }
+                Err(HError::MiniBufferEvent(_)) => return Ok(()),
+                Err(err) => return Err(err)
+            }
+        };
+
+        if new_name == name { return Ok(()) }
+        if new_name.contains('/') {
+            return self.core.show_status("Names can't contain \"/\"");
+        }
+
+        let new_path = file.path.with_file_name(&new_name);
+
+        // Only the case changed and the filesystem doesn't care about it
+        let same_file = |meta: &std::fs::Metadata| {
+            use std::os::unix::fs::MetadataExt;
+
+            file.path
+                .symlink_metadata()
+                .map(|old| old.dev() == meta.dev() && old.ino() == meta.ino())
+                .unwrap_or(false)
+        };
+
+        match new_path.symlink_metadata() {
+            Ok(meta) if !same_file(&meta) => {
+                let msg = format!("Can't rename, {} already exists", new_name);
+                return self.core.show_status(&msg);
+            }
+            _ => {}
+        }
+
+        if let Err(err) = std::fs::rename(&file.path, &new_path) {
+            let msg = format!("Can't rename {}: {}", name, err);
+            return self.core.show_status(&msg);
+        }
+
+        crate::file_ops::push_history(crate::file_ops::FileOpRecord {
+            op: crate::file_ops::FileOp::Move,
+            sources: vec![file.path.clone()],
+            dests: vec![new_path.clone()]
+        });
+
+        self.pending_selection = Some(File::new_from_path(&new_path)?);
+        Ok(())
+    }
+
+    fn goto_selected(&mut self, first: bool) -> HResult<()> {
+        let file = match first {
+            true => self.content.get_selected().next(),
+            false => self.content.get_selected().last()
+        }.cloned();
+
+        match file {
+            Some(file) => self.select_file(&file),
+            None => self.core.show_status("No files selected").log()
+        }
+
+        Ok(())
+    }
+
+    // Wraps around at the ends of the list
+    fn cycle_selected(&mut self, forward: bool) -> HResult<()> {
+        let file = {
+            let files = self.content.iter_files().collect::<Vec<_>>();
+            let len = files.len();
+            let pos = self.get_selection();
+
+            let next = match forward {
+                true => (1..=len).map(|i| (pos + i) % len)
+                                 .find(|&i| files[i].is_selected()),
+                false => (1..=len).map(|i| (pos + len - i) % len)
+                                  .find(|&i| files[i].is_selected())
+            };
+
+            next.map(|i| files[i].clone())
+        };
+
+        match file {
+            Some(file) => self.select_file(&file),
+            None => self.core.show_status("No files selected").log()
+        }
+
+        Ok(())
+    }
+
     pub fn clear_selections(&mut self) {
         for file in self.content.iter_files_mut() {
             file.selected = false;
@@ -585,6 +992,42 @@ impl ListView<Files>
         self.refresh().log();
     }
 
+    // "*.jpg" selects the matching files, "!*.jpg" unselects them
+    fn select_glob(&mut self) -> HResult<()> {
+        let pattern = match self.query_minibuffer("select glob") {
+            Ok(pattern) => pattern,
+            Err(HError::MiniBufferEvent(_)) => return Ok(()),
+            Err(err) => return Err(err)
+        };
+
+        let (select, pattern) = match pattern.strip_prefix('!') {
+            Some(pattern) => (false, pattern),
+            None => (true, pattern.as_str())
+        };
+
+        let mut count = 0;
+        for file in self.content.iter_files_mut() {
+            if file.kind != Kind::Placeholder &&
+                crate::files::glob_match(pattern, &file.name) {
+                file.selected = select;
+                count += 1;
+            }
+        }
+
+        if self.content.filter_selected && self.content.len() == 0 {
+            self.content.toggle_filter_selected();
+            self.core.show_status("Disabled selection filter!").log();
+        }
+
+        self.content.set_dirty();
+        self.refresh().log();
+
+        match select {
+            true => self.core.show_status(&format!("Selected {} files", count)),
+            false => self.core.show_status(&format!("Unselected {} files", count))
+        }
+    }
+
     fn toggle_tag(&mut self) -> HResult<()> {
         self.selected_file_mut().toggle_tag()?;
 
@@ -599,6 +1042,113 @@ impl ListView<Files>
         Ok(())
     }
 
+    fn letter_buckets(&mut self) -> Vec<(char, usize)> {
+        let len = self.content.len();
+        let filter = self.content.filter.clone();
+        let show_hidden = self.content.show_hidden;
+
+        match &self.letter_index {
+            Some(index) if index.len == len &&
+                           index.filter == filter &&
+                           index.show_hidden == show_hidden => {
+                return index.buckets.clone();
+            }
+            _ => {}
+        }
+
+        let buckets = letter_buckets(self.content
+                                         .iter_files()
+                                         .map(|file| file.name.as_str()));
+
+        self.letter_index = Some(LetterIndex {
+            len,
+            filter,
+            show_hidden,
+            buckets: buckets.clone()
+        });
+
+        buckets
+    }
+
+    // Drawn over the bottom of the list while waiting for the letter
+    fn render_letter_index(&self, buckets: &[(char, usize)]) -> String {
+        let xsize = self.core.coordinates.xsize_u();
+        let (xpos, ypos) = self.core.coordinates.u16position();
+        let ysize = self.core.coordinates.ysize() as usize;
+
+        let mut lines = vec![String::new()];
+        for (letter, count) in buckets {
+            let entry = format!("{} {}", letter, count);
+            let line = lines.last_mut().unwrap();
+
+            if !line.is_empty() && term::string_len(line) + entry.len() + 2 > xsize {
+                lines.push(entry);
+            } else if line.is_empty() {
+                line.push_str(&entry);
+            } else {
+                line.push_str("  ");
+                line.push_str(&entry);
+            }
+        }
+
+        let lines = &lines[lines.len().saturating_sub(ysize)..];
+        let top = ypos as usize + ysize - lines.len();
+
+        lines.iter()
+             .enumerate()
+             .map(|(i, line)| {
+                 format!("{}{}{}",
+                         term::goto_xy(xpos, (top + i) as u16),
+                         term::header_color(),
+                         term::sized_string_u(line, xsize))
+             })
+             .collect::<String>() + &term::reset()
+    }
+
+    fn jump_to_letter(&mut self) -> HResult<()> {
+        use crate::minibuffer::MiniBufferEvent::*;
+
+        let buckets = self.letter_buckets();
+
+        let letter = loop {
+            self.core.write_to_screen(&self.render_letter_index(&buckets)).log();
+
+            match self.core.minibuffer_continuous("jump to letter") {
+                Err(HError::MiniBufferEvent(NewInput(input))) => {
+                    break first_letter(&input);
+                }
+                Err(HError::RefreshParent) => {
+                    self.refresh().log();
+                    self.draw().log();
+                }
+                _ => break None
+            }
+        };
+
+        self.core.minibuffer_clear().log();
+        self.core.clear().log();
+
+        let letter = match letter {
+            Some(letter) => letter,
+            None => return Ok(())
+        };
+
+        let file = self.content
+                       .iter_files()
+                       .find(|file| first_letter(&file.name) == Some(letter))
+                       .cloned();
+
+        match file {
+            Some(file) => self.select_file(&file),
+            None => {
+                let msg = format!("No files starting with {}", letter);
+                self.core.show_status(&msg).log();
+            }
+        }
+
+        Ok(())
+    }
+
     fn search_file(&mut self) -> HResult<()> {
         let selected_file = self.clone_selected_file();
 
@@ -620,11 +1170,20 @@ impl ListView<Files>
                     match ev {
                         Done(_) => {}
                         NewInput(input) => {
-                            let file = self.content
-                                           .find_file_with_name(&input)
-                                           .cloned();
-
-                            file.map(|f| self.select_file(&f));
+                            let file = {
+                                let files = self.content.iter_files().collect::<Vec<_>>();
+                                ranked_matches(&files, &input)
+                                    .first()
+                                    .map(|&i| files[i].clone())
+                            };
+
+                            match file {
+                                Some(file) => self.select_file(&file),
+                                None => {
+                                    let msg = "No match here, C-s searches subdirectories";
+                                    self.core.show_status(msg).log();
+                                }
+                            }
 
                             self.draw().log();
 
@@ -636,10 +1195,14 @@ impl ListView<Files>
                             self.select_file(&selected_file);
                         }
                         CycleNext => {
-                            self.search_next().log();
+                            self.search_next(1).log();
                         }
                         CyclePrev => {
-                            self.search_prev().log();
+                            self.search_prev(1).log();
+                        }
+                        SearchSubdirs(input) => {
+                            self.searching = Some(input.clone());
+                            return self.search_subdirs(&input);
                         }
                     }
                 }
@@ -650,63 +1213,127 @@ impl ListView<Files>
         Ok(())
     }
 
-    fn search_next(&mut self) -> HResult<()> {
+    // Runs in the background, the file browser goes to the directory the
+    // match is in once it's found
+    fn search_subdirs(&mut self, search: &str) -> HResult<()> {
+        let sender = self.core.get_sender();
+        self.content.search_subdirs(search, sender);
+        self.core.show_status("Searching subdirectories...")
+    }
+
+    // Jumps over "count" matches, stopping at the last one found. Works on
+    // the files as shown, so the position matches the selection.
+    fn find_nth_match(&self,
+                      search: &str,
+                      count: usize,
+                      forward: bool) -> (Option<File>, bool) {
+        let files = self.content.iter_files().collect::<Vec<_>>();
+        let wrap = self.core.config().search_wrap;
+
+        let mut pos = self.get_selection();
+        let mut found = None;
+        let mut wrapped = false;
+
+        for _ in 0..count {
+            match find_match(&files, search, pos, forward, wrap) {
+                Some((next, wrap)) => {
+                    pos = next;
+                    found = Some(next);
+                    wrapped |= wrap;
+                }
+                None => break
+            }
+        }
+
+        let file = found.map(|i| files[i].clone());
+        (file, wrapped)
+    }
+
+    // Like find_nth_match, but goes through the fuzzy matches from best to
+    // worst. Starts with the best one when the selection isn't a match.
+    fn find_nth_ranked(&self,
+                       search: &str,
+                       count: usize,
+                       forward: bool) -> (Option<File>, bool) {
+        let files = self.content.iter_files().collect::<Vec<_>>();
+        let ranked = ranked_matches(&files, search);
+        let wrap = self.core.config().search_wrap;
+        let len = ranked.len();
+
+        let selection = self.get_selection();
+        let mut rank = ranked.iter().position(|&i| i == selection);
+        let mut found = None;
+        let mut wrapped = false;
+
+        for _ in 0..count {
+            let next = match (rank, forward) {
+                (_, _) if len == 0 => None,
+                (None, _) => Some((0, false)),
+                (Some(r), true) if r + 1 < len => Some((r + 1, false)),
+                (Some(r), false) if r > 0 => Some((r - 1, false)),
+                (Some(_), true) if wrap => Some((0, true)),
+                (Some(_), false) if wrap => Some((len - 1, true)),
+                _ => None
+            };
+
+            match next {
+                Some((next, wrap)) => {
+                    rank = Some(next);
+                    found = Some(ranked[next]);
+                    wrapped |= wrap;
+                }
+                None => break
+            }
+        }
+
+        let file = found.map(|i| files[i].clone());
+        (file, wrapped)
+    }
+
+    fn find_nth(&self,
+                search: &str,
+                count: usize,
+                forward: bool) -> (Option<File>, bool) {
+        match search.contains(' ') {
+            true => self.find_nth_match(search, count, forward),
+            false => self.find_nth_ranked(search, count, forward)
+        }
+    }
+
+    fn search_next(&mut self, count: usize) -> HResult<()> {
         if self.searching.is_none() {
             self.core.show_status("No search pattern set!").log();
         }
         let prev_search = self.searching.clone()?;
-        let selection = self.get_selection();
 
-        let file = self.content
-            .files
-            .iter()
-            .skip(selection+1)
-            .find(|file| {
-                if file.name.to_lowercase().contains(&prev_search) {
-                    true
-                } else {
-                    false
-                }
-            }).clone();
+        let (file, wrapped) = self.find_nth(&prev_search, count, true);
 
         if let Some(file) = file {
-            let file = file.clone();
             self.select_file(&file);
+            if wrapped {
+                self.core.show_status("Search wrapped around to the top").log();
+            }
         } else {
             self.core.show_status("Reached last search result!").log();
         }
         Ok(())
     }
 
-    fn search_prev(&mut self) -> HResult<()> {
+    fn search_prev(&mut self, count: usize) -> HResult<()> {
         if self.searching.is_none() {
             self.core.show_status("No search pattern set!").log();
         }
         let prev_search = self.searching.clone()?;
 
+        let (file, wrapped) = self.find_nth(&prev_search, count, false);
 
-        self.reverse_sort();
-
-        let selection = self.get_selection();
-
-        let file = self.content
-            .files
-            .iter()
-            .skip(selection+1)
-            .find(|file| {
-                if file.name.to_lowercase().contains(&prev_search) {
-                    true
-                } else {
-                    false
-                }
-            }).cloned();
-
-        self.reverse_sort();
         self.core.clear_status().log();
 
         if let Some(file) = file {
-            let file = file.clone();
             self.select_file(&file);
+            if wrapped {
+                self.core.show_status("Search wrapped around to the bottom").log();
+            }
         } else {
             self.core.show_status("Reached last search result!").log();
         }
@@ -718,7 +1345,9 @@ impl ListView<Files>
         let prev_len = self.len();
         let selected_file = self.clone_selected_file();
 
-        self.content.set_filter(filter);
+        if let Err(err) = self.content.set_filter(filter) {
+            self.core.show_status(&format!("{}", err)).log();
+        }
 
         // Only do something if filter changed something
         if self.len() != prev_len {
@@ -790,15 +1419,66 @@ impl ListView<Files>
         render_fn(file)
     }
 
+    fn visible_files(&self) -> impl Iterator<Item=&File> {
+        let ysize = self.get_coordinates().unwrap().ysize_u();
+        let files_above_selection = self.get_selection() - self.offset;
+        let selected_file = self.selected_file();
+
+        self.content
+            .iter_files_from(selected_file, files_above_selection)
+            .take(ysize+1)
+    }
+
+    // The name keeps at least half the width, the least important columns
+    // are dropped first when there isn't enough room
+    fn detail_columns(&self,
+                      xsize: usize,
+                      size_format: SizeFormat,
+                      date_format: &DateFormat) -> Option<DetailColumns> {
+        if !self.details {
+            return None;
+        }
+
+        let (mtime, size) = self.visible_files()
+            .fold((0, 0), |(mtime, size), file| {
+                let mtime_width = file.pretty_mtime(date_format)
+                                      .map(|mtime| term::str_width(&mtime))
+                                      .unwrap_or(0);
+                let size_width = term::str_width(&size_string(file, size_format));
+                (mtime.max(mtime_width), size.max(size_width))
+            });
+
+        let mut columns = DetailColumns {
+            permissions: true,
+            mtime: Some(mtime),
+            size: size
+        };
+
+        if columns.width() > xsize / 2 {
+            columns.permissions = false;
+        }
+        if columns.width() > xsize / 2 {
+            columns.mtime = None;
+        }
+
+        Some(columns)
+    }
+
     #[allow(trivial_bounds)]
     fn render_line_fn(&self) -> impl Fn(&File) -> String {
         use std::fmt::Write;
-        use crate::files::FileError;
 
         let xsize = self.get_coordinates().unwrap().xsize();
         let config = self.core.config();
         let icons = config.icons;
         let icons_space = config.icons_space;
+        let size_format = config.size_format();
+        let date_format = config.date_format.clone();
+        let columns = self.detail_columns(xsize as usize, size_format, &date_format);
+        let flat_dir = self.content
+                           .flat
+                           .map(|_| self.content.directory.path.clone());
+        let trailing_slash = config.dir_trailing_slash;
 
         move |file| -> String {
             let mut line = String::with_capacity(500);
@@ -809,16 +1489,30 @@ impl ListView<Files>
                 _ => ("", "")
             };
 
-            let name = &file.name;
+            // Flattened lists show where files are
+            let name = match &flat_dir {
+                Some(dir) => match file.path.strip_prefix(dir) {
+                    Ok(path) => path.to_string_lossy().to_string(),
+                    Err(_) => file.name.clone()
+                }
+                None => file.name.clone()
+            };
 
-            let size = file.calculate_size();
-            let (size, unit) = match size {
-                Ok((size, unit)) => (size.to_string(), unit),
-                Err(HError::FileError(FileError::MetaPending)) => {
-                    let ticks = crate::files::tick_str();
-                    (String::from(ticks), "")
-                },
-                Err(_) => (String::from("ERR"), "")
+            // Stays visible when the name is cut short
+            let indicator = match trailing_slash {
+                TrailingSlash::Dirs if file.is_dir() => Some('/'),
+                TrailingSlash::Classify => file.type_indicator(),
+                TrailingSlash::Dirs | TrailingSlash::Off => None
+            };
+            let indicator_len = indicator.map(|_| 1).unwrap_or(0);
+
+            let size = size_string(file, size_format);
+            let (size, name_xsize) = match &columns {
+                Some(columns) => {
+                    let details = columns.render(file, &size, &date_format);
+                    (details, xsize.saturating_sub(columns.width() as u16 + 1))
+                }
+                None => (size, xsize)
             };
 
             let (tag, tag_len) = match file.is_tagged() {
@@ -849,19 +1543,21 @@ impl ListView<Files>
                                                .unwrap_or("");
             let link_indicator_len = link_indicator_len.unwrap_or(0);
 
-            let sized_string = term::sized_string(&name, xsize);
+            let sized_string = term::sized_string(&name,
+                                                  name_xsize.saturating_sub(indicator_len));
+            let sized_string = match indicator {
+                Some(indicator) => format!("{}{}", sized_string, indicator),
+                None => sized_string.to_string()
+            };
 
-            let size = size.to_string();
-            let size_pos = xsize - (size.len() as u16 +
-                                    unit.len() as u16 +
-                                    link_indicator_len as u16);
+            let size_pos = xsize.saturating_sub(term::string_len(&size) as u16 +
+                                                link_indicator_len as u16);
 
-            let padding = sized_string.len() - sized_string.width_cjk();
-            let padding = xsize - padding as u16;
-            let padding = padding - tag_len;
-            let padding = padding - icon.width() as u16;
-            let padding = padding - icon_space.len() as u16;
-            let padding = padding - 1;
+            let padding = xsize.saturating_sub(term::extra_width(&sized_string) as u16)
+                               .saturating_sub(tag_len)
+                               .saturating_sub(term::str_width(&icon) as u16)
+                               .saturating_sub(icon_space.len() as u16)
+                               .saturating_sub(1);
 
             write!(&mut line, "{}", termion::cursor::Save).unwrap();
 
@@ -891,13 +1587,12 @@ impl ListView<Files>
             }.unwrap();
 
             write!(&mut line,
-                   "{}{}{}{}{}{}",
+                   "{}{}{}{}{}",
                    termion::cursor::Restore,
                    termion::cursor::Right(size_pos),
                    link_indicator,
                    term::highlight_color(),
-                   size,
-                   unit).unwrap();
+                   size).unwrap();
 
 
             line
@@ -907,13 +1602,8 @@ impl ListView<Files>
 
     fn render(&self) -> Vec<String> {
         let render_fn = self.render_line_fn();
-        let ysize = self.get_coordinates().unwrap().ysize_u();
-        let files_above_selection = self.get_selection() - self.offset;
-        let selected_file = self.selected_file();
 
-        self.content
-            .iter_files_from(selected_file, files_above_selection)
-            .take(ysize+1)
+        self.visible_files()
             .map(|file| render_fn(file))
             .collect()
     }
@@ -922,8 +1612,20 @@ impl ListView<Files>
         let file = self.clone_selected_file();
 
         if let Ok(Some(_)) = self.content.get_refresh() {
+            let file = match self.pending_selection.take() {
+                Some(pending) if self.content.iter_files().any(|f| f == &pending) => {
+                    pending
+                }
+                pending => {
+                    self.pending_selection = pending;
+                    file
+                }
+            };
+
             // Positions might change when files are added/removed/renamed
+            let offset = self.offset;
             self.select_file(&file);
+            self.set_offset(offset);
             self.content.run_jobs(self.core.get_sender());
         }
 
@@ -1000,3 +1702,145 @@ where
         Listable::on_key(self, key)
     }
 }
+
+// Position of the closest file after (or before) "from" with "search" in its
+// name and whether the search had to wrap around to find it
+fn find_match(files: &[&File],
+              search: &str,
+              from: usize,
+              forward: bool,
+              wrap: bool) -> Option<(usize, bool)> {
+    let len = files.len();
+    let matches = |i: &usize| files[*i].name.to_lowercase().contains(search);
+
+    let next = match forward {
+        true => (from+1..len).find(matches),
+        false => (0..from.min(len)).rev().find(matches)
+    };
+
+    match next {
+        Some(i) => Some((i, false)),
+        None if wrap => {
+            let wrapped = match forward {
+                true => (0..from.min(len)).find(matches),
+                false => (from+1..len).rev().find(matches)
+            };
+            wrapped.map(|i| (i, true))
+        }
+        None => None
+    }
+}
+
+// Score of "query" as a subsequence of "name", higher is better. Matches at
+// the start, after a separator and right after the previous match get a
+// bonus, skipped characters cost a bit.
+pub fn fuzzy_score(name: &str, query: &str) -> Option<i64> {
+    let name = name.chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last_match: Option<usize> = None;
+
+    for qchr in query.chars().flat_map(|chr| chr.to_lowercase()) {
+        let found = (pos..name.len())
+            .find(|&i| name[i].to_lowercase().eq(Some(qchr)))?;
+
+        let boundary = found == 0 || match name[found - 1] {
+            '_' | '-' | '.' | ' ' | '/' => true,
+            prev => prev.is_lowercase() && name[found].is_uppercase()
+        };
+
+        score += match (last_match, boundary) {
+            (Some(last), _) if last + 1 == found => 15,
+            (_, true) if found == 0 => 20,
+            (_, true) => 10,
+            _ => 0
+        };
+        score -= (found - pos) as i64;
+
+        last_match = Some(found);
+        pos = found + 1;
+    }
+
+    Some(score)
+}
+
+// Positions of the files matching "search", best first. With a space in it
+// the search is a plain substring and the matches stay in list order.
+fn ranked_matches(files: &[&File], search: &str) -> Vec<usize> {
+    if search.contains(' ') {
+        return (0..files.len())
+            .filter(|&i| files[i].name.to_lowercase().contains(search))
+            .collect();
+    }
+
+    let mut scored = files.iter()
+                          .enumerate()
+                          .filter_map(|(i, file)| {
+                              fuzzy_score(&file.name, search).map(|score| (i, score))
+                          })
+                          .collect::<Vec<_>>();
+
+    // Stable, so equally good matches stay in list order
+    scored.sort_by_key(|&(_, score)| -score);
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+#[test]
+fn test_letter_buckets() {
+    let names = ["foo", "Bar", "baz", ".hidden", "Ärger", "äpfel"];
+
+    assert_eq!(letter_buckets(names.iter().cloned()),
+               vec![('.', 1), ('b', 2), ('f', 1), ('ä', 2)]);
+}
+
+#[test]
+fn test_fuzzy_score() {
+    assert_eq!(fuzzy_score("file_browser.rs", "xyz"), None);
+    assert_eq!(fuzzy_score("file_browser.rs", "rbf"), None);
+    assert!(fuzzy_score("file_browser.rs", "fbr").is_some());
+    assert!(fuzzy_score("FileBrowser.rs", "fbr").is_some());
+
+    // Prefixes and word starts beat matches in the middle of words
+    assert!(fuzzy_score("file_browser.rs", "fbr") > fuzzy_score("fabric.rs", "fbr"));
+    assert!(fuzzy_score("listview.rs", "lis") > fuzzy_score("blister.rs", "lis"));
+    assert!(fuzzy_score("FileBrowser", "fb") > fuzzy_score("fiber", "fb"));
+}
+
+#[test]
+fn test_ranked_matches() {
+    let names = ["fabric.rs", "notes", "file_browser.rs", "fbr"];
+    let files = names.iter()
+                     .map(|name| File::new_from_path(&PathBuf::from(name)).unwrap())
+                     .collect::<Vec<_>>();
+    let files = files.iter().collect::<Vec<_>>();
+
+    assert_eq!(ranked_matches(&files, "fbr"), vec![3, 2, 0]);
+    assert_eq!(ranked_matches(&files, "FBR"), vec![3, 2, 0]);
+    assert_eq!(ranked_matches(&files, "b "), Vec::<usize>::new());
+    assert_eq!(ranked_matches(&files, "e_b"), vec![2]);
+}
+
+#[test]
+fn test_find_match() {
+    let names = ["foo1", "bar", "foo2", "baz", "foo3", "bar2"];
+    let files = names.iter()
+                     .map(|name| File::new_from_path(&PathBuf::from(name)).unwrap())
+                     .collect::<Vec<_>>();
+    let files = files.iter().collect::<Vec<_>>();
+
+    // Cursor on "baz"
+    assert_eq!(find_match(&files, "foo", 3, true, false), Some((4, false)));
+    assert_eq!(find_match(&files, "foo", 3, false, false), Some((2, false)));
+    assert_eq!(find_match(&files, "bar", 3, false, false), Some((1, false)));
+
+    // Cursor on a match skips it
+    assert_eq!(find_match(&files, "foo", 2, true, false), Some((4, false)));
+    assert_eq!(find_match(&files, "foo", 2, false, false), Some((0, false)));
+
+    // At the ends
+    assert_eq!(find_match(&files, "foo", 4, true, false), None);
+    assert_eq!(find_match(&files, "foo", 0, false, false), None);
+    assert_eq!(find_match(&files, "foo", 4, true, true), Some((0, true)));
+    assert_eq!(find_match(&files, "foo", 0, false, true), Some((4, true)));
+    assert_eq!(find_match(&files, "nothing", 3, true, true), None);
+}
@@ -32,11 +32,20 @@ extern crate derivative;
 extern crate nix;
 extern crate strip_ansi_escapes;
 extern crate crossbeam;
+extern crate base64;
+extern crate regex;
 
 extern crate osstrtools;
 extern crate pathbuftools;
 extern crate async_value;
 
+#[cfg(feature = "dump-view")]
+extern crate serde;
+#[cfg(feature = "dump-view")]
+extern crate serde_json;
+#[cfg(feature = "syntax")]
+extern crate syntect;
+
 use failure::Fail;
 use clap::{App, Arg};
 
@@ -70,6 +79,27 @@ mod config_installer;
 mod imgview;
 mod mediaview;
 mod keybind;
+mod clipboard;
+mod dir_config;
+mod desktop_entry;
+mod file_info;
+mod file_ops;
+mod keybind_editor;
+mod frecency;
+mod openers;
+mod mounts;
+mod img_cache;
+mod archive;
+mod command_palette;
+mod duplicates;
+mod grep;
+mod hash;
+#[cfg(feature = "dump-view")]
+mod view_dump;
+#[cfg(feature = "syntax")]
+mod highlight;
+#[cfg(test)]
+mod test_dir;
 
 
 
@@ -101,17 +131,63 @@ fn die_gracefully(core: &WidgetCore) {
 fn main() -> HResult<()> {
     let args = parse_args();
 
+    // Just print MIME and quit
+    if args.is_present("mime") {
+        get_mime(args.value_of("path"), args.is_present("json"))
+            .map_err(|e| eprintln!("{}", e))
+            .ok();
+        // If we get heres something went wrong.
+        std::process::exit(1)
+    }
+
+    if let Some(dir) = args.value_of("list") {
+        match list_dir(dir) {
+            Ok(_) => std::process::exit(0),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1)
+            }
+        }
+    }
+
+    // Just prints what would happen, before the screen is taken over
+    if args.is_present("update-dry-run") {
+        match crate::config_installer::update_config_dry_run() {
+            Ok(_) => std::process::exit(0),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1)
+            }
+        }
+    }
+
+    // The path is relative to where hunter was started, not where it starts
+    #[cfg(feature = "dump-view")]
+    {
+        if let Some(path) = args.value_of("dump-view") {
+            std::env::current_dir()
+                .map(|cwd| cwd.join(path))
+                .map_err(HError::from)
+                .and_then(|path| crate::view_dump::set_dump_path(path))
+                .log();
+        }
+    }
+
     // do this early so it might be ready when needed
     crate::files::load_tags().ok();
 
     let mut core = WidgetCore::new().expect("Can't create WidgetCore!");
 
+    // Applied once the file browser is there
+    let select = args.value_of("select").map(String::from);
+    let filter = args.value_of("filter").map(String::from);
+
     process_args(args, core.clone());
 
     // Resets terminal when hunter crashes :(
     die_gracefully(&core);
 
-    match run(core.clone()) {
+    match run(core.clone(), select, filter) {
         Ok(_) | Err(HError::Quit) => reset_screen(&mut core),
         Err(err) => {
             reset_screen(&mut core)?;
@@ -121,17 +197,27 @@ fn main() -> HResult<()> {
     }
 }
 
-fn run(mut core: WidgetCore) -> HResult<()> {
+fn run(mut core: WidgetCore,
+       select: Option<String>,
+       filter: Option<String>) -> HResult<()> {
     core.screen.clear()?;
 
     let core2 = core.clone();
 
-    // I hate waiting!!!
-    std::thread::spawn(move || {
-        crate::config_installer::ensure_config(core2).log();
-    });
+    // New users get asked a few questions, so the config has to be there
+    match crate::config_installer::has_config() {
+        Ok(true) => {
+            // I hate waiting!!!
+            std::thread::spawn(move || {
+                crate::config_installer::ensure_config(core2).log();
+            });
+        }
+        _ => crate::config_installer::ensure_config(core2).log()
+    }
+
+    let mut filebrowser = FileBrowser::new(&core, None)?;
+    filebrowser.start_with(select, filter).log();
 
-    let filebrowser = FileBrowser::new(&core, None)?;
     let mut tabview = TabView::new(&core);
     tabview.push_widget(filebrowser)?;
 
@@ -145,7 +231,7 @@ fn run(mut core: WidgetCore) -> HResult<()> {
 
 
 fn parse_args() -> clap::ArgMatches<'static> {
-    App::new(clap::crate_name!())
+    let app = App::new(clap::crate_name!())
         .version(clap::crate_version!())
         .author(clap::crate_authors!())
         .about(clap::crate_description!())
@@ -154,7 +240,12 @@ fn parse_args() -> clap::ArgMatches<'static> {
             Arg::with_name("update")
                 .short("u")
                 .long("update-conf")
-                .help("Update configuration\n(WARNING: Overwrites modified previewers/actions with default names!\nMain config/keys are safe!)")
+                .help("Update configuration\n(Previewers/actions you modified are kept!\nMain config/keys are safe!)")
+                .takes_value(false))
+        .arg(
+            Arg::with_name("update-dry-run")
+                .long("update-conf-dry-run")
+                .help("Show which previewers/actions updating the configuration would change")
                 .takes_value(false))
         .arg(
             Arg::with_name("animation-off")
@@ -175,6 +266,11 @@ fn parse_args() -> clap::ArgMatches<'static> {
                 .long("icons")
                 .help("Show icons for different file types")
                 .takes_value(false))
+        .arg(
+            Arg::with_name("read-only")
+                .long("read-only")
+                .help("Disable everything that changes files or runs commands")
+                .takes_value(false))
         .arg(
             Arg::with_name("graphics")
                 .short("g")
@@ -188,11 +284,45 @@ fn parse_args() -> clap::ArgMatches<'static> {
                 .long("mime")
                 .help("Print MIME type of file")
                 .takes_value(false))
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print MIME type as JSON, together with the path")
+                .takes_value(false))
+        .arg(
+            Arg::with_name("list")
+                .long("list")
+                .value_name("dir")
+                .help("Print the files in <dir> as JSON, one per line")
+                .takes_value(true))
+        .arg(
+            Arg::with_name("select")
+                .long("select")
+                .value_name("name")
+                .help("Start with the cursor on <name>")
+                .takes_value(true))
+        .arg(
+            Arg::with_name("filter")
+                .long("filter")
+                .value_name("pattern")
+                .help("Start with <pattern> as filter")
+                .takes_value(true))
         .arg(
             Arg::with_name("path")
                 .index(1)
-                .help("Start in <path>"))
-        .get_matches()
+                .help("Start in <path>"));
+
+    // Only meant for tests and tools, so it's not in --help
+    #[cfg(feature = "dump-view")]
+    let app = app.arg(
+        Arg::with_name("dump-view")
+            .long("dump-view")
+            .value_name("file")
+            .help("Write the current view as JSON to <file> on SIGUSR1")
+            .hidden(true)
+            .takes_value(true));
+
+    app.get_matches()
 }
 
 
@@ -200,15 +330,6 @@ fn parse_args() -> clap::ArgMatches<'static> {
 fn process_args(args: clap::ArgMatches, core: WidgetCore) {
     let path = args.value_of("path");
 
-    // Just print MIME and quit
-    if args.is_present("mime") {
-        get_mime(path)
-            .map_err(|e| eprintln!("{}", e)).
-            ok();
-        // If we get heres something went wrong.
-        std::process::exit(1)
-    }
-
     if args.is_present("update") {
         crate::config_installer::update_config(core, true).log();
     }
@@ -227,12 +348,72 @@ fn process_args(args: clap::ArgMatches, core: WidgetCore) {
 
 
 
-fn get_mime(path: Option<&str>) -> HResult<()> {
+fn get_mime(path: Option<&str>, json: bool) -> HResult<()> {
     let path = path.ok_or(MimeError::NoFileProvided)?;
     let path = std::path::PathBuf::from(path);
     path.get_mime()
-        .map(|mime| println!("{}", mime))
+        .map(|mime| match json {
+            true => println!("{{\"path\":{},\"mime\":{}}}",
+                             json_string(&path.to_string_lossy()),
+                             json_string(&mime.to_string())),
+            false => println!("{}", mime)
+        })
         .map(|_| std::process::exit(0))
         .map_err(|e| eprintln!("{}", e))
         .map_err(|_| std::process::exit(1))
 }
+
+fn json_string(string: &str) -> String {
+    let mut json = String::with_capacity(string.len() + 2);
+    json.push('"');
+
+    for chr in string.chars() {
+        match chr {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            chr if (chr as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", chr as u32)),
+            chr => json.push(chr)
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+// Like ls, symlinks themselves are described, not their targets
+fn list_dir(dir: &str) -> HResult<()> {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+    let dir = std::path::Path::new(dir);
+    let mut files = files::Files::new_from_path_cancellable(dir,
+                                                            async_value::Stale::new())?;
+    files.sort();
+
+    for file in &files.files {
+        // Might be gone already
+        let meta = match std::fs::symlink_metadata(&file.path) {
+            Ok(meta) => meta,
+            Err(_) => continue
+        };
+        let file_type = meta.file_type();
+
+        let kind = if file_type.is_symlink() { "symlink" }
+        else if file_type.is_dir() { "directory" }
+        else if file_type.is_fifo() { "fifo" }
+        else if file_type.is_socket() { "socket" }
+        else if file_type.is_char_device() { "char_device" }
+        else if file_type.is_block_device() { "block_device" }
+        else { "file" };
+
+        println!("{{\"name\":{},\"size\":{},\"kind\":\"{}\",\"mtime\":{}}}",
+                 json_string(&file.name),
+                 meta.len(),
+                 kind,
+                 meta.mtime());
+    }
+
+    Ok(())
+}
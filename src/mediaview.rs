@@ -88,6 +88,7 @@ impl MediaView {
             core: core.clone(),
             buffer: vec![],
             file: None,
+            cache: false,
         };
 
         let (tx_cmd, rx_cmd) = channel();
@@ -70,7 +70,11 @@ impl History {
         self.position = None;
     }
 
-    fn add(&mut self, htype: &str, input: &str) {
+    // Keeps the last "limit" entries of each type, repeating the last
+    // entry doesn't add it again
+    fn add(&mut self, htype: &str, input: &str, limit: usize) {
+        if limit == 0 { return; }
+
         self.load().ok();
         let history = match self.history.get_mut(htype) {
             Some(history) => history,
@@ -80,7 +84,16 @@ impl History {
                 self.history.get_mut(htype).unwrap()
             }
         };
+
+        if history.last().map(|last| last.as_str()) == Some(input) {
+            return;
+        }
+
         history.push(input.to_string());
+
+        let excess = history.len().saturating_sub(limit);
+        history.drain(..excess);
+
         self.save().log();
     }
 
@@ -90,7 +103,7 @@ impl History {
         let mut position = self.position;
         let hist_len = history.len();
 
-        if position == Some(0) { position = None; }
+        if position == Some(0) || position > Some(hist_len) { position = None; }
         if hist_len == 0 { return Err(HError::NoHistoryError); }
 
         if let Some(position) = position {
@@ -112,7 +125,7 @@ impl History {
         let hist_len = history.len();
 
         if hist_len == 0 { return Err(HError::NoHistoryError); }
-        if position == Some(hist_len) ||
+        if position >= Some(hist_len) ||
            position == None
             { position = Some(0); }
 
@@ -135,7 +148,9 @@ pub enum MiniBufferEvent {
     Empty,
     Cancelled,
     CycleNext,
-    CyclePrev
+    CyclePrev,
+    // Asked to search subdirectories for the input
+    SearchSubdirs(String)
 }
 
 #[derive(Debug)]
@@ -147,6 +162,8 @@ pub struct MiniBuffer {
     history: History,
     completions: Vec<OsString>,
     last_completion: Option<String>,
+    // What InsertSelection puts at the cursor, only some prompts have it
+    insertion: Option<String>,
     continuous: bool
 }
 
@@ -165,6 +182,7 @@ impl MiniBuffer {
             history: History::new(),
             completions: vec![],
             last_completion: None,
+            insertion: None,
             continuous: false
         }
     }
@@ -178,6 +196,41 @@ impl MiniBuffer {
             self.clear();
         }
 
+        self.run()
+    }
+
+    // Starts out with "input" already typed and the cursor at "position"
+    pub fn query_prefilled(&mut self,
+                           query: &str,
+                           input: &str,
+                           position: usize) -> HResult<String> {
+        self.continuous = false;
+        self.query = query.to_string();
+        self.clear();
+        self.input = input.to_string();
+        self.position = position.min(input.len());
+
+        self.run()
+    }
+
+    // Like query, but InsertSelection inserts "insertion"
+    pub fn query_with_insertion(&mut self,
+                                query: &str,
+                                insertion: String) -> HResult<String> {
+        self.continuous = false;
+        self.query = query.to_string();
+        self.clear();
+        self.insertion = Some(insertion);
+
+        self.run()
+    }
+
+    // Goes on with the input as it is, after something interrupted it
+    pub fn query_resume(&mut self) -> HResult<String> {
+        self.run()
+    }
+
+    fn run(&mut self) -> HResult<String> {
         self.core.screen()?.cursor_hide().log();
 
         match self.popup() {
@@ -196,8 +249,11 @@ impl MiniBuffer {
     pub fn clear(&mut self) {
         self.input.clear();
         self.position = 0;
+        // Each query starts at the end of its own history
+        self.history.reset();
         self.completions.clear();
         self.last_completion = None;
+        self.insertion = None;
     }
 
     pub fn complete(&mut self) -> HResult<()> {
@@ -289,6 +345,25 @@ impl MiniBuffer {
         Ok(())
     }
 
+    // Only searching the file list does anything with this
+    pub fn search_subdirs(&mut self) -> HResult<()> {
+        if self.query.as_str() == "search" && self.input != "" {
+            return Err(MiniBufferEvent::SearchSubdirs(self.input.clone()))?;
+        }
+        Ok(())
+    }
+
+    pub fn insert_selection(&mut self) -> HResult<()> {
+        let insertion = match &self.insertion {
+            Some(insertion) => insertion.clone(),
+            None => return self.core.show_status("Nothing to insert here")
+        };
+
+        self.input.insert_str(self.position, &insertion);
+        self.position += insertion.len();
+        Ok(())
+    }
+
     pub fn clear_line(&mut self) -> HResult<()> {
         self.input.clear();
         self.position = 0;
@@ -493,7 +568,7 @@ impl Widget for MiniBuffer {
     fn after_draw(&self) -> HResult<()> {
         let cursor_pos = crate::term::string_len(&self.query) +
                          ": ".len() +
-                         self.position;
+                         crate::term::str_width(&self.input[..self.position]);
 
         let mut screen = self.core.screen()?;
         let ysize = screen.ysize()?;
@@ -518,9 +593,10 @@ impl Acting for MiniBuffer {
         use MiniBufferAction::*;
 
         match action {
+            // The position is in bytes, so it has to go over whole chars
             InsertChar(ch) => {
                 self.input.insert(self.position, *ch);
-                self.position += 1;
+                self.position += ch.len_utf8();
             }
             InsertTab(n) => {
                 let fnstr = format!("${}", n-1);
@@ -530,7 +606,8 @@ impl Acting for MiniBuffer {
             Cancel => { self.clear(); self.input_cancelled()? }
             Finish => {
                 if self.input != "" {
-                    self.history.add(&self.query, &self.input);
+                    let limit = self.core.config().history_size;
+                    self.history.add(&self.query, &self.input, limit);
                 }
                 self.input_finnished()?
             },
@@ -541,19 +618,19 @@ impl Acting for MiniBuffer {
                 }
             },
             BackwardDeleteChar => {
-                if self.position != 0 {
-                    self.input.remove(self.position - 1);
-                    self.position -= 1;
+                if let Some(ch) = self.input[..self.position].chars().next_back() {
+                    self.position -= ch.len_utf8();
+                    self.input.remove(self.position);
                 }
             }
             CursorLeft => {
-                if self.position != 0 {
-                    self.position -= 1;
+                if let Some(ch) = self.input[..self.position].chars().next_back() {
+                    self.position -= ch.len_utf8();
                 }
             },
             CursorRight => {
-                if self.position != self.input.len() {
-                    self.position += 1;
+                if let Some(ch) = self.input[self.position..].chars().next() {
+                    self.position += ch.len_utf8();
                 }
             },
             HistoryUp => self.history_up()?,
@@ -562,6 +639,8 @@ impl Acting for MiniBuffer {
             DeleteWord => self.delete_word()?,
             CursorToStart => self.position = 0,
             CursorToEnd => self.position = self.input.len(),
+            SearchSubdirs => self.search_subdirs()?,
+            InsertSelection => self.insert_selection()?,
         }
         Ok(())
     }
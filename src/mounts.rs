@@ -0,0 +1,226 @@
+use termion::event::Key;
+
+use std::path::PathBuf;
+
+use crate::listview::{Listable, ListView};
+use crate::stats::{FsStat, FsExt};
+use crate::fail::{HResult, ErrorLog};
+use crate::keybind::{Acting, Bindings, MountAction, Movement};
+use crate::widget::Widget;
+use crate::term;
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mount {
+    pub dev: String,
+    // None for removable media that isn't mounted yet
+    pub path: Option<PathBuf>,
+    pub info: String
+}
+
+// What the FileBrowser should do after the popup is closed
+#[derive(Debug, Clone, PartialEq)]
+pub enum MountPick {
+    Goto(PathBuf),
+    Mount(String),
+    Unmount(String)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MountList {
+    pub mounts: Vec<Mount>,
+    pub picked: Option<MountPick>
+}
+
+impl MountList {
+    pub fn new(fs_stat: &FsStat) -> MountList {
+        let mut mounts = fs_stat.stats
+            .values()
+            // Leave out things like proc, sysfs and cgroups
+            .filter(|fs| {
+                fs.fs_mounted_from.starts_with("/") ||
+                fs.fs_mounted_from.contains(":/") ||
+                fs.fs_type.starts_with("fuse.")
+            })
+            .map(|fs| Mount {
+                dev: fs.fs_mounted_from.clone(),
+                path: Some(PathBuf::from(&fs.fs_mounted_on)),
+                info: format!("{} {} free of {}",
+                              fs.fs_type,
+                              fs.get_free(),
+                              fs.get_total())
+            })
+            .collect::<Vec<Mount>>();
+
+        mounts.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let unmounted = unmounted_partitions(&mounts);
+        mounts.extend(unmounted);
+
+        MountList {
+            mounts: mounts,
+            picked: None
+        }
+    }
+}
+
+// Partitions of removable disks that aren't mounted anywhere, only on Linux
+fn unmounted_partitions(mounted: &[Mount]) -> Vec<Mount> {
+    let blocks = match std::fs::read_dir("/sys/class/block") {
+        Ok(blocks) => blocks,
+        Err(_) => return vec![]
+    };
+
+    blocks.filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("partition").exists())
+        .filter(|entry| {
+            // The disk is the directory the partition is in
+            let path = match entry.path().canonicalize() {
+                Ok(path) => path,
+                Err(_) => return false
+            };
+            let removable = path.parent()
+                .and_then(|disk| std::fs::read_to_string(disk.join("removable")).ok())
+                .map(|removable| removable.trim() == "1")
+                .unwrap_or(false);
+
+            removable || path.to_string_lossy().contains("/usb")
+        })
+        .map(|entry| format!("/dev/{}", entry.file_name().to_string_lossy()))
+        .filter(|dev| !mounted.iter().any(|mount| &mount.dev == dev))
+        .map(|dev| Mount {
+            dev: dev,
+            path: None,
+            info: String::from("not mounted")
+        })
+        .collect()
+}
+
+impl Listable for ListView<MountList> {
+    type Item = ();
+
+    fn len(&self) -> usize {
+        self.content.mounts.len()
+    }
+
+    fn render(&self) -> Vec<String> {
+        self.content
+            .mounts
+            .iter()
+            .map(|mount| self.render_mount(mount))
+            .collect()
+    }
+
+    fn render_header(&self) -> HResult<String> {
+        Ok(String::from("Filesystems"))
+    }
+
+    fn render_footer(&self) -> HResult<String> {
+        Ok(String::from("Enter: go there, m: mount, u: unmount, Esc: close"))
+    }
+
+    fn on_key(&mut self, key: Key) -> HResult<()> {
+        self.do_key(key)?;
+        self.refresh().log();
+        self.draw()
+    }
+}
+
+impl ListView<MountList> {
+    fn render_mount(&self, mount: &Mount) -> String {
+        let name = match &mount.path {
+            Some(path) => format!("{} ({})", path.to_string_lossy(), mount.dev),
+            None => mount.dev.clone()
+        };
+
+        let xsize = self.core.coordinates.xsize();
+        let sized_string = term::sized_string(&name, xsize);
+        let info_pos = xsize.saturating_sub(term::str_width(&mount.info) as u16);
+        let padding = xsize - term::extra_width(sized_string) as u16;
+
+        format!("{}{}{:padding$}{}{}{}{}",
+                termion::cursor::Save,
+                term::normal_color(),
+                sized_string,
+                termion::cursor::Restore,
+                termion::cursor::Right(info_pos),
+                term::highlight_color(),
+                mount.info,
+                padding = padding as usize)
+    }
+
+    fn selected_mount(&self) -> HResult<&Mount> {
+        Ok(self.content.mounts.get(self.get_selection())?)
+    }
+
+    fn pick(&mut self, picked: MountPick) -> HResult<()> {
+        self.content.picked = Some(picked);
+        self.popup_finnished()
+    }
+
+    fn goto_mount(&mut self) -> HResult<()> {
+        match self.selected_mount()?.path.clone() {
+            Some(path) => self.pick(MountPick::Goto(path)),
+            None => self.core.show_status("Not mounted, mount it first")
+        }
+    }
+
+    fn mount(&mut self) -> HResult<()> {
+        let mount = self.selected_mount()?.clone();
+
+        match mount.path {
+            Some(_) => self.core.show_status("Already mounted"),
+            None => self.pick(MountPick::Mount(mount.dev))
+        }
+    }
+
+    fn unmount(&mut self) -> HResult<()> {
+        let mount = self.selected_mount()?.clone();
+
+        match mount.path {
+            Some(_) => self.pick(MountPick::Unmount(mount.dev)),
+            None => self.core.show_status("Not mounted")
+        }
+    }
+}
+
+impl Acting for ListView<MountList> {
+    type Action = MountAction;
+
+    fn search_in(&self) -> Bindings<Self::Action> {
+        self.core.config().keybinds.mounts
+    }
+
+    fn movement(&mut self, movement: &Movement) -> HResult<()> {
+        use Movement::*;
+
+        match movement {
+            Up(n) => for _ in 0..*n { self.move_up(); },
+            Down(n) => for _ in 0..*n { self.move_down(); },
+            PageUp => self.page_up(),
+            PageDown => self.page_down(),
+            Top => self.move_top(),
+            Bottom => self.move_bottom(),
+            Right => self.goto_mount()?,
+            Left => {}
+        }
+
+        Ok(())
+    }
+
+    fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
+        use MountAction::*;
+
+        if action.is_mutating() && self.core.config().read_only {
+            return self.core.show_status(&format!("{} is disabled in read-only mode",
+                                                  action));
+        }
+
+        match action {
+            Close => self.popup_finnished(),
+            Goto => self.goto_mount(),
+            Mount => self.mount(),
+            Unmount => self.unmount()
+        }
+    }
+}
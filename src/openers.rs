@@ -0,0 +1,101 @@
+use ini::Ini;
+
+use std::collections::HashMap;
+
+use crate::files::File;
+use crate::fail::{HError, ErrorLog};
+
+
+// Commands to open files with instead of xdg-open, from the [openers]
+// section of the openers file. Keys are extensions like "*.rs", MIME types
+// like "text/html" or whole groups like "image/*". Preview filters are
+// read the same way from the [filters] section of the filters file.
+#[derive(Debug, Clone, Default)]
+pub struct Openers(HashMap<String, String>);
+
+impl Openers {
+    // Openers take over Enter for any file, so a project can't add any
+    pub fn load() -> (Openers, Vec<HError>) {
+        Openers::load_section("openers", crate::paths::user_config_layers("openers"))
+    }
+
+    // Filters run as soon as a file is selected, the same goes for them
+    pub fn load_filters() -> (Openers, Vec<HError>) {
+        Openers::load_section("filters", crate::paths::user_config_layers("filters"))
+    }
+
+    // The file and its section have the same name, later files win. Also
+    // returns errors of files that couldn't be parsed at all.
+    fn load_section(name: &str, layers: Vec<std::path::PathBuf>) -> (Openers, Vec<HError>) {
+        let mut errors = vec![];
+        let mut openers = HashMap::new();
+
+        for path in layers {
+            let ini = Ini::load_from_file_noescape(&path)
+                .or_else(|e| {
+                    let file = path.to_string_lossy().to_string();
+                    HError::config_file_error(file, e.to_string())
+                })
+                .log_and()
+                .map_err(|e| errors.push(e));
+
+            let section = match ini.as_ref().map(|ini| ini.section(Some(name))) {
+                Ok(Some(section)) => section,
+                _ => continue
+            };
+
+            for (pattern, cmd) in section.iter() {
+                openers.insert(pattern.trim().to_lowercase(),
+                               cmd.trim().to_string());
+            }
+        }
+
+        (Openers(openers), errors)
+    }
+
+    // The extension is checked first, the MIME type is only needed without
+    // a match, since guessing it can mean reading the file
+    pub fn find(&self, file: &File) -> Option<String> {
+        if self.0.is_empty() {
+            return None;
+        }
+
+        let ext = file.path
+                      .extension()
+                      .map(|ext| format!("*.{}", ext.to_string_lossy().to_lowercase()));
+
+        if let Some(cmd) = ext.and_then(|ext| self.0.get(&ext)) {
+            return Some(cmd.clone());
+        }
+
+        let mime = file.get_mime().ok()?;
+        let exact = format!("{}/{}", mime.type_(), mime.subtype()).to_lowercase();
+        let group = format!("{}/*", mime.type_()).to_lowercase();
+
+        self.0.get(&exact)
+              .or_else(|| self.0.get(&group))
+              .cloned()
+    }
+}
+
+
+#[test]
+fn test_load_section_layers() {
+    let tmp = crate::test_dir::TestDir::new("filters");
+
+    let system = tmp.join("system");
+    let user = tmp.join("user");
+    let broken = tmp.join("broken");
+    std::fs::write(&system, "[filters]\n*.txt=cat\n*.md=cat\n").unwrap();
+    std::fs::write(&user, "[filters]\n*.MD=glow\n[openers]\n*.rs=vim\n").unwrap();
+    std::fs::write(&broken, "[filters\n").unwrap();
+
+    let (filters, errors) = Openers::load_section("filters",
+                                                  vec![system, broken, user]);
+
+    // Later layers win, other sections and unparsable files are skipped
+    assert_eq!(filters.0.get("*.txt").map(String::as_str), Some("cat"));
+    assert_eq!(filters.0.get("*.md").map(String::as_str), Some("glow"));
+    assert_eq!(filters.0.get("*.rs"), None);
+    assert_eq!(errors.len(), 1);
+}
@@ -1,6 +1,6 @@
 use dirs_2;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::fail::HResult;
 
@@ -30,6 +30,112 @@ pub fn hunter_path() -> HResult<PathBuf> {
     Ok(hunter_path)
 }
 
+pub fn system_hunter_path() -> HResult<PathBuf> {
+    let config_dirs = std::env::var("XDG_CONFIG_DIRS")
+        .unwrap_or_else(|_| String::from("/etc/xdg"));
+    let mut system_path = config_dirs
+        .split(":")
+        .find(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("/etc/xdg"));
+    system_path.push("hunter/");
+    Ok(system_path)
+}
+
+// Home trash from the FreeDesktop trash spec
+pub fn trash_path() -> HResult<PathBuf> {
+    let mut trash_path = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .map(Ok)
+        .unwrap_or_else(|| home_path().map(|home| home.join(".local/share")))?;
+    trash_path.push("Trash");
+    Ok(trash_path)
+}
+
+pub fn img_cache_path() -> HResult<PathBuf> {
+    let mut img_cache_path = dirs_2::cache_dir()?;
+    img_cache_path.push("hunter/images");
+    Ok(img_cache_path)
+}
+
+pub fn project_hunter_path() -> HResult<PathBuf> {
+    let mut project_path = std::env::current_dir()?;
+    project_path.push(".hunter/");
+    Ok(project_path)
+}
+
+// Existing files named "name" from the system-wide and user config, in
+// the order they should be applied. Later files take precedence.
+pub fn user_config_layers(name: &str) -> Vec<PathBuf> {
+    let mut layers: Vec<PathBuf> = vec![];
+
+    for path in vec![system_hunter_path(), hunter_path()] {
+        if let Ok(mut path) = path {
+            path.push(name);
+            if path.is_file() && !layers.contains(&path) {
+                layers.push(path);
+            }
+        }
+    }
+
+    layers
+}
+
+// Directories listed in trusted_projects, one per line. Only the
+// system-wide and user config can have that file, never a project.
+pub fn trusted_projects() -> Vec<PathBuf> {
+    user_config_layers("trusted_projects")
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .flat_map(|list| {
+            list.lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty() && !line.starts_with("#"))
+                .collect::<Vec<_>>()
+        })
+        .filter_map(|dir| std::fs::canonicalize(dir).ok())
+        .collect()
+}
+
+// A cloned repository shouldn't get to configure hunter just by being
+// where it's started, so the project config is only read when trusted
+pub fn project_layer(name: &str) -> Option<PathBuf> {
+    let cwd = std::env::current_dir()
+        .and_then(|dir| std::fs::canonicalize(dir))
+        .ok()?;
+
+    trusted_project_layer(&cwd, &trusted_projects(), name)
+}
+
+// "cwd" has to be canonicalized already, like the trusted directories
+fn trusted_project_layer(cwd: &Path,
+                         trusted: &[PathBuf],
+                         name: &str) -> Option<PathBuf> {
+    if !trusted.iter().any(|dir| dir == cwd) {
+        return None;
+    }
+
+    let path = cwd.join(".hunter").join(name);
+    match path.is_file() {
+        true => Some(path),
+        false => None
+    }
+}
+
+// The system-wide, user and trusted project config files named "name"
+pub fn config_layers(name: &str) -> Vec<PathBuf> {
+    let mut layers = user_config_layers(name);
+
+    if let Some(project) = project_layer(name) {
+        if !layers.contains(&project) {
+            layers.push(project);
+        }
+    }
+
+    layers
+}
+
 pub fn config_path() -> HResult<PathBuf> {
     let mut config_path = hunter_path()?;
     config_path.push("config");
@@ -48,12 +154,36 @@ pub fn bookmark_path() -> HResult<PathBuf> {
     Ok(bookmark_path)
 }
 
+pub fn frecency_path() -> HResult<PathBuf> {
+    let mut frecency_path = hunter_path()?;
+    frecency_path.push("frecency");
+    Ok(frecency_path)
+}
+
+pub fn action_usage_path() -> HResult<PathBuf> {
+    let mut action_usage_path = hunter_path()?;
+    action_usage_path.push("action_usage");
+    Ok(action_usage_path)
+}
+
+pub fn scratch_path() -> HResult<PathBuf> {
+    let mut scratch_path = hunter_path()?;
+    scratch_path.push("scratch");
+    Ok(scratch_path)
+}
+
 pub fn tagfile_path() -> HResult<PathBuf> {
     let mut tagfile_path = hunter_path()?;
     tagfile_path.push("tags");
     Ok(tagfile_path)
 }
 
+pub fn sort_settings_path() -> HResult<PathBuf> {
+    let mut sort_settings_path = hunter_path()?;
+    sort_settings_path.push("sort_settings");
+    Ok(sort_settings_path)
+}
+
 pub fn history_path() -> HResult<PathBuf> {
     let mut history_path = hunter_path()?;
     history_path.push("history");
@@ -71,3 +201,20 @@ pub fn previewers_path() -> HResult<PathBuf> {
     previewers_path.push("previewers");
     Ok(previewers_path)
 }
+
+
+#[test]
+fn test_trusted_project_layer() {
+    let tmp = crate::test_dir::TestDir::new("trust");
+    let project = tmp.join("project");
+    std::fs::create_dir_all(project.join(".hunter")).unwrap();
+    std::fs::write(project.join(".hunter/config"), "show_hidden=on\n").unwrap();
+
+    let trusted = trusted_project_layer(&project, &[project.clone()], "config");
+    let untrusted = trusted_project_layer(&project, &[tmp.to_path_buf()], "config");
+    let missing = trusted_project_layer(&project, &[project.clone()], "keys");
+
+    assert_eq!(trusted, Some(project.join(".hunter/config")));
+    assert_eq!(untrusted, None);
+    assert_eq!(missing, None);
+}
@@ -3,10 +3,14 @@ use termion::event::Key;
 use parking_lot::Mutex;
 
 
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashMap;
+use std::ffi::OsString;
 use std::path::PathBuf;
+use std::time::Duration;
 
-use crate::files::{File, Files, Kind, Ticker};
+use crate::files::{File, Files, FileType, Kind, Ticker};
 use crate::fscache::FsCache;
 use crate::listview::{ListView, FileSource};
 use crate::textview::TextView;
@@ -15,6 +19,7 @@ use crate::coordinates::Coordinates;
 use crate::fail::{HResult, HError, ErrorLog};
 use crate::imgview::ImgView;
 use crate::mediaview::MediaView;
+use crate::keybind::PreviewAction;
 
 
 pub type AsyncWidgetFn<W> = dyn FnOnce(&Stale, WidgetCore)
@@ -22,6 +27,9 @@ pub type AsyncWidgetFn<W> = dyn FnOnce(&Stale, WidgetCore)
 
 lazy_static! {
     static ref SUBPROC: Arc<Mutex<Option<u32>>> = Arc::new(Mutex::new(None));
+    // Graphical and text previewers for each extension, in order of preference
+    static ref PREVIEWERS: RwLock<HashMap<OsString, (Vec<PathBuf>, Vec<PathBuf>)>>
+        = RwLock::new(HashMap::new());
 }
 
 fn kill_proc() -> HResult<()> {
@@ -29,25 +37,27 @@ fn kill_proc() -> HResult<()> {
     ImgView::kill_running();
 
     let mut pid = SUBPROC.lock();
-    pid.map(|pid|
-            // Do this in another thread so we can wait on process to exit with SIGHUP
-            std::thread::spawn(move || {
-                use nix::{unistd::Pid,
-                          sys::signal::{killpg, Signal}};
-
-                let sleep_time = std::time::Duration::from_millis(50);
-
-                // Kill using process group, to clean up all child processes, too
-                let pid = Pid::from_raw(pid as i32);
-                killpg(pid, Signal::SIGTERM).ok();
-                std::thread::sleep(sleep_time);
-                killpg(pid, Signal::SIGKILL).ok();
-            })
-    );
+    pid.map(kill_group);
     *pid = None;
     Ok(())
 }
 
+fn kill_group(pid: u32) {
+    // Do this in another thread so we can wait on process to exit with SIGHUP
+    std::thread::spawn(move || {
+        use nix::{unistd::Pid,
+                  sys::signal::{killpg, Signal}};
+
+        let sleep_time = std::time::Duration::from_millis(50);
+
+        // Kill using process group, to clean up all child processes, too
+        let pid = Pid::from_raw(pid as i32);
+        killpg(pid, Signal::SIGTERM).ok();
+        std::thread::sleep(sleep_time);
+        killpg(pid, Signal::SIGKILL).ok();
+    });
+}
+
 
 
 
@@ -232,48 +242,99 @@ enum ExtPreviewer {
     Graphics(PathBuf)
 }
 
-fn find_previewer(file: &File, g_mode: bool) -> HResult<ExtPreviewer> {
+pub fn clear_previewer_cache() {
+    PREVIEWERS.write()
+              .map(|mut previewers| previewers.clear())
+              .ok();
+}
+
+// Previewers are named after the extension, with ".g" for graphical ones.
+// Fallbacks get a number appended, e.g. "pdf", "pdf.2", "pdf.g", "pdf.g.2"
+fn previewer_rank(name: &str, ext: &str) -> Option<(bool, usize)> {
+    let rest = name.strip_prefix(ext)?;
+    let (graphics, rest) = match rest.strip_prefix(".g") {
+        Some(rest) => (true, rest),
+        None => (false, rest)
+    };
+
+    match rest {
+        "" => Some((graphics, 1)),
+        _ => Some((graphics, rest.strip_prefix(".")?.parse().ok()?))
+    }
+}
+
+// Scanning the directory for every file is slow, so results are cached
+fn previewers_for(ext: &std::ffi::OsStr) -> HResult<(Vec<PathBuf>, Vec<PathBuf>)> {
+    if let Some(previewers) = PREVIEWERS.read()?.get(ext) {
+        return Ok(previewers.clone());
+    }
+
+    let path = crate::paths::previewers_path()?;
+    let ext_str = ext.to_str()?;
+    let mut ranked = path.read_dir()?
+        .filter_map(|previewer| previewer.ok())
+        .map(|previewer| previewer.path())
+        .filter(|p| p.is_file())
+        .filter_map(|p| {
+            let rank = previewer_rank(p.file_name()?.to_str()?, ext_str)?;
+            Some((rank, p))
+        })
+        .collect::<Vec<_>>();
+
+    ranked.sort();
+
+    let (g_previewers, previewers): (Vec<_>, Vec<_>) = ranked
+        .into_iter()
+        .partition(|((graphics, _), _)| *graphics);
+
+    let previewers = (g_previewers.into_iter().map(|(_, p)| p).collect(),
+                      previewers.into_iter().map(|(_, p)| p).collect());
+    PREVIEWERS.write()?.insert(ext.to_os_string(), previewers.clone());
+
+    Ok(previewers)
+}
+
+// Graphical previewers come first, then the text ones
+// The built-in highlighter replaces the generic text previewer, but not the
+// ones made for a specific extension
+#[cfg(feature = "syntax")]
+fn builtin_highlight(file: &File, core: &WidgetCore) -> bool {
+    core.config().syntax_highlight &&
+        crate::highlight::find_syntax(&file.path).is_some()
+}
+
+#[cfg(not(feature = "syntax"))]
+fn builtin_highlight(_: &File, _: &WidgetCore) -> bool {
+    false
+}
+
+fn find_previewers(file: &File,
+                   g_mode: bool,
+                   builtin_text: bool) -> HResult<Vec<ExtPreviewer>> {
     let path = crate::paths::previewers_path()?;
     let ext = file.path.extension()?;
+    let (g_previewers, previewers) = previewers_for(ext)?;
+
+    let mut found = vec![];
 
-    // Try to find a graphical previewer first
     if g_mode {
-        let g_previewer = path.read_dir()?
-            .find(|previewer| previewer.as_ref()
-                  .and_then(|p| {
-                      Ok(p.path().file_stem() == Some(ext)
-                         && p.path().extension() == Some(&std::ffi::OsStr::new("g")))
-                  })
-                  .unwrap_or(false))
-            .map(|p| p.map(|p| p.path()));
-        match g_previewer {
-            Some(Ok(g_p)) => return Ok(ExtPreviewer::Graphics(g_p)),
-            _ => {}
-        }
+        found.extend(g_previewers.into_iter().map(ExtPreviewer::Graphics));
     }
 
+    found.extend(previewers.into_iter().map(ExtPreviewer::Text));
 
-
-    // Look for previewers matching the file extension
-    let previewer = path.read_dir()?
-                        .find(|previewer| previewer.as_ref()
-                                   .and_then(|p| Ok(p.file_name() == ext ))
-                                   .unwrap_or(false))
-                        .map(|p| p.map(|p| p.path()));
-    match previewer {
-        Some(Ok(p)) => return Ok(ExtPreviewer::Text(p)),
-        _ => {
-            // Special case to highlight text files that aren't text/*
-            if file.is_text() {
-                let mut previewer = PathBuf::from(&path);
-                previewer.push("definitions/");
-                previewer.push("text");
-                return Ok(ExtPreviewer::Text(previewer));
-            }
-        }
+    // Special case to highlight text files that aren't text/*
+    if file.is_text() && !builtin_text {
+        let mut previewer = PathBuf::from(&path);
+        previewer.push("definitions/");
+        previewer.push("text");
+        found.push(ExtPreviewer::Text(previewer));
     }
 
-    Ok(ExtPreviewer::Text(previewer??))
+    match found.len() {
+        0 => HError::preview_failed(file),
+        _ => Ok(found)
+    }
 }
 
 
@@ -281,6 +342,10 @@ pub struct Previewer {
     widget: AsyncWidget<PreviewWidget>,
     core: WidgetCore,
     file: Option<File>,
+    // Stays previewed while the selection moves on
+    pinned: Option<File>,
+    // Set with TogglePreviewHidden, otherwise the preview_hidden option counts
+    show_hidden: Option<bool>,
     pub cache: FsCache,
     animator: Stale
 }
@@ -299,6 +364,8 @@ impl Previewer {
         Previewer { widget: widget,
                     core: core.clone(),
                     file: None,
+                    pinned: None,
+                    show_hidden: None,
                     cache: cache,
                     animator: Stale::new()}
     }
@@ -312,6 +379,7 @@ impl Previewer {
     }
 
     pub fn set_stale(&mut self) -> HResult<()> {
+        if self.pinned.is_some() { return Ok(()) }
         self.cancel_animation()?;
         self.widget.set_stale()
     }
@@ -320,11 +388,39 @@ impl Previewer {
         self.file.as_ref()
     }
 
+    pub fn pinned(&self) -> Option<&File> {
+        self.pinned.as_ref()
+    }
+
+    // Pins whatever is previewed right now
+    pub fn toggle_pin(&mut self) -> Option<&File> {
+        self.pinned = match self.pinned {
+            Some(_) => None,
+            None => self.file.clone()
+        };
+
+        self.pinned.as_ref()
+    }
+
+    pub fn show_hidden(&self) -> bool {
+        self.show_hidden.unwrap_or_else(|| self.core.config().preview_hidden())
+    }
+
+    pub fn toggle_hidden(&mut self) -> bool {
+        let show_hidden = !self.show_hidden();
+        self.show_hidden = Some(show_hidden);
+        self.reload();
+        show_hidden
+    }
+
     pub fn cancel_animation(&self) -> HResult<()> {
         Ok(self.animator.set_stale()?)
     }
 
+    // The files of a pinned directory aren't the ones of the selected one
     pub fn take_files(&mut self) -> HResult<Files> {
+        if self.pinned.is_some() { return HError::no_files() }
+
         match self.widget.widget_mut() {
             Ok(PreviewWidget::FileList(file_list)) => {
                 let files = std::mem::take(&mut file_list.content);
@@ -337,8 +433,11 @@ impl Previewer {
     pub fn put_preview_files(&mut self,
                              files: Files,
                              selected_file: Option<File>) {
+        if self.pinned.is_some() { return }
+
         let dir = files.directory.clone();
         let cache = self.cache.clone();
+        let show_hidden = self.show_hidden();
         self.file = Some(dir);
 
         self.widget.change_to(move |stale, core| {
@@ -349,6 +448,7 @@ impl Previewer {
                 .with_cache(cache)
                 .with_stale(stale.clone())
                 .select(selected_file)
+                .show_hidden(show_hidden)
                 .build()?;
 
             Ok(PreviewWidget::FileList(list))
@@ -357,6 +457,9 @@ impl Previewer {
 
     pub fn set_file(&mut self,
                     file: &File) -> HResult<()> {
+        if self.pinned.as_ref().map(|pinned| pinned != file).unwrap_or(false) {
+            return Ok(())
+        }
         if Some(file) == self.file.as_ref() && !self.widget.is_stale()? { return Ok(()) }
         self.widget.set_stale().ok();
 
@@ -371,6 +474,8 @@ impl Previewer {
         let core = self.core.clone();
         let cache = self.cache.clone();
         let animator = self.animator.clone();
+        let delay = self.core.config().preview_delay_ms;
+        let show_hidden = self.show_hidden();
 
         if same_dir {
             self.animator.set_fresh().ok();
@@ -390,15 +495,43 @@ impl Previewer {
                         .log();
                 }
 
+                if !Previewer::wait_for_rest(delay, &stale)? {
+                    return Previewer::preview_failed(&file);
+                }
+
                 if file.kind == Kind::Directory  {
                     let preview = Previewer::preview_dir(&file,
                                                          cache,
+                                                         show_hidden,
                                                          &core,
                                                          &stale,
                                                          &animator);
                     return Ok(preview?);
                 }
 
+                // Never read from pipes, devices, etc. show what they are instead
+                if file.file_type() != FileType::Regular {
+                    let mut info = TextView::new_blank(&core);
+                    info.set_coordinates(&coordinates).log();
+                    info.set_lines(crate::file_info::file_info(&file, &core.config())?)?;
+                    info.refresh().log();
+                    info.animate_slide_up(Some(&animator)).log();
+                    return Ok(PreviewWidget::TextView(info));
+                }
+
+                // Filters win over everything else, but when one fails the
+                // file is previewed like it would be without it
+                if let Some(cmd) = core.config().preview_filters.find(&file) {
+                    let preview = Previewer::preview_filter(&file,
+                                                            &cmd,
+                                                            &core,
+                                                            &stale,
+                                                            &animator);
+                    if preview.is_ok() {
+                        return Ok(preview?);
+                    }
+                }
+
                 if let Some(mime) = file.get_mime()
                                         .log_and()
                                         .ok()
@@ -436,13 +569,37 @@ impl Previewer {
                                                               &animator)?);
                         }
                         _ => {
+                            // Archives can still be listed if the scripts fail
+                            let archive = crate::archive::list_cmd(&mime, &file.path)
+                                .is_ok();
+
                             let preview = Previewer::preview_external(&file,
                                                                       &core,
                                                                       &stale,
-                                                                      &animator);
+                                                                      &animator,
+                                                                      !archive);
                             if preview.is_ok() {
                                 return Ok(preview?);
                             }
+
+                            if archive {
+                                let preview = Previewer::preview_archive(&file,
+                                                                         &mime,
+                                                                         &core,
+                                                                         &stale,
+                                                                         &animator);
+                                if preview.is_ok() {
+                                    return Ok(preview?);
+                                }
+                            }
+
+                            // Source files without a previewer script
+                            if builtin_highlight(&file, &core) {
+                                return Ok(Previewer::preview_text(&file,
+                                                                  &core,
+                                                                  &stale,
+                                                                  &animator)?);
+                            }
                         }
                     }
                 }
@@ -462,6 +619,14 @@ impl Previewer {
         }
     }
 
+    // Top line of a text preview, to open it there in the editor
+    pub fn text_line(&self) -> Option<usize> {
+        match self.widget.widget() {
+            Ok(PreviewWidget::TextView(textview)) => Some(textview.top_line()),
+            _ => None
+        }
+    }
+
     pub fn reload_text(&mut self) {
         match self.widget.widget_mut() {
             Ok(PreviewWidget::TextView(w)) => w.load_full(),
@@ -469,6 +634,46 @@ impl Previewer {
         }
     }
 
+    // Returns false when there is no text to scroll, so the key can be
+    // used for something else
+    pub fn scroll_text(&mut self, action: &PreviewAction) -> bool {
+        use PreviewAction::*;
+
+        let textview = match self.widget.widget_mut() {
+            Ok(PreviewWidget::TextView(textview)) => textview,
+            _ => return false
+        };
+
+        // Previews only load as much as fits on screen at first
+        textview.load_full();
+
+        match action {
+            ScrollDown => textview.scroll_down(),
+            ScrollUp => textview.scroll_up(),
+            PageDown => textview.page_down(),
+            PageUp => textview.page_up(),
+            Top => textview.scroll_top(),
+            Bottom => textview.scroll_bottom()
+        }
+
+        true
+    }
+
+
+    // Moving on to another file makes the preview stale, so when scrolling
+    // quickly nothing is started for the files in between
+    fn wait_for_rest(delay: u64, stale: &Stale) -> HResult<bool> {
+        let step = Duration::from_millis(10);
+        let mut waited = Duration::from_millis(0);
+
+        while waited < Duration::from_millis(delay) {
+            if stale.is_stale()? { return Ok(false) }
+            std::thread::sleep(step);
+            waited += step;
+        }
+
+        Ok(!stale.is_stale()?)
+    }
 
     fn preview_failed<T>(file: &File) -> HResult<T> {
         HError::preview_failed(file)
@@ -476,6 +681,7 @@ impl Previewer {
 
     fn preview_dir(file: &File,
                    cache: FsCache,
+                   show_hidden: bool,
                    core: &WidgetCore,
                    stale: &Stale,
                    animator: &Stale)
@@ -483,11 +689,24 @@ impl Previewer {
         use crate::dirty::Dirtyable;
 
         if stale.is_stale()? { return Previewer::preview_failed(&file) }
+
+        // Say why instead of showing nothing
+        if let Err(err) = std::fs::read_dir(&file.path) {
+            let mut info = TextView::new_blank(&core);
+            info.set_coordinates(&core.coordinates).log();
+            info.set_lines(vec![format!("{}{}",
+                                        crate::term::color_red(),
+                                        err)])?;
+            info.animate_slide_up(Some(animator)).log();
+            return Ok(PreviewWidget::TextView(info));
+        }
+
         let source = FileSource::Path(file.clone());
 
         let mut file_list = ListView::builder(core.clone(), source)
             .with_cache(cache)
             .with_stale(stale.clone())
+            .show_hidden(show_hidden)
             .build()?;
 
         if stale.is_stale()? { return Previewer::preview_failed(&file) }
@@ -516,6 +735,14 @@ impl Previewer {
                                                   lines)?;
         if stale.is_stale()? { return Previewer::preview_failed(&file) }
 
+        // Stays plain text if the syntax isn't known
+        #[cfg(feature = "syntax")]
+        {
+            if core.config().syntax_highlight {
+                textview.highlight().ok();
+            }
+        }
+
         textview.set_coordinates(&core.coordinates)?;
         textview.refresh()?;
 
@@ -527,22 +754,48 @@ impl Previewer {
         Ok(PreviewWidget::TextView(textview))
     }
 
-    fn run_external(cmd: PathBuf, file: &File, stale: &Stale) -> HResult<Vec<String>> {
+    // Previewers get the file, the size of the preview in cells and the
+    // same size in pixels, which is 0 if the terminal doesn't report it.
+    // Returns whether the previewer exited successfully and its output.
+    fn run_external(cmd: PathBuf,
+                    file: &File,
+                    core: &WidgetCore,
+                    stale: &Stale,
+                    timeout: u64) -> HResult<(bool, Vec<String>)> {
+        let (xsize, ysize) = core.coordinates.size_u();
+        let (xpix, ypix) = core.coordinates
+                               .size_pixels()
+                               .unwrap_or((0, 0));
+
+        let mut cmd = std::process::Command::new(cmd);
+        cmd.arg(&file.path)
+           .arg(format!("{}", (xsize+1)))
+           .arg(format!("{}", (ysize+1)))
+           .arg(format!("{}", xpix))
+           .arg(format!("{}", ypix));
+
+        Previewer::run_cmd(cmd, file, stale, timeout)
+    }
+
+    // Only one runs at a time, the next preview kills it. It's also killed
+    // after timeout seconds, 0 means no limit.
+    fn run_cmd(mut cmd: std::process::Command,
+               file: &File,
+               stale: &Stale,
+               timeout: u64) -> HResult<(bool, Vec<String>)> {
         use std::os::unix::process::CommandExt;
 
         let process = unsafe {
-            std::process::Command::new(cmd)
-                .arg(&file.path)
-                .stdin(std::process::Stdio::null())
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::null())
-                .pre_exec(|| {
-                    let pid = std::process::id();
-                    // To make killing subprocess possible create new process group
-                    libc::setpgid(pid as i32, pid as i32);
-                    Ok(())
-                })
-                .spawn()?
+            cmd.stdin(std::process::Stdio::null())
+               .stdout(std::process::Stdio::piped())
+               .stderr(std::process::Stdio::null())
+               .pre_exec(|| {
+                   let pid = std::process::id();
+                   // To make killing subprocess possible create new process group
+                   libc::setpgid(pid as i32, pid as i32);
+                   Ok(())
+               })
+               .spawn()?
         };
 
         let pid = process.id();
@@ -551,10 +804,32 @@ impl Previewer {
             *pid_ = Some(pid);
         }
 
+        // Kill the previewer if it doesn't finish in time, 0 means no limit
+        let (finished, watchdog) = std::sync::mpsc::channel::<()>();
+        let timed_out = Arc::new(AtomicBool::new(false));
+        if timeout > 0 {
+            let timed_out = timed_out.clone();
+            std::thread::spawn(move || {
+                use std::sync::mpsc::RecvTimeoutError;
+
+                let timeout = Duration::from_secs(timeout);
+                if let Err(RecvTimeoutError::Timeout) = watchdog.recv_timeout(timeout) {
+                    timed_out.store(true, Ordering::Relaxed);
+                    kill_group(pid);
+                }
+            });
+        }
+
         if stale.is_stale()? { return Previewer::preview_failed(&file) }
         let output = process.wait_with_output()?;
+        finished.send(()).ok();
         if stale.is_stale()? { return Previewer::preview_failed(&file) }
 
+        if timed_out.load(Ordering::Relaxed) {
+            *SUBPROC.lock() = None;
+            return Err(HError::PreviewerTimedOut(file.name.clone()));
+        }
+
         {
             let mut pid_ = SUBPROC.lock();
             *pid_ = None;
@@ -562,54 +837,148 @@ impl Previewer {
 
 
 
-        //let status = output.status.code()?;
+        let success = output.status.success();
 
         let output = std::str::from_utf8(&output.stdout)?
             .to_string()
             .lines().map(|s| s.to_string())
             .collect();
 
-        Ok(output)
+        Ok((success, output))
     }
 
+    // With show_failed the output of the last previewer is shown even if
+    // it failed, since there's nothing else to try
     fn preview_external(file: &File,
                         core: &WidgetCore,
                         stale: &Stale,
-                        animator: &Stale)
+                        animator: &Stale,
+                        show_failed: bool)
                         -> HResult<PreviewWidget> {
         // Show animation while preview is being generated
         let mut ticker = Ticker::start_ticking(core.get_sender());
 
-        let previewer = if core.config().graphics.as_str() != "unicode" {
-             find_previewer(&file, true)?
-        } else {
-            find_previewer(&file, false)?
-        };
+        let g_mode = core.config().graphics.as_str() != "unicode";
+        let builtin_text = builtin_highlight(&file, &core);
+        let previewers = find_previewers(&file, g_mode, builtin_text)?;
+        let last = previewers.len() - 1;
+        let timeout = core.config().previewer_timeout;
+
+        // Try each previewer until one exits successfully with some output
+        for (i, previewer) in previewers.into_iter().enumerate() {
+            if stale.is_stale()? { return Previewer::preview_failed(&file) }
+
+            let (previewer, mut graphics) = match previewer {
+                ExtPreviewer::Text(previewer) => (previewer, false),
+                ExtPreviewer::Graphics(previewer) => (previewer, true)
+            };
+
+            let lines = match Previewer::run_external(previewer,
+                                                      file,
+                                                      core,
+                                                      stale,
+                                                      timeout) {
+                Ok((true, lines)) if lines.len() > 0 => lines,
+                // Nothing left to fall back to, so show whatever there is
+                Ok((_, lines)) if i == last && !graphics && show_failed => lines,
+                Err(HError::PreviewerTimedOut(_)) if i == last => {
+                    graphics = false;
+                    vec![String::from("previewer timed out")]
+                }
+                _ => continue
+            };
 
-        match previewer {
-            ExtPreviewer::Text(previewer) => {
-                if stale.is_stale()? { return Previewer::preview_failed(&file) }
-                let lines = Previewer::run_external(previewer, file, stale)?;
-                if stale.is_stale()? { return Previewer::preview_failed(&file) }
-
-                let mut textview = TextView::new_blank(&core);
-                textview.set_lines(lines)?;
-                textview.set_coordinates(&core.coordinates).log();
-                textview.refresh().log();
-                // Prevent flicker during slide up
-                ticker.stop_ticking();
-                textview.animate_slide_up(Some(animator)).log();
-
-                Ok(PreviewWidget::TextView(textview))
-            },
-            ExtPreviewer::Graphics(previewer) => {
-                let lines = Previewer::run_external(previewer, file, stale)?;
+            if stale.is_stale()? { return Previewer::preview_failed(&file) }
+
+            if graphics {
                 let gfile = lines.first()?;
-                let imgview = ImgView::new_from_file(core.clone(),
-                                                     &PathBuf::from(&gfile))?;
-                Ok(PreviewWidget::ImgView(imgview))
+                match ImgView::new_from_previewer(core.clone(), &PathBuf::from(&gfile)) {
+                    Ok(imgview) => return Ok(PreviewWidget::ImgView(imgview)),
+                    Err(err) if i == last => return Err(err),
+                    Err(_) => continue
+                }
             }
+
+            let mut textview = TextView::new_blank(&core);
+            textview.set_lines(lines)?;
+            textview.set_coordinates(&core.coordinates).log();
+            textview.refresh().log();
+            // Prevent flicker during slide up
+            ticker.stop_ticking();
+            textview.animate_slide_up(Some(animator)).log();
+
+            return Ok(PreviewWidget::TextView(textview));
         }
+
+        Previewer::preview_failed(&file)
+    }
+
+    // Runs through sh with the file as $1, like openers
+    fn preview_filter(file: &File,
+                      cmd: &str,
+                      core: &WidgetCore,
+                      stale: &Stale,
+                      animator: &Stale)
+                      -> HResult<PreviewWidget> {
+        // Show animation while the filter runs
+        let mut ticker = Ticker::start_ticking(core.get_sender());
+
+        let mut sh = std::process::Command::new("sh");
+        sh.arg("-c")
+          .arg(format!("{} \"$1\"", cmd))
+          .arg("hunter")
+          .arg(&file.path);
+
+        let timeout = core.config().previewer_timeout;
+        let lines = match Previewer::run_cmd(sh, file, stale, timeout)? {
+            (true, lines) if lines.len() > 0 => lines,
+            _ => return Previewer::preview_failed(&file)
+        };
+
+        if stale.is_stale()? { return Previewer::preview_failed(&file) }
+
+        let mut textview = TextView::new_blank(&core);
+        textview.set_lines(lines)?;
+        textview.set_coordinates(&core.coordinates).log();
+        textview.refresh().log();
+        // Prevent flicker during slide up
+        ticker.stop_ticking();
+        textview.animate_slide_up(Some(animator)).log();
+
+        Ok(PreviewWidget::TextView(textview))
+    }
+
+    fn preview_archive(file: &File,
+                       mime: &mime::Mime,
+                       core: &WidgetCore,
+                       stale: &Stale,
+                       animator: &Stale)
+                       -> HResult<PreviewWidget> {
+        let mut args = crate::archive::list_cmd(mime, &file.path)?;
+
+        // Show animation while the archive is read
+        let mut ticker = Ticker::start_ticking(core.get_sender());
+
+        let mut cmd = std::process::Command::new(args.remove(0));
+        cmd.args(args);
+
+        let timeout = core.config().previewer_timeout;
+        let lines = match Previewer::run_cmd(cmd, file, stale, timeout)? {
+            (true, lines) => lines,
+            _ => return Previewer::preview_failed(&file)
+        };
+
+        if stale.is_stale()? { return Previewer::preview_failed(&file) }
+
+        let mut textview = TextView::new_blank(&core);
+        textview.set_lines(lines)?;
+        textview.set_coordinates(&core.coordinates).log();
+        textview.refresh().log();
+        // Prevent flicker during slide up
+        ticker.stop_ticking();
+        textview.animate_slide_up(Some(animator)).log();
+
+        Ok(PreviewWidget::TextView(textview))
     }
 }
 
@@ -624,9 +993,10 @@ impl Widget for Previewer {
     fn config_loaded(&mut self) -> HResult<()> {
         use PreviewWidget::*;
 
-        let show_hidden = self.core
-                              .config()
-                              .show_hidden();
+        // Previewers might have been added/removed
+        clear_previewer_cache();
+
+        let show_hidden = self.show_hidden();
 
         match self.widget.widget_mut() {
             Ok(FileList(filelist)) => {
@@ -2,15 +2,18 @@ use std::sync::Arc;
 use std::sync::mpsc::Sender;
 use std::process::{Child, Command};
 use std::os::unix::process::{CommandExt, ExitStatusExt};
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Write};
 use std::ffi::OsString;
 use std::os::unix::ffi::OsStrExt;
+use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use termion::event::Key;
-use unicode_width::UnicodeWidthStr;
 use osstrtools::{OsStringTools, OsStrTools, OsStrConcat};
 use async_value::Stale;
 use parking_lot::Mutex;
+use chrono::{DateTime, Local};
+use strip_ansi_escapes::strip;
 
 use crate::listview::{Listable, ListView};
 use crate::textview::TextView;
@@ -20,18 +23,70 @@ use crate::preview::AsyncWidget;
 use crate::dirty::Dirtyable;
 use crate::hbox::HBox;
 use crate::fail::{HResult, HError, ErrorLog};
-use crate::term::{self, ScreenExt};
-use crate::files::File;
+use crate::term::{self, Screen, ScreenExt};
+use crate::files::{File, SizeFormat};
+use crate::file_ops::{FileJob, FileOp};
+
+// File operations run in a thread, but are shown like processes
+#[derive(Debug)]
+enum Handle {
+    Child(Child),
+    Job(Arc<AtomicBool>)
+}
+
+impl Handle {
+    fn child(&mut self) -> Option<&mut Child> {
+        match self {
+            Handle::Child(child) => Some(child),
+            Handle::Job(_) => None
+        }
+    }
+
+    fn id(&self) -> String {
+        match self {
+            Handle::Child(child) => child.id().to_string(),
+            Handle::Job(_) => String::from("job")
+        }
+    }
+
+    fn kill(&mut self) -> HResult<()> {
+        match self {
+            Handle::Child(child) => child.kill()?,
+            Handle::Job(cancel) => cancel.store(true, Ordering::Relaxed)
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug)]
 struct Process {
     cmd: String,
-    handle: Arc<Mutex<Child>>,
+    handle: Arc<Mutex<Handle>>,
+    progress: Option<(FileOp, Arc<Mutex<(u64, u64)>>)>,
     output: Arc<Mutex<String>>,
     status: Arc<Mutex<Option<i32>>>,
     success: Arc<Mutex<Option<bool>>>,
-    sender: Sender<Events>
+    sender: Sender<Events>,
+    started: DateTime<Local>,
+    start: Instant,
+    runtime: Arc<Mutex<Option<Duration>>>,
+    // Shown with colors even when they're turned off, like diffs
+    keep_colors: bool
+}
+
+#[derive(Debug, PartialEq)]
+struct ProcList {
+    procs: Vec<Process>,
+    detailed: bool
+}
 
+impl ProcList {
+    fn new() -> ProcList {
+        ProcList {
+            procs: vec![],
+            detailed: false
+        }
+    }
 }
 
 pub struct Cmd {
@@ -126,41 +181,175 @@ impl PartialEq for Process {
     }
 }
 
+fn pretty_progress(op: FileOp,
+                   done: u64,
+                   total: u64,
+                   size_format: SizeFormat) -> String {
+    let percent = match total {
+        0 => 0,
+        _ => done * 100 / total
+    };
+
+    match op {
+        FileOp::Delete => format!("{}% ({} / {} files)", percent, done, total),
+        _ => format!("{}% ({} / {})",
+                     percent,
+                     size_format.format(done),
+                     size_format.format(total))
+    }
+}
+
+// How to draw attention to a process that failed
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AlertOnFailure {
+    Off,
+    Bell,
+    Flash,
+    On
+}
+
+impl AlertOnFailure {
+    fn bell(self) -> bool {
+        self == AlertOnFailure::Bell || self == AlertOnFailure::On
+    }
+
+    fn flash(self) -> bool {
+        self == AlertOnFailure::Flash || self == AlertOnFailure::On
+    }
+}
+
+// Ring the bell and/or flash the status bar to draw attention to a failure.
+// The flash is ended from another thread by showing the status again, so
+// the caller never has to wait for it.
+fn alert_failure(screen: &mut Screen,
+                 sender: &Sender<Events>,
+                 status: &str,
+                 alert: AlertOnFailure) -> HResult<()> {
+    if alert.bell() {
+        screen.write_str(&term::bell())?;
+        screen.flush()?;
+    }
+
+    if alert.flash() {
+        let plain = String::from_utf8_lossy(&strip(status)?).to_string();
+        sender.send(Events::Status(format!("{}{}", term::alert_color(), plain)))?;
+
+        let sender = sender.clone();
+        let status = status.to_string();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(300));
+            sender.send(Events::Status(status)).ok();
+        });
+    }
+
+    Ok(())
+}
+
 impl Process {
-    fn read_proc(&mut self) -> HResult<()> {
+    fn percent(&self) -> Option<u64> {
+        let (done, total) = *self.progress.as_ref()?.1.lock();
+        match total {
+            0 => Some(0),
+            _ => Some(done * 100 / total)
+        }
+    }
+
+    fn progress_string(&self, size_format: SizeFormat) -> Option<String> {
+        let (op, progress) = self.progress.as_ref()?;
+        let (done, total) = *progress.lock();
+        Some(pretty_progress(*op, done, total, size_format))
+    }
+
+    fn elapsed(&self) -> Duration {
+        match *self.runtime.lock() {
+            Some(runtime) => runtime,
+            None => self.start.elapsed()
+        }
+    }
+
+    fn pretty_elapsed(&self) -> String {
+        let secs = self.elapsed().as_secs();
+        format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+    }
+
+    fn truncate_output(output: &mut String, limit: usize) {
+        if limit == 0 || output.len() <= limit { return }
+
+        // Drop whole lines from the front if possible
+        let mut start = output.len() - limit;
+        while !output.is_char_boundary(start) { start += 1; }
+        let start = output[start..]
+            .find('\n')
+            .map(|pos| start + pos + 1)
+            .unwrap_or(start);
+
+        output.drain(..start);
+    }
+
+    fn read_proc(&mut self, core: &WidgetCore) -> HResult<()> {
+        let config = core.config();
+        let max_delay = config.proc_read_delay;
+        let output_limit = config.proc_output_limit;
+        let alert = config.alert_on_failure;
+        let mut screen = core.screen.clone();
         let handle = self.handle.clone();
         let output = self.output.clone();
         let status = self.status.clone();
         let success = self.success.clone();
+        let runtime = self.runtime.clone();
+        let start = self.start;
         let sender = self.sender.clone();
         let cmd = self.cmd.clone();
         let pid = self.handle.lock().id();
 
         std::thread::spawn(move || -> HResult<()> {
-            let stdout = handle.lock().stdout.take()?;
+            let stdout = handle.lock().child()?.stdout.take()?;
             let mut stdout = BufReader::new(stdout);
+            let max_delay = Duration::from_millis(max_delay);
             let mut processor = move |cmd, sender: &Sender<Events>| -> HResult<()> {
+                let mut delay = Duration::from_millis(0);
+                let mut last_status = Instant::now();
+                let mut unreported = 0;
+
                 loop {
+                    let capacity = stdout.capacity();
                     let buffer = stdout.fill_buf()?;
                     let len = buffer.len();
+                    let full = len == capacity;
                     let buffer = String::from_utf8_lossy(buffer);
 
                     if len == 0 { return Ok(()) }
 
-                    output.lock().push_str(&buffer);
-
-                    let status = format!("{}: read {} chars!", cmd, len);
-                    sender.send(Events::Status(status))?;
+                    let mut output = output.lock();
+                    output.push_str(&buffer);
+                    Process::truncate_output(&mut output, output_limit);
+                    drop(output);
 
                     stdout.consume(len);
-
-                    // Wait a bit so hunter doesn't explode
-                    std::thread::sleep(std::time::Duration::from_millis(100));
+                    unreported += len;
+
+                    // Don't flood hunter with status updates
+                    if last_status.elapsed() >= max_delay {
+                        let status = format!("{}: read {} chars!", cmd, unreported);
+                        sender.send(Events::Status(status))?;
+                        last_status = Instant::now();
+                        unreported = 0;
+                    }
+
+                    // Keep reading while the buffer fills up, otherwise back
+                    // off a bit so more output can pile up before waking up
+                    if full {
+                        delay = Duration::from_millis(0);
+                    } else {
+                        delay = std::cmp::min(delay * 2 + Duration::from_millis(10),
+                                              max_delay);
+                        std::thread::sleep(delay);
+                    }
                 }
             };
             processor(&cmd, &sender).log();
 
-            if let Ok(proc_status) = handle.lock().wait() {
+            if let Ok(proc_status) = handle.lock().child()?.wait() {
                 let proc_success = proc_status.success();
                 let proc_status = match proc_status.code() {
                     Some(status) => status,
@@ -169,6 +358,7 @@ impl Process {
 
                 *success.lock() = Some(proc_success);
                 *status.lock() = Some(proc_status);
+                *runtime.lock() = Some(start.elapsed());
 
                 let color_success =
                     if proc_success {
@@ -190,7 +380,12 @@ impl Process {
                                      color_success,
                                      term::normal_color(),
                                      color_status);
-                sender.send(Events::Status(status))?;
+
+                sender.send(Events::Status(status.clone()))?;
+
+                if !proc_success {
+                    alert_failure(&mut screen, &sender, &status, alert).log();
+                }
             }
             Ok(())
         });
@@ -199,11 +394,11 @@ impl Process {
     }
 }
 
-impl Listable for ListView<Vec<Process>> {
+impl Listable for ListView<ProcList> {
     type Item = ();
-    fn len(&self) -> usize { self.content.len() }
+    fn len(&self) -> usize { self.content.procs.len() }
     fn render(&self) -> Vec<String> {
-        self.content.iter().map(|proc| {
+        self.content.procs.iter().map(|proc| {
             self.render_proc(proc).unwrap()
         }).collect()
     }
@@ -213,7 +408,7 @@ impl Listable for ListView<Vec<Process>> {
     }
 }
 
-impl ListView<Vec<Process>> {
+impl ListView<ProcList> {
     fn run_proc_subshell(&mut self, mut cmd: Cmd) -> HResult<()> {
         let shell = std::env::var("SHELL").unwrap_or("sh".into());
         let home = crate::paths::home_path()?.into_os_string();
@@ -290,14 +485,91 @@ impl ListView<Vec<Process>> {
 
         let mut proc = Process {
             cmd: short_cmd,
-            handle: Arc::new(Mutex::new(handle)),
+            handle: Arc::new(Mutex::new(Handle::Child(handle))),
+            progress: None,
             output: Arc::new(Mutex::new(String::new())),
             status: Arc::new(Mutex::new(None)),
             success: Arc::new(Mutex::new(None)),
-            sender: self.get_core()?.get_sender()
+            sender: self.get_core()?.get_sender(),
+            started: Local::now(),
+            start: Instant::now(),
+            runtime: Arc::new(Mutex::new(None)),
+            keep_colors: false
+        };
+        proc.read_proc(&self.core)?;
+        self.content.procs.push(proc);
+        Ok(())
+    }
+
+    fn run_file_job(&mut self, job: FileJob) -> HResult<()> {
+        let config = self.core.config();
+        let max_delay = Duration::from_millis(config.proc_read_delay);
+        let alert = config.alert_on_failure;
+        let size_format = config.size_format();
+        let mut screen = self.core.screen.clone();
+        let sender = self.get_core()?.get_sender();
+        let cmd = job.describe();
+        let op = job.op;
+
+        self.core.show_status(&format!("Running: {}", &cmd)).log();
+
+        let proc = Process {
+            cmd: cmd.clone(),
+            handle: Arc::new(Mutex::new(Handle::Job(job.cancel.clone()))),
+            progress: Some((op, job.progress.clone())),
+            output: job.output.clone(),
+            status: Arc::new(Mutex::new(None)),
+            success: Arc::new(Mutex::new(None)),
+            sender: sender.clone(),
+            started: Local::now(),
+            start: Instant::now(),
+            runtime: Arc::new(Mutex::new(None)),
+            keep_colors: false
         };
-        proc.read_proc()?;
-        self.content.push(proc);
+
+        let status = proc.status.clone();
+        let success = proc.success.clone();
+        let runtime = proc.runtime.clone();
+        let start = proc.start;
+
+        std::thread::spawn(move || -> HResult<()> {
+            let mut last_status = Instant::now();
+            let job_success = job.run(&mut |done, total| {
+                // Don't flood hunter with status updates
+                if last_status.elapsed() >= max_delay {
+                    let progress = pretty_progress(op, done, total, size_format);
+                    let status = format!("{}: {}", cmd, progress);
+                    sender.send(Events::Status(status)).ok();
+                    last_status = Instant::now();
+                }
+            });
+
+            *success.lock() = Some(job_success);
+            *status.lock() = Some(if job_success { 0 } else { 1 });
+            *runtime.lock() = Some(start.elapsed());
+
+            let color_success =
+                if job_success {
+                    format!("{}successfully", term::color_green())
+                } else {
+                    format!("{}unsuccessfully", term::color_red())
+                };
+
+            let status = format!("Job: {} finished {}{}",
+                                 cmd,
+                                 color_success,
+                                 term::normal_color());
+
+            sender.send(Events::Status(status.clone()))?;
+
+            if !job_success {
+                alert_failure(&mut screen, &sender, &status, alert).log();
+            }
+
+            Ok(())
+        });
+
+        self.content.procs.push(proc);
         Ok(())
     }
 
@@ -351,6 +623,15 @@ impl ListView<Vec<Process>> {
                                            color_status);
 
                     self.core.show_status(&procinfo)?;
+
+                    if !status.success() {
+                        let alert = self.core.config().alert_on_failure;
+                        let mut screen = self.core.screen.clone();
+                        alert_failure(&mut screen,
+                                      &self.core.get_sender(),
+                                      &procinfo,
+                                      alert).log();
+                    }
                 },
                 err @ Err(_) => {
                     self.core.show_status(&format!("{}{} ",
@@ -371,27 +652,39 @@ impl ListView<Vec<Process>> {
     fn remove_proc(&mut self) -> HResult<()> {
         self.kill_proc().ok();
         let selection = self.get_selection();
-        self.content.remove(selection);
+        self.content.procs.remove(selection);
         Ok(())
     }
 
     fn selected_proc(&mut self) -> Option<&mut Process> {
         let selection = self.get_selection();
-        self.content.get_mut(selection)
+        self.content.procs.get_mut(selection)
+    }
+
+    fn toggle_details(&mut self) {
+        self.content.detailed = !self.content.detailed;
+        self.core.set_dirty();
     }
 
     pub fn render_proc(&self, proc: &Process) -> HResult<String> {
         let pid = proc.handle.lock().id();
-        let status = match *proc.status.lock() {
-            Some(status) => format!("{}", status),
-            None => format!("<{}>", pid),
+        let status = match (*proc.status.lock(), proc.percent()) {
+            (Some(status), _) => format!("{}", status),
+            (None, Some(percent)) => format!("<{}%>", percent),
+            (None, None) => format!("<{}>", pid),
         };
 
+        let cmd = if self.content.detailed {
+            format!("{} {} {}",
+                    proc.started.format("%T"),
+                    proc.pretty_elapsed(),
+                    proc.cmd)
+        } else { proc.cmd.clone() };
+
         let xsize = self.get_coordinates()?.xsize();
-        let sized_string = term::sized_string(&proc.cmd, xsize);
+        let sized_string = term::sized_string(&cmd, xsize);
         let status_pos = xsize - status.len() as u16;
-        let padding = sized_string.len() - sized_string.width_cjk();
-        let padding = xsize - padding as u16;
+        let padding = xsize - term::extra_width(sized_string) as u16;
 
         let color_status = match *proc.success.lock() {
             Some(false) => { format!("{}{}", term::color_red(), status) }
@@ -415,7 +708,7 @@ impl ListView<Vec<Process>> {
 
 #[derive(Debug, PartialEq)]
 enum ProcViewWidgets {
-    List(ListView<Vec<Process>>),
+    List(ListView<ProcList>),
     TextView(AsyncWidget<TextView>),
 }
 
@@ -450,18 +743,20 @@ impl Widget for ProcViewWidgets {
 pub struct ProcView {
     core: WidgetCore,
     hbox: HBox<ProcViewWidgets>,
-    viewing: Option<usize>,
+    // The selected process and how much of its output is shown
+    viewing: Option<(usize, usize)>,
+    strip_colors: bool,
     animator: Stale
 }
 
 impl HBox<ProcViewWidgets> {
-    fn get_listview(&self) -> &ListView<Vec<Process>> {
+    fn get_listview(&self) -> &ListView<ProcList> {
         match &self.widgets[0] {
             ProcViewWidgets::List(listview) => listview,
             _ => unreachable!()
         }
     }
-    fn get_listview_mut(&mut self) -> &mut ListView<Vec<Process>> {
+    fn get_listview_mut(&mut self) -> &mut ListView<ProcList> {
         match &mut self.widgets[0] {
             ProcViewWidgets::List(listview) => listview,
             _ => unreachable!()
@@ -478,7 +773,7 @@ impl HBox<ProcViewWidgets> {
 impl ProcView {
     pub fn new(core: &WidgetCore) -> ProcView {
         let tcore = core.clone();
-        let listview = ListView::new(&core, vec![]);
+        let listview = ListView::new(&core, ProcList::new());
         let textview = AsyncWidget::new(&core, move |_| {
             let textview = TextView::new_blank(&tcore);
             Ok(textview)
@@ -492,15 +787,16 @@ impl ProcView {
             core: core.clone(),
             hbox: hbox,
             viewing: None,
+            strip_colors: false,
             animator: Stale::new()
         }
     }
 
-    fn get_listview(& self) -> & ListView<Vec<Process>> {
+    fn get_listview(& self) -> & ListView<ProcList> {
         self.hbox.get_listview()
     }
 
-    fn get_listview_mut(&mut self) -> &mut ListView<Vec<Process>> {
+    fn get_listview_mut(&mut self) -> &mut ListView<ProcList> {
         self.hbox.get_listview_mut()
     }
 
@@ -518,8 +814,23 @@ impl ProcView {
         Ok(())
     }
 
+    pub fn run_file_job(&mut self, job: FileJob) -> HResult<()> {
+        self.get_listview_mut().run_file_job(job)?;
+        Ok(())
+    }
+
+    // Processes and file jobs that haven't finished yet
+    pub fn running_count(&self) -> usize {
+        self.get_listview()
+            .content
+            .procs
+            .iter()
+            .filter(|proc| proc.success.lock().is_none())
+            .count()
+    }
+
     pub fn remove_proc(&mut self) -> HResult<()> {
-        if self.get_listview_mut().content.len() == 0 { return Ok(()) }
+        if self.get_listview_mut().content.procs.len() == 0 { return Ok(()) }
         self.get_listview_mut().remove_proc()?;
         self.get_textview().get_core()?.clear().log();
         self.get_textview().widget_mut()?.set_text("").log();
@@ -527,11 +838,32 @@ impl ProcView {
         Ok(())
     }
 
+    // So the output of a process that was just started is shown
+    pub fn select_last(&mut self) {
+        self.get_listview_mut().move_bottom();
+    }
+
+    pub fn keep_colors_of_last(&mut self) {
+        if let Some(proc) = self.get_listview_mut().content.procs.last_mut() {
+            proc.keep_colors = true;
+        }
+    }
+
     fn show_output(&mut self) -> HResult<()> {
-        if Some(self.get_listview_mut().get_selection()) == self.viewing {
+        let selection = self.get_listview_mut().get_selection();
+        let output_len = self.get_listview_mut().selected_proc()?.output.lock().len();
+        let same_proc = self.viewing.map(|(viewing, _)| viewing) == Some(selection);
+
+        if self.viewing == Some((selection, output_len)) {
             return Ok(());
         }
-        let output = self.get_listview_mut().selected_proc()?.output.lock().clone();
+        let strip_colors = self.strip_colors;
+        let proc = self.get_listview_mut().selected_proc()?;
+        let output = proc.output.lock().clone();
+        let output = match strip_colors && !proc.keep_colors {
+            true => String::from_utf8_lossy(&strip(&output)?).to_string(),
+            false => output
+        };
 
         let animator = self.animator.clone();
         animator.set_fresh().log();
@@ -539,14 +871,64 @@ impl ProcView {
         self.get_textview().change_to(move |_, core| {
             let mut textview = TextView::new_blank(&core);
             textview.set_text(&output).log();
-            textview.animate_slide_up(Some(&animator)).log();
+            // New output of a running process shouldn't slide in again
+            if !same_proc {
+                textview.animate_slide_up(Some(&animator)).log();
+            }
             Ok(textview)
         }).log();
 
-        self.viewing = Some(self.get_listview_mut().get_selection());
+        self.viewing = Some((selection, output_len));
         Ok(())
     }
 
+    pub fn toggle_details(&mut self) -> HResult<()> {
+        self.get_listview_mut().toggle_details();
+
+        // Make room for the extra columns
+        if self.get_listview().content.detailed {
+            self.hbox.set_ratios(vec![66, 33]);
+        } else {
+            self.hbox.set_ratios(vec![33, 66]);
+        }
+
+        self.core.clear().log();
+        self.hbox.resize_children()
+    }
+
+    fn selected_output(&mut self) -> HResult<String> {
+        let output = self.get_listview_mut().selected_proc()?.output.lock().clone();
+        let output = String::from_utf8_lossy(&strip(&output)?).to_string();
+        Ok(output)
+    }
+
+    pub fn yank_output(&mut self) -> HResult<()> {
+        let output = self.selected_output()?;
+        crate::clipboard::copy(&self.core, &output)?;
+        self.core.show_status(&format!("Copied {} lines of output",
+                                       output.lines().count()))
+    }
+
+    pub fn save_output(&mut self) -> HResult<()> {
+        let output = self.selected_output()?;
+        let path = match self.core.minibuffer("save output to") {
+            Ok(path) => path,
+            Err(HError::MiniBufferEvent(_)) => return Ok(()),
+            Err(e) => Err(e)?
+        };
+
+        std::fs::write(&path, output)?;
+        self.core.show_status(&format!("Saved output to: {}", path))
+    }
+
+    pub fn toggle_colors(&mut self) -> HResult<()> {
+        self.strip_colors = !self.strip_colors;
+
+        // Force output to be shown again
+        self.viewing = None;
+        self.show_output()
+    }
+
     pub fn toggle_follow(&mut self) -> HResult<()> {
         self.get_textview().widget_mut()?.toggle_follow();
         Ok(())
@@ -601,6 +983,7 @@ impl Widget for ProcView {
         let procs_num = listview.len();
         let procs_running = listview
             .content
+            .procs
             .iter()
             .filter(|proc| proc.status.lock().is_none())
             .count();
@@ -616,7 +999,7 @@ impl Widget for ProcView {
         let selection = listview.get_selection();
         let xsize = self.core.coordinates.xsize_u();
 
-        if let Some(proc) = listview.content.get(selection) {
+        if let Some(proc) = listview.content.procs.get(selection) {
             let cmd = &proc.cmd;
             let pid = proc.handle.lock().id();
             let proc_status = proc.status.lock();
@@ -647,7 +1030,22 @@ impl Widget for ProcView {
                                      term::status_bg(),
                                      color_status);
                 procinfo
-            } else { "still running".to_string() };
+            } else {
+                proc.progress_string(self.core.config().size_format())
+                    .unwrap_or_else(|| "still running".to_string())
+            };
+
+            // Full command line, pid and timing information
+            let procinfo = if listview.content.detailed {
+                let procinfo = match proc_status.is_some() {
+                    true => procinfo,
+                    false => format!("{}:{} still running", cmd, pid)
+                };
+                format!("started: {} elapsed: {} {}",
+                        proc.started.format("%F %T"),
+                        proc.pretty_elapsed(),
+                        procinfo)
+            } else { procinfo };
 
             let footer = term::sized_string_u(&procinfo, xsize);
 
@@ -696,6 +1094,11 @@ impl Acting for ProcView {
     fn do_action(&mut self, action: &Self::Action) -> HResult<()> {
         use ProcessAction::*;
 
+        if action.is_mutating() && self.core.config().read_only {
+            return self.core.show_status(&format!("{} is disabled in read-only mode",
+                                                  action));
+        }
+
         match action {
             Close => { self.animator.set_stale().log();
                        self.core.clear().log();
@@ -708,7 +1111,11 @@ impl Acting for ProcView {
             ScrollOutputPageDown => self.page_down()?,
             ScrollOutputPageUp => self.page_up()?,
             ScrollOutputBottom => self.scroll_bottom()?,
-            ScrollOutputTop => self.scroll_top()?
+            ScrollOutputTop => self.scroll_top()?,
+            ToggleDetails => self.toggle_details()?,
+            ToggleColors => self.toggle_colors()?,
+            YankOutput => self.yank_output()?,
+            SaveOutput => self.save_output()?
         }
 
         Ok(())
@@ -716,7 +1123,7 @@ impl Acting for ProcView {
 }
 
 
-impl Acting for ListView<Vec<Process>> {
+impl Acting for ListView<ProcList> {
     type Action=ProcessAction;
 
     fn search_in(&self) -> Bindings<Self::Action> {
@@ -4,11 +4,13 @@ use parking_lot::Mutex;
 
 use async_value::Async;
 
-use std::path::PathBuf;
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc,
                 mpsc::Sender};
 use std::ffi::OsString;
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 
 use crate::fail::{HResult, HError, KeyBindError, ErrorLog};
@@ -21,6 +23,7 @@ use crate::paths;
 use crate::term;
 use crate::term::ScreenExt;
 use crate::keybind::{Bindings, Movement, QuickActionAction};
+use crate::desktop_entry::DesktopEntry;
 
 
 pub type QuickActionView = ListView<Vec<QuickActions>>;
@@ -212,8 +215,11 @@ impl QuickActions {
                subpath: &str,
                description: String,
                sender: Sender<Events>,
-               proc_view: Arc<Mutex<ProcView>>) -> HResult<QuickActions> {
-        let mut actions = files.get_actions(mime.clone(), subpath.to_string());
+               proc_view: Arc<Mutex<ProcView>>,
+               recent_first: bool) -> HResult<QuickActions> {
+        let mut actions = files.get_actions(mime.clone(),
+                                            subpath.to_string(),
+                                            recent_first);
 
         actions.on_ready(move |_,_| {
             sender.send(Events::WidgetReady).ok();
@@ -234,6 +240,31 @@ impl QuickActions {
             proc_view: proc_view
         })
     }
+
+    pub fn applications(files: Vec<File>,
+                        mime: mime::Mime,
+                        apps: Vec<DesktopEntry>,
+                        proc_view: Arc<Mutex<ProcView>>,
+                        recent_first: bool) -> QuickActions {
+        let mut actions = apps.into_iter()
+            .map(|app| QuickAction::from_app(app, mime.clone()))
+            .collect();
+
+        if recent_first {
+            sort_by_recency(&mut actions);
+        }
+
+        QuickActions {
+            description: String::from("Applications"),
+            files: files,
+            mime: mime,
+            content: None,
+            lines: 1,
+            folded: false,
+            actions: Async::new_with_value(actions),
+            proc_view: proc_view
+        }
+    }
 }
 
 pub fn open(files: Vec<File>,
@@ -242,6 +273,7 @@ pub fn open(files: Vec<File>,
            proc_view: Arc<Mutex<ProcView>>) -> HResult<()> {
     let mime  = files.common_mime()
         .unwrap_or_else(|| Mime::from_str("*/").unwrap());
+    let recent_first = core.config().recent_actions_first;
 
 
     let act = QuickActions::new(files.clone(),
@@ -249,7 +281,8 @@ pub fn open(files: Vec<File>,
                                 "",
                                 String::from("UniActions"),
                                 sender.clone(),
-                                proc_view.clone()).unwrap();
+                                proc_view.clone(),
+                                recent_first).unwrap();
 
     let mut action_view: QuickActionView = ListView::new(&core, vec![]);
     action_view.content = vec![act];
@@ -261,7 +294,8 @@ pub fn open(files: Vec<File>,
                                      subdir,
                                      String::from("BaseActions"),
                                      sender.clone(),
-                                     proc_view.clone());
+                                     proc_view.clone(),
+                                     recent_first);
 
     let subdir = &format!("{}/{}",
                           mime.type_().as_str(),
@@ -271,7 +305,8 @@ pub fn open(files: Vec<File>,
                                  subdir,
                                  String::from("SubActions"),
                                  sender,
-                                 proc_view);
+                                 proc_view,
+                                 recent_first);
 
     act_base.map(|act| action_view.content.push(act)).ok();
     act_sub.map(|act| action_view.content.push(act)).ok();
@@ -287,6 +322,68 @@ pub fn open(files: Vec<File>,
     }
 }
 
+// Returns false if there are no applications for the files
+pub fn open_with(files: Vec<File>,
+                 core: WidgetCore,
+                 proc_view: Arc<Mutex<ProcView>>) -> HResult<bool> {
+    let mime = match files.common_mime() {
+        Some(mime) => mime,
+        None => return Ok(false)
+    };
+    let essence = format!("{}/{}", mime.type_(), mime.subtype());
+    let apps = crate::desktop_entry::apps_for_mime(&essence)
+        .log_and()
+        .unwrap_or(vec![]);
+
+    if apps.len() == 0 {
+        return Ok(false);
+    }
+
+    let recent_first = core.config().recent_actions_first;
+    let apps = QuickActions::applications(files, mime, apps, proc_view, recent_first);
+    let mut action_view: QuickActionView = ListView::new(&core, vec![]);
+    action_view.content = vec![apps];
+
+    loop {
+        match action_view.popup() {
+            Err(HError::RefreshParent) => continue,
+            Err(HError::WidgetResizedError) => continue,
+            Err(HError::TerminalResizedError) => continue,
+            r @ _ => break r.map(|_| true)
+        }
+    }
+}
+
+// Runs whatever quick action or application was used last on the files
+pub fn run_last(files: Vec<File>,
+                core: WidgetCore,
+                proc_view: Arc<Mutex<ProcView>>) -> HResult<()> {
+    let last = with_usage(|usage| {
+        usage.iter()
+             .max_by_key(|action_use| action_use.last)
+             .map(|action_use| action_use.path.clone())
+    })?.ok_or_else(|| HError::Error(String::from("No quick action used yet")))?;
+
+    let mime = files.common_mime()
+        .unwrap_or_else(|| Mime::from_str("*/").unwrap());
+
+    // Applications are stored by the ID of their .desktop file
+    let action = match last.is_file() {
+        true => QuickAction::new(last, mime),
+        false => {
+            let id = last.to_string_lossy().to_string();
+            let app = crate::desktop_entry::load_entries()
+                .remove(&id)
+                .ok_or_else(|| HError::Error(format!("Can't find {}", id)))?;
+            QuickAction::from_app(app, mime)
+        }
+    };
+
+    action.run(files, &core, proc_view)?;
+    core.show_status(&format!("Ran {}", action.title)).log();
+    Ok(())
+}
+
 
 #[derive(Debug)]
 pub struct QuickActions {
@@ -340,7 +437,9 @@ pub struct QuickAction {
     title: String,
     queries: Vec<String>,
     sync: bool,
-    mime: mime::Mime
+    confirm: bool,
+    mime: mime::Mime,
+    app: Option<DesktopEntry>
 }
 
 impl QuickAction {
@@ -348,14 +447,79 @@ impl QuickAction {
         let title = path.get_title();
         let queries = path.get_queries();
         let sync = path.get_sync();
+        let confirm = path.get_confirm();
 
         QuickAction {
             path,
             title,
             queries,
             sync,
-            mime
+            confirm,
+            mime,
+            app: None
+        }
+    }
+
+    fn from_app(app: DesktopEntry, mime: mime::Mime) -> QuickAction {
+        QuickAction {
+            path: PathBuf::from(&app.id),
+            title: app.name.clone(),
+            queries: vec![],
+            sync: app.terminal,
+            confirm: false,
+            mime: mime,
+            app: Some(app)
+        }
+    }
+
+    fn run_app(&self,
+               app: &DesktopEntry,
+               files: Vec<File>,
+               core: &WidgetCore,
+               proc_view: Arc<Mutex<ProcView>>) -> HResult<()> {
+        let cwd = files.get(0)?.parent_as_file()?;
+        let paths = files.iter()
+            .map(|f| f.path.clone())
+            .collect::<Vec<PathBuf>>();
+
+        for mut args in app.commands(&paths) {
+            if args.len() == 0 { continue; }
+            let cmd = args.remove(0);
+
+            // Terminal apps need the screen for themselves
+            if app.terminal {
+                let mut screen = core.screen()?;
+                core.get_sender().send(Events::InputEnabled(false))?;
+                screen.suspend().log();
+
+                let status = std::process::Command::new(&cmd)
+                    .args(args)
+                    .current_dir(&cwd.path)
+                    .status();
+
+                screen.activate().log();
+                core.clear().log();
+                core.get_sender().send(Events::InputEnabled(true))?;
+                status?;
+            } else {
+                let cmd = crate::proclist::Cmd {
+                    cmd: cmd,
+                    args: Some(args),
+                    vars: None,
+                    short_cmd: Some(app.name.clone()),
+                    cwd: cwd.clone(),
+                    cwd_files: None,
+                    tab_files: None,
+                    tab_paths: None
+                };
+
+                proc_view
+                    .lock()
+                    .run_proc_raw(cmd)?;
+            }
         }
+
+        Ok(())
     }
 
     fn run(&self,
@@ -364,6 +528,11 @@ impl QuickAction {
            proc_view: Arc<Mutex<ProcView>>) -> HResult<()> {
         use crate::minibuffer::MiniBufferEvent::*;;
 
+        if let Some(app) = &self.app {
+            record_use(&self.path).log();
+            return self.run_app(app, files, core, proc_view);
+        }
+
         let answers = self.queries
             .iter()
             .fold(Ok(vec![]), |mut acc, query| {
@@ -392,6 +561,22 @@ impl QuickAction {
 
         let cwd = files.get(0)?.parent_as_file()?;
 
+        if self.confirm {
+            let targets = match files.len() {
+                1 => files[0].name.clone(),
+                n => format!("{} files", n)
+            };
+            let query = format!("Run {} on {}? (y/n) [n]",
+                                self.path.to_string_lossy(),
+                                targets);
+
+            if !confirm(core, &query)? {
+                return Ok(());
+            }
+        }
+
+        record_use(&self.path).log();
+
         let files: Vec<OsString> = files.iter()
             .map(|f| OsString::from(&f.path))
             .collect();
@@ -428,9 +613,135 @@ impl QuickAction {
 
 
 
+// How often and when each action was used, by the path of the script or
+// the ID of the application
+#[derive(Debug, Clone)]
+struct ActionUse {
+    path: PathBuf,
+    count: u64,
+    // Seconds since the epoch
+    last: u64
+}
+
+// Read from disk on first use, written back after every change
+lazy_static! {
+    static ref USAGE: Mutex<Option<Vec<ActionUse>>> = Mutex::new(None);
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+// One "count:last:path" per line, like the frecency file
+fn load_usage() -> HResult<Vec<ActionUse>> {
+    let content = std::fs::read_to_string(paths::action_usage_path()?)?;
+
+    let usage = content.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ":");
+            let count = parts.next()?.parse().ok()?;
+            let last = parts.next()?.parse().ok()?;
+            let path = PathBuf::from(parts.next()?);
+
+            Some(ActionUse {
+                path: path,
+                count: count,
+                last: last
+            })
+        })
+        .collect();
+
+    Ok(usage)
+}
+
+fn save_usage(usage: &[ActionUse]) -> HResult<()> {
+    let content = usage.iter()
+        .map(|action_use| format!("{}:{}:{}\n",
+                                  action_use.count,
+                                  action_use.last,
+                                  action_use.path.to_string_lossy()))
+        .collect::<String>();
+
+    std::fs::write(paths::action_usage_path()?, content)?;
+    Ok(())
+}
+
+fn with_usage<T>(fun: impl FnOnce(&mut Vec<ActionUse>) -> T) -> HResult<T> {
+    let mut usage = USAGE.lock();
+
+    if usage.is_none() {
+        *usage = Some(load_usage().unwrap_or_else(|_| vec![]));
+    }
+
+    Ok(fun(usage.as_mut()?))
+}
+
+fn record_use(path: &Path) -> HResult<()> {
+    let now = now();
+
+    with_usage(|usage| {
+        match usage.iter_mut().find(|action_use| action_use.path == path) {
+            Some(action_use) => {
+                action_use.count += 1;
+                action_use.last = now;
+            }
+            None => usage.push(ActionUse {
+                path: path.to_path_buf(),
+                count: 1,
+                last: now
+            })
+        }
+
+        save_usage(usage)
+    })?
+}
+
+// Most recently used first, actions never used keep their order
+fn sort_by_recency(actions: &mut Vec<QuickAction>) {
+    let last_used = with_usage(|usage| {
+        actions.iter()
+               .map(|action| {
+                   usage.iter()
+                        .find(|action_use| action_use.path == action.path)
+                        .map(|action_use| action_use.last)
+                        .unwrap_or(0)
+               })
+               .collect::<Vec<_>>()
+    });
+
+    if let Ok(last_used) = last_used {
+        let mut sorted = actions.drain(..)
+                                .zip(last_used)
+                                .collect::<Vec<_>>();
+        sorted.sort_by_key(|(_, last)| Reverse(*last));
+        actions.extend(sorted.into_iter().map(|(action, _)| action));
+    }
+}
+
+
+
+fn confirm(core: &WidgetCore, query: &str) -> HResult<bool> {
+    use crate::minibuffer::MiniBufferEvent::*;
+
+    match core.minibuffer(query) {
+        Ok(answer) => Ok(answer.trim().to_lowercase().starts_with("y")),
+        Err(HError::MiniBufferEvent(Empty)) |
+        Err(HError::MiniBufferEvent(Cancelled)) => Ok(false),
+        Err(e) => Err(e)
+    }
+}
+
+
+
 pub trait QuickFiles {
     fn common_mime(&self) -> Option<Mime>;
-    fn get_actions(&self, mime: mime::Mime, subpath: String) -> Async<Vec<QuickAction>>;
+    fn get_actions(&self,
+                   mime: mime::Mime,
+                   subpath: String,
+                   recent_first: bool) -> Async<Vec<QuickAction>>;
 }
 
 impl QuickFiles for Vec<File> {
@@ -488,11 +799,14 @@ impl QuickFiles for Vec<File> {
             })
     }
 
-    fn get_actions(&self, mime: mime::Mime, subpath: String) -> Async<Vec<QuickAction>> {
+    fn get_actions(&self,
+                   mime: mime::Mime,
+                   subpath: String,
+                   recent_first: bool) -> Async<Vec<QuickAction>> {
         Async::new(move |_| {
             let mut apath = paths::actions_path()?;
             apath.push(subpath);
-            Ok(std::fs::read_dir(apath)?
+            let mut actions = std::fs::read_dir(apath)?
                .filter_map(|file| {
                    let path = file.ok()?.path();
                    if !path.is_dir() {
@@ -500,7 +814,13 @@ impl QuickFiles for Vec<File> {
                    } else {
                        None
                    }
-               }).collect())
+               }).collect();
+
+            if recent_first {
+                sort_by_recency(&mut actions);
+            }
+
+            Ok(actions)
         })
     }
 }
@@ -510,6 +830,7 @@ pub trait QuickPath {
     fn get_title(&self) -> String;
     fn get_queries(&self) -> Vec<String>;
     fn get_sync(&self) -> bool;
+    fn get_confirm(&self) -> bool;
 }
 
 impl QuickPath for PathBuf {
@@ -544,4 +865,22 @@ impl QuickPath for PathBuf {
                  .ends_with("!"))
             .unwrap_or(false)
     }
+    // A "# hunter: confirm" comment at the top of the script
+    fn get_confirm(&self) -> bool {
+        use std::io::Read;
+
+        let mut header = vec![0; 1024];
+        let len = std::fs::File::open(self)
+            .and_then(|mut file| file.read(&mut header))
+            .unwrap_or(0);
+
+        String::from_utf8_lossy(&header[..len])
+            .lines()
+            .take_while(|line| line.starts_with("#"))
+            .any(|line| {
+                line.trim_start_matches("#")
+                    .trim()
+                    .eq_ignore_ascii_case("hunter: confirm")
+            })
+    }
 }
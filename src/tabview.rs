@@ -11,6 +11,9 @@ pub trait Tabbable {
     fn next_tab(&mut self) -> HResult<()>;
     fn prev_tab(&mut self) -> HResult<()>;
     fn goto_tab(&mut self, index: usize) -> HResult<()>;
+    fn rename_tab(&mut self) -> HResult<()> {
+        Ok(())
+    }
     fn on_tab_switch(&mut self) -> HResult<()> {
         Ok(())
     }
@@ -23,6 +26,7 @@ pub trait Tabbable {
     }
     fn on_refresh(&mut self) -> HResult<()> { Ok(()) }
     fn on_config_loaded(&mut self) -> HResult<()> { Ok(()) }
+    fn on_dump_view(&self) -> HResult<()> { Ok(()) }
     fn on_new(&mut self) -> HResult<()> { Ok(()) }
 
 }
@@ -124,6 +128,10 @@ impl<T> Widget for TabView<T> where T: Widget, TabView<T>: Tabbable {
         self.on_config_loaded()
     }
 
+    fn dump_view(&self) -> HResult<()> {
+        self.on_dump_view()
+    }
+
     fn set_coordinates(&mut self, coordinates: &Coordinates) -> HResult<()> {
         self.core.coordinates = coordinates.clone();
         for widget in &mut self.widgets {
@@ -138,9 +146,9 @@ impl<T> Widget for TabView<T> where T: Widget, TabView<T>: Tabbable {
         let tab_names = self.get_tab_names();
         let mut nums_length = 0;
         let tabnums = (0..self.widgets.len()).map(|num| {
-            nums_length += format!("{}:{} ",
-                                   num,
-                                   tab_names[num].as_ref().unwrap()).len();
+            nums_length += crate::term::string_len(&format!("{}:{} ",
+                                                            num,
+                                                            tab_names[num].as_ref().unwrap()));
             if num == self.active {
                 format!(" {}{}:{}{}{}",
                         crate::term::invert(),
@@ -205,6 +213,7 @@ impl<T: Widget> Acting for TabView<T> where TabView<T>: Tabbable {
             CloseTab => self.close_tab()?,
             NextTab => self.next_tab()?,
             PrevTab => self.prev_tab()?,
+            RenameTab => self.rename_tab()?,
         }
 
         Ok(())
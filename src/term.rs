@@ -1,5 +1,6 @@
 use std::io::{Stdout, Write, BufWriter, BufRead};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use termion;
 use termion::screen::AlternateScreen;
@@ -217,26 +218,52 @@ pub fn cell_ratio() -> HResult<f32> {
     Ok(ratio)
 }
 
+// Terminals set up for CJK show characters of ambiguous width, like ①, in
+// two columns. With cjk_width=on they're counted that way, too.
+static CJK_WIDTH: AtomicBool = AtomicBool::new(false);
+
+pub fn set_cjk_width(cjk: bool) {
+    CJK_WIDTH.store(cjk, Ordering::Relaxed);
+}
+
+pub fn char_width(ch: char) -> usize {
+    match CJK_WIDTH.load(Ordering::Relaxed) {
+        true => UnicodeWidthChar::width_cjk(ch),
+        false => UnicodeWidthChar::width(ch)
+    }.unwrap_or(0)
+}
+
+pub fn str_width(string: &str) -> usize {
+    match CJK_WIDTH.load(Ordering::Relaxed) {
+        true => UnicodeWidthStr::width_cjk(string),
+        false => UnicodeWidthStr::width(string)
+    }
+}
+
+// format!'s padding counts chars, so wide ones need that much less of it
+pub fn extra_width(string: &str) -> usize {
+    str_width(string).saturating_sub(string.chars().count())
+}
+
+// Cut off before reaching xsize columns, escape codes don't take up any
 pub fn sized_string(string: &str, xsize: u16) -> &str {
-    let len = string.chars()
-                    .map(|ch| {
-                        if ch.is_ascii() {
-                            (1, 1)
-                        } else {
-                            (UnicodeWidthChar::width(ch).unwrap_or(0), ch.len_utf8())
-                        }
-                    })
-                    .scan((0,0), |(str_width, str_len), (ch_width, ch_len)| {
-                        *str_width += ch_width;
-                        *str_len += ch_len;
-                        Some((*str_width, *str_len))
-                    })
-                    .take_while(|(str_width, _)| *str_width < xsize as usize)
-                    .map(|(_, str_len)| str_len)
-                    .last()
-                    .unwrap_or(0);
-
-    &string[0..len]
+    let mut width = 0;
+    let mut len = 0;
+
+    for token in get_tokens(string) {
+        match token {
+            Token::Ansi(ansi) => len += ansi.len(),
+            Token::Text(text) => for ch in text.chars() {
+                width += char_width(ch);
+                if width >= xsize as usize {
+                    return &string[..len];
+                }
+                len += ch.len_utf8();
+            }
+        }
+    }
+
+    string
 }
 
 #[derive(Debug)]
@@ -281,7 +308,7 @@ pub fn string_len(string: &str) -> usize {
 
     tokens.iter().fold(0, |len, token| {
         match token {
-            Token::Text(text) => len + text.len(),
+            Token::Text(text) => len + str_width(text),
             _ => len
         }
     })
@@ -295,7 +322,7 @@ pub fn sized_string_u(string: &str, xsize: usize) -> String {
         let (tok, tok_width) = match token {
             Token::Text(text) => {
                 let tok_str = text;
-                let tok_width = text.width();
+                let tok_width = str_width(text);
                 (tok_str, tok_width)
             },
             Token::Ansi(ansi) => (ansi, 0)
@@ -303,12 +330,12 @@ pub fn sized_string_u(string: &str, xsize: usize) -> String {
 
         // adding this token makes string larger than xsise
         if width + tok_width > xsize {
-            let chars_left = xsize + 1 - width;
+            let chars_left = xsize - width;
 
             // fill up with chars from token until xsize is reached
             let fillup = tok.chars().try_fold((String::new(), 0),
                                               |(mut fillup, fillup_width), chr| {
-                let chr_width = chr.width().unwrap_or(0);
+                let chr_width = char_width(chr);
 
                 if fillup_width + chr_width > chars_left {
                     Err((fillup, fillup_width))
@@ -474,3 +501,41 @@ pub fn header_color() -> String {
 pub fn status_bg() -> String {
     format!("{}", termion::color::Bg(termion::color::LightBlue))
 }
+
+pub fn alert_color() -> String {
+    format!(
+        "{}{}",
+        termion::color::Fg(termion::color::White),
+        termion::color::Bg(termion::color::Red)
+    )
+}
+
+pub fn bell() -> String {
+    "\x07".to_string()
+}
+
+#[test]
+fn test_sized_string_wide() {
+    assert_eq!(sized_string("日本語.txt", 5), "日本");
+    assert_eq!(sized_string("🦀crab", 4), "🦀c");
+    assert_eq!(sized_string("abc", 10), "abc");
+    // Colors don't take up space and aren't cut in half
+    assert_eq!(sized_string("\x1b[31m日本語", 4), "\x1b[31m日");
+
+    assert_eq!(string_len("\x1b[31m日本\x1b[0m"), 4);
+    assert_eq!(extra_width("日本a"), 2);
+
+    for (string, xsize) in &[("日本語", 5), ("🦀🦀🦀", 4), ("abcdef", 3), ("日a", 6)] {
+        let sized = sized_string_u(string, *xsize);
+        assert_eq!(string_len(&sized), *xsize, "{:?} in {}", string, xsize);
+    }
+}
+
+#[test]
+fn test_cjk_width() {
+    assert_eq!(str_width("①"), 1);
+    set_cjk_width(true);
+    assert_eq!(str_width("①"), 2);
+    assert_eq!(sized_string("①②", 3), "①");
+    set_cjk_width(false);
+}
@@ -0,0 +1,37 @@
+use std::path::{Path, PathBuf};
+
+
+// A fresh directory under the system's temp dir for tests to put files in.
+// It's removed when dropped, so a failing assert doesn't leave it behind.
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    pub fn new(name: &str) -> TestDir {
+        let path = std::env::temp_dir().join(format!("hunter-test-{}-{}",
+                                                     name,
+                                                     std::process::id()));
+        // Left over from a run that was killed
+        std::fs::remove_dir_all(&path).ok();
+        std::fs::create_dir_all(&path).unwrap();
+        TestDir(path)
+    }
+}
+
+impl std::ops::Deref for TestDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
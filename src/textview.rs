@@ -1,4 +1,5 @@
 use std::io::BufRead;
+use std::os::unix::fs::OpenOptionsExt;
 
 use strip_ansi_escapes::strip;
 use termion::event::Key;
@@ -18,6 +19,9 @@ pub struct TextView {
     pub offset: usize,
     file: Option<File>,
     limited: bool,
+    // Lines before the count are already colored
+    #[cfg(feature = "syntax")]
+    highlighter: Option<(crate::highlight::LineHighlighter, usize)>,
 }
 
 impl TextView {
@@ -28,7 +32,9 @@ impl TextView {
             follow: false,
             offset: 0,
             file: None,
-            limited: false
+            limited: false,
+            #[cfg(feature = "syntax")]
+            highlighter: None
         }
     }
 
@@ -41,8 +47,19 @@ impl TextView {
     pub fn new_from_file_limit_lines(core: &WidgetCore,
                                      file: &File,
                                      num: usize) -> HResult<TextView> {
-        let buf = std::fs::File::open(&file.path)
-            .map(|f| std::io::BufReader::new(f))?;
+        // Opening a pipe would block until someone writes to it
+        let handle = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&file.path)?;
+
+        // Checked on the open file, so it can't be swapped in between
+        if !handle.metadata()?.is_file() {
+            return Err(HError::Error(format!("{} is not a regular file",
+                                             file.name)));
+        }
+
+        let buf = std::io::BufReader::new(handle);
 
         let lines = buf.lines()
                        .enumerate()
@@ -64,15 +81,31 @@ impl TextView {
             follow: false,
             offset: 0,
             file: Some(file.clone()),
-            limited: true
+            limited: true,
+            #[cfg(feature = "syntax")]
+            highlighter: None
         })
     }
 
     pub fn set_text(&mut self, text: &str) -> HResult<()> {
-        let lines = text.lines().map(|l| l.to_string()).collect();
+        let lines = text.lines()
+                        .map(|l| {
+                            // Only keep what's left after the last carriage
+                            // return, like a terminal would for progress bars
+                            l.split('\r')
+                             .filter(|l| !l.is_empty())
+                             .last()
+                             .unwrap_or("")
+                             .to_string()
+                        })
+                        .collect();
         self.lines = lines;
         self.limited = false;
         self.file = None;
+        #[cfg(feature = "syntax")]
+        {
+            self.highlighter = None;
+        }
         self.core.set_dirty();
         self.refresh()
     }
@@ -81,12 +114,19 @@ impl TextView {
         self.lines = lines;
         self.limited = false;
         self.file = None;
+        #[cfg(feature = "syntax")]
+        {
+            self.highlighter = None;
+        }
         self.core.set_dirty();
         self.refresh()
     }
 
     pub fn load_full(&mut self) {
         if self.limited {
+            #[cfg(feature = "syntax")]
+            let highlighted = self.highlighter.is_some();
+
             self.file
                 .as_ref()
                 .and_then(|f| {
@@ -96,6 +136,41 @@ impl TextView {
                     *self = v;
                     self.limited = false;
                 });
+
+            #[cfg(feature = "syntax")]
+            {
+                if highlighted {
+                    self.highlight().ok();
+                }
+            }
+        }
+    }
+
+    // Colors the lines with syntect, if it knows the syntax of the file
+    #[cfg(feature = "syntax")]
+    pub fn highlight(&mut self) -> HResult<()> {
+        let path = &self.file.as_ref()?.path;
+        let highlighter = crate::highlight::LineHighlighter::new(path)?;
+        self.highlighter = Some((highlighter, 0));
+        self.highlight_visible();
+        Ok(())
+    }
+
+    // Colors everything up to the bottom of the screen, the rest is done
+    // when scrolling there
+    fn highlight_visible(&mut self) {
+        #[cfg(feature = "syntax")]
+        {
+            let ysize = self.core.coordinates.ysize_u();
+            let end = std::cmp::min(self.offset + ysize + 1, self.lines.len());
+
+            if let Some((highlighter, done)) = self.highlighter.as_mut() {
+                if *done < end {
+                    highlighter.highlight(&mut self.lines[*done..end]);
+                    *done = end;
+                    self.core.set_dirty();
+                }
+            }
         }
     }
 
@@ -126,10 +201,16 @@ impl TextView {
         }
 
         if offset != self.offset as isize {
+            self.highlight_visible();
             self.core.set_dirty();
         }
     }
 
+    // Line number at the top, starting at 1 like editors do
+    pub fn top_line(&self) -> usize {
+        self.offset + 1
+    }
+
     pub fn scroll_up(&mut self) {
         self.scroll(-1);
     }
@@ -174,6 +255,8 @@ impl Widget for TextView {
             self.scroll_bottom();
         }
 
+        self.highlight_visible();
+
         if self.core.is_dirty() {
             self.core.set_clean();
         }
@@ -192,10 +275,12 @@ impl Widget for TextView {
                        .take(ysize as usize)
                        .enumerate()
                        .map(|(i, line)| {
+                           // Reset so colors don't bleed into the next line
                            format!(
-                               "{}{}",
+                               "{}{}{}",
                                crate::term::goto_xy(xpos, i as u16 + ypos),
-                               sized_string_u(&line, (xsize-1) as usize))
+                               sized_string_u(&line, xsize.saturating_sub(1) as usize),
+                               crate::term::reset())
                        })
                        .collect::<String>();
         Ok(output)
@@ -212,7 +297,7 @@ impl Widget for TextView {
                                 current_line_top,
                                 current_line_bot,
                                 lines);
-        let hint_xpos = xsize - line_hint.len();
+        let hint_xpos = xsize.saturating_sub(line_hint.len());
         let hint_ypos = ysize + ypos + 1;
 
         let footer = format!("{}{}",
@@ -0,0 +1,92 @@
+use serde::Serialize;
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::files::{File, Files, Kind};
+use crate::fail::{HResult, HError};
+
+
+// Set with --dump-view, the view is written there on SIGUSR1
+lazy_static! {
+    static ref DUMP_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+pub fn set_dump_path(path: PathBuf) -> HResult<()> {
+    *DUMP_PATH.lock()? = Some(path);
+    Ok(())
+}
+
+pub fn dump_path() -> Option<PathBuf> {
+    DUMP_PATH.lock().ok()?.clone()
+}
+
+
+// What the file list shows right now, for tests and other tools
+#[derive(Debug, Serialize)]
+pub struct ViewDump {
+    pub cwd: PathBuf,
+    pub selection: Option<String>,
+    pub filter: Option<String>,
+    pub sort: String,
+    pub reverse: bool,
+    pub dirs_first: bool,
+    pub show_hidden: bool,
+    pub files: Vec<FileDump>
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileDump {
+    pub name: String,
+    pub path: PathBuf,
+    pub kind: &'static str,
+    // None until the metadata is loaded
+    pub size: Option<u64>,
+    pub selected: bool,
+    pub tagged: bool
+}
+
+impl ViewDump {
+    pub fn new(files: &Files, selection: Option<&File>) -> ViewDump {
+        let dumped = files.iter_files()
+            .map(|file| FileDump {
+                name: file.name.clone(),
+                path: file.path.clone(),
+                kind: match file.kind {
+                    Kind::Directory => "directory",
+                    Kind::File => "file",
+                    Kind::Placeholder => "placeholder"
+                },
+                size: file.meta()
+                          .and_then(|meta| meta.as_ref().map(|meta| meta.len())),
+                selected: file.selected,
+                tagged: file.is_tagged().unwrap_or(false)
+            })
+            .collect();
+
+        ViewDump {
+            cwd: files.directory.path.clone(),
+            selection: selection.map(|file| file.name.clone()),
+            filter: files.filter.clone(),
+            sort: files.sort.to_string(),
+            reverse: files.reverse,
+            dirs_first: files.dirs_first,
+            show_hidden: files.show_hidden,
+            files: dumped
+        }
+    }
+
+    // Written to a temporary file first, so readers never see half of it
+    pub fn write(&self) -> HResult<()> {
+        let path = dump_path()?;
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| HError::Error(e.to_string()))?;
+
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+
+        std::fs::write(&tmp_path, json + "\n")?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+}
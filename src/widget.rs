@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::mpsc::{Sender, Receiver, channel};
 use std::io::{Write, stdin};
@@ -30,6 +31,11 @@ pub enum Events {
     RequestInput,
     Status(String),
     ConfigLoaded,
+    // Starts or stops recording keys
+    RecordMacro,
+    PlayMacro(usize),
+    // SIGUSR1 with --dump-view
+    DumpView,
 }
 
 impl PartialEq for WidgetCore {
@@ -66,19 +72,13 @@ impl WidgetCore {
         let screen = Screen::new()?;
         let (xsize, ysize) = screen.size()?;
         let coords = Coordinates::new_at(term::xsize(),
-                                         term::ysize() - 2,
+                                         term::ysize().saturating_sub(2),
                                          1,
                                          2);
         let (sender, receiver) = channel();
         let status_bar_content = Arc::new(Mutex::new(None));
 
-        let mut config = Async::new(move |_| Ok(Config::load()?));
-        let confsender = sender.clone();
-        config.on_ready(move |_, _| {
-            confsender.send(Events::ConfigLoaded).ok();
-            Ok(())
-        }).log();
-        config.run().log();
+        let config = WidgetCore::load_config(sender.clone());
 
         let core = WidgetCore {
             screen: screen,
@@ -96,6 +96,20 @@ impl WidgetCore {
         Ok(core)
     }
 
+    fn load_config(sender: Sender<Events>) -> Async<Config> {
+        let mut config = Async::new(move |_| Ok(Config::load()?));
+        config.on_ready(move |_, _| {
+            sender.send(Events::ConfigLoaded).ok();
+            Ok(())
+        }).log();
+        config.run().log();
+        config
+    }
+
+    pub fn reload_config(&self) {
+        *self.config.write() = WidgetCore::load_config(self.get_sender());
+    }
+
     pub fn get_sender(&self) -> Sender<Events> {
         self.event_sender.lock().clone()
     }
@@ -119,6 +133,22 @@ impl WidgetCore {
         Ok(())
     }
 
+    // With status_line=on the last row is kept for the status and the
+    // minibuffer, so the footer moves up by one
+    pub fn reserved_rows(&self) -> usize {
+        match self.config().status_line {
+            true => 3,
+            false => 2
+        }
+    }
+
+    pub fn footer_ypos(&self) -> u16 {
+        match self.config().status_line {
+            true => term::ysize().saturating_sub(1),
+            false => term::ysize()
+        }
+    }
+
     pub fn show_status(&self, status: &str) -> HResult<()> {
         HError::log::<()>(status).ok();
         {
@@ -155,6 +185,41 @@ impl WidgetCore {
         answer
     }
 
+    pub fn minibuffer_prefilled(&self,
+                                query: &str,
+                                input: &str,
+                                position: usize) -> HResult<String> {
+        let answer = self.minibuffer
+            .lock()
+            .as_mut()?
+            .query_prefilled(query, input, position);
+        let mut screen = self.screen()?;
+        screen.cursor_hide().log();
+        answer
+    }
+
+    pub fn minibuffer_with_insertion(&self,
+                                     query: &str,
+                                     insertion: String) -> HResult<String> {
+        let answer = self.minibuffer
+            .lock()
+            .as_mut()?
+            .query_with_insertion(query, insertion);
+        let mut screen = self.screen()?;
+        screen.cursor_hide().log();
+        answer
+    }
+
+    pub fn minibuffer_resume(&self) -> HResult<String> {
+        let answer = self.minibuffer
+            .lock()
+            .as_mut()?
+            .query_resume();
+        let mut screen = self.screen()?;
+        screen.cursor_hide().log();
+        answer
+    }
+
     pub fn minibuffer_continuous(&self, query: &str) -> HResult<String> {
         let answer = self.minibuffer
             .lock()
@@ -252,6 +317,7 @@ pub trait Widget {
     fn get_drawlist(&self) -> HResult<String>;
     fn after_draw(&self) -> HResult<()> { Ok(()) }
     fn config_loaded(&mut self) -> HResult<()> { Ok(()) }
+    fn dump_view(&self) -> HResult<()> { Ok(()) }
 
 
 
@@ -307,7 +373,7 @@ pub trait Widget {
 
     fn get_footer_drawlist(&mut self) -> HResult<String> {
         let xsize = self.get_coordinates()?.xsize();
-        let ypos = crate::term::ysize();
+        let ypos = self.get_core()?.footer_ypos();
         Ok(format!(
             "{}{}{:xsize$}{}{}",
             crate::term::goto_xy(1, ypos),
@@ -357,6 +423,20 @@ pub trait Widget {
         HError::popup_finnished()
     }
 
+    // Widgets finishing loading interrupt the minibuffer, this redraws and
+    // lets the user continue typing
+    fn query_minibuffer(&mut self, query: &str) -> HResult<String> {
+        let mut answer = self.get_core()?.minibuffer(query);
+
+        while let Err(HError::RefreshParent) = answer {
+            self.refresh().log();
+            self.draw().log();
+            answer = self.get_core()?.minibuffer_resume();
+        }
+
+        answer
+    }
+
     fn run_widget(&mut self) -> HResult<()> {
         let (tx_event, rx_event) = channel();
         self.get_core()?
@@ -502,6 +582,13 @@ pub trait Widget {
                         .pull_async()
                         .ok();
                     self.config_loaded().log();
+
+                    // status_line changes how many rows are left
+                    let screen = self.get_core()?.screen()?;
+                    term::size().and_then(|size| screen.set_size(size)).log();
+                }
+                Events::DumpView => {
+                    self.dump_view().log();
                 }
                 _ => {}
             }
@@ -510,6 +597,9 @@ pub trait Widget {
                 self.get_core()?.screen()?.take_size().ok();
             }
             self.refresh().ok();
+            if self.get_core()?.config().status_line {
+                self.get_core()?.draw_status().log();
+            }
             self.draw().ok();
         }
         Ok(())
@@ -518,8 +608,9 @@ pub trait Widget {
     fn resize(&mut self) -> HResult<()> {
         if let Ok(true) = self.get_core()?.screen()?.is_resized() {
             let (xsize, ysize) = self.get_core()?.screen()?.get_size()?;
+            let reserved = self.get_core()?.reserved_rows();
             let mut coords = self.get_core()?.coordinates.clone();
-            coords.set_size_u(xsize, ysize-2);
+            coords.set_size_u(xsize, ysize.saturating_sub(reserved));
             self.set_coordinates(&coords)?;
         }
         Ok(())
@@ -539,32 +630,90 @@ fn dispatch_events(tx_internal: Sender<Events>,
     std::thread::spawn(move || {
         let mut tx_exclusive_event: Option<Sender<Events>> = None;
         let mut input_enabled = true;
+        let mut recording: Option<Vec<Key>> = None;
+        let mut macro_keys: Vec<Key> = vec![];
+        let mut replay: VecDeque<Key> = VecDeque::new();
+        // Whether the last key handed out came from a macro
+        let mut replaying = false;
 
         for event in rx_event.iter() {
-            match &event {
+            let event = match event {
                 Events::ExclusiveEvent(tx_event) => {
-                    tx_exclusive_event = match tx_event {
+                    tx_exclusive_event = match &tx_event {
                         Some(locked_sender) => locked_sender.lock().take(),
                         None => None
-                    }
+                    };
+                    Events::ExclusiveEvent(tx_event)
                 }
                 Events::InputEnabled(state) => {
-                    input_enabled = *state;
+                    input_enabled = state;
                     continue;
                 }
+                // Replayed keys are handed out one at a time, just like
+                // typed ones, so popups opened by the macro get theirs
                 Events::RequestInput => {
-                    if input_enabled {
-                        tx_input_req.send(()).unwrap();
+                    if !input_enabled {
+                        continue;
+                    }
+                    match replay.pop_front() {
+                        Some(key) => {
+                            replaying = true;
+                            Events::InputEvent(Event::Key(key))
+                        }
+                        None => {
+                            tx_input_req.send(()).unwrap();
+                            continue;
+                        }
                     }
-                    continue;
+                }
+                Events::InputEvent(Event::Key(key)) => {
+                    replaying = false;
+                    if let Some(keys) = &mut recording {
+                        keys.push(key);
+                    }
+                    Events::InputEvent(Event::Key(key))
+                }
+                Events::RecordMacro => {
+                    let status = match recording.take() {
+                        Some(mut keys) => {
+                            // That's the key that stopped the recording
+                            keys.pop();
+                            macro_keys = keys;
+                            format!("Recorded macro with {} keys", macro_keys.len())
+                        }
+                        None => {
+                            recording = Some(vec![]);
+                            String::from("Recording macro...")
+                        }
+                    };
+                    Events::Status(status)
+                }
+                Events::PlayMacro(times) => {
+                    let status = match (&mut recording, macro_keys.len()) {
+                        (Some(keys), _) => {
+                            // Otherwise the macro would play itself
+                            keys.pop();
+                            "Can't play the macro while recording it"
+                        }
+                        (None, _) if replaying => "Can't play a macro from inside a macro",
+                        (None, 0) => "No macro recorded",
+                        (None, _) => {
+                            for _ in 0..times {
+                                replay.extend(macro_keys.iter().cloned());
+                            }
+                            continue;
+                        }
+                    };
+                    Events::Status(String::from(status))
                 }
                 Events::TerminalResized => {
                     if let Ok(size) = term::size() {
                         screen.set_size(size).log();
                     }
+                    Events::TerminalResized
                 }
-                _ => {}
-            }
+                event => event
+            };
             if let Some(tx_exclusive) =  &tx_exclusive_event {
                 tx_exclusive.send(event).ok();
             } else {
@@ -596,9 +745,23 @@ fn input_thread(tx: Sender<Events>, rx_input_request: Receiver<()>) {
 
 fn signal_thread(tx: Sender<Events>) {
     std::thread::spawn(move || {
-        let rx = notify(&[Signal::WINCH]);
-        for _ in rx.iter() {
-            tx.send(Events::TerminalResized).unwrap();
+        #[allow(unused_mut)]
+        let mut signals = vec![Signal::WINCH];
+
+        // SIGUSR1 would kill hunter otherwise, so only catch it on request
+        #[cfg(feature = "dump-view")]
+        {
+            if crate::view_dump::dump_path().is_some() {
+                signals.push(Signal::USR1);
+            }
+        }
+
+        let rx = notify(&signals);
+        for signal in rx.iter() {
+            match signal {
+                Signal::USR1 => tx.send(Events::DumpView).unwrap(),
+                _ => tx.send(Events::TerminalResized).unwrap()
+            }
         }
     });
 }